@@ -0,0 +1,85 @@
+//! Benchmarks of the decode path: parsing broadcast frames, base64-decoding L2 messages and
+//! parsing batches of transactions.
+//!
+//! Run with `cargo bench --bench decode`.
+
+use base64::{engine::general_purpose, Engine as _};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use sequencer_feed_reader::networks::arbitrum::{
+    bench,
+    decoder::{self, DecodeOptions},
+    types::{borrowed::RootRef, versioned::VersionedRoot, Root},
+};
+
+fn json(c: &mut Criterion) {
+    let mut group = c.benchmark_group("json");
+    for messages in [1, 16, 128] {
+        let frame = bench::frame(messages);
+        group.throughput(Throughput::Bytes(frame.len() as u64));
+        group.bench_with_input(BenchmarkId::new("root", messages), &frame, |b, frame| {
+            b.iter(|| serde_json::from_slice::<Root>(black_box(frame)).unwrap())
+        });
+        group.bench_with_input(
+            BenchmarkId::new("versioned", messages),
+            &frame,
+            |b, frame| b.iter(|| VersionedRoot::parse(black_box(frame)).unwrap()),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("borrowed", messages),
+            &frame,
+            |b, frame| b.iter(|| serde_json::from_slice::<RootRef>(black_box(frame)).unwrap()),
+        );
+        // The path of relay clients, with the SIMD accelerated parser with the `simd-json`
+        // feature. Compare with `versioned`, parsing into the owned types.
+        group.bench_with_input(
+            BenchmarkId::new("versioned_borrowed", messages),
+            &frame,
+            |b, frame| b.iter(|| VersionedRoot::parse_borrowed(black_box(frame)).unwrap()),
+        );
+    }
+    group.finish();
+}
+
+fn base64(c: &mut Criterion) {
+    let mut group = c.benchmark_group("base64");
+    for txs in [1, 32] {
+        let encoded =
+            general_purpose::STANDARD.encode(bench::batch_l2msg(&bench::signed_transactions(txs)));
+        group.throughput(Throughput::Bytes(encoded.len() as u64));
+        group.bench_with_input(BenchmarkId::new("l2msg", txs), &encoded, |b, encoded| {
+            b.iter(|| {
+                general_purpose::STANDARD
+                    .decode(black_box(encoded))
+                    .unwrap()
+            })
+        });
+        // SIMD accelerated with the `simd-base64` feature.
+        group.bench_with_input(BenchmarkId::new("decoder", txs), &encoded, |b, encoded| {
+            b.iter(|| decoder::decode_base64(black_box(encoded.as_bytes())).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn batch(c: &mut Criterion) {
+    let mut group = c.benchmark_group("batch");
+    for txs in [1, 32] {
+        let msg = bench::message(0, bench::batch_l2msg(&bench::signed_transactions(txs)));
+        let header = &msg.message.message;
+        group.throughput(Throughput::Elements(txs as u64));
+        group.bench_function(BenchmarkId::new("recover_senders", txs), |b| {
+            b.iter(|| black_box(header).try_decode().unwrap())
+        });
+        let options = DecodeOptions {
+            recover_senders: false,
+            ..DecodeOptions::default()
+        };
+        group.bench_function(BenchmarkId::new("rlp_only", txs), |b| {
+            b.iter(|| black_box(header).try_decode_with(options).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, json, base64, batch);
+criterion_main!(benches);
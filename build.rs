@@ -0,0 +1,35 @@
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    #[cfg(feature = "capi")]
+    {
+        use std::{env, path::Path};
+
+        println!("cargo:rerun-if-changed=src/capi.rs");
+        println!("cargo:rerun-if-changed=cbindgen.toml");
+        println!("cargo:rerun-if-env-changed=SFR_UPDATE_HEADER");
+        let crate_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+        let bindings = cbindgen::generate_with_config(
+            &crate_dir,
+            cbindgen::Config::from_file(Path::new(&crate_dir).join("cbindgen.toml")).unwrap(),
+        )
+        .unwrap();
+        bindings.write_to_file(
+            Path::new(&env::var("OUT_DIR").unwrap()).join("sequencer_feed_reader.h"),
+        );
+        // The header shipped in `include/` is only regenerated on request, so that builds never
+        // write into the source tree.
+        if env::var_os("SFR_UPDATE_HEADER").is_some() {
+            bindings.write_to_file(Path::new(&crate_dir).join("include/sequencer_feed_reader.h"));
+        }
+    }
+    #[cfg(feature = "protobuf")]
+    {
+        println!("cargo:rerun-if-changed=proto/feed.proto");
+        std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path().unwrap());
+        // The gRPC service is generated along with the messages when enabled.
+        #[cfg(feature = "grpc")]
+        tonic_build::compile_protos("proto/feed.proto").unwrap();
+        #[cfg(not(feature = "grpc"))]
+        prost_build::compile_protos(&["proto/feed.proto"], &["proto"]).unwrap();
+    }
+}
@@ -0,0 +1,6 @@
+//! The C library, declared in `include/sequencer_feed_reader.h`, and with the `python` feature
+//! the Python extension module, built from the `capi` and `python` modules of the main crate.
+
+pub use reader::capi::*;
+#[cfg(feature = "python")]
+pub use reader::python::*;
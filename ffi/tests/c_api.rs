@@ -0,0 +1,62 @@
+use reader::networks::arbitrum::mock::{MockRelay, Scenario, SimEvent, SimulatedSequencer, Step};
+use std::{env, path::Path, process::Command, thread};
+
+/// Builds `smoke.c` against the shipped header and the library, and reads a mock relay with it.
+#[test]
+fn reads_messages_from_c() {
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    // The library is built into the `deps` directory, along with the test.
+    let exe = env::current_exe().unwrap();
+    let lib_dir = exe.parent().unwrap();
+    let smoke = Path::new(env!("CARGO_TARGET_TMPDIR")).join("smoke");
+    let status = Command::new(env::var("CC").unwrap_or_else(|_| "cc".to_string()))
+        .args(["-std=c99", "-Wall", "-Werror", "-o"])
+        .arg(&smoke)
+        .arg(manifest_dir.join("tests/smoke.c"))
+        .arg("-I")
+        .arg(manifest_dir.join("../include"))
+        .arg("-L")
+        .arg(lib_dir)
+        .arg("-lsequencer_feed_reader")
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let scenario = Scenario::new().then(Step::Blocks {
+        count: 2,
+        interval_ms: 10,
+    });
+    let mut events = SimulatedSequencer::new(5, 1_700_000_000, 1).generate(&scenario);
+    events.push(SimEvent::Disconnect);
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    let relay = runtime
+        .block_on(MockRelay::bind("127.0.0.1:0", 42161, events))
+        .unwrap();
+    let url = format!("ws://{}", relay.local_addr().unwrap());
+    let server = thread::spawn(move || runtime.block_on(async { relay.spawn().await }));
+
+    let output = Command::new(&smoke)
+        .arg(url)
+        .env("LD_LIBRARY_PATH", lib_dir)
+        .env("DYLD_LIBRARY_PATH", lib_dir)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let keys: Vec<String> = String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| {
+            let json: serde_json::Value = serde_json::from_str(line).unwrap();
+            json["key"].as_str().unwrap().to_string()
+        })
+        .collect();
+    assert_eq!(keys, ["42161:5", "42161:6"]);
+    server.join().unwrap().unwrap();
+}
@@ -0,0 +1,34 @@
+/* Reads a relay through the C library and prints its messages, one per line. */
+
+#include <stdio.h>
+
+#include "sequencer_feed_reader.h"
+
+int main(int argc, char **argv) {
+    if (argc != 2) {
+        fprintf(stderr, "usage: %s <url>\n", argv[0]);
+        return 2;
+    }
+    if (sfr_connect("not a url", 42161, 0) != NULL || sfr_last_error() == NULL) {
+        fprintf(stderr, "connected to an invalid URL\n");
+        return 1;
+    }
+
+    SfrClient *client = sfr_connect(argv[1], 42161, 0);
+    if (client == NULL) {
+        fprintf(stderr, "%s\n", sfr_last_error());
+        return 1;
+    }
+    char *message;
+    SfrStatus status;
+    while ((status = sfr_next_message(client, &message)) == SFR_OK) {
+        puts(message);
+        sfr_free_message(message);
+    }
+    sfr_free(client);
+    if (status != SFR_END) {
+        fprintf(stderr, "%s\n", sfr_last_error());
+        return 1;
+    }
+    return 0;
+}
@@ -0,0 +1,81 @@
+#ifndef SEQUENCER_FEED_READER_H
+#define SEQUENCER_FEED_READER_H
+
+/* Generated by cbindgen when building with the `capi` feature. Do not edit. */
+
+#include <stdint.h>
+
+/**
+ * The outcome of `sfr_next_message`.
+ */
+typedef enum {
+  /**
+   * A message was returned.
+   */
+  SFR_OK = 0,
+  /**
+   * The end of the stream: the connection is closed and every message was read.
+   */
+  SFR_END = 1,
+  /**
+   * The call failed, with the reason available from `sfr_last_error`.
+   */
+  SFR_ERROR = -1,
+} SfrStatus;
+
+/**
+ * A connection to a relay, created by `sfr_connect` and released by `sfr_free`.
+ */
+typedef struct SfrClient SfrClient;
+
+/**
+ * Connects to a relay, blocking until the connection is established.
+ *
+ * Returns `NULL` on failure, with the reason available from `sfr_last_error`.
+ *
+ * # Safety
+ *
+ * `url` must be a valid NUL-terminated string.
+ */
+SfrClient *sfr_connect(const char *url, uint64_t chain_id, uint64_t sequence_number);
+
+/**
+ * Blocks until the next message and stores it into `message` as a JSON object, in the format
+ * of the JSON sinks: the feed message, its decoded transactions and its provenance. The message
+ * must be released with `sfr_free_message`.
+ *
+ * Returns `SFR_OK` with the message, `SFR_END` once the connection is closed and every message
+ * was read, or `SFR_ERROR`. `message` is set to `NULL` unless a message is returned.
+ *
+ * # Safety
+ *
+ * `client` must have been returned by `sfr_connect` and not yet freed, and `message` must be
+ * valid for writes.
+ */
+SfrStatus sfr_next_message(SfrClient *client, char **message);
+
+/**
+ * Releases a message returned by `sfr_next_message`.
+ *
+ * # Safety
+ *
+ * `message` must have been returned by `sfr_next_message` and not yet freed, or be `NULL`.
+ */
+void sfr_free_message(char *message);
+
+/**
+ * Closes the connection and releases the client.
+ *
+ * # Safety
+ *
+ * `client` must have been returned by `sfr_connect` and not yet freed, or be `NULL`.
+ */
+void sfr_free(SfrClient *client);
+
+/**
+ * Returns why the last call of the calling thread failed, or `NULL` if none did. The string is
+ * valid until the next failing call on the same thread.
+ */
+const char *sfr_last_error(void);
+
+#endif /* SEQUENCER_FEED_READER_H */
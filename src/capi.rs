@@ -0,0 +1,250 @@
+//! A C API for embedding the reader into applications written in other languages, declared in
+//! the `include/sequencer_feed_reader.h` header.
+//!
+//! The library itself is built by the `ffi` crate, with `cargo build -p sequencer-feed-reader-ffi`.
+//! Building with the `capi` feature generates the header into `OUT_DIR`; set `SFR_UPDATE_HEADER`
+//! to regenerate the one in `include/` too.
+//!
+//! ```c
+//! SfrClient *client = sfr_connect("wss://arb1.arbitrum.io/feed", 42161, 0);
+//! if (client == NULL) {
+//!     fprintf(stderr, "%s\n", sfr_last_error());
+//!     return 1;
+//! }
+//! char *message;
+//! SfrStatus status;
+//! while ((status = sfr_next_message(client, &message)) == SFR_OK) {
+//!     puts(message);
+//!     sfr_free_message(message);
+//! }
+//! if (status == SFR_ERROR) {
+//!     fprintf(stderr, "%s\n", sfr_last_error());
+//! }
+//! sfr_free(client);
+//! ```
+
+use crate::{
+    networks::arbitrum::{
+        connect::ConnectOptions,
+        sinks::{encoding::Encoding, IdempotencyKey},
+    },
+    sync::RelayClientSync,
+};
+use std::{
+    cell::RefCell,
+    ffi::{c_char, CStr, CString},
+    fmt::Display,
+    ptr,
+};
+use url::Url;
+
+/// The outcome of `sfr_next_message`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(non_camel_case_types)]
+pub enum SfrStatus {
+    /// A message was returned.
+    SFR_OK = 0,
+    /// The end of the stream: the connection is closed and every message was read.
+    SFR_END = 1,
+    /// The call failed, with the reason available from `sfr_last_error`.
+    SFR_ERROR = -1,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(error: impl Display) {
+    let message = CString::new(error.to_string().replace('\0', "")).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+}
+
+/// A connection to a relay, created by `sfr_connect` and released by `sfr_free`.
+pub struct SfrClient {
+    client: RelayClientSync,
+    chain_id: u64,
+}
+
+/// Connects to a relay, blocking until the connection is established.
+///
+/// Returns `NULL` on failure, with the reason available from `sfr_last_error`.
+///
+/// # Safety
+///
+/// `url` must be a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn sfr_connect(
+    url: *const c_char,
+    chain_id: u64,
+    sequence_number: u64,
+) -> *mut SfrClient {
+    if url.is_null() {
+        set_last_error("url is NULL");
+        return ptr::null_mut();
+    }
+    let url = match CStr::from_ptr(url).to_str().map(Url::parse) {
+        Ok(Ok(url)) => url,
+        Ok(Err(e)) => {
+            set_last_error(e);
+            return ptr::null_mut();
+        }
+        Err(e) => {
+            set_last_error(e);
+            return ptr::null_mut();
+        }
+    };
+
+    let options = ConnectOptions::new().with_sequence_number(sequence_number);
+    match RelayClientSync::connect(url, chain_id, options) {
+        Ok(client) => Box::into_raw(Box::new(SfrClient { client, chain_id })),
+        Err(e) => {
+            set_last_error(format_args!("[{}] {}", e.code(), e));
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Blocks until the next message and stores it into `message` as a JSON object, in the format
+/// of the JSON sinks: the feed message, its decoded transactions and its provenance. The message
+/// must be released with `sfr_free_message`.
+///
+/// Returns `SFR_OK` with the message, `SFR_END` once the connection is closed and every message
+/// was read, or `SFR_ERROR`. `message` is set to `NULL` unless a message is returned.
+///
+/// # Safety
+///
+/// `client` must have been returned by `sfr_connect` and not yet freed, and `message` must be
+/// valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn sfr_next_message(
+    client: *mut SfrClient,
+    message: *mut *mut c_char,
+) -> SfrStatus {
+    let Some(message) = message.as_mut() else {
+        set_last_error("message is NULL");
+        return SfrStatus::SFR_ERROR;
+    };
+    *message = ptr::null_mut();
+    let Some(client) = client.as_mut() else {
+        set_last_error("client is NULL");
+        return SfrStatus::SFR_ERROR;
+    };
+    let Some(msg) = client.client.recv() else {
+        return SfrStatus::SFR_END;
+    };
+
+    let key = IdempotencyKey::new(client.chain_id, msg.sequence_number());
+    match Encoding::Json.encode(key, &msg).map(CString::new) {
+        Ok(Ok(json)) => {
+            *message = json.into_raw();
+            SfrStatus::SFR_OK
+        }
+        Ok(Err(e)) => {
+            set_last_error(e);
+            SfrStatus::SFR_ERROR
+        }
+        Err(e) => {
+            set_last_error(e);
+            SfrStatus::SFR_ERROR
+        }
+    }
+}
+
+/// Releases a message returned by `sfr_next_message`.
+///
+/// # Safety
+///
+/// `message` must have been returned by `sfr_next_message` and not yet freed, or be `NULL`.
+#[no_mangle]
+pub unsafe extern "C" fn sfr_free_message(message: *mut c_char) {
+    if !message.is_null() {
+        drop(CString::from_raw(message));
+    }
+}
+
+/// Closes the connection and releases the client.
+///
+/// # Safety
+///
+/// `client` must have been returned by `sfr_connect` and not yet freed, or be `NULL`.
+#[no_mangle]
+pub unsafe extern "C" fn sfr_free(client: *mut SfrClient) {
+    if !client.is_null() {
+        drop(Box::from_raw(client));
+    }
+}
+
+/// Returns why the last call of the calling thread failed, or `NULL` if none did. The string is
+/// valid until the next failing call on the same thread.
+#[no_mangle]
+pub extern "C" fn sfr_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ptr::null(), |e| e.as_ptr()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::networks::arbitrum::mock::{
+        MockRelay, Scenario, SimEvent, SimulatedSequencer, Step,
+    };
+    use std::thread;
+
+    #[test]
+    fn reads_messages_through_the_c_api() {
+        let scenario = Scenario::new().then(Step::Blocks {
+            count: 2,
+            interval_ms: 10,
+        });
+        let mut events = SimulatedSequencer::new(5, 1_700_000_000, 1).generate(&scenario);
+        events.push(SimEvent::Disconnect);
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let relay = runtime
+            .block_on(MockRelay::bind("127.0.0.1:0", 42161, events))
+            .unwrap();
+        let url = CString::new(format!("ws://{}", relay.local_addr().unwrap())).unwrap();
+        let server = thread::spawn(move || runtime.block_on(async { relay.spawn().await }));
+
+        unsafe {
+            assert!(sfr_connect(c"not a url".as_ptr(), 42161, 0).is_null());
+            assert!(!sfr_last_error().is_null());
+
+            let client = sfr_connect(url.as_ptr(), 42161, 0);
+            assert!(!client.is_null());
+            let mut keys = Vec::new();
+            let mut message = ptr::null_mut();
+            loop {
+                let status = sfr_next_message(client, &mut message);
+                if status != SfrStatus::SFR_OK {
+                    assert_eq!(status, SfrStatus::SFR_END);
+                    assert!(message.is_null());
+                    break;
+                }
+                let json: serde_json::Value =
+                    serde_json::from_slice(CStr::from_ptr(message).to_bytes()).unwrap();
+                keys.push(json["key"].as_str().unwrap().to_string());
+                sfr_free_message(message);
+            }
+            sfr_free(client);
+            assert_eq!(keys, ["42161:5", "42161:6"]);
+            assert_eq!(
+                sfr_next_message(ptr::null_mut(), &mut message),
+                SfrStatus::SFR_ERROR
+            );
+        }
+        server.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn shipped_header_is_up_to_date() {
+        assert_eq!(
+            include_str!("../include/sequencer_feed_reader.h"),
+            include_str!(concat!(env!("OUT_DIR"), "/sequencer_feed_reader.h")),
+            "regenerate the header with SFR_UPDATE_HEADER=1 cargo build --features capi"
+        );
+    }
+}
@@ -1,4 +1,17 @@
+#[cfg(feature = "capi")]
+pub mod capi;
 pub mod networks;
+#[cfg(feature = "python")]
+pub mod python;
+#[cfg(not(target_arch = "wasm32"))]
+mod subscribe;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod sync;
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+pub mod wasm;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use subscribe::{subscribe, subscribe_as, subscribe_relay, Projection};
 
 pub fn add(left: usize, right: usize) -> usize {
     left + right
@@ -0,0 +1,340 @@
+// The command line is native only: on wasm32 the crate is used as a library, see `wasm`.
+#![cfg_attr(target_arch = "wasm32", no_main)]
+#![cfg(not(target_arch = "wasm32"))]
+
+use futures::StreamExt;
+#[cfg(feature = "zstd")]
+use sequencer_feed_reader::networks::arbitrum::archive::journal::JournalWriter;
+use sequencer_feed_reader::{
+    networks::arbitrum::{
+        api::MessageApi,
+        archive::{Archive, ArchiveWriter},
+        cache::LiveCache,
+        dashboard::grafana_dashboard,
+        diff::diff_archives,
+        errors::ArchiveError,
+        feed_client::RelayClient,
+        mirror::FeedMirror,
+        network::ArbitrumNetwork,
+        readiness::ReadinessMonitor,
+        signals::{LifecycleSignal, SignalListener},
+        status::RelayStatus,
+        store::FeedStore,
+        types::BroadcastFeedMessage,
+    },
+    subscribe, subscribe_relay,
+};
+use std::{env, process::ExitCode, sync::Arc, time::Duration};
+
+const USAGE: &str = "usage:
+    sequencer-feed-reader diff <left-archive> <right-archive> [from] [to]
+    sequencer-feed-reader status [--json | --prometheus] <network> [seconds]
+    sequencer-feed-reader dashboard [title]
+    sequencer-feed-reader serve <network> <address> [cache-size] [archive-dir] [jsonl | journal]
+    sequencer-feed-reader mirror <network> <address>
+    sequencer-feed-reader schema [output-dir]  (built with the schema feature)";
+
+/// How long `status` reads the feed for by default.
+const DEFAULT_STATUS_SECONDS: u64 = 10;
+/// How many recent messages `serve` keeps in memory by default.
+const DEFAULT_CACHE_SIZE: u64 = 100_000;
+/// How many messages `serve` writes per archive segment.
+const ARCHIVE_SEGMENT_SIZE: usize = 10_000;
+
+fn main() -> ExitCode {
+    env_logger::init();
+
+    let args: Vec<String> = env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("diff") => diff(&args[1..]),
+        Some("status") => status(&args[1..]),
+        Some("dashboard") => dashboard(&args[1..]),
+        Some("serve") => serve(&args[1..]),
+        Some("mirror") => mirror(&args[1..]),
+        #[cfg(feature = "schema")]
+        Some("schema") => schema(&args[1..]),
+        _ => Err(USAGE.to_string()),
+    };
+
+    match result {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Compares two archives and prints the differences, failing if they are not identical.
+fn diff(args: &[String]) -> Result<ExitCode, String> {
+    let (left, right) = match args {
+        [left, right, ..] => (left, right),
+        _ => return Err(USAGE.to_string()),
+    };
+    let from = parse_arg(args.get(2), 0)?;
+    let to = parse_arg(args.get(3), u64::MAX)?;
+
+    let left = Archive::open(left).map_err(|e| e.to_string())?;
+    let right = Archive::open(right).map_err(|e| e.to_string())?;
+    let diff = diff_archives(&left, &right, from, to).map_err(|e| e.to_string())?;
+
+    println!("compared:           {}", diff.compared);
+    println!("missing (left):     {:?}", diff.missing_left);
+    println!("missing (right):    {:?}", diff.missing_right);
+    println!("duplicated (left):  {:?}", diff.duplicated_left);
+    println!("duplicated (right): {:?}", diff.duplicated_right);
+    println!("divergent:          {:?}", diff.divergent);
+
+    Ok(if diff.is_clean() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+/// Reads the feed of a network for a while and prints the status of the client.
+fn status(args: &[String]) -> Result<ExitCode, String> {
+    let (format, args) = match args.first().map(String::as_str) {
+        Some(flag @ ("--json" | "--prometheus")) => (Some(flag), &args[1..]),
+        _ => (None, args),
+    };
+    let network: ArbitrumNetwork = args.first().ok_or(USAGE)?.parse()?;
+    let seconds = parse_arg(args.get(1), DEFAULT_STATUS_SECONDS)?;
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| e.to_string())?;
+    let status = runtime.block_on(async {
+        let (sender, _receiver) = crossbeam_channel::unbounded();
+        let (update, _updates) = crossbeam_channel::unbounded();
+        let client = RelayClient::new(network.feed_url(), network.chain_id(), 0, sender, update)
+            .await
+            .map_err(|e| e.to_string())?;
+        let handle = client.handle();
+        let task = client.spawn();
+
+        tokio::time::sleep(Duration::from_secs(seconds)).await;
+        let status = handle.status();
+        handle.shutdown();
+        let _ = task.await;
+        Ok::<_, String>(status)
+    })?;
+
+    match format {
+        Some("--json") => println!(
+            "{}",
+            serde_json::to_string_pretty(&status).map_err(|e| e.to_string())?
+        ),
+        Some(_) => print!("{}", RelayStatus::to_prometheus(&[status])),
+        None => {
+            println!("relay:              {} ({})", status.relay_id, status.url);
+            println!("state:              {:?}", status.health.state);
+            println!("sequence number:    {:?}", status.health.sequence_number);
+            println!("messages received:  {}", status.health.messages_received);
+            println!("messages forwarded: {}", status.metrics.messages_forwarded);
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Prints a Grafana dashboard wired to the metrics exported by the crate.
+fn dashboard(args: &[String]) -> Result<ExitCode, String> {
+    let title = args.first().map_or("Sequencer feed", String::as_str);
+    let dashboard = grafana_dashboard(title);
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&dashboard).map_err(|e| e.to_string())?
+    );
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Reads the feed of a network and serves its messages over HTTP until the process is asked to
+/// shut down.
+///
+/// Messages are kept in a live cache and, if `archive-dir` is given, archived so that older
+/// messages stay available after they leave the cache, as JSON lines by default or as compressed
+/// journal segments with `journal`.
+///
+/// The API also reports the readiness of the daemon on `/ready`, for readiness probes, from the
+/// messages, connection updates, events and errors of the client.
+///
+/// `SIGTERM` and `SIGINT` close the current archive segment before exiting, and `SIGUSR1` flushes
+/// it to disk.
+fn serve(args: &[String]) -> Result<ExitCode, String> {
+    let (network, address) = match args {
+        [network, address, ..] => (network.parse::<ArbitrumNetwork>()?, address),
+        _ => return Err(USAGE.to_string()),
+    };
+    let cache_size = parse_arg(args.get(2), DEFAULT_CACHE_SIZE)?;
+
+    let cache = Arc::new(LiveCache::new(cache_size as usize));
+    let mut store = FeedStore::new(cache.clone());
+    let mut writer = match args.get(3) {
+        Some(dir) => {
+            let writer = Recorder::new(dir, args.get(4).map(String::as_str))?;
+            store = store.with_archive(Archive::open(dir).map_err(|e| e.to_string())?);
+            Some(writer)
+        }
+        None => None,
+    };
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| e.to_string())?;
+    runtime.block_on(async {
+        let readiness = Arc::new(ReadinessMonitor::new());
+        let api = MessageApi::bind(address, Arc::new(store))
+            .await
+            .map_err(|e| e.to_string())?
+            .with_readiness(readiness.clone());
+        println!(
+            "serving {:?} messages on http://{}",
+            network,
+            api.local_addr().map_err(|e| e.to_string())?
+        );
+        let _server = api.spawn();
+
+        let mut signals = SignalListener::new().map_err(|e| e.to_string())?;
+        let mut messages = Box::pin(subscribe_relay(
+            network.feed_url(),
+            network.chain_id(),
+            readiness.clone(),
+        ));
+        loop {
+            tokio::select! {
+                msg = messages.next() => {
+                    let Some(msg) = msg else { break };
+                    if let Some(writer) = &mut writer {
+                        if let Err(e) = writer.append(&msg.message) {
+                            eprintln!("failed to archive message {}: {}", msg.sequence_number(), e);
+                        }
+                    }
+                    readiness.observe_message(msg.sequence_number());
+                    cache.push(msg.message.clone());
+                }
+                signal = signals.recv() => match signal {
+                    LifecycleSignal::Shutdown => break,
+                    LifecycleSignal::Flush => {
+                        if let Some(Err(e)) = writer.as_mut().map(Recorder::flush) {
+                            eprintln!("failed to flush the archive: {}", e);
+                        }
+                    }
+                    LifecycleSignal::Reload => eprintln!("serve has no configuration to reload"),
+                },
+            }
+        }
+
+        if let Some(writer) = writer {
+            writer.close().map_err(|e| e.to_string())?;
+        }
+        Ok(ExitCode::SUCCESS)
+    })
+}
+
+/// The writer archiving the messages served by `serve`, in either format.
+enum Recorder {
+    Jsonl(ArchiveWriter),
+    #[cfg(feature = "zstd")]
+    Journal(JournalWriter),
+}
+
+impl Recorder {
+    fn new(dir: &str, format: Option<&str>) -> Result<Self, String> {
+        match format.unwrap_or("jsonl") {
+            "jsonl" => ArchiveWriter::new(dir, ARCHIVE_SEGMENT_SIZE).map(Recorder::Jsonl),
+            #[cfg(feature = "zstd")]
+            "journal" => JournalWriter::new(dir, ARCHIVE_SEGMENT_SIZE).map(Recorder::Journal),
+            #[cfg(not(feature = "zstd"))]
+            "journal" => return Err("the journal format needs the zstd feature".to_string()),
+            format => return Err(format!("unknown archive format {}", format)),
+        }
+        .map_err(|e| e.to_string())
+    }
+
+    fn append(&mut self, msg: &BroadcastFeedMessage) -> Result<(), ArchiveError> {
+        match self {
+            Recorder::Jsonl(writer) => writer.append(msg),
+            #[cfg(feature = "zstd")]
+            Recorder::Journal(writer) => writer.append(msg),
+        }
+    }
+
+    fn flush(&mut self) -> Result<(), ArchiveError> {
+        match self {
+            Recorder::Jsonl(writer) => writer.flush(),
+            #[cfg(feature = "zstd")]
+            Recorder::Journal(writer) => writer.flush(),
+        }
+    }
+
+    fn close(self) -> Result<(), ArchiveError> {
+        match self {
+            Recorder::Jsonl(writer) => writer.close(),
+            #[cfg(feature = "zstd")]
+            Recorder::Journal(writer) => writer.close(),
+        }
+    }
+}
+
+/// Reads the feed of a network and re-broadcasts it to websocket clients until the process is
+/// asked to shut down, acting as a relay.
+fn mirror(args: &[String]) -> Result<ExitCode, String> {
+    let (network, address) = match args {
+        [network, address, ..] => (network.parse::<ArbitrumNetwork>()?, address),
+        _ => return Err(USAGE.to_string()),
+    };
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| e.to_string())?;
+    runtime.block_on(async {
+        let mirror = FeedMirror::bind(address, network.chain_id())
+            .await
+            .map_err(|e| e.to_string())?;
+        println!(
+            "mirroring the {:?} feed on ws://{}",
+            network,
+            mirror.local_addr().map_err(|e| e.to_string())?
+        );
+        let publisher = mirror.publisher();
+        let _server = mirror.spawn();
+
+        let mut signals = SignalListener::new().map_err(|e| e.to_string())?;
+        let mut messages = Box::pin(subscribe(network, |_| true));
+        loop {
+            tokio::select! {
+                msg = messages.next() => match msg {
+                    Some(msg) => publisher.publish(&msg.message),
+                    None => break,
+                },
+                signal = signals.recv() => {
+                    if signal == LifecycleSignal::Shutdown {
+                        break;
+                    }
+                }
+            }
+        }
+        Ok(ExitCode::SUCCESS)
+    })
+}
+
+/// Writes the schemas of the messages produced by the crate, for consumers in other languages.
+#[cfg(feature = "schema")]
+fn schema(args: &[String]) -> Result<ExitCode, String> {
+    let dir = args.first().map_or("schema", String::as_str);
+    let paths = sequencer_feed_reader::networks::arbitrum::schema::write_all(dir.as_ref())
+        .map_err(|e| e.to_string())?;
+    for path in paths {
+        println!("{}", path.display());
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn parse_arg(arg: Option<&String>, default: u64) -> Result<u64, String> {
+    arg.map_or(Ok(default), |a| {
+        a.parse().map_err(|_| format!("invalid number {}", a))
+    })
+}
@@ -1,5 +1,105 @@
+pub mod abi;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod admin;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod api;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod archive;
+pub mod arena;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod audit;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod backpressure;
+pub mod batch;
+pub mod bench;
+pub mod blocks;
+pub mod cache;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod capture;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod checkpoint;
+pub mod config;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod conformance;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod connect;
+pub mod consistency;
+pub mod dashboard;
 pub mod decoder;
+pub mod dedup;
+pub mod delayed;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod diff;
 pub mod errors;
+pub mod events;
+pub mod failover;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod feed_client;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod feed_clients;
+pub mod filter;
+#[cfg(test)]
+pub(crate) mod fixtures;
+pub mod gas;
+#[cfg(all(feature = "grpc", not(target_arch = "wasm32")))]
+pub mod grpc;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod handle;
+#[cfg(all(feature = "tower", not(target_arch = "wasm32")))]
+pub mod handler;
+pub mod health;
+pub mod hub;
+pub mod identity;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod mempool;
+pub mod message;
+pub mod metrics;
+pub mod middleware;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod mirror;
+#[cfg(all(any(test, feature = "mock"), not(target_arch = "wasm32")))]
+pub mod mock;
+pub mod network;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod observer;
+#[cfg(all(feature = "otel", not(target_arch = "wasm32")))]
+pub mod otel;
+pub mod pipeline;
+pub mod priority;
+#[cfg(feature = "protobuf")]
+pub mod protobuf;
+pub mod provenance;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod proxy;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod pubsub;
+pub mod readiness;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod relays;
+pub mod reorg;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod replay;
+pub mod router;
+#[cfg(feature = "schema")]
+pub mod schema;
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) mod server;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod service;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod shutdown;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod signals;
+pub mod sinks;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod startup;
+pub mod stats;
+pub mod status;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod store;
+pub mod throttle;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod tls;
 pub mod types;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod verify;
@@ -0,0 +1,190 @@
+use crate::networks::arbitrum::decoder::DecodedMsg;
+use ethers::{
+    abi::{Abi, Function, Token},
+    types::{Transaction, H160},
+};
+use std::collections::HashMap;
+
+/// A function call decoded from transaction calldata using a registered ABI.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedCall {
+    /// The name given to the contract when its ABI was registered (e.g. `UniswapV3Router`).
+    pub contract: String,
+    /// The name of the called function.
+    pub function: String,
+    /// The full function signature, e.g. `exactInputSingle((address,address,uint24,...))`.
+    pub signature: String,
+    /// The decoded arguments as `(name, value)` pairs, in declaration order.
+    pub inputs: Vec<(String, Token)>,
+}
+
+/// A transaction from the feed together with its decoded function call, if any.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnrichedTx {
+    pub tx: Transaction,
+    /// `None` if the target contract has no registered ABI or the calldata did not match it.
+    pub call: Option<DecodedCall>,
+}
+
+struct RegisteredAbi {
+    name: String,
+    abi: Abi,
+}
+
+/// A registry of contract ABIs used to decode the calldata of transactions read from the feed.
+#[derive(Default)]
+pub struct AbiRegistry {
+    contracts: HashMap<H160, RegisteredAbi>,
+}
+
+impl AbiRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the ABI of the contract deployed at `address`.
+    ///
+    /// Registering a second ABI for the same address replaces the first one.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address of the contract.
+    /// * `name` - A human readable name for the contract, reported in `DecodedCall::contract`.
+    /// * `abi` - The contract ABI.
+    pub fn register(&mut self, address: H160, name: impl Into<String>, abi: Abi) {
+        self.contracts.insert(
+            address,
+            RegisteredAbi {
+                name: name.into(),
+                abi,
+            },
+        );
+    }
+
+    /// Registers a contract ABI given as a JSON string, as produced by solc or block explorers.
+    ///
+    /// # Returns
+    ///
+    /// A `serde_json::Error` if the JSON is not a valid ABI.
+    pub fn register_json(
+        &mut self,
+        address: H160,
+        name: impl Into<String>,
+        json: &str,
+    ) -> Result<(), serde_json::Error> {
+        let abi: Abi = serde_json::from_str(json)?;
+        self.register(address, name, abi);
+        Ok(())
+    }
+
+    /// Returns `true` if no ABI has been registered.
+    pub fn is_empty(&self) -> bool {
+        self.contracts.is_empty()
+    }
+
+    /// Decodes the calldata of `tx` against the ABI registered for its target address.
+    ///
+    /// Returns `None` for contract creations, unknown targets, unknown selectors, or calldata
+    /// that does not match the function's input types.
+    pub fn decode_call(&self, tx: &Transaction) -> Option<DecodedCall> {
+        let registered = self.contracts.get(tx.to.as_ref()?)?;
+        let data = tx.input.as_ref();
+        if data.len() < 4 {
+            return None;
+        }
+
+        let function = find_function(&registered.abi, &data[..4])?;
+        let tokens = function.decode_input(&data[4..]).ok()?;
+        let inputs = function
+            .inputs
+            .iter()
+            .map(|param| param.name.clone())
+            .zip(tokens)
+            .collect();
+
+        Some(DecodedCall {
+            contract: registered.name.clone(),
+            function: function.name.clone(),
+            signature: function.signature(),
+            inputs,
+        })
+    }
+
+    /// Wraps `tx` into an `EnrichedTx`, decoding its calldata if possible.
+    pub fn enrich(&self, tx: Transaction) -> EnrichedTx {
+        let call = self.decode_call(&tx);
+        EnrichedTx { tx, call }
+    }
+
+    /// Enriches every transaction contained in a decoded L2 message.
+    pub fn enrich_msg(&self, msg: DecodedMsg) -> Vec<EnrichedTx> {
+        match msg {
+            DecodedMsg::DecodedBatch(txs) => txs.into_iter().map(|tx| self.enrich(tx)).collect(),
+            DecodedMsg::DecodedSignedTx(tx) => vec![self.enrich(*tx)],
+            _ => Vec::new(),
+        }
+    }
+}
+
+fn find_function<'a>(abi: &'a Abi, selector: &[u8]) -> Option<&'a Function> {
+    abi.functions()
+        .find(|function| function.short_signature() == selector)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::{abi::AbiParser, types::U256};
+
+    #[test]
+    fn decodes_calls_to_registered_contracts() {
+        let token = H160::repeat_byte(0x11);
+        let abi = AbiParser::default()
+            .parse(&["function transfer(address to, uint256 amount) returns (bool)"])
+            .unwrap();
+        let mut registry = AbiRegistry::new();
+        assert!(registry.is_empty());
+        registry.register(token, "Token", abi.clone());
+
+        let recipient = H160::repeat_byte(0x22);
+        let data = abi
+            .function("transfer")
+            .unwrap()
+            .encode_input(&[Token::Address(recipient), Token::Uint(U256::from(5))])
+            .unwrap();
+        let transfer = Transaction {
+            to: Some(token),
+            input: data.into(),
+            ..Default::default()
+        };
+        let call = registry.decode_call(&transfer).unwrap();
+        assert_eq!(call.contract, "Token");
+        assert_eq!(call.function, "transfer");
+        assert_eq!(call.signature, "transfer(address,uint256):(bool)");
+        assert_eq!(
+            call.inputs,
+            [
+                ("to".to_string(), Token::Address(recipient)),
+                ("amount".to_string(), Token::Uint(U256::from(5))),
+            ]
+        );
+
+        // Unknown targets, unknown selectors and transfers without calldata are left undecoded.
+        let elsewhere = Transaction {
+            to: Some(recipient),
+            ..transfer.clone()
+        };
+        let unknown = Transaction {
+            input: vec![0xde, 0xad, 0xbe, 0xef].into(),
+            ..transfer.clone()
+        };
+        let enriched = registry.enrich_msg(DecodedMsg::DecodedBatch(vec![
+            transfer,
+            elsewhere,
+            unknown,
+            Transaction::default(),
+        ]));
+        let decoded: Vec<_> = enriched.iter().map(|tx| tx.call.is_some()).collect();
+        assert_eq!(decoded, [true, false, false, false]);
+    }
+}
@@ -0,0 +1,208 @@
+//! An HTTP admin server to monitor and control a running `FeedService`, when operating the
+//! reader as a standalone service.
+//!
+//! Endpoints:
+//!
+//! * `GET /health` - Whether a relay is connected, with status 200 if so and 503 otherwise.
+//! * `GET /stats` - The next sequence number, the pipeline restarts, the missed priority
+//!   transactions and the status of every connected relay.
+//! * `GET /sequence` - The sequence number of the last message read.
+//! * `GET /relays` - The status of every connected relay.
+//! * `POST /reconnect` - Disconnects every relay, which are reconnected right after, resuming
+//!   after the last message read.
+//! * `POST /pause` - Stops reading the relays, keeping them connected.
+//! * `POST /resume` - Reads the relays again after `/pause`.
+
+use crate::networks::arbitrum::{server, service::FeedServiceControl};
+use hyper::{Body, Method, Request, Response};
+use log::*;
+use serde_json::json;
+use std::{io, net::SocketAddr};
+use tokio::{net::TcpListener, task::JoinHandle};
+
+/// An HTTP server exposing the status and controls of a running `FeedService`.
+///
+/// It has no authentication: bind it to a private address.
+///
+/// # Example
+///
+/// ```no_run
+/// use sequencer_feed_reader::networks::arbitrum::{admin::AdminServer, service::FeedService};
+///
+/// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+/// let service = FeedService::from_config("feed.toml")?.start().await?;
+/// let admin = AdminServer::bind("127.0.0.1:9090", service.control().clone()).await?;
+/// let _server = admin.spawn();
+/// # Ok(())
+/// # }
+/// ```
+pub struct AdminServer {
+    listener: TcpListener,
+    control: FeedServiceControl,
+}
+
+impl AdminServer {
+    /// Binds the server to `addr`, e.g. `127.0.0.1:9090`.
+    pub async fn bind(addr: &str, control: FeedServiceControl) -> io::Result<Self> {
+        Ok(Self {
+            listener: TcpListener::bind(addr).await?,
+            control,
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Serves requests until the task is aborted.
+    pub fn spawn(self) -> JoinHandle<()> {
+        let control = self.control;
+        server::serve_http(self.listener, "admin", move |request| {
+            let response = route(&control, &request);
+            async move { response }
+        })
+    }
+}
+
+fn route(control: &FeedServiceControl, request: &Request<Body>) -> Response<Body> {
+    let method = request.method();
+    match (method, request.uri().path().trim_end_matches('/')) {
+        (&Method::GET, "/health") => {
+            let connected = control
+                .relays()
+                .statuses()
+                .iter()
+                .filter(|status| status.health.state.is_connected())
+                .count();
+            server::json(
+                if connected > 0 { 200 } else { 503 },
+                &json!({
+                    "healthy": connected > 0,
+                    "connectedRelays": connected,
+                    "paused": control.relays().is_paused(),
+                }),
+            )
+        }
+        (&Method::GET, "/stats") => server::json(
+            200,
+            &json!({
+                "nextSequenceNumber": control.next_sequence_number(),
+                "restarts": control.restarts(),
+                "missedPriorityTransactions": control.missed_priority_transactions(),
+                "relays": control.relays().statuses(),
+            }),
+        ),
+        (&Method::GET, "/sequence") => {
+            let next = control.next_sequence_number();
+            server::json(
+                200,
+                &json!({
+                    "nextSequenceNumber": next,
+                    "lastSequenceNumber": next.checked_sub(1),
+                }),
+            )
+        }
+        (&Method::GET, "/relays") => server::json(200, &control.relays().statuses()),
+        (&Method::POST, "/reconnect") => {
+            let reconnected = control.relays().reconnect();
+            info!("Reconnecting {} relays on admin request", reconnected);
+            server::json(200, &json!({ "reconnected": reconnected }))
+        }
+        (&Method::POST, "/pause") => {
+            let paused = control.relays().pause();
+            info!("Pausing {} relays on admin request", paused);
+            server::json(200, &json!({ "paused": paused }))
+        }
+        (&Method::POST, "/resume") => {
+            let resumed = control.relays().resume();
+            info!("Resuming {} relays on admin request", resumed);
+            server::json(200, &json!({ "resumed": resumed }))
+        }
+        (
+            _,
+            "/health" | "/stats" | "/sequence" | "/relays" | "/reconnect" | "/pause" | "/resume",
+        ) => server::error(405, "method not allowed"),
+        _ => server::error(404, "not found"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::networks::arbitrum::{
+        config::Config,
+        mock::{MockRelay, Scenario, SimulatedSequencer, Step},
+        service::FeedService,
+    };
+    use serde_json::Value;
+    use std::time::Duration;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+    };
+
+    async fn request(addr: SocketAddr, method: &str, target: &str) -> (u16, Value) {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let request = format!("{} {} HTTP/1.1\r\nHost: localhost\r\n\r\n", method, target);
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        let status = head.split(' ').nth(1).unwrap().parse().unwrap();
+        (status, serde_json::from_str(body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn reports_and_reconnects_relays() {
+        let scenario = Scenario::new()
+            .then(Step::Blocks {
+                count: 3,
+                interval_ms: 10,
+            })
+            .then(Step::Stall { ms: 60_000 });
+        let events = SimulatedSequencer::new(0, 1_700_000_000, 1).generate(&scenario);
+        let relay = MockRelay::bind("127.0.0.1:0", 42161, events).await.unwrap();
+        let url = format!("ws://{}", relay.local_addr().unwrap());
+        relay.spawn();
+
+        let config =
+            Config::from_toml(&format!("chain_id = 42161\nrelays = [\"{}\"]", url)).unwrap();
+        let service = FeedService::new(config).start().await.unwrap();
+        let admin = AdminServer::bind("127.0.0.1:0", service.control().clone())
+            .await
+            .unwrap();
+        let addr = admin.local_addr().unwrap();
+        admin.spawn();
+
+        let mut health = request(addr, "GET", "/health").await;
+        for _ in 0..50 {
+            if health.0 == 200 && service.next_sequence_number() == 3 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            health = request(addr, "GET", "/health").await;
+        }
+        assert_eq!(health.1["connectedRelays"], 1);
+        assert_eq!(
+            request(addr, "GET", "/sequence").await.1["lastSequenceNumber"],
+            2
+        );
+        let (_, relays) = request(addr, "GET", "/relays").await;
+        assert_eq!(relays[0]["url"], format!("{}/", url));
+        assert_eq!(
+            request(addr, "GET", "/stats").await.1["nextSequenceNumber"],
+            3
+        );
+        assert_eq!(request(addr, "POST", "/pause").await.1["paused"], 1);
+        assert_eq!(request(addr, "GET", "/health").await.1["paused"], true);
+        assert_eq!(request(addr, "POST", "/resume").await.1["resumed"], 1);
+        assert_eq!(request(addr, "GET", "/reconnect").await.0, 405);
+        assert_eq!(
+            request(addr, "POST", "/reconnect").await.1["reconnected"],
+            1
+        );
+        assert_eq!(request(addr, "GET", "/nope").await.0, 404);
+        service.stop().await;
+    }
+}
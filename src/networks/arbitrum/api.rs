@@ -0,0 +1,216 @@
+//! An HTTP API serving feed messages by sequence number.
+//!
+//! Endpoints:
+//!
+//! * `GET /messages/{seq}` - The message with sequence number `seq`.
+//! * `GET /messages?from=&to=` - The messages with sequence numbers in `from..=to`, both bounds
+//!   being optional.
+//! * `GET /stats` - The messages held by the live cache and, if a `ReadinessMonitor` is set, the
+//!   readiness report.
+//! * `GET /ready` - The readiness report, with status 200 if ready and 503 otherwise, for
+//!   readiness probes. Always ready without a `ReadinessMonitor`.
+//!
+//! Messages are served from a `FeedStore`, so recent messages come from the live cache and older
+//! ones from the archive.
+
+use crate::networks::arbitrum::{
+    readiness::ReadinessMonitor,
+    server,
+    store::{FeedStore, QueryRange},
+    types::BroadcastFeedMessage,
+};
+use hyper::{Body, Method, Request, Response};
+use serde_json::json;
+use std::{io, net::SocketAddr, ops::RangeInclusive, sync::Arc};
+use tokio::{net::TcpListener, task::JoinHandle};
+
+/// The largest number of messages a range query returns by default.
+const DEFAULT_MAX_RANGE: u64 = 1000;
+
+/// An HTTP server exposing the messages of a `FeedStore`.
+pub struct MessageApi {
+    listener: TcpListener,
+    routes: Routes,
+}
+
+struct Routes {
+    store: Arc<FeedStore>,
+    max_range: u64,
+    readiness: Option<Arc<ReadinessMonitor>>,
+}
+
+impl MessageApi {
+    /// Binds the server to `addr`, e.g. `127.0.0.1:8080`.
+    pub async fn bind(addr: &str, store: Arc<FeedStore>) -> io::Result<Self> {
+        Ok(Self {
+            listener: TcpListener::bind(addr).await?,
+            routes: Routes {
+                store,
+                max_range: DEFAULT_MAX_RANGE,
+                readiness: None,
+            },
+        })
+    }
+
+    /// Sets the largest number of messages a range query returns. Larger ranges are truncated.
+    pub fn with_max_range(mut self, max_range: u64) -> Self {
+        self.routes.max_range = max_range.max(1);
+        self
+    }
+
+    /// Reports the readiness computed by `readiness` on `/ready` and `/stats`.
+    pub fn with_readiness(mut self, readiness: Arc<ReadinessMonitor>) -> Self {
+        self.routes.readiness = Some(readiness);
+        self
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Serves requests until the task is aborted.
+    pub fn spawn(self) -> JoinHandle<()> {
+        let routes = Arc::new(self.routes);
+        server::serve_http(self.listener, "API", move |request| {
+            let routes = routes.clone();
+            async move { routes.route(request).await }
+        })
+    }
+}
+
+impl Routes {
+    async fn route(&self, request: Request<Body>) -> Response<Body> {
+        if request.method() != Method::GET {
+            return server::error(405, "only GET is supported");
+        }
+        let query = request.uri().query().unwrap_or_default();
+        match request.uri().path().trim_end_matches('/') {
+            "/ready" => match &self.readiness {
+                Some(readiness) => {
+                    let report = readiness.report();
+                    server::json(if report.ready { 200 } else { 503 }, &report)
+                }
+                None => server::json(200, &json!({ "ready": true, "conditions": [] })),
+            },
+            "/stats" => {
+                let cache = self.store.cache();
+                let range = cache.sequence_range();
+                server::json(
+                    200,
+                    &json!({
+                        "cache": {
+                            "messages": cache.len(),
+                            "firstSequenceNumber": range.as_ref().map(|r| *r.start()),
+                            "lastSequenceNumber": range.as_ref().map(|r| *r.end()),
+                        },
+                        "readiness": self.readiness.as_ref().map(|r| r.report()),
+                    }),
+                )
+            }
+            "/messages" => {
+                let mut from = 0;
+                let mut to = None;
+                for (key, value) in query.split('&').filter_map(|p| p.split_once('=')) {
+                    let Ok(value) = value.parse::<u64>() else {
+                        return server::error(400, format!("invalid {}", key));
+                    };
+                    match key {
+                        "from" => from = value,
+                        "to" => to = Some(value),
+                        _ => {}
+                    }
+                }
+                let to = to
+                    .unwrap_or(u64::MAX)
+                    .min(from.saturating_add(self.max_range - 1));
+                if from > to {
+                    return server::error(400, "from is greater than to");
+                }
+                match self.query(from..=to).await {
+                    Ok(messages) => server::json(200, &messages),
+                    Err(e) => server::error(500, e),
+                }
+            }
+            path => match path.strip_prefix("/messages/").map(str::parse::<u64>) {
+                Some(Ok(seq)) => match self.query(seq..=seq).await {
+                    Ok(messages) => match messages.first() {
+                        Some(msg) => server::json(200, msg),
+                        None => server::error(404, format!("message {} not found", seq)),
+                    },
+                    Err(e) => server::error(500, e),
+                },
+                Some(Err(_)) => server::error(400, "invalid sequence number"),
+                None => server::error(404, "not found"),
+            },
+        }
+    }
+
+    /// Queries the store off the runtime, since reading the archive blocks.
+    async fn query(&self, range: RangeInclusive<u64>) -> Result<Vec<BroadcastFeedMessage>, String> {
+        let store = self.store.clone();
+        tokio::task::spawn_blocking(move || store.query(QueryRange::Sequence(range), |_| true))
+            .await
+            .map_err(|e| e.to_string())?
+            .map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::networks::arbitrum::{cache::LiveCache, fixtures::message_with};
+    use serde_json::Value;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+    };
+
+    async fn get(addr: SocketAddr, target: &str) -> (u16, Value) {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let request = format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", target);
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        let status = head.split(' ').nth(1).unwrap().parse().unwrap();
+        (status, serde_json::from_str(body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn serves_messages_by_sequence_number() {
+        let cache = Arc::new(LiveCache::new(16));
+        for seq in 10..20 {
+            cache.push(message_with(seq, 0, Vec::new()));
+        }
+        let api = MessageApi::bind("127.0.0.1:0", Arc::new(FeedStore::new(cache)))
+            .await
+            .unwrap()
+            .with_max_range(3);
+        let addr = api.local_addr().unwrap();
+        let server = api.spawn();
+
+        let (status, body) = get(addr, "/messages/12").await;
+        assert_eq!(status, 200);
+        assert_eq!(body["sequenceNumber"], 12);
+
+        let (status, body) = get(addr, "/messages?from=14&to=100").await;
+        assert_eq!(status, 200);
+        let seqs: Vec<_> = body
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|msg| msg["sequenceNumber"].as_u64().unwrap())
+            .collect();
+        assert_eq!(seqs, vec![14, 15, 16]);
+
+        assert_eq!(get(addr, "/messages/99").await.0, 404);
+        assert_eq!(
+            get(addr, "/stats").await.1["cache"]["lastSequenceNumber"],
+            19
+        );
+        assert_eq!(get(addr, "/messages?from=x").await.0, 400);
+
+        server.abort();
+    }
+}
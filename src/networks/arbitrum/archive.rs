@@ -0,0 +1,320 @@
+pub mod history;
+#[cfg(feature = "zstd")]
+pub mod journal;
+pub mod layout;
+#[cfg(feature = "s3")]
+pub mod s3;
+pub mod store;
+pub mod uploader;
+
+use crate::networks::arbitrum::{
+    archive::layout::{DayManifest, ManifestEntry, SegmentNaming, TimeWindow},
+    errors::ArchiveError,
+    types::{BroadcastFeedMessage, Root},
+};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+const SEGMENT_EXTENSION: &str = "jsonl";
+
+/// Records feed messages to disk as a directory of JSON lines segments.
+///
+/// Each segment holds at most `segment_size` messages and, if a time window is set, the messages
+/// of a single window. It is named after the sequence number of its first message while written,
+/// then renamed according to its `SegmentNaming`, if any, once completed.
+///
+/// With a time window, completed segments are also listed in the `DayManifest` of the day of
+/// their first message.
+pub struct ArchiveWriter {
+    dir: PathBuf,
+    segment_size: usize,
+    window: Option<TimeWindow>,
+    naming: Option<SegmentNaming>,
+    current: Option<OpenSegment>,
+}
+
+/// The segment being written.
+struct OpenSegment {
+    writer: BufWriter<File>,
+    path: PathBuf,
+    first_sequence: u64,
+    last_sequence: u64,
+    messages: usize,
+    first_timestamp: u64,
+    last_timestamp: u64,
+}
+
+impl ArchiveWriter {
+    /// Creates a new writer recording into `dir`, creating the directory if needed.
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - The directory the segments are written to.
+    /// * `segment_size` - The maximum number of messages per segment.
+    pub fn new(dir: impl Into<PathBuf>, segment_size: usize) -> Result<Self, ArchiveError> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+
+        Ok(Self {
+            dir,
+            segment_size: segment_size.max(1),
+            window: None,
+            naming: None,
+            current: None,
+        })
+    }
+
+    /// Also rolls over to a new segment when a message falls into a new time window.
+    pub fn with_time_window(mut self, window: TimeWindow) -> Self {
+        self.window = Some(window);
+        self
+    }
+
+    /// Renames completed segments according to `naming`.
+    pub fn with_naming(mut self, naming: SegmentNaming) -> Self {
+        self.naming = Some(naming);
+        self
+    }
+
+    /// Appends a message to the current segment, rolling over to a new segment when full.
+    pub fn append(&mut self, msg: &BroadcastFeedMessage) -> Result<(), ArchiveError> {
+        let timestamp = msg.message.message.header.timestamp;
+        if let Some(segment) = &self.current {
+            let new_window = self.window.is_some_and(|window| {
+                window.start(timestamp) != window.start(segment.first_timestamp)
+            });
+            if segment.messages >= self.segment_size || new_window {
+                self.roll()?;
+            }
+        }
+
+        let segment = match &mut self.current {
+            Some(segment) => segment,
+            None => {
+                let path = segment_path(&self.dir, msg.sequence_number);
+                let file = OpenOptions::new().create(true).append(true).open(&path)?;
+                self.current.insert(OpenSegment {
+                    writer: BufWriter::new(file),
+                    path,
+                    first_sequence: msg.sequence_number,
+                    last_sequence: msg.sequence_number,
+                    messages: 0,
+                    first_timestamp: timestamp,
+                    last_timestamp: timestamp,
+                })
+            }
+        };
+
+        serde_json::to_writer(&mut segment.writer, msg)?;
+        segment.writer.write_all(b"\n")?;
+        segment.last_sequence = msg.sequence_number;
+        segment.last_timestamp = timestamp;
+        segment.messages += 1;
+        Ok(())
+    }
+
+    /// Appends every message contained in `root`.
+    pub fn append_root(&mut self, root: &Root) -> Result<(), ArchiveError> {
+        for msg in &root.messages {
+            self.append(msg)?;
+        }
+        Ok(())
+    }
+
+    /// Flushes buffered messages to disk.
+    pub fn flush(&mut self) -> Result<(), ArchiveError> {
+        if let Some(segment) = &mut self.current {
+            segment.writer.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Completes the current segment, renaming it and listing it in its manifest.
+    ///
+    /// Dropping the writer only flushes the current segment, which is then left under its
+    /// temporary name.
+    pub fn close(mut self) -> Result<(), ArchiveError> {
+        self.roll()
+    }
+
+    fn roll(&mut self) -> Result<(), ArchiveError> {
+        let Some(mut segment) = self.current.take() else {
+            return Ok(());
+        };
+        segment.writer.flush()?;
+
+        let window = match self.window {
+            Some(window) => window.label(window.start(segment.first_timestamp)),
+            None => layout::date(segment.first_timestamp),
+        };
+        let name = match &self.naming {
+            Some(naming) => {
+                let name = format!(
+                    "{}.{}",
+                    naming.render(segment.first_sequence, segment.last_sequence, &window),
+                    SEGMENT_EXTENSION
+                );
+                fs::rename(&segment.path, self.dir.join(&name))?;
+                name
+            }
+            None => segment_name(segment.first_sequence),
+        };
+
+        if self.window.is_some() {
+            DayManifest::record(
+                &self.dir,
+                ManifestEntry {
+                    name,
+                    first_sequence: segment.first_sequence,
+                    last_sequence: segment.last_sequence,
+                    messages: segment.messages,
+                    first_timestamp: segment.first_timestamp,
+                    last_timestamp: segment.last_timestamp,
+                },
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for ArchiveWriter {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+/// A segment of an archive, covering messages starting at `first_sequence`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    pub first_sequence: u64,
+    pub path: PathBuf,
+}
+
+/// Read access to a directory of segments written by `ArchiveWriter` and, with the `zstd`
+/// feature, by `JournalWriter`.
+pub struct Archive {
+    dir: PathBuf,
+}
+
+impl Archive {
+    pub fn open(dir: impl Into<PathBuf>) -> Result<Self, ArchiveError> {
+        let dir = dir.into();
+        if !dir.is_dir() {
+            return Err(ArchiveError::NotADirectory(dir));
+        }
+        Ok(Self { dir })
+    }
+
+    /// Lists the segments of the archive, ordered by their first sequence number.
+    pub fn segments(&self) -> Result<Vec<Segment>, ArchiveError> {
+        let mut segments = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some(SEGMENT_EXTENSION) {
+                continue;
+            }
+
+            let stem = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .ok_or_else(|| ArchiveError::InvalidSegmentName(path.clone()))?;
+            // Segments renamed by a `SegmentNaming` are located by their first message.
+            let first_sequence = match stem.parse::<u64>() {
+                Ok(first_sequence) => first_sequence,
+                Err(_) => match first_message(&path)? {
+                    Some(msg) => msg.sequence_number,
+                    None => continue,
+                },
+            };
+            segments.push(Segment {
+                first_sequence,
+                path,
+            });
+        }
+
+        segments.sort_by_key(|s| s.first_sequence);
+        Ok(segments)
+    }
+
+    /// Reads the messages whose sequence number is within `[from, to]` and which are accepted by
+    /// `predicate`, in sequence order, from the segments of both formats.
+    pub fn read<F>(
+        &self,
+        from: u64,
+        to: u64,
+        mut predicate: F,
+    ) -> Result<Vec<BroadcastFeedMessage>, ArchiveError>
+    where
+        F: FnMut(&BroadcastFeedMessage) -> bool,
+    {
+        let segments = self.segments()?;
+        let mut result = Vec::new();
+
+        for (i, segment) in segments.iter().enumerate() {
+            if segment.first_sequence > to {
+                break;
+            }
+            // The next segment starts at or before `from`, so this one can't contain it.
+            if let Some(next) = segments.get(i + 1) {
+                if next.first_sequence <= from {
+                    continue;
+                }
+            }
+
+            read_segment(&segment.path, |msg| {
+                if (from..=to).contains(&msg.sequence_number) && predicate(&msg) {
+                    result.push(msg);
+                }
+            })?;
+        }
+
+        #[cfg(feature = "zstd")]
+        {
+            let journal = journal::Journal::open(&self.dir)?.read(from, to, &mut predicate)?;
+            if !journal.is_empty() {
+                result.extend(journal);
+                result.sort_by_key(|msg| msg.sequence_number);
+            }
+        }
+        Ok(result)
+    }
+}
+
+/// Reads every message of a segment file, calling `f` for each of them.
+pub fn read_segment<F>(path: &Path, mut f: F) -> Result<(), ArchiveError>
+where
+    F: FnMut(BroadcastFeedMessage),
+{
+    let reader = BufReader::new(File::open(path)?);
+    for line in reader.lines() {
+        let line = line?;
+        if line.is_empty() {
+            continue;
+        }
+        f(serde_json::from_str(&line)?);
+    }
+    Ok(())
+}
+
+/// Reads the first message of a segment file, if any.
+pub(crate) fn first_message(path: &Path) -> Result<Option<BroadcastFeedMessage>, ArchiveError> {
+    let reader = BufReader::new(File::open(path)?);
+    for line in reader.lines() {
+        let line = line?;
+        if !line.is_empty() {
+            return Ok(Some(serde_json::from_str(&line)?));
+        }
+    }
+    Ok(None)
+}
+
+fn segment_name(first_sequence: u64) -> String {
+    format!("{:020}.{}", first_sequence, SEGMENT_EXTENSION)
+}
+
+fn segment_path(dir: &Path, first_sequence: u64) -> PathBuf {
+    dir.join(segment_name(first_sequence))
+}
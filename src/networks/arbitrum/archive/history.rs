@@ -0,0 +1,278 @@
+//! Queries over recorded feeds, for backtesting against historical sequencer data.
+
+#[cfg(feature = "zstd")]
+use crate::networks::arbitrum::archive::journal::{self, Journal};
+use crate::networks::arbitrum::{
+    archive::{self, Archive, Segment},
+    errors::ArchiveError,
+    message::{FeedMessage, FeedTransaction},
+    provenance::{Origin, Provenance},
+    store::QueryRange,
+    types::BroadcastFeedMessage,
+};
+use std::{collections::VecDeque, path::PathBuf, vec};
+
+/// The format of a recorded segment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    /// A JSON lines segment written by `ArchiveWriter`.
+    Jsonl,
+    /// A compressed segment written by `JournalWriter`.
+    #[cfg(feature = "zstd")]
+    Journal,
+}
+
+/// A directory of recorded segments, queried by sequence number or timestamp range.
+///
+/// Both the JSON lines segments of `ArchiveWriter` and, with the `zstd` feature, the journal
+/// segments of `JournalWriter` are read. Queries stream their results: segments are only read
+/// once the iterator reaches them, and reading stops at the end of the range.
+///
+/// # Example
+///
+/// ```no_run
+/// use sequencer_feed_reader::networks::arbitrum::{
+///     archive::history::FeedArchive, store::QueryRange,
+/// };
+///
+/// let archive = FeedArchive::open("recordings").unwrap();
+/// for tx in archive.transactions(QueryRange::Timestamp(1_700_000_000..=1_700_003_600)).unwrap() {
+///     let tx = tx.unwrap();
+///     println!("{} {:?} {:?}", tx.sequence_number, tx.tx.hash, tx.tx.to);
+/// }
+/// ```
+pub struct FeedArchive {
+    dir: PathBuf,
+}
+
+impl FeedArchive {
+    pub fn open(dir: impl Into<PathBuf>) -> Result<Self, ArchiveError> {
+        let dir = dir.into();
+        if !dir.is_dir() {
+            return Err(ArchiveError::NotADirectory(dir));
+        }
+        Ok(Self { dir })
+    }
+
+    /// Returns the messages within `range`, decoded, in sequence order.
+    ///
+    /// The messages are reported as replayed in their provenance.
+    ///
+    /// # Returns
+    ///
+    /// An iterator over the messages, ending after the first error, or an `ArchiveError` if the
+    /// segments could not be listed.
+    pub fn messages(&self, range: QueryRange) -> Result<ArchiveMessages, ArchiveError> {
+        let segments = self.segments()?;
+
+        // Segments are ordered by both sequence number and timestamp: the range starts in the
+        // last segment starting before it.
+        let (mut low, mut high) = (0, segments.len());
+        while low < high {
+            let mid = (low + high) / 2;
+            if starts_before(&segments[mid], &range)? {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        let start = low.saturating_sub(1);
+
+        Ok(ArchiveMessages {
+            segments: segments.into_iter().skip(start).collect(),
+            current: Vec::new().into_iter(),
+            range,
+            done: false,
+        })
+    }
+
+    /// Returns the decoded transactions of the messages within `range`, in sequence order.
+    pub fn transactions(
+        &self,
+        range: QueryRange,
+    ) -> Result<impl Iterator<Item = Result<FeedTransaction, ArchiveError>>, ArchiveError> {
+        Ok(self.messages(range)?.flat_map(|msg| match msg {
+            Ok(msg) => msg.transactions().into_iter().map(Ok).collect(),
+            Err(e) => vec![Err(e)],
+        }))
+    }
+
+    /// Lists the segments of both formats, ordered by their first sequence number.
+    fn segments(&self) -> Result<Vec<(Segment, Format)>, ArchiveError> {
+        let mut segments: Vec<_> = Archive::open(&self.dir)?
+            .segments()?
+            .into_iter()
+            .map(|segment| (segment, Format::Jsonl))
+            .collect();
+        #[cfg(feature = "zstd")]
+        segments.extend(
+            Journal::open(&self.dir)?
+                .segments()?
+                .into_iter()
+                .map(|segment| (segment, Format::Journal)),
+        );
+
+        segments.sort_by_key(|(segment, _)| segment.first_sequence);
+        Ok(segments)
+    }
+}
+
+/// Returns `true` if `segment` starts before `range`, so that the segments before it can't hold
+/// any message of the range.
+fn starts_before(
+    (segment, format): &(Segment, Format),
+    range: &QueryRange,
+) -> Result<bool, ArchiveError> {
+    Ok(match range {
+        QueryRange::Sequence(range) => segment.first_sequence <= *range.start(),
+        // Several messages may share a timestamp, so a segment starting at the start of the range
+        // may be preceded by messages of the range.
+        QueryRange::Timestamp(range) => {
+            first_timestamp(segment, *format)?.is_none_or(|timestamp| timestamp < *range.start())
+        }
+    })
+}
+
+fn first_timestamp(segment: &Segment, format: Format) -> Result<Option<u64>, ArchiveError> {
+    match format {
+        Format::Jsonl => {
+            Ok(archive::first_message(&segment.path)?
+                .map(|msg| msg.message.message.header.timestamp))
+        }
+        #[cfg(feature = "zstd")]
+        Format::Journal => Ok(journal::read_index(&segment.path)?
+            .first()
+            .map(|entry| entry.first_timestamp)),
+    }
+}
+
+fn read(segment: &Segment, format: Format) -> Result<Vec<BroadcastFeedMessage>, ArchiveError> {
+    let mut messages = Vec::new();
+    match format {
+        Format::Jsonl => archive::read_segment(&segment.path, |msg| messages.push(msg))?,
+        #[cfg(feature = "zstd")]
+        Format::Journal => journal::read_journal_segment(&segment.path, |msg| messages.push(msg))?,
+    }
+    Ok(messages)
+}
+
+/// The iterator of `FeedArchive::messages`, reading one segment at a time.
+pub struct ArchiveMessages {
+    segments: VecDeque<(Segment, Format)>,
+    current: vec::IntoIter<BroadcastFeedMessage>,
+    range: QueryRange,
+    done: bool,
+}
+
+impl ArchiveMessages {
+    /// Returns `true` if `msg` and every message after it are past the end of the range.
+    fn is_past(&self, msg: &BroadcastFeedMessage) -> bool {
+        match &self.range {
+            QueryRange::Sequence(range) => msg.sequence_number > *range.end(),
+            QueryRange::Timestamp(range) => msg.message.message.header.timestamp > *range.end(),
+        }
+    }
+}
+
+impl Iterator for ArchiveMessages {
+    type Item = Result<FeedMessage, ArchiveError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            let Some(message) = self.current.next() else {
+                let (segment, format) = self.segments.pop_front()?;
+                match read(&segment, format) {
+                    Ok(messages) => self.current = messages.into_iter(),
+                    Err(e) => {
+                        self.done = true;
+                        return Some(Err(e));
+                    }
+                }
+                continue;
+            };
+
+            if self.is_past(&message) {
+                self.done = true;
+            } else if self.range.contains(&message) {
+                return Some(Ok(FeedMessage {
+                    decoded: message.message.message.try_decode(),
+                    message,
+                    provenance: Provenance::historical(0, Origin::Replay),
+                }));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::networks::arbitrum::{archive::ArchiveWriter, fixtures::message_with};
+    use ethers::{
+        signers::{LocalWallet, Signer},
+        types::{transaction::eip2718::TypedTransaction, TransactionRequest, H160},
+    };
+    use std::{env, fs};
+
+    #[test]
+    fn streams_ranges_of_recorded_messages() {
+        let dir = env::temp_dir().join(format!("sfr-history-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        let wallet = "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318"
+            .parse::<LocalWallet>()
+            .unwrap()
+            .with_chain_id(42161u64);
+        let tx: TypedTransaction = TransactionRequest::new()
+            .to(H160::repeat_byte(0x11))
+            .nonce(1u64)
+            .gas(21_000u64)
+            .gas_price(1u64)
+            .chain_id(42161u64)
+            .into();
+        let mut signed_tx = vec![4];
+        signed_tx.extend_from_slice(&tx.rlp_signed(&wallet.sign_transaction_sync(&tx).unwrap()));
+
+        // Two messages per second, so timestamps are shared across segment boundaries.
+        let mut writer = ArchiveWriter::new(&dir, 5).unwrap();
+        for seq in 0..30 {
+            writer
+                .append(&message_with(seq, 1_000 + seq / 2, signed_tx.clone()))
+                .unwrap();
+        }
+        writer.close().unwrap();
+
+        let archive = FeedArchive::open(&dir).unwrap();
+        let seqs = |range| -> Vec<u64> {
+            archive
+                .messages(range)
+                .unwrap()
+                .map(|msg| msg.unwrap().sequence_number())
+                .collect()
+        };
+        assert_eq!(
+            seqs(QueryRange::Sequence(7..=12)),
+            (7..=12).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            seqs(QueryRange::Timestamp(1_002..=1_004)),
+            (4..=9).collect::<Vec<_>>()
+        );
+        assert_eq!(seqs(QueryRange::Sequence(40..=50)), Vec::<u64>::new());
+
+        let txs: Vec<_> = archive
+            .transactions(QueryRange::Sequence(28..=u64::MAX))
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(txs.len(), 2);
+        assert_eq!(txs[0].tx.from, wallet.address());
+        assert_eq!(
+            (txs[1].sequence_number, txs[1].header.timestamp),
+            (29, 1_014)
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
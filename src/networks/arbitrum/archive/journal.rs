@@ -0,0 +1,507 @@
+//! A compact, binary alternative to the JSON lines segments of `ArchiveWriter`.
+//!
+//! A journal segment is a sequence of blocks, each holding up to `block_size` messages and
+//! compressed independently with zstd. On disk, a block is its compressed length as a
+//! little-endian `u32` followed by the compressed bytes. Once decompressed, it holds its
+//! messages as JSON, each prefixed by its length as a little-endian `u32`.
+//!
+//! Every segment has an index next to it, listing for each block the sequence number and
+//! timestamp of its first message and its offset in the segment, as three little-endian `u64`.
+//! Reads locate the blocks of a range in the index and only decompress those.
+
+use crate::networks::arbitrum::{
+    archive::Segment,
+    errors::ArchiveError,
+    types::{BroadcastFeedMessage, Root},
+};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+const SEGMENT_EXTENSION: &str = "journal";
+const INDEX_EXTENSION: &str = "idx";
+const INDEX_ENTRY_SIZE: usize = 24;
+
+/// Records feed messages to disk as a directory of zstd-compressed journal segments.
+///
+/// Each segment holds at most `segment_size` messages and is named after the sequence number of
+/// its first message. A segment that already exists, e.g. after a restart, is appended to, past
+/// its last complete block.
+pub struct JournalWriter {
+    dir: PathBuf,
+    segment_size: usize,
+    block_size: usize,
+    level: i32,
+    current: Option<OpenJournal>,
+}
+
+/// The segment being written.
+struct OpenJournal {
+    writer: BufWriter<File>,
+    index: BufWriter<File>,
+    offset: u64,
+    messages: usize,
+    block: Vec<u8>,
+    block_messages: usize,
+    block_first: IndexEntry,
+}
+
+/// An entry of a segment index, locating a block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndexEntry {
+    /// The sequence number of the first message of the block.
+    pub first_sequence: u64,
+    /// The timestamp of the first message of the block.
+    pub first_timestamp: u64,
+    /// The offset of the block in the segment.
+    pub offset: u64,
+}
+
+impl IndexEntry {
+    fn to_bytes(self) -> [u8; INDEX_ENTRY_SIZE] {
+        let mut bytes = [0; INDEX_ENTRY_SIZE];
+        bytes[..8].copy_from_slice(&self.first_sequence.to_le_bytes());
+        bytes[8..16].copy_from_slice(&self.first_timestamp.to_le_bytes());
+        bytes[16..].copy_from_slice(&self.offset.to_le_bytes());
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Self {
+        let word = |i: usize| u64::from_le_bytes(bytes[i..i + 8].try_into().unwrap());
+        Self {
+            first_sequence: word(0),
+            first_timestamp: word(8),
+            offset: word(16),
+        }
+    }
+}
+
+impl JournalWriter {
+    /// Creates a new writer recording into `dir`, creating the directory if needed.
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - The directory the segments are written to.
+    /// * `segment_size` - The maximum number of messages per segment.
+    pub fn new(dir: impl Into<PathBuf>, segment_size: usize) -> Result<Self, ArchiveError> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+
+        Ok(Self {
+            dir,
+            segment_size: segment_size.max(1),
+            block_size: 64,
+            level: zstd::DEFAULT_COMPRESSION_LEVEL,
+            current: None,
+        })
+    }
+
+    /// Sets the maximum number of messages per compressed block, 64 by default.
+    ///
+    /// Larger blocks compress better, smaller blocks make range reads decompress less.
+    pub fn with_block_size(mut self, block_size: usize) -> Self {
+        self.block_size = block_size.max(1);
+        self
+    }
+
+    /// Sets the zstd compression level, zstd's default level by default.
+    pub fn with_compression_level(mut self, level: i32) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Appends a message to the current block, rolling over to a new segment when full.
+    pub fn append(&mut self, msg: &BroadcastFeedMessage) -> Result<(), ArchiveError> {
+        if self
+            .current
+            .as_ref()
+            .is_some_and(|segment| segment.messages >= self.segment_size)
+        {
+            self.roll()?;
+        }
+
+        let entry = IndexEntry {
+            first_sequence: msg.sequence_number,
+            first_timestamp: msg.message.message.header.timestamp,
+            offset: 0,
+        };
+        let segment = match &mut self.current {
+            Some(segment) => segment,
+            None => {
+                let path = segment_path(&self.dir, msg.sequence_number);
+                let (writer, index, offset) = open_for_append(&path)?;
+                self.current.insert(OpenJournal {
+                    writer: BufWriter::new(writer),
+                    index: BufWriter::new(index),
+                    offset,
+                    messages: 0,
+                    block: Vec::new(),
+                    block_messages: 0,
+                    block_first: entry,
+                })
+            }
+        };
+
+        if segment.block_messages == 0 {
+            segment.block_first = entry;
+        }
+        let json = serde_json::to_vec(msg)?;
+        segment
+            .block
+            .extend_from_slice(&(json.len() as u32).to_le_bytes());
+        segment.block.extend_from_slice(&json);
+        segment.block_messages += 1;
+        segment.messages += 1;
+
+        if segment.block_messages >= self.block_size {
+            segment.write_block(self.level)?;
+        }
+        Ok(())
+    }
+
+    /// Appends every message contained in `root`.
+    pub fn append_root(&mut self, root: &Root) -> Result<(), ArchiveError> {
+        for msg in &root.messages {
+            self.append(msg)?;
+        }
+        Ok(())
+    }
+
+    /// Compresses the pending messages into a block, even if not full, and flushes it to disk.
+    pub fn flush(&mut self) -> Result<(), ArchiveError> {
+        if let Some(segment) = &mut self.current {
+            segment.write_block(self.level)?;
+            segment.writer.flush()?;
+            segment.index.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Completes the current segment.
+    pub fn close(mut self) -> Result<(), ArchiveError> {
+        self.roll()
+    }
+
+    fn roll(&mut self) -> Result<(), ArchiveError> {
+        self.flush()?;
+        self.current = None;
+        Ok(())
+    }
+}
+
+impl OpenJournal {
+    fn write_block(&mut self, level: i32) -> Result<(), ArchiveError> {
+        if self.block_messages == 0 {
+            return Ok(());
+        }
+        let compressed = zstd::bulk::compress(&self.block, level)?;
+        self.writer
+            .write_all(&(compressed.len() as u32).to_le_bytes())?;
+        self.writer.write_all(&compressed)?;
+        // Indexed once written, so that the index never points past the blocks on disk.
+        self.index.write_all(
+            &IndexEntry {
+                offset: self.offset,
+                ..self.block_first
+            }
+            .to_bytes(),
+        )?;
+
+        self.offset += 4 + compressed.len() as u64;
+        self.block.clear();
+        self.block_messages = 0;
+        Ok(())
+    }
+}
+
+impl Drop for JournalWriter {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+/// Opens the segment at `path` and its index for appending, creating them if needed, and drops
+/// what an interrupted writer left past the last indexed block.
+///
+/// # Returns
+///
+/// The segment, its index and the offset the next block is written at.
+fn open_for_append(path: &Path) -> Result<(File, File, u64), ArchiveError> {
+    let open = |path: &Path| {
+        OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(path)
+    };
+    let mut segment = open(path)?;
+    let index = open(&path.with_extension(INDEX_EXTENSION))?;
+
+    let entries = read_index(path)?;
+    index.set_len((entries.len() * INDEX_ENTRY_SIZE) as u64)?;
+    let end = match entries.last() {
+        Some(last) => {
+            let mut len = [0; 4];
+            segment.seek(SeekFrom::Start(last.offset))?;
+            segment.read_exact(&mut len)?;
+            last.offset + 4 + u64::from(u32::from_le_bytes(len))
+        }
+        None => 0,
+    };
+    segment.set_len(end)?;
+    Ok((segment, index, end))
+}
+
+/// Read access to a directory of segments written by `JournalWriter`.
+pub struct Journal {
+    dir: PathBuf,
+}
+
+impl Journal {
+    pub fn open(dir: impl Into<PathBuf>) -> Result<Self, ArchiveError> {
+        let dir = dir.into();
+        if !dir.is_dir() {
+            return Err(ArchiveError::NotADirectory(dir));
+        }
+        Ok(Self { dir })
+    }
+
+    /// Lists the segments of the journal, ordered by their first sequence number.
+    pub fn segments(&self) -> Result<Vec<Segment>, ArchiveError> {
+        let mut segments = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some(SEGMENT_EXTENSION) {
+                continue;
+            }
+            let first_sequence = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .and_then(|s| s.parse::<u64>().ok())
+                .ok_or_else(|| ArchiveError::InvalidSegmentName(path.clone()))?;
+            segments.push(Segment {
+                first_sequence,
+                path,
+            });
+        }
+
+        segments.sort_by_key(|s| s.first_sequence);
+        Ok(segments)
+    }
+
+    /// Reads the messages whose sequence number is within `[from, to]` and which are accepted by
+    /// `predicate`, in sequence order, decompressing only the blocks that may contain them.
+    pub fn read<F>(
+        &self,
+        from: u64,
+        to: u64,
+        mut predicate: F,
+    ) -> Result<Vec<BroadcastFeedMessage>, ArchiveError>
+    where
+        F: FnMut(&BroadcastFeedMessage) -> bool,
+    {
+        let segments = self.segments()?;
+        let mut result = Vec::new();
+
+        for (i, segment) in segments.iter().enumerate() {
+            if segment.first_sequence > to {
+                break;
+            }
+            if let Some(next) = segments.get(i + 1) {
+                if next.first_sequence <= from {
+                    continue;
+                }
+            }
+
+            let index = read_index(&segment.path)?;
+            // The last block starting at or before `from` is the first that may contain it.
+            let start = index
+                .partition_point(|entry| entry.first_sequence <= from)
+                .saturating_sub(1);
+            let Some(first) = index.get(start) else {
+                continue;
+            };
+
+            let mut reader = BufReader::new(File::open(&segment.path)?);
+            reader.seek(SeekFrom::Start(first.offset))?;
+            for entry in &index[start..] {
+                if entry.first_sequence > to {
+                    break;
+                }
+                let Some(block) = read_block(&mut reader, &segment.path)? else {
+                    break;
+                };
+                for_each_record(&block, &segment.path, |msg| {
+                    if (from..=to).contains(&msg.sequence_number) && predicate(&msg) {
+                        result.push(msg);
+                    }
+                })?;
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+/// Reads the index of a journal segment.
+pub fn read_index(segment: &Path) -> Result<Vec<IndexEntry>, ArchiveError> {
+    let bytes = fs::read(segment.with_extension(INDEX_EXTENSION))?;
+    // A trailing partial entry is left by a writer interrupted while indexing.
+    Ok(bytes
+        .chunks_exact(INDEX_ENTRY_SIZE)
+        .map(IndexEntry::from_bytes)
+        .collect())
+}
+
+/// Reads every message of a journal segment, calling `f` for each of them.
+///
+/// A block truncated by an interrupted writer ends the segment.
+pub fn read_journal_segment<F>(path: &Path, mut f: F) -> Result<(), ArchiveError>
+where
+    F: FnMut(BroadcastFeedMessage),
+{
+    let mut reader = BufReader::new(File::open(path)?);
+    while let Some(block) = read_block(&mut reader, path)? {
+        for_each_record(&block, path, &mut f)?;
+    }
+    Ok(())
+}
+
+/// Reads and decompresses the next block, or `None` at the end of the segment.
+fn read_block(reader: &mut impl Read, path: &Path) -> Result<Option<Vec<u8>>, ArchiveError> {
+    let mut len = [0; 4];
+    match reader.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+    let mut compressed = vec![0; u32::from_le_bytes(len) as usize];
+    match reader.read_exact(&mut compressed) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+    zstd::stream::decode_all(&*compressed)
+        .map(Some)
+        .map_err(|_| ArchiveError::CorruptJournal(path.to_path_buf()))
+}
+
+fn for_each_record<F>(mut block: &[u8], path: &Path, mut f: F) -> Result<(), ArchiveError>
+where
+    F: FnMut(BroadcastFeedMessage),
+{
+    while !block.is_empty() {
+        let corrupt = || ArchiveError::CorruptJournal(path.to_path_buf());
+        let (len, rest) = block.split_at_checked(4).ok_or_else(corrupt)?;
+        let len = u32::from_le_bytes(len.try_into().unwrap()) as usize;
+        let (json, rest) = rest.split_at_checked(len).ok_or_else(corrupt)?;
+        f(serde_json::from_slice(json)?);
+        block = rest;
+    }
+    Ok(())
+}
+
+fn segment_path(dir: &Path, first_sequence: u64) -> PathBuf {
+    dir.join(format!("{:020}.{}", first_sequence, SEGMENT_EXTENSION))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::networks::arbitrum::{
+        archive::{Archive, ArchiveWriter},
+        fixtures::message_with,
+    };
+    use std::env;
+
+    #[test]
+    fn reads_ranges_through_the_index() {
+        let dir = env::temp_dir().join(format!("sfr-journal-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        let mut writer = JournalWriter::new(dir.join("journal"), 10)
+            .unwrap()
+            .with_block_size(4);
+        let mut jsonl = ArchiveWriter::new(dir.join("jsonl"), 10).unwrap();
+        for seq in 0..25 {
+            let msg = message_with(seq, 1_700_000_000 + seq, vec![0xaa; 64]);
+            writer.append(&msg).unwrap();
+            jsonl.append(&msg).unwrap();
+        }
+        writer.close().unwrap();
+        jsonl.close().unwrap();
+
+        let journal = Journal::open(dir.join("journal")).unwrap();
+        let firsts: Vec<_> = journal
+            .segments()
+            .unwrap()
+            .iter()
+            .map(|segment| segment.first_sequence)
+            .collect();
+        assert_eq!(firsts, [0, 10, 20]);
+
+        let index = read_index(&segment_path(&dir.join("journal"), 10)).unwrap();
+        let blocks: Vec<_> = index.iter().map(|entry| entry.first_sequence).collect();
+        assert_eq!(blocks, [10, 14, 18]);
+        assert_eq!(index[1].first_timestamp, 1_700_000_014);
+
+        let read: Vec<_> = journal
+            .read(7, 13, |_| true)
+            .unwrap()
+            .iter()
+            .map(|msg| msg.sequence_number)
+            .collect();
+        assert_eq!(read, (7..=13).collect::<Vec<_>>());
+
+        let mut all = Vec::new();
+        read_journal_segment(&segment_path(&dir.join("journal"), 20), |msg| all.push(msg)).unwrap();
+        assert_eq!(all.len(), 5);
+        assert_eq!(all[0], message_with(20, 1_700_000_020, vec![0xaa; 64]));
+
+        let size = |name: &str, ext: &str| -> u64 {
+            fs::read_dir(dir.join(name))
+                .unwrap()
+                .map(|entry| entry.unwrap().path())
+                .filter(|path| path.extension().is_some_and(|e| e == ext))
+                .map(|path| fs::metadata(path).unwrap().len())
+                .sum()
+        };
+        assert!(size("journal", SEGMENT_EXTENSION) < size("jsonl", "jsonl") / 2);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn appends_to_existing_segments() {
+        let dir = env::temp_dir().join(format!("sfr-journal-append-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let write = |sequence_numbers: &[u64]| {
+            let mut writer = JournalWriter::new(&dir, 100).unwrap().with_block_size(2);
+            for &seq in sequence_numbers {
+                writer.append(&message_with(seq, 0, Vec::new())).unwrap();
+            }
+            writer.close().unwrap();
+        };
+        write(&[10, 11, 12]);
+
+        // A block interrupted while being written, after the last indexed one.
+        let path = segment_path(&dir, 10);
+        let mut segment = OpenOptions::new().append(true).open(&path).unwrap();
+        segment.write_all(&[0xff, 0, 0, 0, 1, 2]).unwrap();
+        drop(segment);
+
+        // Restarted from the same sequence number.
+        write(&[10, 13]);
+        let mut read = Vec::new();
+        read_journal_segment(&path, |msg| read.push(msg.sequence_number)).unwrap();
+        assert_eq!(read, [10, 11, 12, 10, 13]);
+        assert_eq!(read_index(&path).unwrap().len(), 3);
+        // Archives read journal segments too.
+        let archived = Archive::open(&dir).unwrap().read(11, 13, |_| true).unwrap();
+        assert_eq!(archived.len(), 3);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,212 @@
+//! How `ArchiveWriter` splits the archive into segments and names them.
+
+use crate::networks::arbitrum::errors::ArchiveError;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+const MANIFEST_EXTENSION: &str = "manifest.json";
+
+/// A time window segments are rolled over at, in UTC, according to the timestamps of the
+/// messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeWindow {
+    Hourly,
+    Daily,
+}
+
+impl TimeWindow {
+    fn seconds(&self) -> u64 {
+        match self {
+            TimeWindow::Hourly => 3600,
+            TimeWindow::Daily => 86400,
+        }
+    }
+
+    /// Returns the start of the window containing `timestamp`, in seconds.
+    pub fn start(&self, timestamp: u64) -> u64 {
+        timestamp - timestamp % self.seconds()
+    }
+
+    /// Formats the window starting at `start`, as `2023-10-16T13` or `2023-10-16`.
+    pub fn label(&self, start: u64) -> String {
+        match self {
+            TimeWindow::Hourly => format!("{}T{:02}", date(start), start % 86400 / 3600),
+            TimeWindow::Daily => date(start),
+        }
+    }
+}
+
+/// A template segment names are rendered from, without the extension.
+///
+/// The template may contain the following placeholders, and must contain `{first}` so that names
+/// are unique:
+///
+/// * `{chain_id}` - The chain ID of the network.
+/// * `{first}` and `{last}` - The first and last sequence numbers of the segment, zero padded.
+/// * `{window}` - The time window of the segment, or the date of its first message if the writer
+///   doesn't roll over by time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SegmentNaming {
+    template: String,
+    chain_id: u64,
+}
+
+impl SegmentNaming {
+    /// # Arguments
+    ///
+    /// * `template` - The template, e.g. `arb1-{chain_id}-{window}-{first}-{last}`.
+    /// * `chain_id` - The value of `{chain_id}`.
+    pub fn new(template: impl Into<String>, chain_id: u64) -> Result<Self, ArchiveError> {
+        let template = template.into();
+        if !template.contains("{first}") || template.contains(['/', '\\']) {
+            return Err(ArchiveError::InvalidTemplate(template));
+        }
+        Ok(Self { template, chain_id })
+    }
+
+    /// Renders the name of a segment.
+    pub fn render(&self, first: u64, last: u64, window: &str) -> String {
+        self.template
+            .replace("{chain_id}", &self.chain_id.to_string())
+            .replace("{first}", &format!("{:020}", first))
+            .replace("{last}", &format!("{:020}", last))
+            .replace("{window}", window)
+    }
+}
+
+/// A segment listed in a `DayManifest`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// The file name of the segment.
+    pub name: String,
+    pub first_sequence: u64,
+    pub last_sequence: u64,
+    pub messages: usize,
+    /// The timestamp of the first message, in seconds.
+    pub first_timestamp: u64,
+    /// The timestamp of the last message, in seconds.
+    pub last_timestamp: u64,
+}
+
+/// The index of the segments started on a day, so that batch jobs can locate data by time
+/// without scanning the archive.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DayManifest {
+    /// The day, as `2023-10-16`.
+    pub date: String,
+    /// The completed segments, in sequence order.
+    pub segments: Vec<ManifestEntry>,
+}
+
+impl DayManifest {
+    /// Returns the path of the manifest of `date` in `dir`.
+    pub fn path(dir: &Path, date: &str) -> PathBuf {
+        dir.join(format!("{}.{}", date, MANIFEST_EXTENSION))
+    }
+
+    /// Loads the manifest of `date` from `dir`, or returns `None` if no segment was completed on
+    /// that day.
+    pub fn load(dir: &Path, date: &str) -> Result<Option<Self>, ArchiveError> {
+        match fs::read(Self::path(dir, date)) {
+            Ok(data) => Ok(Some(serde_json::from_slice(&data)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Adds a completed segment to the manifest of the day of its first message, replacing the
+    /// manifest atomically.
+    pub(crate) fn record(dir: &Path, entry: ManifestEntry) -> Result<(), ArchiveError> {
+        let date = date(entry.first_timestamp);
+        let mut manifest = Self::load(dir, &date)?.unwrap_or_else(|| DayManifest {
+            date: date.clone(),
+            segments: Vec::new(),
+        });
+        manifest.segments.retain(|s| s.name != entry.name);
+        manifest.segments.push(entry);
+        manifest.segments.sort_by_key(|s| s.first_sequence);
+
+        let path = Self::path(dir, &date);
+        let tmp = dir.join(format!(".{}.tmp", date));
+        fs::write(&tmp, serde_json::to_vec_pretty(&manifest)?)?;
+        fs::rename(tmp, path)?;
+        Ok(())
+    }
+}
+
+/// Formats the UTC date of a timestamp in seconds, as `2023-10-16`.
+pub fn date(timestamp: u64) -> String {
+    // Converts days since the epoch to a civil date, see
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let days = (timestamp / 86400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::networks::arbitrum::{
+        archive::{Archive, ArchiveWriter},
+        fixtures::message_with,
+    };
+    use std::env;
+
+    #[test]
+    fn rolls_hourly_with_templated_names_and_manifests() {
+        assert_eq!(date(0), "1970-01-01");
+        assert_eq!(date(1_709_164_800), "2024-02-29");
+
+        let dir = env::temp_dir().join(format!("sfr-layout-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let naming = SegmentNaming::new("{chain_id}-{window}-{first}-{last}", 42161).unwrap();
+        let mut writer = ArchiveWriter::new(&dir, 100)
+            .unwrap()
+            .with_time_window(TimeWindow::Hourly)
+            .with_naming(naming);
+
+        // 2023-11-14T22:13:20, 22:30, 23:00 and 23:30.
+        let timestamps = [1_700_000_000, 1_700_001_000, 1_700_002_800, 1_700_004_600];
+        for (seq, timestamp) in (5..).zip(timestamps) {
+            writer
+                .append(&message_with(seq, timestamp, Vec::new()))
+                .unwrap();
+        }
+        writer.close().unwrap();
+
+        let manifest = DayManifest::load(&dir, "2023-11-14").unwrap().unwrap();
+        let names: Vec<_> = manifest.segments.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "42161-2023-11-14T22-00000000000000000005-00000000000000000006.jsonl",
+                "42161-2023-11-14T23-00000000000000000007-00000000000000000008.jsonl"
+            ]
+        );
+        assert_eq!(manifest.segments[1].first_timestamp, 1_700_002_800);
+        assert_eq!(manifest.segments[1].messages, 2);
+
+        let archive = Archive::open(&dir).unwrap();
+        let firsts: Vec<_> = archive
+            .segments()
+            .unwrap()
+            .iter()
+            .map(|s| s.first_sequence)
+            .collect();
+        assert_eq!(firsts, [5, 7]);
+        assert_eq!(archive.read(6, 7, |_| true).unwrap().len(), 2);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
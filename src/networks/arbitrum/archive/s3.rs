@@ -0,0 +1,78 @@
+use super::store::ArchiveStore;
+use crate::networks::arbitrum::errors::ArchiveError;
+use async_trait::async_trait;
+use futures::TryStreamExt;
+use object_store::{
+    aws::{AmazonS3, AmazonS3Builder},
+    path::Path,
+    ObjectStore,
+};
+
+/// Keeps segments in an S3-compatible bucket, under a key prefix.
+pub struct S3ArchiveStore {
+    client: AmazonS3,
+    prefix: Path,
+}
+
+impl S3ArchiveStore {
+    /// Creates a store using an existing client.
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - The client of the bucket.
+    /// * `prefix` - The key prefix segments are stored under, e.g. `arb1/archive`.
+    pub fn new(client: AmazonS3, prefix: &str) -> Self {
+        Self {
+            client,
+            prefix: Path::from(prefix),
+        }
+    }
+
+    /// Creates a store for `bucket`, configured from the standard `AWS_*` environment variables.
+    ///
+    /// S3-compatible services are reached by setting `AWS_ENDPOINT`, and `AWS_ALLOW_HTTP` for
+    /// plain HTTP endpoints.
+    pub fn from_env(bucket: &str, prefix: &str) -> Result<Self, ArchiveError> {
+        let client = AmazonS3Builder::from_env()
+            .with_bucket_name(bucket)
+            .build()?;
+        Ok(Self::new(client, prefix))
+    }
+
+    fn path(&self, name: &str) -> Path {
+        self.prefix.child(name)
+    }
+}
+
+#[async_trait]
+impl ArchiveStore for S3ArchiveStore {
+    async fn put(&self, name: &str, data: Vec<u8>) -> Result<(), ArchiveError> {
+        self.client.put(&self.path(name), data.into()).await?;
+        Ok(())
+    }
+
+    async fn get(&self, name: &str) -> Result<Option<Vec<u8>>, ArchiveError> {
+        match self.client.get(&self.path(name)).await {
+            Ok(result) => Ok(Some(result.bytes().await?.to_vec())),
+            Err(object_store::Error::NotFound { .. }) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn list(&self) -> Result<Vec<String>, ArchiveError> {
+        let objects: Vec<_> = self.client.list(Some(&self.prefix)).try_collect().await?;
+        let mut names: Vec<String> = objects
+            .into_iter()
+            .filter_map(|meta| meta.location.filename().map(str::to_string))
+            .collect();
+        names.sort();
+        Ok(names)
+    }
+
+    async fn delete(&self, name: &str) -> Result<(), ArchiveError> {
+        match self.client.delete(&self.path(name)).await {
+            Ok(()) | Err(object_store::Error::NotFound { .. }) => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use crate::networks::arbitrum::errors::ArchiveError;
+use async_trait::async_trait;
+use std::{fs, io, path::PathBuf};
+
+/// A place archive segments are kept, such as a local directory or an object store.
+///
+/// Segments are opaque blobs identified by a name, e.g. the file name of a segment written by
+/// `ArchiveWriter`. Implementations must uphold the following semantics, checked by
+/// `conformance::archive_store`:
+///
+/// * `put` replaces any segment of the same name, and is atomic: `get` never observes a partially
+///   written segment.
+/// * `get` returns `None` for unknown segments.
+/// * `list` returns the names of every stored segment, sorted.
+/// * `delete` is idempotent: deleting an unknown segment succeeds.
+/// * Segments are durable: another instance opened on the same storage sees them.
+#[async_trait]
+pub trait ArchiveStore: Send + Sync {
+    async fn put(&self, name: &str, data: Vec<u8>) -> Result<(), ArchiveError>;
+
+    async fn get(&self, name: &str) -> Result<Option<Vec<u8>>, ArchiveError>;
+
+    async fn list(&self) -> Result<Vec<String>, ArchiveError>;
+
+    async fn delete(&self, name: &str) -> Result<(), ArchiveError>;
+}
+
+/// Keeps segments as files of a local directory.
+#[derive(Debug, Clone)]
+pub struct LocalArchiveStore {
+    dir: PathBuf,
+}
+
+impl LocalArchiveStore {
+    /// Opens the store at `dir`, creating the directory if needed.
+    pub fn new(dir: impl Into<PathBuf>) -> Result<Self, ArchiveError> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    fn path(&self, name: &str) -> Result<PathBuf, ArchiveError> {
+        if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+            return Err(ArchiveError::InvalidSegmentName(name.into()));
+        }
+        Ok(self.dir.join(name))
+    }
+}
+
+/// Runs blocking file system work off the async runtime.
+async fn blocking<T, F>(f: F) -> Result<T, ArchiveError>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, ArchiveError> + Send + 'static,
+{
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(io::Error::other)?
+}
+
+#[async_trait]
+impl ArchiveStore for LocalArchiveStore {
+    async fn put(&self, name: &str, data: Vec<u8>) -> Result<(), ArchiveError> {
+        let path = self.path(name)?;
+        // Hidden files are never listed, so a partially written segment stays invisible.
+        let tmp = self.dir.join(format!(".{}.tmp", name));
+        blocking(move || {
+            fs::write(&tmp, data)?;
+            fs::rename(&tmp, &path)?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn get(&self, name: &str) -> Result<Option<Vec<u8>>, ArchiveError> {
+        let path = self.path(name)?;
+        blocking(move || match fs::read(path) {
+            Ok(data) => Ok(Some(data)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        })
+        .await
+    }
+
+    async fn list(&self) -> Result<Vec<String>, ArchiveError> {
+        let dir = self.dir.clone();
+        blocking(move || {
+            let mut names = Vec::new();
+            for entry in fs::read_dir(dir)? {
+                let entry = entry?;
+                let name = entry.file_name().to_string_lossy().into_owned();
+                if entry.file_type()?.is_file() && !name.starts_with('.') {
+                    names.push(name);
+                }
+            }
+            names.sort();
+            Ok(names)
+        })
+        .await
+    }
+
+    async fn delete(&self, name: &str) -> Result<(), ArchiveError> {
+        let path = self.path(name)?;
+        blocking(move || match fs::remove_file(path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        })
+        .await
+    }
+}
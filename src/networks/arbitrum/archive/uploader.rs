@@ -0,0 +1,220 @@
+use super::{store::ArchiveStore, Archive, Segment};
+use crate::networks::arbitrum::{errors::ArchiveError, sinks::RetryPolicy};
+use ethers::utils::keccak256;
+use log::*;
+use std::{
+    collections::HashSet,
+    fs,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::task::JoinHandle;
+
+/// How often a spawned uploader looks for completed segments by default.
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The outcome of an upload pass.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UploadReport {
+    /// The segments uploaded and verified.
+    pub uploaded: Vec<String>,
+    /// The segments that failed to upload after every retry. They are retried on the next pass.
+    pub failed: Vec<String>,
+    /// The local segments removed after their upload was confirmed.
+    pub removed: Vec<String>,
+}
+
+/// Ships the completed segments of a local archive to an `ArchiveStore`, such as an
+/// `S3ArchiveStore`.
+///
+/// Every segment but the one `ArchiveWriter` is appending to is completed. Uploads are verified by
+/// reading the segment back and comparing its checksum, and only verified segments are removed
+/// locally, keeping the most recent `keep_local` ones so that the local archive still serves
+/// recent history.
+pub struct SegmentUploader<S> {
+    dir: PathBuf,
+    store: Arc<S>,
+    retry: RetryPolicy,
+    keep_local: usize,
+    interval: Duration,
+    /// The segments uploaded by this uploader, so that kept segments aren't uploaded again.
+    uploaded: Mutex<HashSet<String>>,
+}
+
+impl<S: ArchiveStore + 'static> SegmentUploader<S> {
+    /// # Arguments
+    ///
+    /// * `dir` - The directory `ArchiveWriter` records into.
+    /// * `store` - Where the segments are shipped to.
+    pub fn new(dir: impl Into<PathBuf>, store: S) -> Self {
+        Self {
+            dir: dir.into(),
+            store: Arc::new(store),
+            retry: RetryPolicy::default(),
+            keep_local: 0,
+            interval: DEFAULT_INTERVAL,
+            uploaded: Mutex::default(),
+        }
+    }
+
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Keeps the `keep_local` most recent completed segments locally after uploading them.
+    pub fn with_keep_local(mut self, keep_local: usize) -> Self {
+        self.keep_local = keep_local;
+        self
+    }
+
+    /// Sets how often `spawn` looks for completed segments.
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Uploads the completed segments not uploaded yet, and removes the local copies beyond
+    /// `keep_local`.
+    pub async fn upload_completed(&self) -> Result<UploadReport, ArchiveError> {
+        let mut segments = Archive::open(&self.dir)?.segments()?;
+        // The last segment is still being written.
+        segments.pop();
+
+        let mut report = UploadReport::default();
+        let removable = segments.len().saturating_sub(self.keep_local);
+        for (i, segment) in segments.iter().enumerate() {
+            let name = segment_name(segment)?;
+            let uploaded = self.uploaded.lock().unwrap().contains(&name);
+            if !uploaded {
+                match self.upload(segment, &name).await {
+                    Ok(()) => {
+                        self.uploaded.lock().unwrap().insert(name.clone());
+                        report.uploaded.push(name.clone());
+                    }
+                    Err(e) => {
+                        error!("Failed to upload archive segment {}: {}", name, e);
+                        report.failed.push(name);
+                        continue;
+                    }
+                }
+            }
+
+            if i < removable {
+                fs::remove_file(&segment.path)?;
+                self.uploaded.lock().unwrap().remove(&name);
+                report.removed.push(name);
+            }
+        }
+        Ok(report)
+    }
+
+    /// Uploads completed segments every interval, until the task is aborted.
+    pub fn spawn(self) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(self.interval);
+            loop {
+                ticker.tick().await;
+                match self.upload_completed().await {
+                    Ok(report) if !report.uploaded.is_empty() => {
+                        info!("Uploaded archive segments {:?}", report.uploaded)
+                    }
+                    Ok(_) => (),
+                    Err(e) => error!("Failed to upload archive segments: {}", e),
+                }
+            }
+        })
+    }
+
+    /// Uploads a segment and verifies it, retrying according to the retry policy.
+    async fn upload(&self, segment: &Segment, name: &str) -> Result<(), ArchiveError> {
+        let data = fs::read(&segment.path)?;
+        let checksum = keccak256(&data);
+
+        let mut attempt = 1;
+        loop {
+            let result = match self.store.put(name, data.clone()).await {
+                Ok(()) => self.verify(name, checksum).await,
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(()) => return Ok(()),
+                Err(e) if attempt >= self.retry.max_attempts => return Err(e),
+                Err(e) => {
+                    warn!(
+                        "Failed to upload archive segment {} (attempt {}): {}",
+                        name, attempt, e
+                    );
+                    tokio::time::sleep(self.retry.backoff(attempt)).await;
+                    attempt += 1;
+                }
+            }
+        }
+    }
+
+    async fn verify(&self, name: &str, checksum: [u8; 32]) -> Result<(), ArchiveError> {
+        match self.store.get(name).await? {
+            Some(stored) if keccak256(&stored) == checksum => Ok(()),
+            _ => Err(ArchiveError::ChecksumMismatch(name.to_string())),
+        }
+    }
+}
+
+fn segment_name(segment: &Segment) -> Result<String, ArchiveError> {
+    segment
+        .path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .map(str::to_string)
+        .ok_or_else(|| ArchiveError::InvalidSegmentName(segment.path.clone()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::networks::arbitrum::{
+        archive::{store::LocalArchiveStore, ArchiveWriter},
+        fixtures::message_with,
+    };
+    use std::env;
+
+    #[tokio::test]
+    async fn uploads_completed_segments_and_cleans_up() {
+        let dir = env::temp_dir().join(format!("sfr-uploader-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let archive_dir = dir.join("archive");
+
+        let mut writer = ArchiveWriter::new(&archive_dir, 2).unwrap();
+        for seq in 0..7 {
+            writer.append(&message_with(seq, 0, Vec::new())).unwrap();
+        }
+        writer.flush().unwrap();
+
+        let store = LocalArchiveStore::new(dir.join("remote")).unwrap();
+        let uploader = SegmentUploader::new(&archive_dir, store.clone()).with_keep_local(1);
+        let report = uploader.upload_completed().await.unwrap();
+
+        let names: Vec<_> = [0, 2, 4]
+            .iter()
+            .map(|seq| format!("{:020}.jsonl", seq))
+            .collect();
+        assert_eq!(report.uploaded, names);
+        assert_eq!(report.removed, names[..2]);
+        assert_eq!(store.list().await.unwrap(), names);
+        let local: Vec<_> = Archive::open(&archive_dir)
+            .unwrap()
+            .segments()
+            .unwrap()
+            .iter()
+            .map(|segment| segment.first_sequence)
+            .collect();
+        assert_eq!(local, vec![4, 6]);
+
+        // The kept segment isn't uploaded again.
+        let report = uploader.upload_completed().await.unwrap();
+        assert!(report.uploaded.is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
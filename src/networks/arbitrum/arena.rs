@@ -0,0 +1,113 @@
+//! Per-thread buffers for base64-decoding L2 messages without an allocation per message.
+//!
+//! The L2 messages of owned feed messages are decoded into a shared chunk and handed out as
+//! slices of it: a chunk is allocated once per `CHUNK_SIZE` bytes of messages instead of once per
+//! message, and freed once every message sliced from it is dropped. Messages larger than
+//! `MAX_SHARED_LEN` are copied out to an allocation of their own instead, so that they neither
+//! use up chunks nor keep them allocated. Messages decoded only to be parsed, such as those of
+//! borrowed feed messages, are decoded into a reused scratch buffer.
+//!
+//! Chunks and scratch buffers are zero-filled once, when allocated, rather than before every
+//! message.
+
+use crate::networks::arbitrum::{decoder::decode_base64_to_slice, errors::DecodeError};
+use bytes::{Bytes, BytesMut};
+use std::{cell::RefCell, mem};
+
+/// The size of the chunks L2 messages are sliced from.
+pub const CHUNK_SIZE: usize = 64 * 1024;
+
+/// L2 messages possibly decoding to more bytes than this get an allocation of their own.
+pub const MAX_SHARED_LEN: usize = CHUNK_SIZE / 8;
+
+/// Scratch buffers larger than this are not kept for reuse.
+const MAX_SCRATCH_CAPACITY: usize = 1024 * 1024;
+
+thread_local! {
+    static ARENA: RefCell<BytesMut> = RefCell::new(BytesMut::new());
+    static SCRATCH: RefCell<Vec<u8>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Decodes a base64 encoded L2 message into a slice of the current chunk of the thread, or into
+/// its own allocation if larger than `MAX_SHARED_LEN`.
+///
+/// Holding on to a small message keeps its whole chunk allocated: copy messages retained for long
+/// out of the feed, e.g. with `Bytes::copy_from_slice`.
+pub fn decode(encoded: &[u8]) -> Result<Bytes, DecodeError> {
+    let max_len = base64::decoded_len_estimate(encoded.len());
+    if max_len > MAX_SHARED_LEN {
+        return with_decoded(encoded, Bytes::copy_from_slice);
+    }
+    ARENA.with(|arena| {
+        let mut arena = arena.borrow_mut();
+        // The rest of the chunk stays zero-filled, or holds earlier writes, so it can be decoded
+        // into without filling it again.
+        if arena.len() < max_len {
+            *arena = BytesMut::zeroed(CHUNK_SIZE);
+        }
+        let len = decode_base64_to_slice(encoded, &mut arena[..max_len])?;
+        Ok(arena.split_to(len).freeze())
+    })
+}
+
+/// Decodes a base64 encoded L2 message into the scratch buffer of the thread and calls `f` with
+/// it.
+///
+/// # Returns
+///
+/// What `f` returned, or a `DecodeError` if the message is not valid base64.
+pub fn with_decoded<R>(encoded: &[u8], f: impl FnOnce(&[u8]) -> R) -> Result<R, DecodeError> {
+    // Taken out of the thread local, so that `f` may decode messages itself.
+    let mut scratch = SCRATCH.with(|scratch| mem::take(&mut *scratch.borrow_mut()));
+    let max_len = base64::decoded_len_estimate(encoded.len());
+    // Kept at its largest length, so that only newly needed bytes are zero-filled.
+    if scratch.len() < max_len {
+        scratch.resize(max_len, 0);
+    }
+
+    let result =
+        decode_base64_to_slice(encoded, &mut scratch[..max_len]).map(|len| f(&scratch[..len]));
+
+    if scratch.capacity() <= MAX_SCRATCH_CAPACITY {
+        SCRATCH.with(|cell| *cell.borrow_mut() = scratch);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::{engine::general_purpose, Engine as _};
+
+    #[test]
+    fn slices_messages_from_shared_chunks() {
+        let encode = |data: &[u8]| general_purpose::STANDARD.encode(data);
+
+        let first = decode(encode(&[1; 100]).as_bytes()).unwrap();
+        let second = decode(encode(&[2; 50]).as_bytes()).unwrap();
+        assert_eq!((&first[..], &second[..]), (&[1; 100][..], &[2; 50][..]));
+        // The second message follows the first in the same chunk.
+        assert_eq!(second.as_ptr(), first[100..].as_ptr());
+
+        // Large messages are copied out rather than sliced from the chunk.
+        let large = decode(encode(&vec![3; MAX_SHARED_LEN + 1]).as_bytes()).unwrap();
+        assert_eq!(&large[..], &vec![3; MAX_SHARED_LEN + 1][..]);
+        assert!(decode(b"AAAA!AAA").is_err());
+        let third = decode(encode(&[5; 10]).as_bytes()).unwrap();
+        assert_eq!(third.as_ptr(), second[50..].as_ptr());
+        assert_eq!(&first[..], &[1; 100][..]);
+
+        // A chunk too short for the next message is replaced by a new one.
+        let filler = CHUNK_SIZE - 160 - MAX_SHARED_LEN / 2;
+        for _ in 0..filler / (MAX_SHARED_LEN / 2) {
+            decode(encode(&vec![6; MAX_SHARED_LEN / 2]).as_bytes()).unwrap();
+        }
+        let fourth = decode(encode(&vec![7; MAX_SHARED_LEN]).as_bytes()).unwrap();
+        assert_eq!(&fourth[..], &vec![7; MAX_SHARED_LEN][..]);
+
+        let sum = with_decoded(encode(&[4; 10]).as_bytes(), |data| {
+            data.iter().map(|&b| b as u32).sum::<u32>()
+        });
+        assert_eq!(sum, Ok(40));
+    }
+}
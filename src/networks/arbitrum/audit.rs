@@ -0,0 +1,124 @@
+use crate::networks::arbitrum::{
+    capture::now_ms,
+    errors::{ConnectionUpdate, SinkError},
+    events::FeedEvent,
+};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufWriter, Write},
+    path::Path,
+    sync::Mutex,
+    time::UNIX_EPOCH,
+};
+
+/// A notable occurrence recorded for later auditing, such as a reconnection or a suspected
+/// sequencer failover.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    /// UNIX time of the occurrence, in milliseconds.
+    pub at_ms: u64,
+    /// The relay the entry is about, if any.
+    pub relay_id: Option<u32>,
+    /// What happened, in camelCase, e.g. `relayPromoted` or `reconnecting`.
+    pub kind: String,
+    /// The details of the occurrence.
+    pub detail: Value,
+}
+
+impl AuditEntry {
+    /// Records a `FeedEvent` noticed now.
+    pub fn from_event(event: &FeedEvent) -> Self {
+        let detail = serde_json::to_value(event).unwrap_or_default();
+        let relay_id = match event {
+            FeedEvent::RelayPromoted { relay, .. } | FeedEvent::RelayMismatch { relay, .. } => {
+                Some(relay.id)
+            }
+            _ => None,
+        };
+        Self {
+            at_ms: now_ms() as u64,
+            relay_id,
+            kind: detail["type"].as_str().unwrap_or_default().to_string(),
+            detail,
+        }
+    }
+
+    /// Records a `ConnectionUpdate`, at the time it happened.
+    pub fn from_update(update: &ConnectionUpdate) -> Self {
+        let (kind, detail) = match update {
+            ConnectionUpdate::Connected { .. } => ("connected", Value::Null),
+            ConnectionUpdate::Reconnecting { attempt, .. } => {
+                ("reconnecting", json!({ "attempt": attempt }))
+            }
+            ConnectionUpdate::Closed { code, reason, .. } => {
+                ("closed", json!({ "code": code, "reason": reason }))
+            }
+            ConnectionUpdate::ProtocolError { error, .. } => {
+                ("protocolError", json!({ "error": error }))
+            }
+            ConnectionUpdate::StoppedSendingFrames { .. } => ("stoppedSendingFrames", Value::Null),
+            ConnectionUpdate::Unknown { .. } => ("unknown", Value::Null),
+        };
+        Self {
+            at_ms: update
+                .at()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |at| at.as_millis() as u64),
+            relay_id: Some(update.id()),
+            kind: kind.to_string(),
+            detail,
+        }
+    }
+}
+
+/// A destination audit entries are recorded to, such as a file or a database table.
+///
+/// Implementations must uphold the following semantics, checked by `conformance::audit_sink`:
+///
+/// * Entries recorded by a single caller are kept in order.
+/// * `record` may be called concurrently.
+/// * Once `flush` returned, every entry recorded before is durable.
+#[async_trait]
+pub trait AuditSink: Send + Sync {
+    async fn record(&self, entry: &AuditEntry) -> Result<(), SinkError>;
+
+    async fn flush(&self) -> Result<(), SinkError> {
+        Ok(())
+    }
+}
+
+/// Appends audit entries to a JSON lines file.
+pub struct JsonLinesAuditSink {
+    writer: Mutex<BufWriter<File>>,
+}
+
+impl JsonLinesAuditSink {
+    /// Opens `path` for appending, creating it if needed.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, SinkError> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            writer: Mutex::new(BufWriter::new(file)),
+        })
+    }
+}
+
+#[async_trait]
+impl AuditSink for JsonLinesAuditSink {
+    async fn record(&self, entry: &AuditEntry) -> Result<(), SinkError> {
+        let mut line = serde_json::to_vec(entry)?;
+        line.push(b'\n');
+        self.writer.lock().unwrap().write_all(&line)?;
+        Ok(())
+    }
+
+    async fn flush(&self) -> Result<(), SinkError> {
+        let mut writer = self.writer.lock().unwrap();
+        writer.flush()?;
+        writer.get_ref().sync_data()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,309 @@
+use crate::networks::arbitrum::{
+    metrics::RelayMetrics,
+    types::{BroadcastFeedMessage, Root},
+};
+use crossbeam_channel::{Receiver, Sender};
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+/// What a `RelayClient` does when its consumer can't keep up.
+#[derive(Debug, Clone, Default)]
+pub enum BackpressurePolicy {
+    /// Wait for the consumer to make room. Stalls websocket reads while the queue is full.
+    #[default]
+    Block,
+    /// Discard the oldest queued message to make room. Needs a clone of the receiving side of
+    /// the consumer channel to pop messages from.
+    DropOldest(Receiver<Root>),
+    /// Discard the message that doesn't fit.
+    DropNewest,
+    /// Close the connection, ending `RelayClient::run` with `RelayError::ConsumerTooSlow`.
+    Disconnect,
+    /// Discard bulk messages first and keep the ones matching priority classes, see
+    /// `LoadShedding`.
+    Shed(LoadShedding),
+}
+
+type MessageFilter = Arc<dyn Fn(&BroadcastFeedMessage) -> bool + Send + Sync>;
+
+#[derive(Clone)]
+struct TrafficClass {
+    name: String,
+    filter: MessageFilter,
+    max_pending: Option<usize>,
+    shed: Arc<AtomicU64>,
+}
+
+/// A shedding policy that degrades gracefully under overload.
+///
+/// Messages are sorted into the classes registered with `with_class`, in registration order,
+/// and messages matching no class are bulk traffic. Once the consumer queue is full, bulk
+/// messages are discarded while each class keeps being forwarded until the queue reaches its own
+/// limit, so the most important traffic survives the longest.
+#[derive(Clone)]
+pub struct LoadShedding {
+    classes: Vec<TrafficClass>,
+    bulk_shed: Arc<AtomicU64>,
+}
+
+impl LoadShedding {
+    /// The class name under which discarded bulk messages are reported by `shed`.
+    pub const BULK: &'static str = "bulk";
+
+    pub fn new() -> Self {
+        Self {
+            classes: Vec::new(),
+            bulk_shed: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Adds a traffic class, with a lower priority than the classes added before it.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name under which the messages of the class are reported by `shed`.
+    /// * `filter` - Returns `true` for the messages belonging to the class.
+    /// * `max_pending` - The queue length at which messages of the class are discarded too.
+    ///   `None` never discards them, blocking websocket reads while the queue is full instead.
+    pub fn with_class<F>(
+        mut self,
+        name: impl Into<String>,
+        filter: F,
+        max_pending: Option<usize>,
+    ) -> Self
+    where
+        F: Fn(&BroadcastFeedMessage) -> bool + Send + Sync + 'static,
+    {
+        self.classes.push(TrafficClass {
+            name: name.into(),
+            filter: Arc::new(filter),
+            max_pending,
+            shed: Arc::new(AtomicU64::new(0)),
+        });
+        self
+    }
+
+    /// Returns the number of messages discarded so far for each class, followed by `BULK`.
+    ///
+    /// The counters are shared between clones of the policy.
+    pub fn shed(&self) -> Vec<(String, u64)> {
+        self.classes
+            .iter()
+            .map(|class| (class.name.clone(), class.shed.load(Ordering::Relaxed)))
+            .chain([(
+                Self::BULK.to_string(),
+                self.bulk_shed.load(Ordering::Relaxed),
+            )])
+            .collect()
+    }
+
+    /// Whether `msg` is forwarded while `pending` messages are queued beyond the policy limit.
+    fn admit(&self, msg: &BroadcastFeedMessage, pending: usize) -> bool {
+        let (max_pending, shed) = match self.classes.iter().find(|class| (class.filter)(msg)) {
+            Some(class) => (class.max_pending, &class.shed),
+            None => (Some(0), &self.bulk_shed),
+        };
+        let admitted = max_pending.is_none_or(|max_pending| pending < max_pending);
+        if !admitted {
+            shed.fetch_add(1, Ordering::Relaxed);
+        }
+        admitted
+    }
+}
+
+impl Default for LoadShedding {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for LoadShedding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LoadShedding")
+            .field("shed", &self.shed())
+            .finish()
+    }
+}
+
+/// A `BackpressurePolicy` applied once the consumer has `max_pending` messages queued.
+#[derive(Debug, Clone, Default)]
+pub struct Backpressure {
+    pub policy: BackpressurePolicy,
+    /// The queue length at which the policy kicks in. Defaults to the channel capacity, so
+    /// unbounded channels need an explicit limit.
+    pub max_pending: Option<usize>,
+}
+
+/// The result of forwarding a message to the consumer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Forwarded {
+    Sent,
+    Dropped,
+    /// The consumer is too slow and the policy is `Disconnect`.
+    Overloaded,
+    /// The consumer dropped its receiver.
+    Closed,
+}
+
+impl Backpressure {
+    pub(crate) fn forward(
+        &self,
+        sender: &Sender<Root>,
+        root: Root,
+        metrics: &RelayMetrics,
+    ) -> Forwarded {
+        let mut messages = root.messages.len() as u64;
+        let limit = self.max_pending.or(sender.capacity());
+        let full = limit.is_some_and(|limit| sender.len() >= limit);
+
+        let result = match (&self.policy, full) {
+            (BackpressurePolicy::Block, _) | (_, false) => match sender.send(root) {
+                Ok(()) => Forwarded::Sent,
+                Err(_) => Forwarded::Closed,
+            },
+            (BackpressurePolicy::DropNewest, true) => {
+                RelayMetrics::incr(&metrics.dropped_newest, messages);
+                Forwarded::Dropped
+            }
+            (BackpressurePolicy::Disconnect, true) => Forwarded::Overloaded,
+            (BackpressurePolicy::Shed(shedding), true) => {
+                let pending = sender.len();
+                let mut root = root;
+                root.messages.retain(|msg| shedding.admit(msg, pending));
+                let kept = root.messages.len() as u64;
+                RelayMetrics::incr(&metrics.dropped_newest, messages - kept);
+                messages = kept;
+                if kept == 0 {
+                    Forwarded::Dropped
+                } else {
+                    match sender.send(root) {
+                        Ok(()) => Forwarded::Sent,
+                        Err(_) => Forwarded::Closed,
+                    }
+                }
+            }
+            (BackpressurePolicy::DropOldest(receiver), true) => {
+                let limit = limit.unwrap_or(usize::MAX);
+                while sender.len() >= limit {
+                    match receiver.try_recv() {
+                        Ok(oldest) => RelayMetrics::incr(
+                            &metrics.dropped_oldest,
+                            oldest.messages.len() as u64,
+                        ),
+                        Err(_) => break,
+                    }
+                }
+                match sender.send(root) {
+                    Ok(()) => Forwarded::Sent,
+                    Err(_) => Forwarded::Closed,
+                }
+            }
+        };
+
+        if result == Forwarded::Sent {
+            RelayMetrics::incr(&metrics.messages_forwarded, messages);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::networks::arbitrum::fixtures::message_with;
+    use crossbeam_channel::bounded;
+
+    fn root(seq: u64) -> Root {
+        Root {
+            provenance: Default::default(),
+            version: 1,
+            messages: vec![message_with(seq, 0, Vec::new())],
+        }
+    }
+
+    fn queued(rx: &Receiver<Root>) -> Vec<u64> {
+        rx.try_iter()
+            .map(|r| r.messages[0].sequence_number)
+            .collect()
+    }
+
+    #[test]
+    fn policies_apply_once_queue_is_full() {
+        let metrics = RelayMetrics::default();
+        let (tx, rx) = bounded(2);
+
+        let drop_newest = Backpressure {
+            policy: BackpressurePolicy::DropNewest,
+            max_pending: None,
+        };
+        for seq in 0..4 {
+            drop_newest.forward(&tx, root(seq), &metrics);
+        }
+        assert_eq!(queued(&rx), vec![0, 1]);
+
+        let drop_oldest = Backpressure {
+            policy: BackpressurePolicy::DropOldest(rx.clone()),
+            max_pending: None,
+        };
+        for seq in 0..4 {
+            drop_oldest.forward(&tx, root(seq), &metrics);
+        }
+        assert_eq!(queued(&rx), vec![2, 3]);
+
+        let disconnect = Backpressure {
+            policy: BackpressurePolicy::Disconnect,
+            max_pending: Some(1),
+        };
+        assert_eq!(disconnect.forward(&tx, root(0), &metrics), Forwarded::Sent);
+        assert_eq!(
+            disconnect.forward(&tx, root(1), &metrics),
+            Forwarded::Overloaded
+        );
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.dropped_newest, 2);
+        assert_eq!(snapshot.dropped_oldest, 2);
+        assert_eq!(snapshot.messages_forwarded, 7);
+    }
+
+    #[test]
+    fn shedding_preserves_priority_classes() {
+        let metrics = RelayMetrics::default();
+        let (tx, rx) = bounded(8);
+        let shedding = LoadShedding::new()
+            .with_class("critical", |msg| msg.sequence_number % 10 == 0, None)
+            .with_class("even", |msg| msg.sequence_number % 2 == 0, Some(3));
+        let shed = Backpressure {
+            policy: BackpressurePolicy::Shed(shedding.clone()),
+            max_pending: Some(2),
+        };
+
+        let batch = Root {
+            provenance: Default::default(),
+            version: 1,
+            messages: (10..14)
+                .map(|seq| message_with(seq, 0, Vec::new()))
+                .collect(),
+        };
+        for seq in [1, 3, 2, 4, 5, 6, 20] {
+            shed.forward(&tx, root(seq), &metrics);
+        }
+        shed.forward(&tx, batch, &metrics);
+
+        assert_eq!(queued(&rx), vec![1, 3, 2, 20, 10]);
+        assert_eq!(
+            shedding.shed(),
+            vec![
+                ("critical".to_string(), 0),
+                ("even".to_string(), 3),
+                (LoadShedding::BULK.to_string(), 3),
+            ]
+        );
+        assert_eq!(metrics.snapshot().dropped_newest, 6);
+    }
+}
@@ -0,0 +1,159 @@
+use crate::networks::arbitrum::message::{FeedMessage, FeedTransaction};
+use crossbeam_channel::{Receiver, RecvTimeoutError, Sender, TryRecvError};
+use std::{
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+/// Which messages a `Batcher` coalesces into a single send.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BatchWindow {
+    /// The messages received within the duration after the first message of a batch.
+    Duration(Duration),
+    /// The messages of the same websocket frame, told apart by their `Provenance`. A batch is
+    /// sent as soon as no more messages of its frame are queued, so batching never waits.
+    Frame,
+}
+
+/// Coalesces the transactions of consecutive messages into `Vec<FeedTransaction>` batches, for
+/// consumers whose per-send overhead dominates at high throughput.
+///
+/// Transactions keep their order, and messages without transactions are skipped.
+#[derive(Debug)]
+pub struct Batcher {
+    window: BatchWindow,
+    max_transactions: usize,
+}
+
+impl Batcher {
+    pub fn new(window: BatchWindow) -> Self {
+        Self {
+            window,
+            max_transactions: usize::MAX,
+        }
+    }
+
+    /// Sends a batch once it holds at least `max_transactions` transactions, without waiting
+    /// for the end of its window.
+    pub fn with_max_transactions(mut self, max_transactions: usize) -> Self {
+        self.max_transactions = max_transactions.max(1);
+        self
+    }
+
+    /// Starts batching the messages received on `input`.
+    ///
+    /// The batcher stops, and drops `output`, once either side is disconnected. The pending
+    /// batch is sent first when `input` is disconnected.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - The receiver channel of decoded `FeedMessage`s, e.g. fed by a `DecodePool`.
+    /// * `output` - The sender channel for sending the batches of transactions.
+    pub fn spawn(
+        self,
+        input: Receiver<FeedMessage>,
+        output: Sender<Vec<FeedTransaction>>,
+    ) -> JoinHandle<()> {
+        thread::spawn(move || {
+            let mut next = None;
+            while let Some(batch) = self.next_batch(&input, &mut next) {
+                if !batch.is_empty() && output.send(batch).is_err() {
+                    return;
+                }
+            }
+        })
+    }
+
+    /// Collects the next batch, starting with `next` if a message was held back from the
+    /// previous one.
+    ///
+    /// # Returns
+    ///
+    /// The batch, possibly empty, or `None` once `input` is disconnected.
+    fn next_batch(
+        &self,
+        input: &Receiver<FeedMessage>,
+        next: &mut Option<FeedMessage>,
+    ) -> Option<Vec<FeedTransaction>> {
+        let first = match next.take() {
+            Some(msg) => msg,
+            None => input.recv().ok()?,
+        };
+        let deadline = match self.window {
+            BatchWindow::Duration(window) => Some(Instant::now() + window),
+            BatchWindow::Frame => None,
+        };
+        let frame = first.provenance.clone();
+        let mut batch = first.transactions();
+
+        while batch.len() < self.max_transactions {
+            let msg = match deadline {
+                Some(deadline) => match input.recv_deadline(deadline) {
+                    Ok(msg) => msg,
+                    Err(RecvTimeoutError::Timeout | RecvTimeoutError::Disconnected) => break,
+                },
+                None => match input.try_recv() {
+                    Ok(msg) => msg,
+                    Err(TryRecvError::Empty | TryRecvError::Disconnected) => break,
+                },
+            };
+            if deadline.is_none() && msg.provenance != frame {
+                *next = Some(msg);
+                break;
+            }
+            batch.extend(msg.transactions());
+        }
+
+        Some(batch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::networks::arbitrum::{
+        decoder::DecodedMsg, fixtures::feed_message, provenance::Provenance,
+    };
+    use crossbeam_channel::unbounded;
+    use ethers::types::Transaction;
+
+    #[test]
+    fn coalesces_messages_into_batches() {
+        let batch = |seq: u64, txs: usize, received_at_ms: u64| FeedMessage {
+            provenance: Provenance::live(1, 0).with_received_at_ms(received_at_ms),
+            ..feed_message(
+                seq,
+                0,
+                vec![],
+                Some(DecodedMsg::DecodedBatch(vec![Transaction::default(); txs])),
+            )
+        };
+        let run = |batcher: Batcher| {
+            let (input, messages) = unbounded();
+            for (seq, txs, received_at_ms) in [(1, 2, 100), (2, 0, 100), (3, 1, 100), (4, 3, 200)] {
+                input.send(batch(seq, txs, received_at_ms)).unwrap();
+            }
+            drop(input);
+            let (output, batches) = unbounded();
+            batcher.spawn(messages, output).join().unwrap();
+            batches
+                .iter()
+                .map(|batch| batch.iter().map(|tx| tx.sequence_number).collect())
+                .collect::<Vec<Vec<u64>>>()
+        };
+
+        assert_eq!(
+            run(Batcher::new(BatchWindow::Frame)),
+            vec![vec![1, 1, 3], vec![4, 4, 4]]
+        );
+        assert_eq!(
+            run(Batcher::new(BatchWindow::Duration(Duration::from_secs(1)))
+                .with_max_transactions(3)),
+            vec![vec![1, 1, 3], vec![4, 4, 4]]
+        );
+        assert_eq!(
+            run(Batcher::new(BatchWindow::Duration(Duration::from_secs(1)))),
+            vec![vec![1, 1, 3, 4, 4, 4]]
+        );
+    }
+}
@@ -0,0 +1,156 @@
+//! Feed payloads for benchmarking the decode path, shaped like Arbitrum One traffic: mostly
+//! EIP-1559 router swaps and token transfers, with some legacy transfers, one transaction per
+//! message as sent by the sequencer, or batched.
+//!
+//! The transactions are signed with a fixed key, so the payloads are the same on every run.
+
+use crate::networks::arbitrum::{
+    decoder::{l1::L1_MESSAGE_KIND_L2_MESSAGE, L2_MESSAGE_KIND_BATCH, L2_MESSAGE_KIND_SIGNED_TX},
+    types::{BroadcastFeedMessage, Header, L1IncomingMessageHeader, MessageWithMetadata, Root},
+};
+use ethers::{
+    signers::{LocalWallet, Signer},
+    types::{
+        transaction::eip2718::TypedTransaction, Eip1559TransactionRequest, TransactionRequest, H160,
+    },
+};
+use serde_json::Value;
+
+const CHAIN_ID: u64 = 42161;
+const SIGNER_KEY: &str = "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318";
+
+/// Returns `count` signed transactions, RLP encoded: router swaps with about 260 bytes of
+/// calldata, ERC-20 transfers and plain transfers, in a 3:3:1 mix.
+pub fn signed_transactions(count: usize) -> Vec<Vec<u8>> {
+    let wallet = SIGNER_KEY
+        .parse::<LocalWallet>()
+        .expect("the bench key is valid")
+        .with_chain_id(CHAIN_ID);
+
+    (0..count)
+        .map(|i| {
+            let nonce = i as u64;
+            let tx: TypedTransaction = match i % 7 {
+                0..=2 => Eip1559TransactionRequest::new()
+                    .to(H160::repeat_byte(0x68))
+                    .data(calldata([0x5a, 0xe4, 0x01, 0xdc], 8, i))
+                    .value(10_000_000_000_000_000u64)
+                    .nonce(nonce)
+                    .gas(350_000u64)
+                    .max_fee_per_gas(10_000_000u64)
+                    .max_priority_fee_per_gas(0u64)
+                    .chain_id(CHAIN_ID)
+                    .into(),
+                3..=5 => Eip1559TransactionRequest::new()
+                    .to(H160::repeat_byte(0xaf))
+                    .data(calldata([0xa9, 0x05, 0x9c, 0xbb], 2, i))
+                    .nonce(nonce)
+                    .gas(60_000u64)
+                    .max_fee_per_gas(10_000_000u64)
+                    .max_priority_fee_per_gas(0u64)
+                    .chain_id(CHAIN_ID)
+                    .into(),
+                _ => TransactionRequest::new()
+                    .to(H160::from_low_u64_be(i as u64 + 1))
+                    .value(1_000_000_000_000_000u64)
+                    .nonce(nonce)
+                    .gas(21_000u64)
+                    .gas_price(10_000_000u64)
+                    .chain_id(CHAIN_ID)
+                    .into(),
+            };
+            let signature = wallet
+                .sign_transaction_sync(&tx)
+                .expect("the bench transactions are signable");
+            tx.rlp_signed(&signature).to_vec()
+        })
+        .collect()
+}
+
+/// Builds calldata of a selector followed by `words` 32-byte arguments varying with `seed`.
+fn calldata(selector: [u8; 4], words: usize, seed: usize) -> Vec<u8> {
+    let mut data = selector.to_vec();
+    for word in 0..words {
+        let mut arg = [0; 32];
+        arg[24..].copy_from_slice(&((seed * 31 + word) as u64).to_be_bytes());
+        data.extend_from_slice(&arg);
+    }
+    data
+}
+
+/// Returns the L2 message of a single signed transaction.
+pub fn signed_tx_l2msg(tx: &[u8]) -> Vec<u8> {
+    let mut l2msg = vec![L2_MESSAGE_KIND_SIGNED_TX];
+    l2msg.extend_from_slice(tx);
+    l2msg
+}
+
+/// Returns the L2 message of a batch of signed transactions.
+pub fn batch_l2msg(txs: &[Vec<u8>]) -> Vec<u8> {
+    let mut l2msg = vec![L2_MESSAGE_KIND_BATCH];
+    for tx in txs {
+        l2msg.extend_from_slice(&((tx.len() + 1) as u64).to_be_bytes());
+        l2msg.push(L2_MESSAGE_KIND_SIGNED_TX);
+        l2msg.extend_from_slice(tx);
+    }
+    l2msg
+}
+
+/// Returns a feed message carrying `l2msg`.
+pub fn message(sequence_number: u64, l2msg: Vec<u8>) -> BroadcastFeedMessage {
+    BroadcastFeedMessage {
+        sequence_number,
+        message: MessageWithMetadata {
+            message: L1IncomingMessageHeader {
+                header: Header {
+                    kind: L1_MESSAGE_KIND_L2_MESSAGE,
+                    sender: "0xa4b000000000000000000073657175656e636572".to_string(),
+                    block_number: 19_000_000 + sequence_number / 40,
+                    timestamp: 1_700_000_000 + sequence_number / 4,
+                    request_id: Value::Null,
+                    base_fee_l1: Value::Null,
+                },
+                l2msg: l2msg.into(),
+            },
+            delayed_messages_read: 1_500_000,
+        },
+        signature: Value::Null,
+    }
+}
+
+/// Returns a broadcast frame, as sent by a relay, of `messages` messages each holding a single
+/// signed transaction.
+pub fn frame(messages: usize) -> Vec<u8> {
+    let root = Root {
+        version: 1,
+        messages: signed_transactions(messages)
+            .iter()
+            .enumerate()
+            .map(|(i, tx)| message(200_000_000 + i as u64, signed_tx_l2msg(tx)))
+            .collect(),
+        provenance: Default::default(),
+    };
+    serde_json::to_vec(&root).expect("roots serialize")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::networks::arbitrum::{decoder::DecodedMsg, types::versioned::VersionedRoot};
+
+    #[test]
+    fn fixtures_decode() {
+        let root = VersionedRoot::parse(&frame(7)).unwrap().into_root();
+        assert_eq!(root.messages.len(), 7);
+        assert!(root.messages.iter().all(|msg| matches!(
+            msg.message.message.try_decode(),
+            Ok(Some(DecodedMsg::DecodedSignedTx(_)))
+        )));
+
+        let batch = message(0, batch_l2msg(&signed_transactions(10)));
+        match batch.message.message.try_decode() {
+            Ok(Some(DecodedMsg::DecodedBatch(txs))) => assert_eq!(txs.len(), 10),
+            decoded => panic!("unexpected {:?}", decoded),
+        }
+    }
+}
@@ -0,0 +1,281 @@
+use crate::networks::arbitrum::{decoder::DecodedMsg, message::FeedMessage};
+use crossbeam_channel::{Receiver, RecvTimeoutError, Sender};
+use ethers::types::Transaction;
+use log::*;
+use std::{
+    collections::BTreeMap,
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+/// How many blocks may be held back waiting for missing messages, at most.
+const MAX_HELD_BLOCKS: usize = 4096;
+
+/// An L2 block as it will be produced by the sequencer, reconstructed from the feed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingBlock {
+    /// The L2 block number.
+    pub number: u64,
+    /// The L2 timestamp of the block, in seconds.
+    pub timestamp: u64,
+    /// The L1 block number the sequencer saw when producing the block.
+    pub l1_block_number: u64,
+    /// The sequence number of the feed message the block is produced from.
+    pub sequence_number: u64,
+    /// The transactions of the block, excluding the internal ArbOS ones. Empty for delayed
+    /// messages and messages that couldn't be decoded.
+    pub txs: Vec<Transaction>,
+    /// How many blocks right before this one are missing from the feed, 0 unless messages were
+    /// lost.
+    pub missing_before: u64,
+}
+
+/// Groups feed messages into pending L2 blocks.
+///
+/// Nitro produces exactly one L2 block per message, so each message marks the end of a block and
+/// block numbers follow sequence numbers, offset by the chain's genesis block. Messages are
+/// expected in sequence order: duplicates are skipped, and gaps are logged and flagged with
+/// `PendingBlock::missing_before` since their blocks can't be reconstructed.
+///
+/// With a gap timeout, the blocks following a gap are held back until the missing messages
+/// arrive, e.g. late from another relay, so that blocks are still produced in order. Once a block
+/// was held for longer than the timeout, or too many blocks are held, the held blocks are flushed
+/// and the gap is flagged, so that a message that never arrives doesn't stall the assembler.
+#[derive(Debug, Clone)]
+pub struct BlockAssembler {
+    genesis_block_number: u64,
+    last_sequence_number: Option<u64>,
+    gap_timeout: Option<Duration>,
+    /// The blocks held back after a gap, by sequence number, with when they were assembled.
+    held: BTreeMap<u64, (Instant, PendingBlock)>,
+}
+
+impl BlockAssembler {
+    /// # Arguments
+    ///
+    /// * `genesis_block_number` - The L2 block number of sequence number 0, see
+    ///   `ArbitrumNetwork::genesis_block_number`.
+    pub fn new(genesis_block_number: u64) -> Self {
+        Self {
+            genesis_block_number,
+            last_sequence_number: None,
+            gap_timeout: None,
+            held: BTreeMap::new(),
+        }
+    }
+
+    /// Holds the blocks following a gap back for up to `timeout`, waiting for the missing
+    /// messages.
+    pub fn with_gap_timeout(mut self, timeout: Duration) -> Self {
+        self.gap_timeout = Some(timeout);
+        self
+    }
+
+    /// Returns the block produced from `msg`, or `None` if it was already assembled or is held
+    /// back after a gap. Held blocks are returned by `pop`.
+    pub fn push(&mut self, msg: &FeedMessage) -> Option<PendingBlock> {
+        self.push_at(msg, Instant::now())
+    }
+
+    fn push_at(&mut self, msg: &FeedMessage, now: Instant) -> Option<PendingBlock> {
+        let sequence_number = msg.sequence_number();
+        let gap = match self.last_sequence_number {
+            Some(last) if sequence_number <= last || self.held.contains_key(&sequence_number) => {
+                return None;
+            }
+            Some(last) => sequence_number > last + 1,
+            None => false,
+        };
+        let block = self.assemble(msg);
+        if gap && self.gap_timeout.is_some() {
+            self.held.insert(sequence_number, (now, block));
+            return None;
+        }
+        Some(self.produce(block))
+    }
+
+    /// Returns the next held block once it can be produced: when the missing messages before it
+    /// arrived, or when the gap timed out.
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - The current time, to check held blocks against the gap timeout.
+    pub fn pop(&mut self, now: Instant) -> Option<PendingBlock> {
+        let (&sequence_number, _) = self.held.first_key_value()?;
+        let contiguous = self.last_sequence_number.map(|last| last + 1) == Some(sequence_number);
+        let expired = self.deadline().is_some_and(|deadline| deadline <= now);
+        if !contiguous && !expired && self.held.len() <= MAX_HELD_BLOCKS {
+            return None;
+        }
+        let (_, block) = self.held.pop_first()?.1;
+        Some(self.produce(block))
+    }
+
+    /// Returns when the block held back the longest times out, if any.
+    pub fn deadline(&self) -> Option<Instant> {
+        let timeout = self.gap_timeout?;
+        let held_since = self.held.values().map(|(since, _)| *since).min()?;
+        Some(held_since + timeout)
+    }
+
+    /// Produces every held block, regardless of the gap timeout.
+    pub fn flush(&mut self) -> Vec<PendingBlock> {
+        let held = std::mem::take(&mut self.held);
+        held.into_values()
+            .map(|(_, block)| self.produce(block))
+            .collect()
+    }
+
+    /// Flags and logs the gap before `block`, if any, and makes it the last block produced.
+    fn produce(&mut self, mut block: PendingBlock) -> PendingBlock {
+        if let Some(last) = self.last_sequence_number {
+            block.missing_before = block.sequence_number - last - 1;
+            if block.missing_before > 0 {
+                warn!(
+                    "Blocks {} to {} missing from the feed",
+                    self.genesis_block_number + last + 1,
+                    block.number - 1
+                );
+            }
+        }
+        self.last_sequence_number = Some(block.sequence_number);
+        block
+    }
+
+    fn assemble(&self, msg: &FeedMessage) -> PendingBlock {
+        let sequence_number = msg.sequence_number();
+        let header = &msg.message.message.message.header;
+        let txs = match &msg.decoded {
+            Ok(Some(DecodedMsg::DecodedBatch(txs))) => txs.clone(),
+            Ok(Some(DecodedMsg::DecodedSignedTx(tx))) => vec![(**tx).clone()],
+            _ => Vec::new(),
+        };
+        PendingBlock {
+            number: self.genesis_block_number + sequence_number,
+            timestamp: header.timestamp,
+            l1_block_number: header.block_number,
+            sequence_number,
+            txs,
+            missing_before: 0,
+        }
+    }
+
+    /// Assembles the blocks of the messages received on `input` on a new thread, producing the
+    /// held blocks as soon as they time out.
+    ///
+    /// The thread stops once `input` is disconnected, after producing the held blocks, or once
+    /// `output` is dropped.
+    pub fn spawn(
+        mut self,
+        input: Receiver<FeedMessage>,
+        output: Sender<PendingBlock>,
+    ) -> JoinHandle<()> {
+        thread::spawn(move || loop {
+            let received = match self.deadline() {
+                Some(deadline) => input.recv_deadline(deadline),
+                None => input.recv().map_err(RecvTimeoutError::from),
+            };
+            let mut blocks = match received {
+                Ok(msg) => self.push(&msg).into_iter().collect(),
+                Err(RecvTimeoutError::Timeout) => Vec::new(),
+                Err(RecvTimeoutError::Disconnected) => {
+                    for block in self.flush() {
+                        let _ = output.send(block);
+                    }
+                    return;
+                }
+            };
+            let now = Instant::now();
+            while let Some(block) = self.pop(now) {
+                blocks.push(block);
+            }
+            for block in blocks {
+                if output.send(block).is_err() {
+                    return;
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::networks::arbitrum::fixtures::feed_message;
+
+    #[test]
+    fn one_block_per_message() {
+        let mut assembler = BlockAssembler::new(22_207_817);
+
+        let first = assembler
+            .push(&feed_message(
+                10,
+                1_700_000_010,
+                vec![3],
+                Some(DecodedMsg::DecodedBatch(Vec::new())),
+            ))
+            .unwrap();
+        assert_eq!(first.number, 22_207_827);
+        assert_eq!(first.timestamp, 1_700_000_010);
+        assert_eq!(
+            assembler.push(&feed_message(
+                10,
+                1_700_000_010,
+                vec![3],
+                Some(DecodedMsg::DecodedBatch(Vec::new()))
+            )),
+            None
+        );
+        assert_eq!(
+            assembler
+                .push(&feed_message(
+                    12,
+                    1_700_000_012,
+                    vec![3],
+                    Some(DecodedMsg::DecodedBatch(Vec::new()))
+                ))
+                .unwrap()
+                .number,
+            22_207_829
+        );
+    }
+
+    #[test]
+    fn holds_blocks_after_a_gap_until_it_times_out() {
+        let timeout = Duration::from_secs(2);
+        let mut assembler = BlockAssembler::new(0).with_gap_timeout(timeout);
+        let start = Instant::now();
+
+        assert!(assembler
+            .push_at(&feed_message(1, 1_700_000_001, vec![3], None), start)
+            .is_some());
+        assert_eq!(
+            assembler.push_at(&feed_message(3, 1_700_000_003, vec![3], None), start),
+            None
+        );
+        assert_eq!(assembler.pop(start), None);
+        // The missing message arrives late: both blocks are produced in order.
+        let late = assembler
+            .push_at(&feed_message(2, 1_700_000_002, vec![3], None), start)
+            .unwrap();
+        assert_eq!((late.number, late.missing_before), (2, 0));
+        assert_eq!(assembler.pop(start).unwrap().number, 3);
+        assert_eq!(assembler.pop(start), None);
+
+        // Messages 4 and 5 never arrive.
+        assert_eq!(
+            assembler.push_at(&feed_message(6, 1_700_000_006, vec![3], None), start),
+            None
+        );
+        assert_eq!(
+            assembler.push_at(&feed_message(7, 1_700_000_007, vec![3], None), start),
+            None
+        );
+        assert_eq!(assembler.deadline(), Some(start + timeout));
+        assert_eq!(assembler.pop(start + timeout / 2), None);
+        let flushed = assembler.pop(start + timeout).unwrap();
+        assert_eq!((flushed.number, flushed.missing_before), (6, 2));
+        assert_eq!(assembler.pop(start + timeout).unwrap().missing_before, 0);
+        assert_eq!(assembler.deadline(), None);
+    }
+}
@@ -0,0 +1,73 @@
+use crate::networks::arbitrum::types::{BroadcastFeedMessage, Root};
+use std::{collections::VecDeque, ops::RangeInclusive, sync::RwLock};
+
+/// A bounded, thread-safe ring buffer holding the most recent feed messages.
+///
+/// Messages are expected to be pushed in increasing sequence number order. Once `capacity` is
+/// reached the oldest message is evicted.
+pub struct LiveCache {
+    capacity: usize,
+    messages: RwLock<VecDeque<BroadcastFeedMessage>>,
+}
+
+impl LiveCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            messages: RwLock::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    /// Pushes a message into the cache, evicting the oldest one if the cache is full.
+    ///
+    /// Messages whose sequence number is not greater than the newest cached one are ignored.
+    pub fn push(&self, msg: BroadcastFeedMessage) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let mut messages = self.messages.write().unwrap();
+        if let Some(last) = messages.back() {
+            if msg.sequence_number <= last.sequence_number {
+                return;
+            }
+        }
+
+        if messages.len() == self.capacity {
+            messages.pop_front();
+        }
+        messages.push_back(msg);
+    }
+
+    /// Pushes every message contained in `root`.
+    pub fn push_root(&self, root: &Root) {
+        for msg in &root.messages {
+            self.push(msg.clone());
+        }
+    }
+
+    /// Returns the range of sequence numbers currently held by the cache.
+    pub fn sequence_range(&self) -> Option<RangeInclusive<u64>> {
+        let messages = self.messages.read().unwrap();
+        let first = messages.front()?.sequence_number;
+        let last = messages.back()?.sequence_number;
+        Some(first..=last)
+    }
+
+    /// Returns clones of the cached messages accepted by `predicate`, in sequence order.
+    pub fn collect<F>(&self, mut predicate: F) -> Vec<BroadcastFeedMessage>
+    where
+        F: FnMut(&BroadcastFeedMessage) -> bool,
+    {
+        let messages = self.messages.read().unwrap();
+        messages.iter().filter(|m| predicate(m)).cloned().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.messages.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
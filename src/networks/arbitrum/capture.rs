@@ -0,0 +1,101 @@
+use base64::{engine::general_purpose, Engine as _};
+use serde::Serialize;
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tungstenite::Message;
+
+/// Connection metadata written at the start of every capture file.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CaptureMetadata {
+    pub relay_id: u32,
+    pub url: String,
+    pub chain_id: u64,
+    pub started_at_ms: u128,
+    pub frames: usize,
+}
+
+/// A single frame as received on the wire.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CapturedFrame<'a> {
+    received_at_ms: u128,
+    opcode: &'static str,
+    len: usize,
+    /// The base64 encoded frame payload.
+    payload: &'a str,
+}
+
+/// Dumps the next `frames` raw websocket frames to a JSON lines file for offline analysis.
+///
+/// The first line holds the `CaptureMetadata`, every following line one frame.
+pub struct FrameCapture {
+    writer: BufWriter<File>,
+    remaining: usize,
+}
+
+impl FrameCapture {
+    /// Creates the capture file at `path`, truncating it if it exists.
+    pub fn create(path: &Path, metadata: CaptureMetadata) -> io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer(&mut writer, &metadata)?;
+        writer.write_all(b"\n")?;
+
+        Ok(Self {
+            writer,
+            remaining: metadata.frames,
+        })
+    }
+
+    /// Records a frame.
+    ///
+    /// # Returns
+    ///
+    /// `true` once the requested number of frames has been captured and the file was flushed.
+    pub fn record(&mut self, message: &Message) -> io::Result<bool> {
+        if self.remaining == 0 {
+            return Ok(true);
+        }
+
+        let (opcode, data): (_, &[u8]) = match message {
+            Message::Text(text) => ("text", text.as_bytes()),
+            Message::Binary(data) => ("binary", data),
+            Message::Ping(data) => ("ping", data),
+            Message::Pong(data) => ("pong", data),
+            Message::Close(_) => ("close", &[]),
+            Message::Frame(frame) => ("frame", frame.payload()),
+        };
+        let payload = general_purpose::STANDARD.encode(data);
+        let frame = CapturedFrame {
+            received_at_ms: now_ms(),
+            opcode,
+            len: data.len(),
+            payload: &payload,
+        };
+        serde_json::to_writer(&mut self.writer, &frame)?;
+        self.writer.write_all(b"\n")?;
+
+        self.remaining -= 1;
+        if self.remaining == 0 {
+            self.writer.flush()?;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    /// Flushes the frames captured so far.
+    pub fn finish(mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+pub(crate) fn now_ms() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis()
+}
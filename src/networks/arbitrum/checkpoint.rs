@@ -0,0 +1,312 @@
+//! Persistence of the last processed sequence number, so that a restarted process resumes the
+//! feed where it stopped instead of at the live head.
+//!
+//! The file store is always available; the `sled` and `redis` features add stores backed by an
+//! embedded database and a Redis server.
+
+use crate::networks::arbitrum::{connect::ConnectOptions, errors::CheckpointError};
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
+/// How often a `Checkpointer` persists the sequence number by default.
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A place the last processed sequence number is persisted to.
+///
+/// Implementations must uphold the following semantics, checked by
+/// `conformance::checkpoint_store`:
+///
+/// * `load` returns `None` until a checkpoint is saved.
+/// * `load` returns the last saved sequence number, even if lower than a previous one.
+/// * `save` is atomic: a crash never leaves a partially written checkpoint behind.
+/// * Checkpoints are durable: another instance opened on the same storage sees them.
+///
+/// Stores are written to frequently; `Checkpointer` throttles the writes.
+pub trait CheckpointStore: Send + Sync {
+    /// Returns the last saved sequence number, or `None` if nothing was saved yet.
+    fn load(&self) -> Result<Option<u64>, CheckpointError>;
+
+    /// Saves `sequence_number` as the last processed one.
+    fn save(&self, sequence_number: u64) -> Result<(), CheckpointError>;
+}
+
+/// Distinguishes the temporary files of the saves of this process.
+static NEXT_TMP: AtomicU64 = AtomicU64::new(0);
+
+/// Stores the checkpoint in a file, replaced atomically and durably on each save.
+#[derive(Debug, Clone)]
+pub struct FileCheckpointStore {
+    path: PathBuf,
+}
+
+impl FileCheckpointStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl CheckpointStore for FileCheckpointStore {
+    fn load(&self) -> Result<Option<u64>, CheckpointError> {
+        let content = match fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        content
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(|_| CheckpointError::Corrupt(content))
+    }
+
+    fn save(&self, sequence_number: u64) -> Result<(), CheckpointError> {
+        // Unique, so that concurrent saves never write to each other's temporary file.
+        let name = self.path.file_name().unwrap_or_default().to_string_lossy();
+        let tmp = self.path.with_file_name(format!(
+            ".{}.{}.{}.tmp",
+            name,
+            std::process::id(),
+            NEXT_TMP.fetch_add(1, Ordering::Relaxed)
+        ));
+        let written = fs::File::create(&tmp).and_then(|mut file| {
+            file.write_all(sequence_number.to_string().as_bytes())?;
+            file.sync_all()
+        });
+        if let Err(e) = written.and_then(|()| fs::rename(&tmp, &self.path)) {
+            let _ = fs::remove_file(&tmp);
+            return Err(e.into());
+        }
+        sync_parent(&self.path)?;
+        Ok(())
+    }
+}
+
+/// Flushes the directory holding `path`, so that a file just renamed there survives a crash.
+#[cfg(unix)]
+fn sync_parent(path: &Path) -> io::Result<()> {
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    fs::File::open(parent)?.sync_all()
+}
+
+/// Directories can't be opened, let alone flushed, on other platforms.
+#[cfg(not(unix))]
+fn sync_parent(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+/// Stores the checkpoint under a key of a sled database.
+#[cfg(feature = "sled")]
+pub struct SledCheckpointStore {
+    db: sled::Db,
+    key: String,
+}
+
+#[cfg(feature = "sled")]
+impl SledCheckpointStore {
+    /// Opens the database at `path`, creating it if needed.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The directory of the database.
+    /// * `key` - The key the checkpoint is stored under, so several readers can share a database.
+    pub fn open(
+        path: impl AsRef<std::path::Path>,
+        key: impl Into<String>,
+    ) -> Result<Self, CheckpointError> {
+        Ok(Self {
+            db: sled::open(path)?,
+            key: key.into(),
+        })
+    }
+}
+
+#[cfg(feature = "sled")]
+impl CheckpointStore for SledCheckpointStore {
+    fn load(&self) -> Result<Option<u64>, CheckpointError> {
+        match self.db.get(&self.key)? {
+            Some(value) => {
+                let bytes: [u8; 8] = value
+                    .as_ref()
+                    .try_into()
+                    .map_err(|_| CheckpointError::Corrupt(format!("{:?}", value)))?;
+                Ok(Some(u64::from_be_bytes(bytes)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn save(&self, sequence_number: u64) -> Result<(), CheckpointError> {
+        self.db.insert(&self.key, &sequence_number.to_be_bytes())?;
+        self.db.flush()?;
+        Ok(())
+    }
+}
+
+/// Stores the checkpoint under a key of a Redis server.
+#[cfg(feature = "redis")]
+pub struct RedisCheckpointStore {
+    connection: std::sync::Mutex<redis::Connection>,
+    key: String,
+}
+
+#[cfg(feature = "redis")]
+impl RedisCheckpointStore {
+    /// Connects to the Redis server at `url`, e.g. `redis://127.0.0.1/`.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL of the server.
+    /// * `key` - The key the checkpoint is stored under.
+    pub fn open(url: &str, key: impl Into<String>) -> Result<Self, CheckpointError> {
+        let connection = redis::Client::open(url)?.get_connection()?;
+        Ok(Self {
+            connection: std::sync::Mutex::new(connection),
+            key: key.into(),
+        })
+    }
+}
+
+#[cfg(feature = "redis")]
+impl CheckpointStore for RedisCheckpointStore {
+    fn load(&self) -> Result<Option<u64>, CheckpointError> {
+        use redis::Commands;
+        Ok(self.connection.lock().unwrap().get(&self.key)?)
+    }
+
+    fn save(&self, sequence_number: u64) -> Result<(), CheckpointError> {
+        use redis::Commands;
+        Ok(self
+            .connection
+            .lock()
+            .unwrap()
+            .set(&self.key, sequence_number)?)
+    }
+}
+
+/// Tracks the processed messages and persists the last sequence number to a `CheckpointStore`.
+///
+/// Saves are throttled to one per interval; call `flush` before exiting to persist the latest
+/// sequence number.
+pub struct Checkpointer<S> {
+    store: S,
+    interval: Duration,
+    last_saved_at: Option<Instant>,
+    pending: Option<u64>,
+}
+
+impl<S: CheckpointStore> Checkpointer<S> {
+    pub fn new(store: S) -> Self {
+        Self {
+            store,
+            interval: DEFAULT_INTERVAL,
+            last_saved_at: None,
+            pending: None,
+        }
+    }
+
+    /// Sets the minimum delay between two saves. `Duration::ZERO` saves on every message.
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// Returns the sequence number to resume the feed at: the one after the checkpoint, or 0
+    /// (the live head) if there is none.
+    pub fn resume_sequence_number(&self) -> Result<u64, CheckpointError> {
+        Ok(self.store.load()?.map_or(0, |last| last + 1))
+    }
+
+    /// Returns `options` set up to resume the feed after the checkpoint.
+    pub fn resume(&self, options: ConnectOptions) -> Result<ConnectOptions, CheckpointError> {
+        Ok(options.with_sequence_number(self.resume_sequence_number()?))
+    }
+
+    /// Records that every message up to `sequence_number` has been processed, saving it if the
+    /// interval elapsed since the last save.
+    pub fn processed(&mut self, sequence_number: u64) -> Result<(), CheckpointError> {
+        self.pending = Some(sequence_number);
+        if self
+            .last_saved_at
+            .is_some_and(|at| at.elapsed() < self.interval)
+        {
+            return Ok(());
+        }
+        self.flush()
+    }
+
+    /// Saves the last processed sequence number, if it wasn't saved yet.
+    pub fn flush(&mut self) -> Result<(), CheckpointError> {
+        if let Some(sequence_number) = self.pending.take() {
+            self.store.save(sequence_number)?;
+            self.last_saved_at = Some(Instant::now());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resumes_after_the_checkpoint() {
+        let path = std::env::temp_dir().join(format!("sfr-checkpoint-{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let store = FileCheckpointStore::new(&path);
+
+        let mut checkpointer = Checkpointer::new(store).with_interval(Duration::from_secs(60));
+        assert_eq!(checkpointer.resume_sequence_number().unwrap(), 0);
+
+        checkpointer.processed(10).unwrap();
+        checkpointer.processed(11).unwrap();
+        // Throttled until the next flush.
+        assert_eq!(checkpointer.store().load().unwrap(), Some(10));
+        checkpointer.flush().unwrap();
+
+        let restarted = Checkpointer::new(FileCheckpointStore::new(&path));
+        let options = restarted.resume(ConnectOptions::new()).unwrap();
+        assert_eq!(options.sequence_number, 12);
+
+        fs::write(&path, "garbage").unwrap();
+        assert!(matches!(
+            restarted.store().load(),
+            Err(CheckpointError::Corrupt(_))
+        ));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn saves_concurrently_without_leaving_temporary_files() {
+        let dir = std::env::temp_dir().join(format!("sfr-checkpoints-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let store = FileCheckpointStore::new(dir.join("checkpoint"));
+
+        std::thread::scope(|scope| {
+            for thread in 0..4 {
+                let store = &store;
+                scope.spawn(move || {
+                    for sequence_number in 0..50 {
+                        store.save(thread * 100 + sequence_number).unwrap();
+                    }
+                });
+            }
+        });
+
+        assert_eq!(store.load().unwrap().map(|n| n % 100), Some(49));
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
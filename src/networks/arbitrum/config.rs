@@ -0,0 +1,492 @@
+#[cfg(not(target_arch = "wasm32"))]
+use crate::networks::arbitrum::connect::ConnectOptions;
+use crate::networks::arbitrum::{
+    decoder::{DecodeOptions, DecodedMsg, DEFAULT_MAX_L2_MESSAGE_SIZE},
+    errors::ConfigError,
+    message::{FeedMessage, FeedTransaction},
+    network::ArbitrumNetwork,
+};
+use ethers::types::H160;
+use serde::{de, Deserialize, Deserializer};
+use std::{fs, path::Path, time::Duration};
+use url::Url;
+
+/// The prefix of the environment variables overriding the configuration file.
+pub const ENV_PREFIX: &str = "SEQUENCER_FEED_";
+
+/// How a `FeedService` is set up, read from a TOML file and environment variables so that
+/// deployments can be configured without recompiling.
+///
+/// # Example
+///
+/// ```toml
+/// network = "one"
+/// relays = ["wss://arb1.arbitrum.io/feed", "wss://relay.example.com/feed"]
+///
+/// [reconnect]
+/// delay_ms = 500
+///
+/// [filter]
+/// to = ["0x4752ba5dbc23f44d87826276bf6fd6b1c372ad24"]
+///
+/// [[priority]]
+/// to = ["0x5e325eda8064b456f4781070c0738d849c824258"]
+///
+/// [[sink]]
+/// type = "kafka"
+/// brokers = "localhost:9092"
+/// topic = "arbitrum-feed"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// A public network, providing the chain ID and relay when they are not set.
+    pub network: Option<ArbitrumNetwork>,
+    pub chain_id: Option<u64>,
+    /// The relays to read from. The first one is the primary, the others hot standbys.
+    #[serde(default)]
+    pub relays: Vec<Url>,
+    #[serde(default)]
+    pub reconnect: ReconnectConfig,
+    #[serde(default)]
+    pub connection: ConnectionConfig,
+    #[serde(default)]
+    pub decode: DecodeConfig,
+    #[serde(default)]
+    pub filter: FilterConfig,
+    /// High priority filters: the transactions matching any of them are also delivered on the
+    /// priority lane, ahead of the ordered stream, see `FeedService::with_priority_output`.
+    #[serde(default)]
+    pub priority: Vec<FilterConfig>,
+    /// Emits a `FeedEvent::Summary` of the statistics of the feed every this many seconds, if
+    /// set.
+    pub summary_period_secs: Option<u64>,
+    /// Marks the messages seen before among the last this many, see `Deduplicator`, if set.
+    pub dedup_window: Option<usize>,
+    /// A Nitro node whose `arb_checkPublisherHealth` method cross-checks the suspected sequencer
+    /// failovers, see `SequencerHealth`, if set.
+    pub health_rpc: Option<Url>,
+    #[serde(default, rename = "sink")]
+    pub sinks: Vec<SinkConfig>,
+}
+
+/// How a `RelayFailover` picks the relays whose messages are forwarded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RelayStrategy {
+    /// Forwards the messages of the active relay, promoting a standby when it fails.
+    #[default]
+    Failover,
+    /// Forwards every message from whichever relay delivers it first, for the lowest latency.
+    FirstWins,
+}
+
+/// How relays are reconnected and failed over.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReconnectConfig {
+    /// The delay before the first reconnection attempt, doubled after every failed attempt.
+    pub delay_ms: u64,
+    /// The longest delay between reconnection attempts.
+    pub max_delay_ms: u64,
+    /// How long the active relay may stay silent while a standby keeps delivering.
+    pub stall_timeout_ms: u64,
+    /// `failover`, or `first_wins` to race the relays.
+    pub strategy: RelayStrategy,
+    /// Cross-checks the messages of the relays over this many sequence numbers, if set.
+    pub consistency_window: Option<usize>,
+}
+
+impl Default for ReconnectConfig {
+    fn default() -> Self {
+        Self {
+            delay_ms: 1_000,
+            max_delay_ms: 30_000,
+            stall_timeout_ms: 2_000,
+            strategy: RelayStrategy::Failover,
+            consistency_window: None,
+        }
+    }
+}
+
+impl ReconnectConfig {
+    /// Returns the delay before reconnection attempt `attempt`, starting at 0.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let delay = self.delay_ms.saturating_mul(1 << attempt.min(16));
+        Duration::from_millis(delay.min(self.max_delay_ms))
+    }
+}
+
+/// The deadlines of relay connections, see `ConnectOptions`. Unset deadlines keep their default.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConnectionConfig {
+    pub connect_timeout_ms: Option<u64>,
+    pub handshake_timeout_ms: Option<u64>,
+    pub read_timeout_ms: Option<u64>,
+}
+
+impl ConnectionConfig {
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn connect_options(&self) -> ConnectOptions {
+        let mut options = ConnectOptions::new();
+        if let Some(ms) = self.connect_timeout_ms {
+            options.connect_timeout = Some(Duration::from_millis(ms));
+        }
+        if let Some(ms) = self.handshake_timeout_ms {
+            options.handshake_timeout = Some(Duration::from_millis(ms));
+        }
+        options.read_timeout = self.read_timeout_ms.map(Duration::from_millis);
+        options
+    }
+}
+
+/// How messages are decoded, see `DecodeOptions`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DecodeConfig {
+    /// The size above which L2 messages are delivered undecoded.
+    pub max_l2_message_size: usize,
+}
+
+impl Default for DecodeConfig {
+    fn default() -> Self {
+        Self {
+            max_l2_message_size: DEFAULT_MAX_L2_MESSAGE_SIZE,
+        }
+    }
+}
+
+impl DecodeConfig {
+    pub fn decode_options(&self) -> DecodeOptions {
+        DecodeOptions {
+            max_l2_message_size: self.max_l2_message_size,
+            ..DecodeOptions::default()
+        }
+    }
+}
+
+/// Which messages are delivered. Empty lists match every message, and `from`, `to` and
+/// `selectors` must all match the same transaction.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FilterConfig {
+    /// The L1 message kinds to deliver.
+    pub kinds: Vec<u8>,
+    /// Delivers messages with a transaction sent from one of these addresses. Never matches
+    /// messages whose senders weren't recovered, see `Provenance::degraded`.
+    pub from: Vec<H160>,
+    /// Delivers messages with a transaction sent to one of these addresses.
+    pub to: Vec<H160>,
+    /// Delivers messages with a transaction calling one of these function selectors, the first
+    /// four bytes of its input, written as hex strings such as `"0xa9059cbb"`.
+    #[serde(deserialize_with = "selectors")]
+    pub selectors: Vec<[u8; 4]>,
+}
+
+impl FilterConfig {
+    /// Returns `true` if `msg` matches every set filter.
+    pub fn matches(&self, msg: &FeedMessage) -> bool {
+        let kind = msg.message.message.message.header.kind;
+        if !self.kinds.is_empty() && !self.kinds.contains(&kind) {
+            return false;
+        }
+        if self.from.is_empty() && self.to.is_empty() && self.selectors.is_empty() {
+            return true;
+        }
+
+        let txs = match &msg.decoded {
+            Ok(Some(DecodedMsg::DecodedBatch(txs))) => txs.iter().collect(),
+            Ok(Some(DecodedMsg::DecodedSignedTx(tx))) => vec![tx.as_ref()],
+            _ => Vec::new(),
+        };
+        txs.iter().any(|tx| {
+            (self.from.is_empty() || !msg.provenance.degraded && self.from.contains(&tx.from))
+                && (self.to.is_empty() || tx.to.is_some_and(|to| self.to.contains(&to)))
+                && self.matches_selector(&tx.input)
+        })
+    }
+
+    /// Returns `true` if `tx` matches every set filter, on its own rather than as part of its
+    /// message.
+    pub fn matches_transaction(&self, tx: &FeedTransaction) -> bool {
+        (self.kinds.is_empty() || self.kinds.contains(&tx.header.kind))
+            && (self.from.is_empty() || tx.sender().is_some_and(|from| self.from.contains(&from)))
+            && (self.to.is_empty() || tx.tx.to.is_some_and(|to| self.to.contains(&to)))
+            && self.matches_selector(&tx.tx.input)
+    }
+
+    fn matches_selector(&self, input: &[u8]) -> bool {
+        self.selectors.is_empty()
+            || input
+                .get(..4)
+                .is_some_and(|selector| self.selectors.iter().any(|s| s[..] == *selector))
+    }
+}
+
+/// Parses function selectors from hex strings, with or without a `0x` prefix.
+fn selectors<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<[u8; 4]>, D::Error> {
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|selector| {
+            let mut bytes = [0; 4];
+            hex::decode_to_slice(selector.trim_start_matches("0x"), &mut bytes).map_err(|e| {
+                de::Error::custom(format!("invalid function selector {}: {}", selector, e))
+            })?;
+            Ok(bytes)
+        })
+        .collect()
+}
+
+/// A sink delivered to, selected by its `type`. Sinks are only available when the crate is
+/// built with their feature.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum SinkConfig {
+    Kafka {
+        brokers: String,
+        topic: String,
+        #[serde(default)]
+        protobuf: bool,
+    },
+    Nats {
+        url: String,
+        /// A `SubjectTemplate`.
+        subject: String,
+        #[serde(default)]
+        jetstream: bool,
+        #[serde(default)]
+        protobuf: bool,
+    },
+    Postgres {
+        url: String,
+        table: String,
+        /// Creates the table if it doesn't exist.
+        #[serde(default)]
+        create_table: bool,
+        /// The L2 block number of sequence number 0, required unless the chain is a public
+        /// Arbitrum chain, see `ArbitrumNetwork::genesis_block_number`.
+        #[serde(default)]
+        genesis_block_number: Option<u64>,
+    },
+}
+
+impl Config {
+    /// Reads the TOML configuration file at `path`, overridden by the environment variables.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        // Validated once overridden.
+        let config: Self = toml::from_str(&fs::read_to_string(path)?)?;
+        config.with_env(std::env::vars())
+    }
+
+    /// Reads the configuration from the environment variables only.
+    pub fn from_env() -> Result<Self, ConfigError> {
+        Self::default().with_env(std::env::vars())
+    }
+
+    /// Parses a TOML configuration.
+    pub fn from_toml(toml: &str) -> Result<Self, ConfigError> {
+        let config: Self = toml::from_str(toml)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Overrides the configuration with the variables prefixed with `ENV_PREFIX`:
+    /// `SEQUENCER_FEED_NETWORK`, `SEQUENCER_FEED_CHAIN_ID` and `SEQUENCER_FEED_RELAYS`, a comma
+    /// separated list of URLs.
+    pub fn with_env(
+        mut self,
+        vars: impl IntoIterator<Item = (String, String)>,
+    ) -> Result<Self, ConfigError> {
+        for (name, value) in vars {
+            let Some(key) = name.strip_prefix(ENV_PREFIX) else {
+                continue;
+            };
+            let invalid = |reason: String| ConfigError::Env {
+                name: name.clone(),
+                reason,
+            };
+            match key {
+                "NETWORK" => self.network = Some(value.parse().map_err(invalid)?),
+                "CHAIN_ID" => {
+                    self.chain_id = Some(
+                        value
+                            .parse()
+                            .map_err(|e: std::num::ParseIntError| invalid(e.to_string()))?,
+                    )
+                }
+                "RELAYS" => {
+                    self.relays = value
+                        .split(',')
+                        .map(|url| Url::parse(url.trim()))
+                        .collect::<Result<_, _>>()
+                        .map_err(|e| invalid(e.to_string()))?
+                }
+                _ => (),
+            }
+        }
+        self.validate()?;
+        Ok(self)
+    }
+
+    /// The chain ID of the relays: `chain_id`, or the one of `network`.
+    pub fn chain_id(&self) -> Result<u64, ConfigError> {
+        self.chain_id
+            .or(self.network.map(|network| network.chain_id()))
+            .ok_or_else(|| ConfigError::Invalid("either network or chain_id must be set".into()))
+    }
+
+    /// The relays to read from: `relays`, or the public relay of `network`.
+    pub fn relays(&self) -> Result<Vec<Url>, ConfigError> {
+        match (&self.relays[..], self.network) {
+            ([], Some(network)) => Ok(vec![network.feed_url()]),
+            ([], None) => Err(ConfigError::Invalid(
+                "either network or relays must be set".into(),
+            )),
+            (relays, _) => Ok(relays.to_vec()),
+        }
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        if let (Some(network), Some(chain_id)) = (self.network, self.chain_id) {
+            if network.chain_id() != chain_id {
+                return Err(ConfigError::Invalid(format!(
+                    "chain_id {} is not the one of {:?}",
+                    chain_id, network
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::networks::arbitrum::fixtures::feed_message;
+    use ethers::types::Transaction;
+
+    #[test]
+    fn parses_toml_with_env_overrides() {
+        let config = Config::from_toml(
+            r#"
+            network = "nova"
+            summary_period_secs = 300
+            dedup_window = 1000
+            health_rpc = "http://localhost:8547"
+
+            [reconnect]
+            delay_ms = 500
+            strategy = "first_wins"
+
+            [decode]
+            max_l2_message_size = 524288
+
+            [filter]
+            kinds = [3]
+            to = ["0x4752ba5dbc23f44d87826276bf6fd6b1c372ad24"]
+
+            [[priority]]
+            from = ["0x5e325eda8064b456f4781070c0738d849c824258"]
+
+            [[sink]]
+            type = "postgres"
+            url = "postgres://localhost/feed"
+            table = "transactions"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.chain_id().unwrap(), 42170);
+        assert_eq!(config.relays().unwrap(), [ArbitrumNetwork::Nova.feed_url()]);
+        assert_eq!(config.reconnect.backoff(2), Duration::from_secs(2));
+        assert_eq!(config.reconnect.backoff(10), Duration::from_secs(30));
+        assert_eq!(config.reconnect.strategy, RelayStrategy::FirstWins);
+        assert_eq!(
+            config.decode.decode_options().max_l2_message_size,
+            512 * 1024
+        );
+        assert_eq!(config.filter.to.len(), 1);
+        assert_eq!(config.priority[0].from.len(), 1);
+        assert_eq!(config.summary_period_secs, Some(300));
+        assert_eq!(config.dedup_window, Some(1000));
+        assert_eq!(config.health_rpc.as_ref().unwrap().port(), Some(8547));
+        assert_eq!(
+            config.sinks,
+            [SinkConfig::Postgres {
+                url: "postgres://localhost/feed".into(),
+                table: "transactions".into(),
+                create_table: false,
+                genesis_block_number: None,
+            }]
+        );
+
+        let env = |pairs: &[(&str, &str)]| {
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect::<Vec<_>>()
+        };
+        let config = config
+            .with_env(env(&[
+                ("SEQUENCER_FEED_NETWORK", "one"),
+                ("SEQUENCER_FEED_RELAYS", "ws://a:9642, ws://b:9642"),
+                ("PATH", "/usr/bin"),
+            ]))
+            .unwrap();
+        assert_eq!(config.chain_id().unwrap(), 42161);
+        assert_eq!(config.relays().unwrap().len(), 2);
+
+        assert!(Config::from_toml("network = \"one\"\nchain_id = 1").is_err());
+        assert!(Config::from_toml("relay = []").is_err());
+        assert!(Config::default()
+            .with_env(env(&[("SEQUENCER_FEED_CHAIN_ID", "x")]))
+            .is_err());
+    }
+
+    #[test]
+    fn matches_from_and_to_on_the_same_transaction() {
+        let tx = |from: u8, to: u8| Transaction {
+            from: H160::repeat_byte(from),
+            to: Some(H160::repeat_byte(to)),
+            ..Default::default()
+        };
+        let mut msg = feed_message(
+            1,
+            0,
+            vec![],
+            Some(DecodedMsg::DecodedBatch(vec![tx(1, 2), tx(3, 4)])),
+        );
+        let filter = |from: u8, to: u8| FilterConfig {
+            from: vec![H160::repeat_byte(from)],
+            to: vec![H160::repeat_byte(to)],
+            ..Default::default()
+        };
+
+        assert!(filter(1, 2).matches(&msg));
+        assert!(filter(3, 4).matches(&msg));
+        assert!(!filter(1, 4).matches(&msg));
+        let selector = FilterConfig {
+            selectors: vec![[0xa9, 0x05, 0x9c, 0xbb]],
+            ..filter(1, 2)
+        };
+        assert!(!selector.matches(&msg));
+        if let Ok(Some(DecodedMsg::DecodedBatch(txs))) = &mut msg.decoded {
+            txs[1].input = vec![0xa9, 0x05, 0x9c, 0xbb].into();
+        }
+        // The selector is only called by the other transaction.
+        assert!(!selector.matches(&msg));
+        assert!(FilterConfig {
+            selectors: selector.selectors.clone(),
+            ..filter(3, 4)
+        }
+        .matches(&msg));
+        msg.provenance.degraded = true;
+        assert!(!filter(1, 2).matches(&msg));
+        assert!(FilterConfig {
+            to: vec![H160::repeat_byte(2)],
+            ..Default::default()
+        }
+        .matches(&msg));
+    }
+}
@@ -0,0 +1,222 @@
+//! Conformance suites for implementations of the storage traits.
+//!
+//! Each suite exercises the semantics documented on its trait and panics with a description of
+//! the first violated requirement, so they can be called from the tests of a custom backend:
+//!
+//! ```no_run
+//! use sequencer_feed_reader::networks::arbitrum::{checkpoint::FileCheckpointStore, conformance};
+//!
+//! // In a test, against a scratch location of the backend.
+//! conformance::checkpoint_store(|| FileCheckpointStore::new("/tmp/conformance-checkpoint"));
+//! ```
+//!
+//! Suites expect to start from empty storage.
+
+use crate::networks::arbitrum::{
+    archive::store::ArchiveStore,
+    audit::{AuditEntry, AuditSink},
+    checkpoint::CheckpointStore,
+};
+use futures::future::join_all;
+use serde_json::json;
+
+/// Checks the semantics of `CheckpointStore`.
+///
+/// # Arguments
+///
+/// * `open` - Opens a store on the storage under test. Called several times to check that
+///   checkpoints survive a restart.
+pub fn checkpoint_store<S, F>(open: F)
+where
+    S: CheckpointStore,
+    F: Fn() -> S,
+{
+    let store = open();
+    assert_eq!(
+        store.load().expect("load from empty storage"),
+        None,
+        "empty storage has no checkpoint"
+    );
+
+    for sequence_number in [5, 1_000_000, u64::MAX] {
+        store.save(sequence_number).expect("save");
+        assert_eq!(
+            store.load().expect("load"),
+            Some(sequence_number),
+            "load returns the last saved sequence number"
+        );
+    }
+
+    store.save(42).expect("save a lower sequence number");
+    assert_eq!(
+        store.load().expect("load"),
+        Some(42),
+        "save overwrites higher sequence numbers"
+    );
+
+    drop(store);
+    assert_eq!(
+        open().load().expect("load after reopening"),
+        Some(42),
+        "checkpoints survive a restart"
+    );
+}
+
+/// Checks the semantics of `ArchiveStore`.
+///
+/// # Arguments
+///
+/// * `open` - Opens a store on the storage under test. Called several times to check that
+///   segments are durable.
+pub async fn archive_store<S, F>(open: F)
+where
+    S: ArchiveStore,
+    F: Fn() -> S,
+{
+    let store = open();
+    assert!(
+        store.list().await.expect("list empty storage").is_empty(),
+        "empty storage has no segments"
+    );
+    assert_eq!(
+        store
+            .get("missing.jsonl")
+            .await
+            .expect("get missing segment"),
+        None,
+        "get returns None for unknown segments"
+    );
+
+    store.put("2.jsonl", b"second".to_vec()).await.expect("put");
+    store.put("1.jsonl", b"first".to_vec()).await.expect("put");
+    assert_eq!(
+        store.list().await.expect("list"),
+        vec!["1.jsonl", "2.jsonl"],
+        "list returns every segment, sorted"
+    );
+    assert_eq!(
+        store.get("1.jsonl").await.expect("get").as_deref(),
+        Some(&b"first"[..]),
+        "get returns the segment put"
+    );
+
+    store
+        .put("1.jsonl", b"replaced".to_vec())
+        .await
+        .expect("put");
+    assert_eq!(
+        store.get("1.jsonl").await.expect("get").as_deref(),
+        Some(&b"replaced"[..]),
+        "put replaces segments of the same name"
+    );
+
+    let large = vec![0xab; 4 * 1024 * 1024];
+    store
+        .put("large.jsonl", large.clone())
+        .await
+        .expect("put large segment");
+    assert_eq!(
+        store.get("large.jsonl").await.expect("get large segment"),
+        Some(large),
+        "large segments are stored intact"
+    );
+
+    store.delete("2.jsonl").await.expect("delete");
+    store.delete("2.jsonl").await.expect("delete is idempotent");
+    store.delete("large.jsonl").await.expect("delete");
+    assert_eq!(
+        store.list().await.expect("list"),
+        vec!["1.jsonl"],
+        "deleted segments are not listed"
+    );
+
+    drop(store);
+    assert_eq!(
+        open()
+            .get("1.jsonl")
+            .await
+            .expect("get after reopening")
+            .as_deref(),
+        Some(&b"replaced"[..]),
+        "segments are durable"
+    );
+}
+
+/// Checks the semantics of `AuditSink`.
+///
+/// # Arguments
+///
+/// * `sink` - The sink under test.
+/// * `recorded` - Reads back the entries durably recorded by the sink, in order.
+pub async fn audit_sink<S, F>(sink: &S, recorded: F)
+where
+    S: AuditSink,
+    F: FnOnce() -> Vec<AuditEntry>,
+{
+    let entry = |i: u64| AuditEntry {
+        at_ms: 1_700_000_000_000 + i,
+        relay_id: Some(i as u32),
+        kind: "conformance".to_string(),
+        detail: json!({ "index": i }),
+    };
+
+    for i in 0..10 {
+        sink.record(&entry(i)).await.expect("record");
+    }
+    let concurrent: Vec<_> = (10..20).map(entry).collect();
+    for result in join_all(concurrent.iter().map(|entry| sink.record(entry))).await {
+        result.expect("concurrent record");
+    }
+    sink.flush().await.expect("flush");
+
+    let entries = recorded();
+    assert_eq!(entries.len(), 20, "every entry is recorded once flushed");
+    assert_eq!(
+        entries[..10],
+        (0..10).map(entry).collect::<Vec<_>>(),
+        "entries recorded by a single caller are kept in order"
+    );
+    let mut rest: Vec<_> = entries[10..].iter().map(|entry| entry.at_ms).collect();
+    rest.sort();
+    assert_eq!(
+        rest,
+        concurrent
+            .iter()
+            .map(|entry| entry.at_ms)
+            .collect::<Vec<_>>(),
+        "concurrently recorded entries are all kept"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::networks::arbitrum::{
+        archive::store::LocalArchiveStore, audit::JsonLinesAuditSink,
+        checkpoint::FileCheckpointStore,
+    };
+    use std::{env, fs};
+
+    #[tokio::test]
+    async fn builtin_stores_conform() {
+        let dir = env::temp_dir().join(format!("sfr-conformance-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        checkpoint_store(|| FileCheckpointStore::new(dir.join("checkpoint")));
+        archive_store(|| LocalArchiveStore::new(dir.join("archive")).unwrap()).await;
+
+        let audit_path = dir.join("audit.jsonl");
+        let sink = JsonLinesAuditSink::open(&audit_path).unwrap();
+        audit_sink(&sink, || {
+            fs::read_to_string(&audit_path)
+                .unwrap()
+                .lines()
+                .map(|line| serde_json::from_str(line).unwrap())
+                .collect()
+        })
+        .await;
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
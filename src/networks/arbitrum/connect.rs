@@ -0,0 +1,315 @@
+use crate::networks::arbitrum::{
+    errors::RelayError, identity::RelayInfo, proxy::Proxy, tls::TlsOptions,
+};
+use base64::{engine::general_purpose, Engine as _};
+use std::{fmt, future::Future, time::Duration};
+use tokio::net::TcpStream;
+use tokio_tungstenite::{client_async, client_async_tls, MaybeTlsStream, WebSocketStream};
+use tungstenite::http::{Request, Response};
+use url::Url;
+
+/// The credentials sent on the upgrade request to relays requiring authentication.
+#[derive(Clone, PartialEq, Eq)]
+pub enum RelayAuth {
+    /// HTTP Basic authentication.
+    Basic { username: String, password: String },
+    /// A bearer token, sent as `Authorization: Bearer <token>`.
+    Bearer(String),
+    /// An arbitrary header, e.g. an API key header required by a hosted feed provider.
+    Header { name: String, value: String },
+}
+
+impl RelayAuth {
+    /// Returns the name and value of the header carrying the credentials.
+    pub fn header(&self) -> (&str, String) {
+        match self {
+            RelayAuth::Basic { username, password } => (
+                "Authorization",
+                format!(
+                    "Basic {}",
+                    general_purpose::STANDARD.encode(format!("{}:{}", username, password))
+                ),
+            ),
+            RelayAuth::Bearer(token) => ("Authorization", format!("Bearer {}", token)),
+            RelayAuth::Header { name, value } => (name, value.clone()),
+        }
+    }
+}
+
+impl fmt::Debug for RelayAuth {
+    /// Never prints the secrets, so options can be logged safely.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RelayAuth::Basic { username, .. } => f
+                .debug_struct("Basic")
+                .field("username", username)
+                .finish_non_exhaustive(),
+            RelayAuth::Bearer(_) => f.write_str("Bearer(..)"),
+            RelayAuth::Header { name, .. } => f
+                .debug_struct("Header")
+                .field("name", name)
+                .finish_non_exhaustive(),
+        }
+    }
+}
+
+/// What a relay announced in the response to the websocket upgrade request, so that clients can
+/// log and act on the capabilities of the server.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HandshakeInfo {
+    /// The version of the feed protocol spoken by the server, from `Arbitrum-Feed-Server-Version`.
+    pub feed_server_version: Option<u64>,
+    /// The chain ID of the feed, from `Arbitrum-Chain-Id`.
+    pub chain_id: Option<u64>,
+    /// The sequence number the server agreed to start the feed at, echoed in
+    /// `Arbitrum-Requested-Sequence-Number`. Servers not supporting resumption omit it.
+    pub requested_sequence_number: Option<u64>,
+}
+
+impl HandshakeInfo {
+    /// Reads the Arbitrum headers of a handshake response, ignoring the malformed ones.
+    pub fn from_response<T>(response: &Response<T>) -> Self {
+        let header = |name: &str| -> Option<u64> {
+            response
+                .headers()
+                .get(name)?
+                .to_str()
+                .ok()?
+                .trim()
+                .parse()
+                .ok()
+        };
+        Self {
+            feed_server_version: header("arbitrum-feed-server-version"),
+            chain_id: header("arbitrum-chain-id"),
+            requested_sequence_number: header("arbitrum-requested-sequence-number"),
+        }
+    }
+
+    /// Returns `true` if the server agreed to start the feed at `sequence_number`.
+    pub fn accepted_sequence_number(&self, sequence_number: u64) -> bool {
+        self.requested_sequence_number == Some(sequence_number)
+    }
+}
+
+/// The default deadline of the TCP connection, proxy tunnel included.
+const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// The default deadline of the TLS and websocket handshakes.
+const DEFAULT_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How a `RelayClient` connects to its relay.
+#[derive(Debug, Clone)]
+pub struct ConnectOptions {
+    /// The sequence number of the first message to emit.
+    pub sequence_number: u64,
+    /// The proxy to connect through, if any.
+    pub proxy: Option<Proxy>,
+    /// The TLS setup used for `wss` relays, instead of the default one.
+    pub tls: Option<TlsOptions>,
+    /// The credentials sent on the upgrade request, if any.
+    pub auth: Option<RelayAuth>,
+    /// The deadline of the TCP connection, proxy tunnel included.
+    pub connect_timeout: Option<Duration>,
+    /// The deadline of the TLS and websocket handshakes.
+    pub handshake_timeout: Option<Duration>,
+    /// How long the client waits for a frame before giving up on the relay. Disabled by default.
+    pub read_timeout: Option<Duration>,
+    /// Accepts the chain ID announced by the relay instead of checking it against the expected
+    /// one, see `RelayClient::chain_id`.
+    pub detect_chain_id: bool,
+    /// Fails with `RelayError::SequenceGap` when the relay starts the feed after
+    /// `sequence_number`, instead of only logging it.
+    pub contiguous_start: bool,
+    /// The name of the relay in logs and metrics, see `RelayInfo`.
+    pub name: Option<String>,
+    /// Where the relay is hosted, reported in logs and metrics.
+    pub region: Option<String>,
+}
+
+impl Default for ConnectOptions {
+    fn default() -> Self {
+        Self {
+            sequence_number: 0,
+            proxy: None,
+            tls: None,
+            auth: None,
+            connect_timeout: Some(DEFAULT_CONNECT_TIMEOUT),
+            handshake_timeout: Some(DEFAULT_HANDSHAKE_TIMEOUT),
+            read_timeout: None,
+            detect_chain_id: false,
+            contiguous_start: false,
+            name: None,
+            region: None,
+        }
+    }
+}
+
+impl ConnectOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts the feed at `sequence_number`, see `RelayClient::new_from`.
+    pub fn with_sequence_number(mut self, sequence_number: u64) -> Self {
+        self.sequence_number = sequence_number;
+        self
+    }
+
+    /// Establishes the websocket connection through `proxy`.
+    pub fn with_proxy(mut self, proxy: Proxy) -> Self {
+        self.proxy = Some(proxy);
+        self
+    }
+
+    /// Connects to `wss` relays with a custom TLS setup.
+    pub fn with_tls(mut self, tls: TlsOptions) -> Self {
+        self.tls = Some(tls);
+        self
+    }
+
+    /// Authenticates with the relay using `auth`.
+    pub fn with_auth(mut self, auth: RelayAuth) -> Self {
+        self.auth = Some(auth);
+        self
+    }
+
+    /// Sets the deadlines of the connection and of the handshakes; `None` waits forever.
+    pub fn with_timeouts(
+        mut self,
+        connect_timeout: Option<Duration>,
+        handshake_timeout: Option<Duration>,
+    ) -> Self {
+        self.connect_timeout = connect_timeout;
+        self.handshake_timeout = handshake_timeout;
+        self
+    }
+
+    /// Fails with `RelayError::Timeout` once no frame has been received for `read_timeout`.
+    pub fn with_read_timeout(mut self, read_timeout: Duration) -> Self {
+        self.read_timeout = Some(read_timeout);
+        self
+    }
+
+    /// Reads the chain ID from the `arbitrum-chain-id` header of the relay instead of failing
+    /// when it isn't the expected one, for tools connecting to arbitrary relays.
+    pub fn with_chain_id_detection(mut self) -> Self {
+        self.detect_chain_id = true;
+        self
+    }
+
+    /// Fails when the relay no longer has the message at `sequence_number` and starts the feed
+    /// after it, for consumers resuming from stored history that can't tolerate a gap.
+    pub fn with_contiguous_start(mut self) -> Self {
+        self.contiguous_start = true;
+        self
+    }
+
+    /// Names the relay in logs and metrics, e.g. `arb1-primary`.
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Reports where the relay is hosted in logs and metrics, e.g. `eu-west`.
+    pub fn with_region(mut self, region: impl Into<String>) -> Self {
+        self.region = Some(region.into());
+        self
+    }
+
+    /// Returns the `RelayInfo` of the relay at `url` read by the client `id` with these options.
+    pub fn relay_info(&self, id: u32, url: &Url) -> RelayInfo {
+        RelayInfo::new(id, url)
+            .with_name(self.name.clone())
+            .with_region(self.region.clone())
+    }
+
+    /// Performs the websocket handshake with the relay at `url`.
+    pub(crate) async fn open(
+        &self,
+        url: &Url,
+        request: Request<()>,
+    ) -> Result<
+        (
+            WebSocketStream<MaybeTlsStream<TcpStream>>,
+            Response<Option<Vec<u8>>>,
+        ),
+        RelayError,
+    > {
+        let host = url.host_str().ok_or(RelayError::InvalidUrl)?;
+        let port = url.port_or_known_default().ok_or(RelayError::InvalidUrl)?;
+        let stream = with_deadline("connect", self.connect_timeout, async {
+            match &self.proxy {
+                Some(proxy) => proxy.connect(host, port).await,
+                None => Ok(TcpStream::connect((host, port)).await?),
+            }
+        })
+        .await?;
+        stream.set_nodelay(true)?;
+
+        let tls = self.tls.as_ref().filter(|_| url.scheme() == "wss");
+        with_deadline("handshake", self.handshake_timeout, async {
+            match tls {
+                Some(tls) => {
+                    let stream = tls.handshake(host, stream).await?;
+                    Ok(client_async(request, MaybeTlsStream::Rustls(stream)).await?)
+                }
+                None => Ok(client_async_tls(request, stream).await?),
+            }
+        })
+        .await
+    }
+}
+
+/// Runs `future`, failing with `RelayError::Timeout` if it doesn't complete within `timeout`.
+async fn with_deadline<T>(
+    stage: &'static str,
+    timeout: Option<Duration>,
+    future: impl Future<Output = Result<T, RelayError>>,
+) -> Result<T, RelayError> {
+    match timeout {
+        Some(after) => tokio::time::timeout(after, future)
+            .await
+            .map_err(|_| RelayError::Timeout { stage, after })?,
+        None => future.await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_handshake_headers() {
+        let response = Response::builder()
+            .header("Arbitrum-Feed-Server-Version", "2")
+            .header("Arbitrum-Chain-Id", "42161")
+            .header("Arbitrum-Requested-Sequence-Number", "x")
+            .body(())
+            .unwrap();
+        let info = HandshakeInfo::from_response(&response);
+        assert_eq!(info.feed_server_version, Some(2));
+        assert_eq!(info.chain_id, Some(42161));
+        assert_eq!(info.requested_sequence_number, None);
+        assert!(!info.accepted_sequence_number(0));
+    }
+
+    #[test]
+    fn auth_headers_and_redacted_debug() {
+        let basic = RelayAuth::Basic {
+            username: "alice".to_string(),
+            password: "secret".to_string(),
+        };
+        assert_eq!(
+            basic.header(),
+            ("Authorization", "Basic YWxpY2U6c2VjcmV0".to_string())
+        );
+        assert!(!format!("{:?}", basic).contains("secret"));
+
+        let bearer = RelayAuth::Bearer("token".to_string());
+        assert_eq!(
+            bearer.header(),
+            ("Authorization", "Bearer token".to_string())
+        );
+        assert_eq!(format!("{:?}", bearer), "Bearer(..)");
+    }
+}
@@ -0,0 +1,161 @@
+//! Cross-checks of the messages delivered by several relays, to notice a misbehaving or malicious
+//! relay.
+
+use crate::networks::arbitrum::{
+    events::FeedEvent,
+    identity::RelayInfo,
+    types::{BroadcastFeedMessage, Root},
+};
+use log::*;
+use std::{
+    collections::{hash_map::RandomState, HashMap, VecDeque},
+    hash::{BuildHasher, Hasher},
+    sync::Arc,
+};
+
+/// How many sequence numbers a `ConsistencyChecker` remembers by default.
+pub const DEFAULT_CONSISTENCY_WINDOW: usize = 4096;
+
+/// Compares the messages of the same sequence number delivered by different relays.
+///
+/// The first copy of every message is remembered, by digest, for the last `window` sequence
+/// numbers. Every later copy from another relay differing from it raises a
+/// `FeedEvent::RelayMismatch`. A relay re-sending a message differently, after a reorg, is left
+/// to the reorg detection: see `reorg`.
+pub struct ConsistencyChecker {
+    window: usize,
+    /// Keys the digests, so that a relay can't forge a copy with the same digest.
+    hasher: RandomState,
+    /// The relay which delivered each message first, and the digest of the message.
+    seen: HashMap<u64, (Arc<RelayInfo>, u64)>,
+    /// The sequence numbers of `seen`, oldest first.
+    order: VecDeque<u64>,
+    mismatches: u64,
+}
+
+impl ConsistencyChecker {
+    pub fn new(window: usize) -> Self {
+        Self {
+            window: window.max(1),
+            hasher: RandomState::new(),
+            seen: HashMap::new(),
+            order: VecDeque::new(),
+            mismatches: 0,
+        }
+    }
+
+    /// Compares the messages of `root` with the copies of the same sequence numbers received
+    /// before, from any relay.
+    ///
+    /// # Returns
+    ///
+    /// A `FeedEvent::RelayMismatch` for every message differing from its first copy.
+    pub fn check(&mut self, root: &Root) -> Vec<FeedEvent> {
+        let relay = root.provenance.relay();
+        let mut events = Vec::new();
+        for msg in &root.messages {
+            let digest = self.digest(msg);
+            match self.seen.get(&msg.sequence_number) {
+                Some((first_relay, first)) if first_relay.id != relay.id && *first != digest => {
+                    warn!(
+                        "Relay {} delivered message {} differently than relay {}",
+                        relay, msg.sequence_number, first_relay
+                    );
+                    events.push(FeedEvent::RelayMismatch {
+                        sequence_number: msg.sequence_number,
+                        relay: (*relay).clone(),
+                        first_relay: (**first_relay).clone(),
+                    });
+                    self.mismatches += 1;
+                }
+                Some(_) => (),
+                None => self.remember(msg.sequence_number, relay.clone(), digest),
+            }
+        }
+        events
+    }
+
+    /// Forgets the messages from sequence number `from` on, superseded by a reorg, so that their
+    /// new contents are compared instead.
+    pub fn reorg(&mut self, from: u64) {
+        self.seen
+            .retain(|&sequence_number, _| sequence_number < from);
+        self.order.retain(|&sequence_number| sequence_number < from);
+    }
+
+    /// Returns how many mismatching messages were noticed.
+    pub fn mismatches(&self) -> u64 {
+        self.mismatches
+    }
+
+    fn digest(&self, msg: &BroadcastFeedMessage) -> u64 {
+        let mut hasher = self.hasher.build_hasher();
+        msg.hash_content(&mut hasher);
+        hasher.finish()
+    }
+
+    fn remember(&mut self, sequence_number: u64, relay: Arc<RelayInfo>, digest: u64) {
+        self.seen.insert(sequence_number, (relay, digest));
+        self.order.push_back(sequence_number);
+        while self.order.len() > self.window {
+            if let Some(oldest) = self.order.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::networks::arbitrum::{fixtures::message_with, provenance::Provenance};
+
+    #[test]
+    fn reports_relays_disagreeing_on_a_message() {
+        let root = |relay_id, messages| Root {
+            version: 1,
+            messages,
+            provenance: Provenance::live(relay_id, 0),
+        };
+        let mut checker = ConsistencyChecker::new(2);
+
+        let first = root(
+            0,
+            vec![
+                message_with(1, 10, vec![4, 1]),
+                message_with(2, 10, vec![4, 2]),
+            ],
+        );
+        assert!(checker.check(&first).is_empty());
+        assert!(checker.check(&root(1, first.messages.clone())).is_empty());
+
+        let tampered = root(2, vec![message_with(2, 10, vec![4, 3])]);
+        assert_eq!(
+            checker.check(&tampered),
+            vec![FeedEvent::RelayMismatch {
+                sequence_number: 2,
+                relay: RelayInfo::from_id(2),
+                first_relay: RelayInfo::from_id(0),
+            }]
+        );
+        assert_eq!(checker.mismatches(), 1);
+
+        // The same relay re-sending a message isn't a mismatch, nor is a reorged message.
+        assert!(checker
+            .check(&root(0, vec![message_with(2, 10, vec![4, 4])]))
+            .is_empty());
+        checker.reorg(2);
+        assert!(checker.check(&tampered).is_empty());
+        assert!(checker
+            .check(&root(1, vec![message_with(2, 10, vec![4, 3])]))
+            .is_empty());
+
+        // Message 1 falls out of the window once message 3 is received.
+        assert!(checker
+            .check(&root(0, vec![message_with(3, 11, vec![4])]))
+            .is_empty());
+        assert!(checker
+            .check(&root(2, vec![message_with(1, 10, vec![4, 9])]))
+            .is_empty());
+    }
+}
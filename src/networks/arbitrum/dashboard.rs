@@ -0,0 +1,79 @@
+use crate::networks::arbitrum::metrics::{MetricKind, RELAY_METRICS};
+use serde_json::{json, Value};
+
+/// The width of a dashboard panel, in Grafana grid units (out of 24).
+const PANEL_WIDTH: u64 = 12;
+const PANEL_HEIGHT: u64 = 8;
+
+/// Generates a Grafana dashboard showing every metric of `metrics::RELAY_METRICS`, one panel per
+/// metric and one series per relay.
+///
+/// Counters are plotted as per-second rates. The dashboard reads from a Prometheus data source
+/// chosen through its `datasource` variable.
+///
+/// # Arguments
+///
+/// * `title` - The title of the dashboard.
+pub fn grafana_dashboard(title: &str) -> Value {
+    let panels: Vec<Value> = RELAY_METRICS
+        .iter()
+        .enumerate()
+        .map(|(i, descriptor)| {
+            let name = descriptor.full_name();
+            let expr = match descriptor.kind {
+                MetricKind::Counter => format!("rate({}[$__rate_interval])", name),
+                MetricKind::Gauge => name,
+            };
+            let i = i as u64;
+            json!({
+                "id": i + 1,
+                "type": "timeseries",
+                "title": descriptor.help.trim_end_matches('.'),
+                "datasource": { "type": "prometheus", "uid": "${datasource}" },
+                "gridPos": {
+                    "h": PANEL_HEIGHT,
+                    "w": PANEL_WIDTH,
+                    "x": (i % 2) * PANEL_WIDTH,
+                    "y": (i / 2) * PANEL_HEIGHT,
+                },
+                "targets": [{
+                    "refId": "A",
+                    "expr": expr,
+                    "legendFormat": "relay {{relay_id}}",
+                }],
+            })
+        })
+        .collect();
+
+    json!({
+        "title": title,
+        "uid": null,
+        "schemaVersion": 39,
+        "time": { "from": "now-1h", "to": "now" },
+        "refresh": "10s",
+        "templating": {
+            "list": [{
+                "name": "datasource",
+                "type": "datasource",
+                "query": "prometheus",
+            }],
+        },
+        "panels": panels,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn one_panel_per_metric() {
+        let dashboard = grafana_dashboard("Feed");
+        let panels = dashboard["panels"].as_array().unwrap();
+        assert_eq!(panels.len(), RELAY_METRICS.len());
+        assert_eq!(
+            panels[0]["targets"][0]["expr"],
+            "rate(sequencer_feed_frames_received_total[$__rate_interval])"
+        );
+    }
+}
@@ -1,11 +1,36 @@
-use crate::networks::arbitrum::types::L1IncomingMessageHeader;
-use base64::{engine::general_purpose, Engine as _};
+#[cfg(feature = "alloy")]
+pub mod alloy;
+pub mod arbos;
+pub mod envelope;
+pub mod l1;
+pub mod registry;
+pub mod rlp;
+
+use crate::networks::arbitrum::{arena, errors::DecodeError, types::L1IncomingMessageHeader};
+use base64::{engine::general_purpose, DecodeSliceError, Engine as _};
+use envelope::decode_signed_tx_with;
 use ethers::{
-    types::{Transaction, H160},
-    utils::rlp::{self, DecoderError, Rlp},
+    types::{Bytes, Transaction, H160, U256},
+    utils::rlp::{Decodable, DecoderError, Rlp},
+};
+use l1::{
+    BatchPostingReport, EthDeposit, L1_MESSAGE_KIND_BATCH_POSTING_REPORT,
+    L1_MESSAGE_KIND_ETH_DEPOSIT,
 };
+use log::*;
+
+/// The default limit on the size of L2 messages and batch entries, Nitro's `MaxL2MessageSize`.
+pub const DEFAULT_MAX_L2_MESSAGE_SIZE: usize = 256 * 1024;
 
-const MAX_L2_MESSAGE_SIZE: usize = 256 * 1024;
+/// Batches may be nested; Nitro refuses to parse deeper than this.
+const MAX_BATCH_DEPTH: usize = 16;
+
+/// The L2 message kind of batches of L2 messages.
+pub const L2_MESSAGE_KIND_BATCH: u8 = 3;
+/// The L2 message kind of signed transactions.
+pub const L2_MESSAGE_KIND_SIGNED_TX: u8 = 4;
+/// The L2 message kind of signed transactions compressed by the sequencer.
+pub const L2_MESSAGE_KIND_SIGNED_COMPRESSED_TX: u8 = 7;
 
 enum L2MessageKind {
     UnsignedUserTx,
@@ -18,17 +43,39 @@ enum L2MessageKind {
     SignedCompressedTx,
 }
 
-impl From<u8> for L2MessageKind {
-    fn from(v: u8) -> Self {
+impl TryFrom<u8> for L2MessageKind {
+    type Error = u8;
+
+    fn try_from(v: u8) -> Result<Self, Self::Error> {
         match v {
-            0 => L2MessageKind::UnsignedUserTx,
-            1 => L2MessageKind::ContractTx,
-            2 => L2MessageKind::NonMutatingCall,
-            3 => L2MessageKind::Batch,
-            4 => L2MessageKind::SignedTx,
-            6 => L2MessageKind::Heartbeat,
-            7 => L2MessageKind::SignedCompressedTx,
-            _ => panic!("L2MessageKind {} is not supported", v),
+            0 => Ok(L2MessageKind::UnsignedUserTx),
+            1 => Ok(L2MessageKind::ContractTx),
+            2 => Ok(L2MessageKind::NonMutatingCall),
+            L2_MESSAGE_KIND_BATCH => Ok(L2MessageKind::Batch),
+            L2_MESSAGE_KIND_SIGNED_TX => Ok(L2MessageKind::SignedTx),
+            6 => Ok(L2MessageKind::Heartbeat),
+            L2_MESSAGE_KIND_SIGNED_COMPRESSED_TX => Ok(L2MessageKind::SignedCompressedTx),
+            _ => Err(v),
+        }
+    }
+}
+
+/// Controls the optional work done while decoding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeOptions {
+    /// Recover the sender of every transaction from its signature, the most expensive part of
+    /// decoding. When disabled, `Transaction::from` is left as the zero address.
+    pub recover_senders: bool,
+    /// Larger L2 messages and batch entries fail with `DecodeError::MessageTooLarge`. Arbitrum
+    /// has raised its limit before, so decoders may need to follow without a release.
+    pub max_l2_message_size: usize,
+}
+
+impl Default for DecodeOptions {
+    fn default() -> Self {
+        Self {
+            recover_senders: true,
+            max_l2_message_size: DEFAULT_MAX_L2_MESSAGE_SIZE,
         }
     }
 }
@@ -42,10 +89,56 @@ pub enum Action {
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum DecodedMsg {
     DecodedBatch(Vec<Transaction>),
-    DecodedSignedTx(Transaction),
+    DecodedSignedTx(Box<Transaction>),
+    /// A call run against the current state without being sequenced, such as an `eth_call`.
+    DecodedCall(NonMutatingCall),
+    /// A deprecated heartbeat, still sent by some relays while the chain is idle. Carries no
+    /// payload, but tells liveness monitors the sequencer is up.
+    Heartbeat,
+    /// The report of a batch posted to L1, an L1 message rather than an L2 message.
+    BatchPostingReport(BatchPostingReport),
+    /// ETH deposited from L1, an L1 message rather than an L2 message.
+    EthDeposit(EthDeposit),
+    /// A message of a kind specific to a customized chain, produced by a `DecodeHook`.
+    Custom {
+        kind: u8,
+        payload: Vec<u8>,
+    },
+}
+
+/// The payload of a `NonMutatingCall` L2 message: 32-byte big-endian gas limit and max fee per
+/// gas, the target left-padded to 32 bytes, then the calldata.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NonMutatingCall {
+    pub gas_limit: U256,
+    pub max_fee_per_gas: U256,
+    pub to: H160,
+    pub data: Bytes,
+}
+
+impl NonMutatingCall {
+    /// Decodes the payload of a `NonMutatingCall` L2 message, its kind byte excluded.
+    pub fn decode(payload: &[u8]) -> Result<Self, DecodeError> {
+        let word = |offset: usize, field: &'static str| {
+            payload
+                .get(offset..offset + 32)
+                .ok_or(DecodeError::TruncatedField {
+                    field,
+                    needed: offset + 32,
+                    available: payload.len(),
+                })
+        };
+
+        Ok(Self {
+            gas_limit: U256::from_big_endian(word(0, "gas limit")?),
+            max_fee_per_gas: U256::from_big_endian(word(32, "max fee per gas")?),
+            to: H160::from_slice(&word(64, "call target")?[12..]),
+            data: Bytes::from(payload[96..].to_vec()),
+        })
+    }
 }
 
-impl rlp::Decodable for Action {
+impl Decodable for Action {
     /// Decodes an RLP-encoded `Action` object and returns a `Result` containing the decoded object or a `DecoderError`.
     fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
         if rlp.is_empty() {
@@ -61,90 +154,565 @@ impl rlp::Decodable for Action {
 }
 
 impl L1IncomingMessageHeader {
-    /// Decodes the L2 message and returns a `DecodedMsg` if successful.
-    /// Returns `None` if the L2 message length exceeds `MAX_L2_MESSAGE_SIZE`.
+    /// Decodes the L2 message with the default `DecodeOptions` and returns a `DecodedMsg` if
+    /// successful.
+    ///
+    /// Returns `None` if the L2 message is malformed, if its kind is not supported, or if it
+    /// exceeds the default size limit, which is logged as a warning. Use `try_decode_with` to
+    /// configure the limit and tell these cases apart.
     pub fn decode(&self) -> Option<DecodedMsg> {
-        if self.l2msg.len() > MAX_L2_MESSAGE_SIZE {
-            return None;
+        match self.try_decode() {
+            Ok(decoded) => decoded,
+            Err(e @ DecodeError::MessageTooLarge { .. }) => {
+                warn!("Skipped L2 message: {}", e);
+                None
+            }
+            Err(e) => {
+                debug!("Failed to decode L2 message: {}", e);
+                None
+            }
         }
+    }
+
+    /// Decodes the L2 message, reporting why a malformed message could not be decoded.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(None)` for well-formed messages of an unsupported kind, or a `DecodeError` if the
+    /// message is malformed.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use sequencer_feed_reader::networks::arbitrum::{
+    ///     decoder::DecodedMsg, types::BroadcastFeedMessage,
+    /// };
+    ///
+    /// fn handle(msg: &BroadcastFeedMessage) {
+    ///     match msg.message.message.try_decode() {
+    ///         Ok(Some(DecodedMsg::DecodedBatch(txs))) => println!("{} transactions", txs.len()),
+    ///         Ok(Some(DecodedMsg::DecodedSignedTx(tx))) => println!("transaction {:?}", tx.hash),
+    ///         Ok(_) => {} // Other messages, or kinds the decoder doesn't support.
+    ///         Err(e) => eprintln!("malformed message: {}", e),
+    ///     }
+    /// }
+    /// ```
+    pub fn try_decode(&self) -> Result<Option<DecodedMsg>, DecodeError> {
+        self.try_decode_with(DecodeOptions::default())
+    }
+
+    /// Like `try_decode`, skipping the work disabled in `options`.
+    pub fn try_decode_with(
+        &self,
+        options: DecodeOptions,
+    ) -> Result<Option<DecodedMsg>, DecodeError> {
+        decode_message_with(
+            self.header.kind,
+            &self.header.sender,
+            self.l2_bytes_with(options)?,
+            options,
+        )
+    }
+
+    /// Returns the L2 message, checking it doesn't exceed `DEFAULT_MAX_L2_MESSAGE_SIZE`.
+    pub fn l2_bytes(&self) -> Result<&[u8], DecodeError> {
+        self.l2_bytes_with(DecodeOptions::default())
+    }
 
-        let l2_bytes = general_purpose::STANDARD
-            .decode(&self.l2msg)
-            .unwrap_or_default();
+    /// Returns the L2 message, checking it doesn't exceed the limit of `options`.
+    pub fn l2_bytes_with(&self, options: DecodeOptions) -> Result<&[u8], DecodeError> {
+        check_l2_size(&self.l2msg, options.max_l2_message_size)
+    }
+}
 
-        get_decoded_msg(l2_bytes)
+/// Decodes standard base64, the encoding of L2 messages in the feed.
+///
+/// With the `simd-base64` feature, the decoder uses the SIMD instructions supported by the CPU,
+/// as detected at runtime, and falls back to the scalar decoder otherwise.
+pub fn decode_base64(encoded: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    #[cfg(feature = "simd-base64")]
+    if let Ok(decoded) = base64_simd::STANDARD.decode_to_vec(encoded) {
+        return Ok(decoded);
     }
+    // Invalid input goes through the scalar decoder, which reports why it is invalid.
+    Ok(general_purpose::STANDARD.decode(encoded)?)
 }
 
-/// Decodes an L2 message from the given bytes and returns the decoded message.
+/// Like `decode_base64`, decoding into `out`, which must be at least
+/// `base64::decoded_len_estimate(encoded.len())` bytes long.
 ///
-/// # Arguments
+/// # Returns
 ///
-/// * `l2_bytes` - A vector of bytes representing the L2 message to be decoded.
+/// The number of bytes written to `out`.
 ///
-/// # Returns
+/// # Panics
 ///
-/// An `Option` containing the decoded message if the decoding was successful, or `None` otherwise.
+/// If `out` is shorter than the estimate.
+pub fn decode_base64_to_slice(encoded: &[u8], out: &mut [u8]) -> Result<usize, DecodeError> {
+    #[cfg(feature = "simd-base64")]
+    if let Ok(decoded) =
+        base64_simd::STANDARD.decode(encoded, base64_simd::AsOut::as_out(&mut *out))
+    {
+        return Ok(decoded.len());
+    }
+    match general_purpose::STANDARD.decode_slice(encoded, out) {
+        Ok(len) => Ok(len),
+        Err(DecodeSliceError::DecodeError(e)) => Err(e.into()),
+        Err(DecodeSliceError::OutputSliceTooSmall) => {
+            panic!("base64 output slice shorter than the decoded length estimate")
+        }
+    }
+}
+
+/// Decodes the base64 encoded payload of an L1 message of the given kind and sender.
+pub(crate) fn decode_l2msg(
+    kind: u8,
+    sender: &str,
+    l2msg: &str,
+    options: DecodeOptions,
+) -> Result<Option<DecodedMsg>, DecodeError> {
+    // Checked before decoding, so that oversized messages are not decoded at all.
+    check_encoded_l2_size(l2msg.as_bytes(), options.max_l2_message_size)?;
+    arena::with_decoded(l2msg.as_bytes(), |l2_bytes| {
+        decode_message_with(kind, sender, l2_bytes, options)
+    })?
+}
+
+/// Decodes the payload of an L1 message according to its kind: the L1 messages carrying their
+/// own payload format, or an L2 message for every other kind.
+fn decode_message_with(
+    kind: u8,
+    sender: &str,
+    l2_bytes: &[u8],
+    options: DecodeOptions,
+) -> Result<Option<DecodedMsg>, DecodeError> {
+    match kind {
+        L1_MESSAGE_KIND_ETH_DEPOSIT => Ok(Some(DecodedMsg::EthDeposit(EthDeposit::decode(
+            sender, l2_bytes,
+        )?))),
+        L1_MESSAGE_KIND_BATCH_POSTING_REPORT => Ok(Some(DecodedMsg::BatchPostingReport(
+            BatchPostingReport::decode(l2_bytes)?,
+        ))),
+        _ => get_decoded_msg_with(l2_bytes, options),
+    }
+}
+
+fn check_l2_size(l2_bytes: &[u8], max: usize) -> Result<&[u8], DecodeError> {
+    if l2_bytes.len() > max {
+        return Err(DecodeError::MessageTooLarge {
+            size: l2_bytes.len(),
+            max,
+        });
+    }
+
+    Ok(l2_bytes)
+}
+
+/// Like `check_l2_size`, for a base64 encoded L2 message, from the length it decodes to.
+fn check_encoded_l2_size(encoded: &[u8], max: usize) -> Result<(), DecodeError> {
+    let padding = encoded
+        .iter()
+        .rev()
+        .take(2)
+        .take_while(|&&b| b == b'=')
+        .count();
+    let size = (encoded.len() / 4 * 3 + encoded.len() % 4 * 3 / 4).saturating_sub(padding);
+    if size > max {
+        return Err(DecodeError::MessageTooLarge { size, max });
+    }
+
+    Ok(())
+}
+
+/// Decodes an L2 message from the given bytes and returns the decoded message.
 ///
-/// # Example
+/// # Arguments
 ///
-/// ```
-/// use sequencer_feed_reader::networks::arbitrum::decoder::get_decoded_msg;
-/// use sequencer_feed_reader::networks::arbitrum::DecodedMsg;
+/// * `l2_bytes` - The bytes of the L2 message to be decoded.
 ///
-/// let l2_bytes = vec![0x01, 0x02, 0x03];
-/// let decoded_msg = get_decoded_msg(l2_bytes);
+/// # Returns
 ///
-/// match decoded_msg {
-///     Some(DecodedMsg::DecodedBatch(vec_tx)) => {
-///         // Do something with the batch of transactions
-///     },
-///     Some(DecodedMsg::DecodedSignedTx(tx)) => {
-///         // Do something with the signed transaction
-///     },
-///     None => {
-///         // Handle the case where decoding failed
-///     }
-/// }
-/// ```
-fn get_decoded_msg(l2_bytes: Vec<u8>) -> Option<DecodedMsg> {
-    match L2MessageKind::from(l2_bytes[0]) {
-        L2MessageKind::Batch => {
-            let vec_tx = parse_batch_transactions(&l2_bytes[1..]);
-            Some(DecodedMsg::DecodedBatch(vec_tx))
-        }
-        L2MessageKind::SignedTx => {
-            let tx = ethers::utils::rlp::decode(&l2_bytes[1..]).unwrap();
-            Some(DecodedMsg::DecodedSignedTx(tx))
-        }
-        _ => None,
+/// The decoded message, `None` if its kind is not supported, or a `DecodeError` if it is
+/// malformed.
+#[cfg(test)]
+fn get_decoded_msg(l2_bytes: &[u8]) -> Result<Option<DecodedMsg>, DecodeError> {
+    get_decoded_msg_with(l2_bytes, DecodeOptions::default())
+}
+
+fn get_decoded_msg_with(
+    l2_bytes: &[u8],
+    options: DecodeOptions,
+) -> Result<Option<DecodedMsg>, DecodeError> {
+    let (&kind, payload) = l2_bytes.split_first().ok_or(DecodeError::Empty)?;
+    match L2MessageKind::try_from(kind) {
+        Ok(L2MessageKind::Batch) => {
+            let mut vec_tx = Vec::new();
+            parse_batch_transactions(payload, 0, options, &mut vec_tx)?;
+            Ok(Some(DecodedMsg::DecodedBatch(vec_tx)))
+        }
+        Ok(L2MessageKind::SignedTx) => {
+            let tx = decode_signed_tx_with(payload, options.recover_senders)?;
+            Ok(Some(DecodedMsg::DecodedSignedTx(Box::new(tx))))
+        }
+        Ok(L2MessageKind::NonMutatingCall) => Ok(Some(DecodedMsg::DecodedCall(
+            NonMutatingCall::decode(payload)?,
+        ))),
+        Ok(L2MessageKind::Heartbeat) => Ok(Some(DecodedMsg::Heartbeat)),
+        _ => Ok(None),
+    }
+}
+
+/// An iterator over the length-prefixed entries of a batch.
+///
+/// Every entry is a big-endian `u64` size followed by that many bytes. The iterator yields a
+/// `DecodeError` and stops if an entry exceeds the maximum size or the remaining bytes.
+pub struct BatchEntries<'a> {
+    data: &'a [u8],
+    offset: usize,
+    failed: bool,
+    max_size: usize,
+}
+
+impl<'a> BatchEntries<'a> {
+    /// Iterates over the entries of `data`, limited to `DEFAULT_MAX_L2_MESSAGE_SIZE`.
+    pub fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            offset: 0,
+            failed: false,
+            max_size: DEFAULT_MAX_L2_MESSAGE_SIZE,
+        }
+    }
+
+    /// Fails on entries larger than `max_size` instead.
+    pub fn with_max_size(mut self, max_size: usize) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    fn read_entry(&self) -> Result<&'a [u8], DecodeError> {
+        let rest = &self.data[self.offset..];
+        let truncated = |needed| DecodeError::Truncated {
+            offset: self.offset,
+            needed,
+            available: rest.len(),
+        };
+
+        let (size_bytes, rest) = rest.split_first_chunk::<8>().ok_or(truncated(8))?;
+        let size = u64::from_be_bytes(*size_bytes);
+        if size > self.max_size as u64 {
+            return Err(DecodeError::MessageTooLarge {
+                size: size as usize,
+                max: self.max_size,
+            });
+        }
+
+        rest.get(..size as usize).ok_or(truncated(8 + size))
+    }
+}
+
+impl<'a> Iterator for BatchEntries<'a> {
+    type Item = Result<&'a [u8], DecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed || self.offset >= self.data.len() {
+            return None;
+        }
+
+        match self.read_entry() {
+            Ok(entry) => {
+                self.offset += 8 + entry.len();
+                Some(Ok(entry))
+            }
+            Err(e) => {
+                self.failed = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// A signed transaction type batches can be decoded into.
+pub(crate) trait BatchTransaction: Sized {
+    type Error: std::fmt::Display;
+
+    /// Decodes an EIP-2718 encoded signed transaction.
+    fn decode_signed(raw: &[u8], options: DecodeOptions) -> Result<Self, Self::Error>;
+}
+
+impl BatchTransaction for Transaction {
+    type Error = DecodeError;
+
+    fn decode_signed(raw: &[u8], options: DecodeOptions) -> Result<Self, Self::Error> {
+        decode_signed_tx_with(raw, options.recover_senders)
     }
 }
 
 /// Parses a batch of transactions from a byte slice.
 ///
+/// Each batch entry is a length-prefixed L2 message. Signed transactions are decoded according
+/// to their EIP-2718 envelope, nested batches are flattened, and other message kinds are skipped.
+/// Transactions that fail to decode are skipped, but malformed framing fails the whole batch.
+///
 /// # Arguments
 ///
 /// * `data` - A byte slice containing the batch of transactions.
-///
-/// # Example
-///
-/// ```
-/// # use crate::networks::arbitrum::Transaction;
-/// let data = vec![0x00, 0x00, 0x00, 0x0A, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06];
-/// let transactions = parse_batch_transactions(&data);
-/// assert_eq!(transactions.len(), 1);
-/// ```
-fn parse_batch_transactions(data: &[u8]) -> Vec<Transaction> {
-    let mut result = Vec::new();
-    let mut i = 0;
-    while i < data.len() - 8 {
-        let size_bytes = &data[i..i + 8];
-        let size = u64::from_be_bytes(size_bytes.try_into().unwrap()) as usize;
-        let msg = &data[i + 8..i + 8 + size];
-        result.push(ethers::utils::rlp::decode(&msg[1..]).unwrap());
-        i += 8 + size;
-    }
-
-    result
+/// * `depth` - The nesting depth of this batch.
+/// * `options` - The optional decoding work to do.
+/// * `result` - The vector the decoded transactions are appended to.
+pub(crate) fn parse_batch_transactions<T: BatchTransaction>(
+    data: &[u8],
+    depth: usize,
+    options: DecodeOptions,
+    result: &mut Vec<T>,
+) -> Result<(), DecodeError> {
+    if depth >= MAX_BATCH_DEPTH {
+        return Err(DecodeError::BatchTooDeep(MAX_BATCH_DEPTH));
+    }
+
+    for entry in BatchEntries::new(data).with_max_size(options.max_l2_message_size) {
+        let (&kind, msg) = entry?.split_first().ok_or(DecodeError::Empty)?;
+        match L2MessageKind::try_from(kind) {
+            Ok(L2MessageKind::SignedTx) => match T::decode_signed(msg, options) {
+                Ok(tx) => result.push(tx),
+                Err(e) => warn!("Failed to decode batched transaction: {}", e),
+            },
+            Ok(L2MessageKind::Batch) => parse_batch_transactions(msg, depth + 1, options, result)?,
+            _ => (),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::{
+        signers::{LocalWallet, Signer},
+        types::{
+            transaction::eip2718::TypedTransaction, Eip1559TransactionRequest, TransactionRequest,
+            H256,
+        },
+        utils::keccak256,
+    };
+
+    fn wallet() -> LocalWallet {
+        "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318"
+            .parse::<LocalWallet>()
+            .unwrap()
+            .with_chain_id(42161u64)
+    }
+
+    fn sign(wallet: &LocalWallet, tx: TypedTransaction) -> Vec<u8> {
+        let signature = wallet.sign_transaction_sync(&tx).unwrap();
+        tx.rlp_signed(&signature).to_vec()
+    }
+
+    fn batch_entry(kind: u8, payload: &[u8]) -> Vec<u8> {
+        let mut entry = ((payload.len() + 1) as u64).to_be_bytes().to_vec();
+        entry.push(kind);
+        entry.extend_from_slice(payload);
+        entry
+    }
+
+    #[test]
+    fn decode_signed_tx_recovers_sender_and_hash() {
+        let wallet = wallet();
+        let raw = sign(
+            &wallet,
+            TransactionRequest::new()
+                .to(H160::repeat_byte(0x11))
+                .value(1_000u64)
+                .nonce(7u64)
+                .gas(21_000u64)
+                .gas_price(100_000_000u64)
+                .chain_id(42161u64)
+                .into(),
+        );
+
+        let decoded = envelope::decode_signed_tx(&raw).unwrap();
+        assert_eq!(decoded.from, wallet.address());
+        assert_eq!(decoded.hash, H256(keccak256(&raw)));
+        assert_eq!(decoded.nonce, 7u64.into());
+    }
+
+    #[test]
+    fn batch_decodes_typed_and_nested_transactions() {
+        let wallet = wallet();
+        let legacy = sign(
+            &wallet,
+            TransactionRequest::new()
+                .to(H160::repeat_byte(0x22))
+                .nonce(1u64)
+                .gas(21_000u64)
+                .gas_price(1u64)
+                .chain_id(42161u64)
+                .into(),
+        );
+        let dynamic_fee = sign(
+            &wallet,
+            Eip1559TransactionRequest::new()
+                .to(H160::repeat_byte(0x33))
+                .nonce(2u64)
+                .gas(21_000u64)
+                .max_fee_per_gas(2u64)
+                .max_priority_fee_per_gas(1u64)
+                .chain_id(42161u64)
+                .into(),
+        );
+
+        let inner = batch_entry(4, &dynamic_fee);
+        let mut data = batch_entry(4, &legacy);
+        data.extend(batch_entry(3, &inner));
+
+        let mut txs: Vec<Transaction> = Vec::new();
+        parse_batch_transactions(&data, 0, DecodeOptions::default(), &mut txs).unwrap();
+
+        assert_eq!(txs.len(), 2);
+        assert_eq!(txs[0].transaction_type, None);
+        assert_eq!(txs[1].transaction_type, Some(2u64.into()));
+        assert!(txs.iter().all(|tx| tx.from == wallet.address()));
+        assert_eq!(txs[1].hash, H256(keccak256(&dynamic_fee)));
+    }
+
+    /// A small xorshift generator so the fuzz tests are deterministic without extra dependencies.
+    struct XorShift(u64);
+
+    impl XorShift {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn bytes(&mut self, len: usize) -> Vec<u8> {
+            (0..len).map(|_| self.next() as u8).collect()
+        }
+    }
+
+    #[test]
+    fn decodes_calls_and_heartbeats() {
+        let mut msg = vec![2];
+        msg.extend(H256::from_low_u64_be(50_000).as_bytes());
+        msg.extend(H256::from_low_u64_be(100_000_000).as_bytes());
+        msg.extend(H256::from(H160::repeat_byte(0x55)).as_bytes());
+        msg.extend([0x70, 0xa0, 0x82, 0x31]);
+
+        assert_eq!(
+            get_decoded_msg(&msg),
+            Ok(Some(DecodedMsg::DecodedCall(NonMutatingCall {
+                gas_limit: 50_000u64.into(),
+                max_fee_per_gas: 100_000_000u64.into(),
+                to: H160::repeat_byte(0x55),
+                data: vec![0x70, 0xa0, 0x82, 0x31].into(),
+            })))
+        );
+        assert_eq!(get_decoded_msg(&[6]), Ok(Some(DecodedMsg::Heartbeat)));
+        assert_eq!(
+            get_decoded_msg(&msg[..80]),
+            Err(DecodeError::TruncatedField {
+                field: "call target",
+                needed: 96,
+                available: 79
+            })
+        );
+    }
+
+    #[test]
+    fn batch_framing_errors() {
+        let mut txs: Vec<Transaction> = Vec::new();
+
+        let short = [0u8; 5];
+        assert_eq!(
+            parse_batch_transactions(&short, 0, DecodeOptions::default(), &mut txs),
+            Err(DecodeError::Truncated {
+                offset: 0,
+                needed: 8,
+                available: 5
+            })
+        );
+
+        let mut overflowing = 100u64.to_be_bytes().to_vec();
+        overflowing.extend_from_slice(&[4, 1, 2]);
+        assert!(matches!(
+            parse_batch_transactions(&overflowing, 0, DecodeOptions::default(), &mut txs),
+            Err(DecodeError::Truncated { needed: 108, .. })
+        ));
+
+        let oversized = u64::MAX.to_be_bytes();
+        assert!(matches!(
+            parse_batch_transactions(&oversized, 0, DecodeOptions::default(), &mut txs),
+            Err(DecodeError::MessageTooLarge { .. })
+        ));
+
+        let mut nested = Vec::new();
+        for _ in 0..=MAX_BATCH_DEPTH {
+            nested = batch_entry(3, &nested);
+        }
+        assert_eq!(
+            parse_batch_transactions(&nested, 0, DecodeOptions::default(), &mut txs),
+            Err(DecodeError::BatchTooDeep(MAX_BATCH_DEPTH))
+        );
+        assert!(txs.is_empty());
+    }
+
+    #[test]
+    fn decodes_base64_like_the_scalar_decoder() {
+        let mut rng = XorShift(0x5851_f42d_4c95_7f2d);
+        for len in 0..300 {
+            let data = rng.bytes(len);
+            let encoded = general_purpose::STANDARD.encode(&data);
+            assert_eq!(decode_base64(encoded.as_bytes()), Ok(data));
+        }
+        assert_eq!(
+            decode_base64(b"AAAA!AAA"),
+            Err(DecodeError::Base64(base64::DecodeError::InvalidByte(
+                4, b'!'
+            )))
+        );
+    }
+
+    #[test]
+    fn fuzz_random_messages_never_panic() {
+        let mut rng = XorShift(0x2545_f491_4f6c_dd1d);
+        for _ in 0..20_000 {
+            let len = (rng.next() % 96) as usize;
+            let mut data = rng.bytes(len);
+            if let Some(kind) = data.first_mut() {
+                *kind %= 8;
+            }
+            let _ = get_decoded_msg(&data);
+        }
+    }
+
+    #[test]
+    fn fuzz_mutated_batches_never_panic() {
+        let wallet = wallet();
+        let tx = sign(
+            &wallet,
+            TransactionRequest::new()
+                .to(H160::repeat_byte(0x44))
+                .nonce(3u64)
+                .gas(21_000u64)
+                .gas_price(1u64)
+                .chain_id(42161u64)
+                .into(),
+        );
+        let mut batch = vec![3];
+        batch.extend(batch_entry(4, &tx));
+        batch.extend(batch_entry(3, &batch_entry(4, &tx)));
+
+        let mut rng = XorShift(0x9e37_79b9_7f4a_7c15);
+        for _ in 0..1_000 {
+            let mut data = batch.clone();
+            for _ in 0..=(rng.next() % 4) {
+                let i = (rng.next() as usize) % data.len();
+                data[i] = rng.next() as u8;
+            }
+            data.truncate((rng.next() as usize) % (data.len() + 1));
+            let _ = get_decoded_msg(&data);
+        }
+    }
 }
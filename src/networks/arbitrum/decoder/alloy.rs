@@ -0,0 +1,127 @@
+//! Decoding into Alloy types, for users migrating from ethers: the transactions of the feed are
+//! decoded straight into `alloy_consensus::TxEnvelope`s, without `ethers::types::Transaction`.
+
+use crate::networks::arbitrum::{
+    decoder::{
+        l1::{L1_MESSAGE_KIND_BATCH_POSTING_REPORT, L1_MESSAGE_KIND_ETH_DEPOSIT},
+        parse_batch_transactions, BatchTransaction, DecodeOptions, L2MessageKind,
+    },
+    errors::DecodeError,
+    types::L1IncomingMessageHeader,
+};
+use alloy_eips::eip2718::Decodable2718;
+use ethers::types::{H160, H256};
+
+pub use alloy_consensus::TxEnvelope;
+pub use alloy_primitives::{Address, B256, U256};
+
+impl L1IncomingMessageHeader {
+    /// Decodes the transactions of the L2 message as Alloy envelopes.
+    ///
+    /// # Returns
+    ///
+    /// The signed transaction of the message, or the signed transactions of its batch, nested
+    /// batches flattened. Messages of other kinds have no transactions. Fails like `try_decode`
+    /// on malformed messages and batches.
+    pub fn alloy_transactions(&self) -> Result<Vec<TxEnvelope>, DecodeError> {
+        self.alloy_transactions_with(DecodeOptions::default())
+    }
+
+    /// Like `alloy_transactions`, with the L2 message size limit of `options`. Senders are never
+    /// recovered: `TxEnvelope` keeps the signature instead.
+    pub fn alloy_transactions_with(
+        &self,
+        options: DecodeOptions,
+    ) -> Result<Vec<TxEnvelope>, DecodeError> {
+        let mut txs = Vec::new();
+        if matches!(
+            self.header.kind,
+            L1_MESSAGE_KIND_ETH_DEPOSIT | L1_MESSAGE_KIND_BATCH_POSTING_REPORT
+        ) {
+            return Ok(txs);
+        }
+
+        let (&kind, payload) = self
+            .l2_bytes_with(options)?
+            .split_first()
+            .ok_or(DecodeError::Empty)?;
+        match L2MessageKind::try_from(kind) {
+            Ok(L2MessageKind::SignedTx) => txs.push(decode_tx_envelope(payload)?),
+            Ok(L2MessageKind::Batch) => parse_batch_transactions(payload, 0, options, &mut txs)?,
+            _ => (),
+        }
+        Ok(txs)
+    }
+}
+
+/// Decodes an EIP-2718 encoded signed transaction, legacy transactions included.
+pub fn decode_tx_envelope(raw: &[u8]) -> Result<TxEnvelope, DecodeError> {
+    TxEnvelope::decode_2718(&mut &raw[..]).map_err(|e| DecodeError::Envelope(e.to_string()))
+}
+
+impl BatchTransaction for TxEnvelope {
+    type Error = DecodeError;
+
+    fn decode_signed(raw: &[u8], _options: DecodeOptions) -> Result<Self, Self::Error> {
+        decode_tx_envelope(raw)
+    }
+}
+
+/// Converts an ethers address.
+pub fn to_address(address: H160) -> Address {
+    Address::from(address.0)
+}
+
+/// Converts an ethers hash.
+pub fn to_b256(hash: H256) -> B256 {
+    B256::from(hash.0)
+}
+
+/// Converts an ethers integer.
+pub fn to_u256(value: ethers::types::U256) -> U256 {
+    let mut bytes = [0u8; 32];
+    value.to_big_endian(&mut bytes);
+    U256::from_be_bytes(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::networks::arbitrum::fixtures::message_with;
+    use ethers::{
+        signers::{LocalWallet, Signer},
+        types::{transaction::eip2718::TypedTransaction, Eip1559TransactionRequest},
+        utils::keccak256,
+    };
+
+    #[test]
+    fn decodes_batched_transactions_into_envelopes() {
+        let wallet = "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318"
+            .parse::<LocalWallet>()
+            .unwrap()
+            .with_chain_id(42161u64);
+        let tx: TypedTransaction = Eip1559TransactionRequest::new()
+            .to(H160::repeat_byte(0x33))
+            .nonce(2u64)
+            .gas(21_000u64)
+            .max_fee_per_gas(2u64)
+            .max_priority_fee_per_gas(1u64)
+            .chain_id(42161u64)
+            .into();
+        let raw = tx
+            .rlp_signed(&wallet.sign_transaction_sync(&tx).unwrap())
+            .to_vec();
+
+        let mut l2msg = vec![3];
+        l2msg.extend(((raw.len() + 1) as u64).to_be_bytes());
+        l2msg.push(4);
+        l2msg.extend(&raw);
+        let header = message_with(1, 0, l2msg).message.message;
+
+        let txs = header.alloy_transactions().unwrap();
+        assert_eq!(txs.len(), 1);
+        assert!(matches!(txs[0], TxEnvelope::Eip1559(_)));
+        assert_eq!(*txs[0].tx_hash(), to_b256(H256(keccak256(&raw))));
+        assert_eq!(to_u256(42161u64.into()), U256::from(42161u64));
+    }
+}
@@ -0,0 +1,401 @@
+//! The Arbitrum specific transaction types, created by ArbOS from L1 messages rather than signed
+//! by users. They are not broadcast on the feed as such, but appear in the blocks reconstructed
+//! from it and in the blocks reported by Nitro nodes.
+
+use crate::networks::arbitrum::{
+    decoder::{
+        envelope::{address, bytes, h256, optional_address, u256},
+        rlp::{Item, RlpError},
+    },
+    errors::DecodeError,
+};
+use ethers::{
+    types::{Address, Bytes, Transaction, H160, H256, U256},
+    utils::keccak256,
+};
+use serde::Serialize;
+use serde_json::Value;
+
+pub const ARBITRUM_DEPOSIT_TX_TYPE: u8 = 0x64;
+pub const ARBITRUM_UNSIGNED_TX_TYPE: u8 = 0x65;
+pub const ARBITRUM_CONTRACT_TX_TYPE: u8 = 0x66;
+pub const ARBITRUM_RETRY_TX_TYPE: u8 = 0x68;
+pub const ARBITRUM_SUBMIT_RETRYABLE_TX_TYPE: u8 = 0x69;
+pub const ARBITRUM_INTERNAL_TX_TYPE: u8 = 0x6a;
+
+/// The address ArbOS sends its internal transactions from and to.
+pub const ARBOS_ADDRESS: H160 = H160([
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x0a, 0x4b, 0x05,
+]);
+/// The `ArbRetryableTx` precompile, the recipient of retryable submissions.
+pub const ARB_RETRYABLE_TX_ADDRESS: H160 = H160([
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x6e,
+]);
+
+/// ETH deposited from L1, minted to `to`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArbitrumDepositTx {
+    pub chain_id: U256,
+    pub l1_request_id: H256,
+    pub from: Address,
+    pub to: Address,
+    pub value: U256,
+}
+
+/// A transaction sent from L1 by an account, without a signature.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArbitrumUnsignedTx {
+    pub chain_id: U256,
+    pub from: Address,
+    pub nonce: u64,
+    pub gas_fee_cap: U256,
+    pub gas: u64,
+    /// `None` for contract creations.
+    pub to: Option<Address>,
+    pub value: U256,
+    pub data: Bytes,
+}
+
+/// A transaction sent from L1 by a contract, identified by its L1 request rather than a nonce.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArbitrumContractTx {
+    pub chain_id: U256,
+    pub request_id: H256,
+    pub from: Address,
+    pub gas_fee_cap: U256,
+    pub gas: u64,
+    /// `None` for contract creations.
+    pub to: Option<Address>,
+    pub value: U256,
+    pub data: Bytes,
+}
+
+/// A redemption of the retryable ticket `ticket_id`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArbitrumRetryTx {
+    pub chain_id: U256,
+    pub nonce: u64,
+    pub from: Address,
+    pub gas_fee_cap: U256,
+    pub gas: u64,
+    /// `None` for contract creations.
+    pub to: Option<Address>,
+    pub value: U256,
+    pub data: Bytes,
+    pub ticket_id: H256,
+    pub refund_to: Address,
+    pub max_refund: U256,
+    pub submission_fee_refund: U256,
+}
+
+/// The submission of a retryable ticket from L1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArbitrumSubmitRetryableTx {
+    pub chain_id: U256,
+    pub request_id: H256,
+    pub from: Address,
+    pub l1_base_fee: U256,
+    pub deposit_value: U256,
+    pub gas_fee_cap: U256,
+    pub gas: u64,
+    /// `None` for contract creations.
+    pub retry_to: Option<Address>,
+    pub retry_value: U256,
+    pub beneficiary: Address,
+    pub max_submission_fee: U256,
+    pub fee_refund_addr: Address,
+    pub retry_data: Bytes,
+}
+
+/// A transaction ArbOS runs at the start of blocks, e.g. to record the L1 block number.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArbitrumInternalTx {
+    pub chain_id: U256,
+    pub data: Bytes,
+}
+
+/// A transaction of one of the Arbitrum specific types, `0x64` to `0x6a`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ArbitrumTx {
+    Deposit(ArbitrumDepositTx),
+    Unsigned(ArbitrumUnsignedTx),
+    Contract(ArbitrumContractTx),
+    Retry(ArbitrumRetryTx),
+    SubmitRetryable(ArbitrumSubmitRetryableTx),
+    Internal(ArbitrumInternalTx),
+}
+
+impl ArbitrumTx {
+    /// Returns `true` if `tx_type` is an Arbitrum specific transaction type.
+    pub fn is_arbitrum_type(tx_type: u8) -> bool {
+        matches!(
+            tx_type,
+            ARBITRUM_DEPOSIT_TX_TYPE
+                | ARBITRUM_UNSIGNED_TX_TYPE
+                | ARBITRUM_CONTRACT_TX_TYPE
+                | ARBITRUM_RETRY_TX_TYPE
+                | ARBITRUM_SUBMIT_RETRYABLE_TX_TYPE
+                | ARBITRUM_INTERNAL_TX_TYPE
+        )
+    }
+
+    /// Decodes an EIP-2718 envelope of an Arbitrum specific type.
+    ///
+    /// # Arguments
+    ///
+    /// * `raw` - The type byte followed by the RLP encoded fields.
+    pub fn decode(raw: &[u8]) -> Result<Self, DecodeError> {
+        let (&tx_type, payload) = raw.split_first().ok_or(RlpError::Empty)?;
+        let fields = Item::decode(payload)?.list()?;
+        match tx_type {
+            ARBITRUM_DEPOSIT_TX_TYPE => {
+                let [chain_id, l1_request_id, from, to, value] = fields.exactly()?;
+                Ok(ArbitrumTx::Deposit(ArbitrumDepositTx {
+                    chain_id: u256(&chain_id)?,
+                    l1_request_id: h256(&l1_request_id)?,
+                    from: address(&from)?,
+                    to: address(&to)?,
+                    value: u256(&value)?,
+                }))
+            }
+            ARBITRUM_UNSIGNED_TX_TYPE => {
+                let [chain_id, from, nonce, gas_fee_cap, gas, to, value, data] =
+                    fields.exactly()?;
+                Ok(ArbitrumTx::Unsigned(ArbitrumUnsignedTx {
+                    chain_id: u256(&chain_id)?,
+                    from: address(&from)?,
+                    nonce: nonce.u64()?,
+                    gas_fee_cap: u256(&gas_fee_cap)?,
+                    gas: gas.u64()?,
+                    to: optional_address(&to)?,
+                    value: u256(&value)?,
+                    data: bytes(&data)?,
+                }))
+            }
+            ARBITRUM_CONTRACT_TX_TYPE => {
+                let [chain_id, request_id, from, gas_fee_cap, gas, to, value, data] =
+                    fields.exactly()?;
+                Ok(ArbitrumTx::Contract(ArbitrumContractTx {
+                    chain_id: u256(&chain_id)?,
+                    request_id: h256(&request_id)?,
+                    from: address(&from)?,
+                    gas_fee_cap: u256(&gas_fee_cap)?,
+                    gas: gas.u64()?,
+                    to: optional_address(&to)?,
+                    value: u256(&value)?,
+                    data: bytes(&data)?,
+                }))
+            }
+            ARBITRUM_RETRY_TX_TYPE => {
+                let [chain_id, nonce, from, gas_fee_cap, gas, to, value, data, ticket_id, refund_to, max_refund, submission_fee_refund] =
+                    fields.exactly()?;
+                Ok(ArbitrumTx::Retry(ArbitrumRetryTx {
+                    chain_id: u256(&chain_id)?,
+                    nonce: nonce.u64()?,
+                    from: address(&from)?,
+                    gas_fee_cap: u256(&gas_fee_cap)?,
+                    gas: gas.u64()?,
+                    to: optional_address(&to)?,
+                    value: u256(&value)?,
+                    data: bytes(&data)?,
+                    ticket_id: h256(&ticket_id)?,
+                    refund_to: address(&refund_to)?,
+                    max_refund: u256(&max_refund)?,
+                    submission_fee_refund: u256(&submission_fee_refund)?,
+                }))
+            }
+            ARBITRUM_SUBMIT_RETRYABLE_TX_TYPE => {
+                let [chain_id, request_id, from, l1_base_fee, deposit_value, gas_fee_cap, gas, retry_to, retry_value, beneficiary, max_submission_fee, fee_refund_addr, retry_data] =
+                    fields.exactly()?;
+                Ok(ArbitrumTx::SubmitRetryable(ArbitrumSubmitRetryableTx {
+                    chain_id: u256(&chain_id)?,
+                    request_id: h256(&request_id)?,
+                    from: address(&from)?,
+                    l1_base_fee: u256(&l1_base_fee)?,
+                    deposit_value: u256(&deposit_value)?,
+                    gas_fee_cap: u256(&gas_fee_cap)?,
+                    gas: gas.u64()?,
+                    retry_to: optional_address(&retry_to)?,
+                    retry_value: u256(&retry_value)?,
+                    beneficiary: address(&beneficiary)?,
+                    max_submission_fee: u256(&max_submission_fee)?,
+                    fee_refund_addr: address(&fee_refund_addr)?,
+                    retry_data: bytes(&retry_data)?,
+                }))
+            }
+            ARBITRUM_INTERNAL_TX_TYPE => {
+                let [chain_id, data] = fields.exactly()?;
+                Ok(ArbitrumTx::Internal(ArbitrumInternalTx {
+                    chain_id: u256(&chain_id)?,
+                    data: bytes(&data)?,
+                }))
+            }
+            _ => Err(DecodeError::UnknownTxType(tx_type)),
+        }
+    }
+
+    /// Returns the EIP-2718 type of the transaction.
+    pub fn tx_type(&self) -> u8 {
+        match self {
+            ArbitrumTx::Deposit(_) => ARBITRUM_DEPOSIT_TX_TYPE,
+            ArbitrumTx::Unsigned(_) => ARBITRUM_UNSIGNED_TX_TYPE,
+            ArbitrumTx::Contract(_) => ARBITRUM_CONTRACT_TX_TYPE,
+            ArbitrumTx::Retry(_) => ARBITRUM_RETRY_TX_TYPE,
+            ArbitrumTx::SubmitRetryable(_) => ARBITRUM_SUBMIT_RETRYABLE_TX_TYPE,
+            ArbitrumTx::Internal(_) => ARBITRUM_INTERNAL_TX_TYPE,
+        }
+    }
+
+    /// Converts the transaction into an ethers `Transaction`, like a Nitro node reports it over
+    /// JSON-RPC.
+    ///
+    /// The fields ethers doesn't know about are kept in `Transaction::other` under their
+    /// JSON-RPC names. Retryable submissions are sent to the `ArbRetryableTx` precompile, their
+    /// calldata is left empty.
+    ///
+    /// # Arguments
+    ///
+    /// * `raw` - The envelope the transaction was decoded from, whose hash is the transaction
+    ///   hash.
+    pub fn into_transaction(self, raw: &[u8]) -> Transaction {
+        let mut tx = Transaction {
+            hash: H256(keccak256(raw)),
+            transaction_type: Some(self.tx_type().into()),
+            ..Default::default()
+        };
+        let other = match self {
+            ArbitrumTx::Deposit(deposit) => {
+                tx.chain_id = Some(deposit.chain_id);
+                tx.from = deposit.from;
+                tx.to = Some(deposit.to);
+                tx.value = deposit.value;
+                vec![("requestId", json(&deposit.l1_request_id))]
+            }
+            ArbitrumTx::Unsigned(unsigned) => {
+                tx.chain_id = Some(unsigned.chain_id);
+                tx.from = unsigned.from;
+                tx.nonce = unsigned.nonce.into();
+                tx.max_fee_per_gas = Some(unsigned.gas_fee_cap);
+                tx.gas = unsigned.gas.into();
+                tx.to = unsigned.to;
+                tx.value = unsigned.value;
+                tx.input = unsigned.data;
+                Vec::new()
+            }
+            ArbitrumTx::Contract(contract) => {
+                tx.chain_id = Some(contract.chain_id);
+                tx.from = contract.from;
+                tx.max_fee_per_gas = Some(contract.gas_fee_cap);
+                tx.gas = contract.gas.into();
+                tx.to = contract.to;
+                tx.value = contract.value;
+                tx.input = contract.data;
+                vec![("requestId", json(&contract.request_id))]
+            }
+            ArbitrumTx::Retry(retry) => {
+                tx.chain_id = Some(retry.chain_id);
+                tx.nonce = retry.nonce.into();
+                tx.from = retry.from;
+                tx.max_fee_per_gas = Some(retry.gas_fee_cap);
+                tx.gas = retry.gas.into();
+                tx.to = retry.to;
+                tx.value = retry.value;
+                tx.input = retry.data;
+                vec![
+                    ("ticketId", json(&retry.ticket_id)),
+                    ("refundTo", json(&retry.refund_to)),
+                    ("maxRefund", json(&retry.max_refund)),
+                    ("submissionFeeRefund", json(&retry.submission_fee_refund)),
+                ]
+            }
+            ArbitrumTx::SubmitRetryable(submit) => {
+                tx.chain_id = Some(submit.chain_id);
+                tx.from = submit.from;
+                tx.max_fee_per_gas = Some(submit.gas_fee_cap);
+                tx.gas = submit.gas.into();
+                tx.to = Some(ARB_RETRYABLE_TX_ADDRESS);
+                vec![
+                    ("requestId", json(&submit.request_id)),
+                    ("l1BaseFee", json(&submit.l1_base_fee)),
+                    ("depositValue", json(&submit.deposit_value)),
+                    ("retryTo", json(&submit.retry_to)),
+                    ("retryValue", json(&submit.retry_value)),
+                    ("beneficiary", json(&submit.beneficiary)),
+                    ("maxSubmissionFee", json(&submit.max_submission_fee)),
+                    ("refundTo", json(&submit.fee_refund_addr)),
+                    ("retryData", json(&submit.retry_data)),
+                ]
+            }
+            ArbitrumTx::Internal(internal) => {
+                tx.chain_id = Some(internal.chain_id);
+                tx.from = ARBOS_ADDRESS;
+                tx.to = Some(ARBOS_ADDRESS);
+                tx.input = internal.data;
+                Vec::new()
+            }
+        };
+        for (name, value) in other {
+            tx.other.insert(name.to_string(), value);
+        }
+        tx
+    }
+}
+
+fn json<T: Serialize>(value: &T) -> Value {
+    serde_json::to_value(value).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::networks::arbitrum::decoder::envelope::decode_signed_tx;
+    use ethers::utils::rlp::RlpStream;
+
+    #[test]
+    fn decodes_retry_and_internal_transactions() {
+        let from = Address::repeat_byte(0x11);
+        let ticket_id = H256::repeat_byte(0x22);
+        let mut retry = RlpStream::new_list(12);
+        retry.append(&42161u64);
+        retry.append(&0u64);
+        retry.append(&from);
+        retry.append(&100_000_000u64);
+        retry.append(&500_000u64);
+        // A contract creation.
+        retry.append_empty_data();
+        retry.append(&1_000u64);
+        retry.append(&vec![0x60, 0x80]);
+        retry.append(&ticket_id);
+        retry.append(&from);
+        retry.append(&7u64);
+        retry.append(&8u64);
+        let mut raw = vec![ARBITRUM_RETRY_TX_TYPE];
+        raw.extend_from_slice(&retry.out());
+
+        let decoded = ArbitrumTx::decode(&raw).unwrap();
+        let ArbitrumTx::Retry(retry_tx) = &decoded else {
+            panic!("expected a retry transaction");
+        };
+        assert_eq!(retry_tx.to, None);
+        assert_eq!(retry_tx.ticket_id, ticket_id);
+        assert_eq!(retry_tx.submission_fee_refund, 8u64.into());
+
+        let tx = decode_signed_tx(&raw).unwrap();
+        assert_eq!(tx, decoded.into_transaction(&raw));
+        assert_eq!(tx.transaction_type, Some(0x68u64.into()));
+        assert_eq!(tx.from, from);
+        assert_eq!(tx.hash, H256(keccak256(&raw)));
+        assert_eq!(tx.other["ticketId"], json(&ticket_id));
+
+        let mut internal = RlpStream::new_list(2);
+        internal.append(&42161u64);
+        internal.append(&vec![0x6b, 0xf6, 0xa4, 0x2d]);
+        let mut raw = vec![ARBITRUM_INTERNAL_TX_TYPE];
+        raw.extend_from_slice(&internal.out());
+        let tx = decode_signed_tx(&raw).unwrap();
+        assert_eq!(tx.from, ARBOS_ADDRESS);
+        assert_eq!(tx.input.as_ref(), &[0x6b, 0xf6, 0xa4, 0x2d]);
+
+        assert!(decode_signed_tx(&[0x67, 0xc0]).is_err());
+    }
+}
@@ -0,0 +1,444 @@
+use crate::networks::arbitrum::{
+    decoder::{
+        arbos::ArbitrumTx,
+        rlp::{self, Item, RlpError},
+    },
+    errors::DecodeError,
+};
+use ethers::{
+    types::{
+        transaction::eip2930::{AccessList, AccessListItem},
+        Address, Bytes, Signature, Transaction, H256, U256,
+    },
+    utils::keccak256,
+};
+use log::*;
+use serde::Deserialize;
+
+/// The EIP-2718 type of a signed transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TxType {
+    /// A legacy (optionally EIP-155) transaction, encoded as a bare RLP list.
+    Legacy,
+    /// An EIP-2930 access list transaction (`0x01`).
+    AccessList,
+    /// An EIP-1559 dynamic fee transaction (`0x02`).
+    DynamicFee,
+    /// An EIP-4844 blob transaction (`0x03`).
+    Blob,
+    /// One of the Arbitrum specific transaction types created by ArbOS (`0x64` to `0x6a`), see
+    /// `ArbitrumTx`.
+    Arbitrum(u8),
+}
+
+impl TxType {
+    /// Detects the type of an encoded transaction from its first byte.
+    ///
+    /// Legacy transactions start with an RLP list prefix (`>= 0xc0`), typed transactions with
+    /// their type byte (`<= 0x7f`). Returns `None` for unknown or empty envelopes.
+    pub fn detect(raw: &[u8]) -> Option<Self> {
+        match *raw.first()? {
+            0x01 => Some(TxType::AccessList),
+            0x02 => Some(TxType::DynamicFee),
+            0x03 => Some(TxType::Blob),
+            b if ArbitrumTx::is_arbitrum_type(b) => Some(TxType::Arbitrum(b)),
+            b if b >= 0xc0 => Some(TxType::Legacy),
+            _ => None,
+        }
+    }
+}
+
+/// Decodes a signed transaction, recovering its sender and computing its hash.
+///
+/// The `from` field is left as the zero address if the signature can't be recovered.
+///
+/// # Arguments
+///
+/// * `raw` - The RLP (or EIP-2718 envelope) encoded signed transaction.
+pub fn decode_signed_tx(raw: &[u8]) -> Result<Transaction, DecodeError> {
+    decode_signed_tx_with(raw, true)
+}
+
+/// Like `decode_signed_tx`, leaving `from` as the zero address unless `recover_sender` is set.
+///
+/// Transactions are decoded with the vendored RLP decoder, see `rlp`.
+pub fn decode_signed_tx_with(raw: &[u8], recover_sender: bool) -> Result<Transaction, DecodeError> {
+    let (&first, payload) = raw.split_first().ok_or(RlpError::Empty)?;
+    let tx_type = TxType::detect(raw).ok_or(DecodeError::UnknownTxType(first))?;
+    let mut tx = match tx_type {
+        TxType::Legacy => decode_legacy_tx(raw)?,
+        TxType::AccessList => decode_access_list_tx(payload)?,
+        TxType::DynamicFee => decode_dynamic_fee_tx(payload)?,
+        TxType::Blob => return decode_blob_tx(payload, recover_sender),
+        // Arbitrum transactions are unsigned: their sender is one of their fields.
+        TxType::Arbitrum(_) => return Ok(ArbitrumTx::decode(raw)?.into_transaction(raw)),
+    };
+    tx.hash = H256(keccak256(raw));
+    if recover_sender {
+        if let Err(e) = tx.recover_from_mut() {
+            debug!("Failed to recover sender of transaction: {}", e);
+        }
+    }
+    Ok(tx)
+}
+
+/// Decodes a legacy transaction, taking its chain ID from `v` if it is signed per EIP-155.
+fn decode_legacy_tx(raw: &[u8]) -> Result<Transaction, DecodeError> {
+    let [nonce, gas_price, gas, to, value, input, v, r, s] =
+        Item::decode(raw)?.list()?.exactly()?;
+    let v = v.u64()?;
+    Ok(Transaction {
+        nonce: u256(&nonce)?,
+        gas_price: Some(u256(&gas_price)?),
+        gas: u256(&gas)?,
+        to: optional_address(&to)?,
+        value: u256(&value)?,
+        input: bytes(&input)?,
+        v: v.into(),
+        r: u256(&r)?,
+        s: u256(&s)?,
+        chain_id: v.checked_sub(35).map(|v| (v / 2).into()),
+        ..Default::default()
+    })
+}
+
+/// Decodes the RLP payload of an EIP-2930 transaction, without its type byte.
+fn decode_access_list_tx(payload: &[u8]) -> Result<Transaction, DecodeError> {
+    let [chain_id, nonce, gas_price, gas, to, value, input, access_list, y_parity, r, s] =
+        Item::decode(payload)?.list()?.exactly()?;
+    Ok(Transaction {
+        transaction_type: Some(1u64.into()),
+        chain_id: Some(u256(&chain_id)?),
+        nonce: u256(&nonce)?,
+        gas_price: Some(u256(&gas_price)?),
+        gas: u256(&gas)?,
+        to: optional_address(&to)?,
+        value: u256(&value)?,
+        input: bytes(&input)?,
+        access_list: Some(decode_access_list(&access_list)?),
+        v: y_parity.u64()?.into(),
+        r: u256(&r)?,
+        s: u256(&s)?,
+        ..Default::default()
+    })
+}
+
+/// Decodes the RLP payload of an EIP-1559 transaction, without its type byte.
+fn decode_dynamic_fee_tx(payload: &[u8]) -> Result<Transaction, DecodeError> {
+    let [chain_id, nonce, max_priority_fee_per_gas, max_fee_per_gas, gas, to, value, input, access_list, y_parity, r, s] =
+        Item::decode(payload)?.list()?.exactly()?;
+    Ok(Transaction {
+        transaction_type: Some(2u64.into()),
+        chain_id: Some(u256(&chain_id)?),
+        nonce: u256(&nonce)?,
+        max_priority_fee_per_gas: Some(u256(&max_priority_fee_per_gas)?),
+        max_fee_per_gas: Some(u256(&max_fee_per_gas)?),
+        gas: u256(&gas)?,
+        to: optional_address(&to)?,
+        value: u256(&value)?,
+        input: bytes(&input)?,
+        access_list: Some(decode_access_list(&access_list)?),
+        v: y_parity.u64()?.into(),
+        r: u256(&r)?,
+        s: u256(&s)?,
+        ..Default::default()
+    })
+}
+
+/// The fields specific to EIP-4844 blob transactions.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlobFields {
+    pub max_fee_per_blob_gas: U256,
+    pub blob_versioned_hashes: Vec<H256>,
+}
+
+impl BlobFields {
+    /// Returns the blob fields of `tx`, `None` if it isn't a blob transaction.
+    pub fn of(tx: &Transaction) -> Option<Self> {
+        if tx.transaction_type != Some(3u64.into()) {
+            return None;
+        }
+        Some(Self {
+            max_fee_per_blob_gas: serde_json::from_value(tx.other.get("maxFeePerBlobGas")?.clone())
+                .ok()?,
+            blob_versioned_hashes: serde_json::from_value(
+                tx.other.get("blobVersionedHashes")?.clone(),
+            )
+            .ok()?,
+        })
+    }
+}
+
+/// Number of fields of an EIP-4844 transaction payload that are covered by its signature.
+const BLOB_TX_UNSIGNED_FIELDS: usize = 11;
+
+/// Decodes the RLP payload of an EIP-4844 transaction (without its type byte), and computes its
+/// hash.
+///
+/// ethers doesn't know about blob transactions, so the blob specific fields are kept in
+/// `Transaction::other` under their JSON-RPC names, see `BlobFields`. Transactions in their
+/// network form, wrapped together with their blobs, commitments and proofs, are accepted too:
+/// the sidecar is dropped.
+fn decode_blob_tx(payload: &[u8], recover_sender: bool) -> Result<Transaction, DecodeError> {
+    let item = Item::decode(payload)?;
+    let body = match item.list()?.next() {
+        Some(Ok(first)) if first.is_list() => {
+            let [body, _blobs, _commitments, _proofs] = item.list()?.exactly()?;
+            body
+        }
+        _ => item,
+    };
+    let fields: [Item; BLOB_TX_UNSIGNED_FIELDS + 3] = body.list()?.exactly()?;
+    let [chain_id, nonce, max_priority_fee_per_gas, max_fee_per_gas, gas, to, value, input, access_list, max_fee_per_blob_gas, blob_versioned_hashes, v, r, s] =
+        fields;
+
+    let mut tx = Transaction {
+        transaction_type: Some(3u64.into()),
+        chain_id: Some(u256(&chain_id)?),
+        nonce: u256(&nonce)?,
+        max_priority_fee_per_gas: Some(u256(&max_priority_fee_per_gas)?),
+        max_fee_per_gas: Some(u256(&max_fee_per_gas)?),
+        gas: u256(&gas)?,
+        to: Some(address(&to)?),
+        value: u256(&value)?,
+        input: bytes(&input)?,
+        access_list: Some(decode_access_list(&access_list)?),
+        v: v.u64()?.into(),
+        r: u256(&r)?,
+        s: u256(&s)?,
+        ..Default::default()
+    };
+    // The hash covers the type byte followed by the transaction, without its sidecar.
+    let mut hashed = vec![0x03];
+    hashed.extend_from_slice(body.raw());
+    tx.hash = H256(keccak256(hashed));
+
+    let blob_versioned_hashes = blob_versioned_hashes
+        .list()?
+        .map(|hash| hash.and_then(|hash| h256(&hash)))
+        .collect::<Result<Vec<_>, _>>()?;
+    tx.other.insert(
+        "maxFeePerBlobGas".to_string(),
+        serde_json::to_value(u256(&max_fee_per_blob_gas)?).unwrap_or_default(),
+    );
+    tx.other.insert(
+        "blobVersionedHashes".to_string(),
+        serde_json::to_value(blob_versioned_hashes).unwrap_or_default(),
+    );
+
+    if !recover_sender {
+        return Ok(tx);
+    }
+
+    // The signing hash covers the type byte followed by the unsigned fields.
+    let unsigned: Vec<u8> = fields
+        .iter()
+        .take(BLOB_TX_UNSIGNED_FIELDS)
+        .flat_map(|field| field.raw())
+        .copied()
+        .collect();
+    let mut preimage = vec![0x03];
+    preimage.extend_from_slice(&rlp::list_header(unsigned.len()));
+    preimage.extend_from_slice(&unsigned);
+
+    let signature = Signature {
+        r: tx.r,
+        s: tx.s,
+        v: tx.v.as_u64(),
+    };
+    match signature.recover(H256(keccak256(preimage))) {
+        Ok(from) => tx.from = from,
+        Err(e) => debug!("Failed to recover sender of blob transaction: {}", e),
+    }
+
+    Ok(tx)
+}
+
+pub(super) fn u256(item: &Item) -> Result<U256, DecodeError> {
+    Ok(U256::from_big_endian(item.uint_bytes(32)?))
+}
+
+pub(super) fn address(item: &Item) -> Result<Address, DecodeError> {
+    match item.bytes()? {
+        bytes if bytes.len() == 20 => Ok(Address::from_slice(bytes)),
+        _ => Err(RlpError::IncorrectListLen.into()),
+    }
+}
+
+/// An address field that is empty for contract creations.
+pub(super) fn optional_address(item: &Item) -> Result<Option<Address>, DecodeError> {
+    match item.bytes()? {
+        [] => Ok(None),
+        _ => address(item).map(Some),
+    }
+}
+
+pub(super) fn h256(item: &Item) -> Result<H256, DecodeError> {
+    match item.bytes()? {
+        bytes if bytes.len() == 32 => Ok(H256::from_slice(bytes)),
+        _ => Err(RlpError::IncorrectListLen.into()),
+    }
+}
+
+pub(super) fn bytes(item: &Item) -> Result<Bytes, DecodeError> {
+    Ok(Bytes::from(item.bytes()?.to_vec()))
+}
+
+fn decode_access_list(item: &Item) -> Result<AccessList, DecodeError> {
+    item.list()?
+        .map(|entry| {
+            let [address_item, storage_keys] = entry?.list()?.exactly()?;
+            Ok(AccessListItem {
+                address: address(&address_item)?,
+                storage_keys: storage_keys
+                    .list()?
+                    .map(|key| key.and_then(|key| h256(&key)))
+                    .collect::<Result<_, _>>()?,
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map(AccessList)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::{
+        signers::{LocalWallet, Signer},
+        types::{
+            transaction::eip2718::TypedTransaction, Eip1559TransactionRequest,
+            Eip2930TransactionRequest, TransactionRequest,
+        },
+        utils::rlp::{self as ethers_rlp, RlpStream},
+    };
+
+    #[test]
+    fn decodes_signed_transactions_like_ethers() {
+        let wallet: LocalWallet =
+            "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318"
+                .parse()
+                .unwrap();
+        let to = Address::repeat_byte(0x22);
+        let legacy = TransactionRequest::new()
+            .to(to)
+            .value(1_000u64)
+            .nonce(7u64)
+            .gas(21_000u64)
+            .gas_price(100_000_000u64)
+            .data(vec![0xde, 0xad]);
+        let access_list = AccessList(vec![AccessListItem {
+            address: to,
+            storage_keys: vec![H256::repeat_byte(0x01)],
+        }]);
+        let requests: Vec<TypedTransaction> = vec![
+            legacy.clone().into(),
+            legacy.clone().chain_id(42161u64).into(),
+            Eip2930TransactionRequest::new(legacy.chain_id(42161u64), access_list.clone()).into(),
+            Eip1559TransactionRequest::new()
+                .nonce(8u64)
+                .gas(21_000u64)
+                .max_fee_per_gas(2u64)
+                .max_priority_fee_per_gas(1u64)
+                .access_list(access_list)
+                .chain_id(42161u64)
+                .into(),
+        ];
+
+        for request in requests {
+            let raw = request
+                .rlp_signed(&wallet.sign_transaction_sync(&request).unwrap())
+                .to_vec();
+            let mut expected: Transaction = ethers_rlp::decode(&raw).unwrap();
+            expected.recover_from_mut().unwrap();
+
+            let tx = decode_signed_tx(&raw).unwrap();
+            assert_eq!(tx, expected);
+            assert_eq!(tx.from, wallet.address());
+
+            // Truncated, or with any byte corrupted, the transaction fails to decode or decodes
+            // to another one, but never panics.
+            for end in 0..raw.len() {
+                assert!(decode_signed_tx(&raw[..end]).is_err());
+            }
+            for i in 0..raw.len() {
+                let mut corrupted = raw.clone();
+                corrupted[i] ^= 0xff;
+                let _ = decode_signed_tx(&corrupted);
+            }
+        }
+    }
+
+    #[test]
+    fn decodes_blob_tx_and_recovers_sender() {
+        let wallet: LocalWallet =
+            "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318"
+                .parse()
+                .unwrap();
+        let to = Address::repeat_byte(0x22);
+        let blob_hash = H256::repeat_byte(0x01);
+
+        let append_unsigned = |stream: &mut RlpStream| {
+            stream.append(&42161u64);
+            stream.append(&3u64);
+            stream.append(&1_000u64);
+            stream.append(&2_000u64);
+            stream.append(&21_000u64);
+            stream.append(&to);
+            stream.append(&5u64);
+            stream.append(&vec![0xde, 0xad]);
+            stream
+                .begin_list(1)
+                .begin_list(2)
+                .append(&to)
+                .begin_list(1)
+                .append(&blob_hash);
+            stream.append(&7u64);
+            stream.begin_list(1).append(&blob_hash);
+        };
+        let mut unsigned = RlpStream::new_list(BLOB_TX_UNSIGNED_FIELDS);
+        append_unsigned(&mut unsigned);
+        let mut preimage = vec![0x03];
+        preimage.extend_from_slice(&unsigned.out());
+        let signature = wallet.sign_hash(H256(keccak256(&preimage))).unwrap();
+
+        let mut signed = RlpStream::new_list(BLOB_TX_UNSIGNED_FIELDS + 3);
+        append_unsigned(&mut signed);
+        signed.append(&(signature.v - 27));
+        signed.append(&signature.r);
+        signed.append(&signature.s);
+        let mut raw = vec![0x03];
+        raw.extend_from_slice(&signed.out());
+
+        let tx = decode_signed_tx(&raw).unwrap();
+        assert_eq!(tx.from, wallet.address());
+        assert_eq!(tx.to, Some(to));
+        assert_eq!(tx.nonce, 3u64.into());
+        assert_eq!(tx.input.as_ref(), &[0xde, 0xad]);
+        assert_eq!(
+            tx.access_list.as_ref().unwrap().0[0].storage_keys,
+            vec![blob_hash]
+        );
+        assert_eq!(tx.hash, H256(keccak256(&raw)));
+        assert_eq!(
+            BlobFields::of(&tx),
+            Some(BlobFields {
+                max_fee_per_blob_gas: 7u64.into(),
+                blob_versioned_hashes: vec![blob_hash],
+            })
+        );
+
+        // The network form wraps the transaction with its sidecar, which doesn't change it.
+        let mut wrapped = RlpStream::new_list(4);
+        wrapped.append_raw(&raw[1..], 1);
+        wrapped.begin_list(1).append(&vec![0u8; 32]);
+        wrapped.begin_list(1).append(&vec![0u8; 48]);
+        wrapped.begin_list(1).append(&vec![0u8; 48]);
+        let mut network_raw = vec![0x03];
+        network_raw.extend_from_slice(&wrapped.out());
+        assert_eq!(decode_signed_tx(&network_raw).unwrap(), tx);
+
+        assert!(decode_signed_tx(&raw[..raw.len() - 1]).is_err());
+        assert_eq!(BlobFields::of(&Transaction::default()), None);
+    }
+}
@@ -0,0 +1,194 @@
+//! Decoding of the L1 messages whose payload is not an L2 message, such as the batch posting
+//! reports the sequencer inbox emits for every batch and the ETH deposits of the bridge.
+
+use crate::networks::arbitrum::errors::DecodeError;
+use ethers::types::{H160, H256, U256};
+
+/// The L1 message kind of L2 messages posted by the sequencer.
+pub const L1_MESSAGE_KIND_L2_MESSAGE: u8 = 3;
+/// The L1 message kind of L2 messages funded by L1, from the delayed inbox.
+pub const L1_MESSAGE_KIND_L2_FUNDED_BY_L1: u8 = 7;
+/// The L1 message kind of retryable tickets submitted on L1.
+pub const L1_MESSAGE_KIND_SUBMIT_RETRYABLE: u8 = 9;
+/// The L1 message kind of the initialization message of the chain.
+pub const L1_MESSAGE_KIND_INITIALIZE: u8 = 11;
+/// The L1 message kind of `EthDeposit`s.
+pub const L1_MESSAGE_KIND_ETH_DEPOSIT: u8 = 12;
+/// The L1 message kind of `BatchPostingReport`s.
+pub const L1_MESSAGE_KIND_BATCH_POSTING_REPORT: u8 = 13;
+
+/// Reads the fixed-size fields of an L1 message payload in order.
+struct Fields<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Fields<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, offset: 0 }
+    }
+
+    fn take(&mut self, field: &'static str, len: usize) -> Result<&'a [u8], DecodeError> {
+        let bytes =
+            self.data
+                .get(self.offset..self.offset + len)
+                .ok_or(DecodeError::TruncatedField {
+                    field,
+                    needed: self.offset + len,
+                    available: self.data.len(),
+                })?;
+        self.offset += len;
+        Ok(bytes)
+    }
+
+    fn word(&mut self, field: &'static str) -> Result<U256, DecodeError> {
+        Ok(U256::from_big_endian(self.take(field, 32)?))
+    }
+
+    fn hash(&mut self, field: &'static str) -> Result<H256, DecodeError> {
+        Ok(H256::from_slice(self.take(field, 32)?))
+    }
+
+    fn address(&mut self, field: &'static str) -> Result<H160, DecodeError> {
+        Ok(H160::from_slice(self.take(field, 20)?))
+    }
+
+    /// Reads a 32-byte word that must fit in a `u64`.
+    fn word_u64(&mut self, field: &'static str) -> Result<u64, DecodeError> {
+        let word = self.word(field)?;
+        if word > U256::from(u64::MAX) {
+            return Err(DecodeError::Overflow(field));
+        }
+        Ok(word.as_u64())
+    }
+
+    fn is_empty(&self) -> bool {
+        self.offset >= self.data.len()
+    }
+}
+
+/// The report of a batch posted to the sequencer inbox, the source of the L1 costs the batch
+/// poster is reimbursed for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchPostingReport {
+    /// The L1 timestamp of the batch.
+    pub batch_timestamp: U256,
+    pub batch_poster: H160,
+    /// The hash of the batch data posted to L1.
+    pub data_hash: H256,
+    pub batch_number: u64,
+    /// The L1 base fee when the batch was posted.
+    pub l1_base_fee: U256,
+    /// The gas spent posting the batch on top of its calldata, 0 if not reported.
+    pub extra_gas: u64,
+}
+
+impl BatchPostingReport {
+    /// Decodes the payload of a `BatchPostingReport` L1 message: the 32-byte batch timestamp,
+    /// the 20-byte poster address, the 32-byte data hash, batch number and L1 base fee, then an
+    /// optional 8-byte extra gas.
+    pub fn decode(payload: &[u8]) -> Result<Self, DecodeError> {
+        let mut fields = Fields::new(payload);
+        Ok(Self {
+            batch_timestamp: fields.word("batch timestamp")?,
+            batch_poster: fields.address("batch poster")?,
+            data_hash: fields.hash("data hash")?,
+            batch_number: fields.word_u64("batch number")?,
+            l1_base_fee: fields.word("L1 base fee")?,
+            extra_gas: if fields.is_empty() {
+                0
+            } else {
+                u64::from_be_bytes(fields.take("extra gas", 8)?.try_into().unwrap())
+            },
+        })
+    }
+}
+
+/// ETH deposited into the bridge on L1 and credited on L2.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EthDeposit {
+    /// The L1 sender of the deposit, from the message header.
+    pub from: H160,
+    pub to: H160,
+    pub value: U256,
+}
+
+impl EthDeposit {
+    /// Decodes the payload of an `EthDeposit` L1 message: the 20-byte recipient and the 32-byte
+    /// value.
+    ///
+    /// # Arguments
+    ///
+    /// * `sender` - The sender of the message header, as sent by the feed.
+    /// * `payload` - The payload of the message.
+    pub fn decode(sender: &str, payload: &[u8]) -> Result<Self, DecodeError> {
+        let from = sender
+            .parse()
+            .map_err(|_| DecodeError::InvalidSender(sender.to_string()))?;
+        let mut fields = Fields::new(payload);
+        Ok(Self {
+            from,
+            to: fields.address("deposit recipient")?,
+            value: fields.word("deposit value")?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_batch_posting_reports() {
+        let mut payload = H256::from_low_u64_be(1_700_000_000).as_bytes().to_vec();
+        payload.extend(H160::repeat_byte(0xaa).as_bytes());
+        payload.extend(H256::repeat_byte(0xbb).as_bytes());
+        payload.extend(H256::from_low_u64_be(512_345).as_bytes());
+        payload.extend(H256::from_low_u64_be(30_000_000_000).as_bytes());
+
+        let report = BatchPostingReport::decode(&payload).unwrap();
+        assert_eq!(report.batch_timestamp, 1_700_000_000u64.into());
+        assert_eq!(report.batch_poster, H160::repeat_byte(0xaa));
+        assert_eq!(report.data_hash, H256::repeat_byte(0xbb));
+        assert_eq!(report.batch_number, 512_345);
+        assert_eq!(report.l1_base_fee, 30_000_000_000u64.into());
+        assert_eq!(report.extra_gas, 0);
+
+        payload.extend(21_000u64.to_be_bytes());
+        assert_eq!(
+            BatchPostingReport::decode(&payload).unwrap().extra_gas,
+            21_000
+        );
+
+        payload.truncate(100);
+        assert_eq!(
+            BatchPostingReport::decode(&payload),
+            Err(DecodeError::TruncatedField {
+                field: "batch number",
+                needed: 116,
+                available: 100
+            })
+        );
+    }
+
+    #[test]
+    fn decodes_eth_deposits() {
+        let mut payload = H160::repeat_byte(0x22).as_bytes().to_vec();
+        payload.extend(H256::from_low_u64_be(10u64.pow(18)).as_bytes());
+
+        let sender = "0x1111111111111111111111111111111111111111";
+        assert_eq!(
+            EthDeposit::decode(sender, &payload),
+            Ok(EthDeposit {
+                from: H160::repeat_byte(0x11),
+                to: H160::repeat_byte(0x22),
+                value: 10u64.pow(18).into(),
+            })
+        );
+        assert_eq!(
+            EthDeposit::decode("0x11", &payload),
+            Err(DecodeError::InvalidSender("0x11".to_string()))
+        );
+        assert!(EthDeposit::decode(sender, &payload[..40]).is_err());
+    }
+}
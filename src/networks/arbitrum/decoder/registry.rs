@@ -0,0 +1,102 @@
+use super::{decode_message_with, DecodeOptions, DecodedMsg};
+use crate::networks::arbitrum::{
+    errors::DecodeError,
+    types::{Header, L1IncomingMessageHeader},
+};
+use std::{collections::HashMap, sync::Arc};
+
+/// What a `DecodeHook` made of a message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeOutcome {
+    /// The hook decoded the message; the default decoder is not run.
+    Decoded(DecodedMsg),
+    /// The message should be dropped.
+    Ignore,
+    /// The hook doesn't handle this message; try the next hook, then the default decoder.
+    Fallthrough,
+}
+
+/// A custom decoding step for the messages of a given chain.
+///
+/// Hooks see every message before the default Arbitrum decoder, which lets them handle message
+/// kinds the default decoder doesn't know about or replace how known kinds are decoded.
+pub trait DecodeHook: Send + Sync {
+    /// Decodes a message.
+    ///
+    /// # Arguments
+    ///
+    /// * `header` - The L1 header of the message.
+    /// * `l2_bytes` - The L2 message, starting with its L2 message kind.
+    fn decode(&self, header: &Header, l2_bytes: &[u8]) -> Result<DecodeOutcome, DecodeError>;
+}
+
+impl<F> DecodeHook for F
+where
+    F: Fn(&Header, &[u8]) -> Result<DecodeOutcome, DecodeError> + Send + Sync,
+{
+    fn decode(&self, header: &Header, l2_bytes: &[u8]) -> Result<DecodeOutcome, DecodeError> {
+        self(header, l2_bytes)
+    }
+}
+
+/// Decode hooks registered per chain ID, extending the default Arbitrum decoder.
+///
+/// Chains without hooks, and messages every hook falls through on, are decoded by the default
+/// decoder.
+#[derive(Default, Clone)]
+pub struct DecoderRegistry {
+    hooks: HashMap<u64, Vec<Arc<dyn DecodeHook>>>,
+}
+
+impl DecoderRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a hook for `chain_id`. Hooks run in registration order.
+    pub fn register(&mut self, chain_id: u64, hook: impl DecodeHook + 'static) {
+        self.hooks.entry(chain_id).or_default().push(Arc::new(hook));
+    }
+
+    /// Returns `true` if hooks are registered for `chain_id`.
+    pub fn has_hooks(&self, chain_id: u64) -> bool {
+        self.hooks.contains_key(&chain_id)
+    }
+
+    /// Decodes a message of `chain_id`, running its hooks before the default decoder.
+    ///
+    /// # Returns
+    ///
+    /// The decoded message, `None` if it was ignored or is of an unsupported kind, or a
+    /// `DecodeError` if it is malformed.
+    pub fn decode(
+        &self,
+        chain_id: u64,
+        msg: &L1IncomingMessageHeader,
+    ) -> Result<Option<DecodedMsg>, DecodeError> {
+        self.decode_with(chain_id, msg, DecodeOptions::default())
+    }
+
+    /// Like `decode`, skipping the work disabled in `options` in the default decoder.
+    pub fn decode_with(
+        &self,
+        chain_id: u64,
+        msg: &L1IncomingMessageHeader,
+        options: DecodeOptions,
+    ) -> Result<Option<DecodedMsg>, DecodeError> {
+        let Some(hooks) = self.hooks.get(&chain_id) else {
+            return msg.try_decode_with(options);
+        };
+
+        let l2_bytes = msg.l2_bytes_with(options)?;
+        for hook in hooks {
+            match hook.decode(&msg.header, l2_bytes)? {
+                DecodeOutcome::Decoded(decoded) => return Ok(Some(decoded)),
+                DecodeOutcome::Ignore => return Ok(None),
+                DecodeOutcome::Fallthrough => (),
+            }
+        }
+
+        decode_message_with(msg.header.kind, &msg.header.sender, l2_bytes, options)
+    }
+}
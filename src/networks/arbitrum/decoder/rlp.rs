@@ -0,0 +1,290 @@
+//! A minimal, panic-free RLP decoder for the transaction formats found on the feed.
+//!
+//! Items borrow from the input and are only validated as far as they are read, so decoding the
+//! fields of a transaction doesn't allocate. Non-canonical encodings (e.g. a single byte below
+//! `0x80` wrapped in a string header, or lengths with leading zeros) are rejected like geth does.
+
+use crate::networks::arbitrum::errors::DecodeError;
+use thiserror::Error;
+
+/// Why RLP input couldn't be decoded, reported as `DecodeError::Rlp`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum RlpError {
+    #[error("RLP input is empty")]
+    Empty,
+    #[error("RLP item is longer than its input")]
+    Truncated,
+    #[error("RLP input has trailing bytes")]
+    TrailingBytes,
+    #[error("RLP item is not canonically encoded")]
+    NonCanonical,
+    #[error("expected an RLP string")]
+    ExpectedString,
+    #[error("expected an RLP list")]
+    ExpectedList,
+    #[error("RLP integer is too large")]
+    Overflow,
+    #[error("RLP list has an unexpected number of items")]
+    IncorrectListLen,
+}
+
+/// A decoded RLP item, borrowing from the input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Item<'a> {
+    /// The full encoding of the item, header included.
+    raw: &'a [u8],
+    /// The payload of the item, header excluded.
+    payload: &'a [u8],
+    is_list: bool,
+}
+
+impl<'a> Item<'a> {
+    /// Decodes `data`, which must hold exactly one item.
+    pub fn decode(data: &'a [u8]) -> Result<Self, DecodeError> {
+        let (item, rest) = Self::split(data)?;
+        if !rest.is_empty() {
+            return Err(RlpError::TrailingBytes.into());
+        }
+        Ok(item)
+    }
+
+    /// Decodes the first item of `data`, returning it with the bytes following it.
+    pub fn split(data: &'a [u8]) -> Result<(Self, &'a [u8]), DecodeError> {
+        let (&prefix, rest) = data.split_first().ok_or(RlpError::Empty)?;
+        let (header_len, payload_len, is_list) = match prefix {
+            0x00..=0x7f => (0, 1, false),
+            0x80..=0xb7 => (1, (prefix - 0x80) as usize, false),
+            0xb8..=0xbf => {
+                let len_of_len = (prefix - 0xb7) as usize;
+                (1 + len_of_len, long_length(rest, len_of_len)?, false)
+            }
+            0xc0..=0xf7 => (1, (prefix - 0xc0) as usize, true),
+            0xf8..=0xff => {
+                let len_of_len = (prefix - 0xf7) as usize;
+                (1 + len_of_len, long_length(rest, len_of_len)?, true)
+            }
+        };
+
+        let total = header_len
+            .checked_add(payload_len)
+            .ok_or(RlpError::Truncated)?;
+        let raw = data.get(..total).ok_or(RlpError::Truncated)?;
+        let rest = data.get(total..).ok_or(RlpError::Truncated)?;
+        // A single byte below 0x80 is its own payload.
+        let payload = raw.get(header_len..).ok_or(RlpError::Truncated)?;
+        if prefix == 0x81 && payload.first().is_some_and(|&b| b < 0x80) {
+            return Err(RlpError::NonCanonical.into());
+        }
+
+        Ok((
+            Self {
+                raw,
+                payload,
+                is_list,
+            },
+            rest,
+        ))
+    }
+
+    /// The full encoding of the item, header included.
+    pub fn raw(&self) -> &'a [u8] {
+        self.raw
+    }
+
+    pub fn is_list(&self) -> bool {
+        self.is_list
+    }
+
+    /// Returns the bytes of a string item.
+    pub fn bytes(&self) -> Result<&'a [u8], DecodeError> {
+        if self.is_list {
+            return Err(RlpError::ExpectedString.into());
+        }
+        Ok(self.payload)
+    }
+
+    /// Returns the bytes of a string item holding an unsigned integer of at most `max_len` bytes,
+    /// big-endian and without leading zeros.
+    pub fn uint_bytes(&self, max_len: usize) -> Result<&'a [u8], DecodeError> {
+        let bytes = self.bytes()?;
+        if bytes.first() == Some(&0) {
+            return Err(RlpError::NonCanonical.into());
+        }
+        if bytes.len() > max_len {
+            return Err(RlpError::Overflow.into());
+        }
+        Ok(bytes)
+    }
+
+    /// Returns the value of a string item holding an unsigned integer that fits in a `u64`.
+    pub fn u64(&self) -> Result<u64, DecodeError> {
+        Ok(self
+            .uint_bytes(8)?
+            .iter()
+            .fold(0, |acc, &b| (acc << 8) | b as u64))
+    }
+
+    /// Returns the items of a list item.
+    pub fn list(&self) -> Result<List<'a>, DecodeError> {
+        if !self.is_list {
+            return Err(RlpError::ExpectedList.into());
+        }
+        Ok(List {
+            remaining: self.payload,
+        })
+    }
+}
+
+/// An iterator over the items of an RLP list.
+#[derive(Debug, Clone)]
+pub struct List<'a> {
+    remaining: &'a [u8],
+}
+
+impl<'a> List<'a> {
+    /// Collects the items of the list, failing unless there are exactly `N` of them.
+    pub fn exactly<const N: usize>(self) -> Result<[Item<'a>; N], DecodeError> {
+        let items = self.collect::<Result<Vec<_>, _>>()?;
+        items
+            .try_into()
+            .map_err(|_| RlpError::IncorrectListLen.into())
+    }
+}
+
+impl<'a> Iterator for List<'a> {
+    type Item = Result<Item<'a>, DecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining.is_empty() {
+            return None;
+        }
+        match Item::split(self.remaining) {
+            Ok((item, rest)) => {
+                self.remaining = rest;
+                Some(Ok(item))
+            }
+            Err(e) => {
+                self.remaining = &[];
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// Encodes the header of a list whose items take `payload_len` bytes.
+pub fn list_header(payload_len: usize) -> Vec<u8> {
+    if payload_len <= 55 {
+        return vec![0xc0 + payload_len as u8];
+    }
+    let len_bytes = payload_len.to_be_bytes();
+    let skip = len_bytes.iter().take_while(|&&b| b == 0).count();
+    let mut header = vec![0xf7 + (len_bytes.len() - skip) as u8];
+    header.extend(len_bytes.iter().skip(skip));
+    header
+}
+
+/// Reads the big-endian length following a long-form header.
+fn long_length(data: &[u8], len_of_len: usize) -> Result<usize, RlpError> {
+    let bytes = data.get(..len_of_len).ok_or(RlpError::Truncated)?;
+    if bytes.first() == Some(&0) {
+        return Err(RlpError::NonCanonical);
+    }
+    if len_of_len > std::mem::size_of::<usize>() {
+        return Err(RlpError::Overflow);
+    }
+    let len = bytes.iter().fold(0usize, |acc, &b| (acc << 8) | b as usize);
+    if len <= 55 {
+        return Err(RlpError::NonCanonical);
+    }
+    Ok(len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::utils::rlp::RlpStream;
+
+    #[test]
+    fn decodes_nested_lists_and_rejects_malformed_input() {
+        let long = vec![0xab; 60];
+        let mut stream = RlpStream::new_list(3);
+        stream.append(&1024u64);
+        stream.append(&long);
+        stream.begin_list(1).append(&0u64);
+        let encoded = stream.out().to_vec();
+
+        let [number, bytes, nested] = Item::decode(&encoded)
+            .unwrap()
+            .list()
+            .unwrap()
+            .exactly()
+            .unwrap();
+        assert_eq!(number.u64(), Ok(1024));
+        assert_eq!(bytes.bytes(), Ok(&long[..]));
+        let [zero] = nested.list().unwrap().exactly().unwrap();
+        assert_eq!(zero.u64(), Ok(0));
+
+        assert_eq!(Item::decode(&[]), Err(DecodeError::Rlp(RlpError::Empty)));
+        assert_eq!(
+            Item::decode(&[0x81, 0x05]),
+            Err(DecodeError::Rlp(RlpError::NonCanonical))
+        );
+        assert_eq!(
+            Item::decode(&[0xb8, 0x02, 0, 0]),
+            Err(DecodeError::Rlp(RlpError::NonCanonical))
+        );
+        assert_eq!(
+            Item::decode(&[0x82, 0x01]),
+            Err(DecodeError::Rlp(RlpError::Truncated))
+        );
+        assert_eq!(
+            Item::decode(&[0x01, 0x02]),
+            Err(DecodeError::Rlp(RlpError::TrailingBytes))
+        );
+        assert_eq!(
+            Item::decode(&[0x82, 0x00, 0x01]).unwrap().u64(),
+            Err(DecodeError::Rlp(RlpError::NonCanonical))
+        );
+        assert_eq!(
+            Item::decode(&[0xbf, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]),
+            Err(DecodeError::Rlp(RlpError::Truncated))
+        );
+        assert_eq!(
+            Item::decode(&encoded[..encoded.len() - 1]),
+            Err(DecodeError::Rlp(RlpError::Truncated))
+        );
+        assert_eq!(list_header(encoded.len() - 2), encoded[..2]);
+    }
+
+    #[test]
+    fn never_panics_on_truncated_or_corrupted_input() {
+        let mut stream = RlpStream::new_list(4);
+        stream.append(&u64::MAX);
+        stream.append(&vec![0x5a; 70]);
+        stream.begin_list(2).append(&1u64).append(&vec![0x01; 3]);
+        stream.append_empty_data();
+        let encoded = stream.out().to_vec();
+
+        fn walk(item: Item) -> Result<usize, DecodeError> {
+            if !item.is_list() {
+                return Ok(item.u64().map_or(item.bytes()?.len(), |_| 1));
+            }
+            item.list()?.map(|item| walk(item?)).sum()
+        }
+
+        // Truncated every which way, and with every byte replaced by the values found in headers,
+        // the decoder never panics.
+        for end in 0..encoded.len() {
+            let _ = Item::decode(&encoded[..end]).and_then(walk);
+        }
+        for i in 0..encoded.len() {
+            for byte in [
+                0x00, 0x7f, 0x80, 0x81, 0xb7, 0xb8, 0xbf, 0xc0, 0xf7, 0xf8, 0xff,
+            ] {
+                let mut corrupted = encoded.clone();
+                corrupted[i] = byte;
+                let _ = Item::decode(&corrupted).and_then(walk);
+            }
+        }
+    }
+}
@@ -0,0 +1,222 @@
+use crate::networks::arbitrum::{decoder::DecodedMsg, events::FeedEvent, message::FeedMessage};
+use crossbeam_channel::{Receiver, Sender};
+use ethers::types::H256;
+use log::*;
+use std::{
+    collections::{hash_map::RandomState, HashMap, VecDeque},
+    hash::{BuildHasher, Hash, Hasher},
+    thread::{self, JoinHandle},
+};
+
+/// How many sequence numbers and transaction hashes a `Deduplicator` remembers by default.
+pub const DEFAULT_DEDUP_WINDOW: usize = 10_000;
+
+/// A set remembering its `capacity` most recently used keys.
+struct LruSet<K> {
+    capacity: usize,
+    tick: u64,
+    last_used: HashMap<K, u64>,
+    /// Keys by use, oldest first. Keys used again keep their older entries until they are
+    /// popped, which are then told apart from the latest by their tick.
+    uses: VecDeque<(K, u64)>,
+}
+
+impl<K: Copy + Eq + Hash> LruSet<K> {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            tick: 0,
+            last_used: HashMap::new(),
+            uses: VecDeque::new(),
+        }
+    }
+
+    fn clear(&mut self) {
+        self.last_used.clear();
+        self.uses.clear();
+    }
+
+    fn contains(&self, key: &K) -> bool {
+        self.last_used.contains_key(key)
+    }
+
+    /// Marks `key` as the most recently used one, evicting the least recently used key if the
+    /// set is full.
+    fn touch(&mut self, key: K) {
+        self.tick += 1;
+        self.last_used.insert(key, self.tick);
+        self.uses.push_back((key, self.tick));
+
+        while self.last_used.len() > self.capacity {
+            let Some((key, tick)) = self.uses.pop_front() else {
+                break;
+            };
+            if self.last_used.get(&key) == Some(&tick) {
+                self.last_used.remove(&key);
+            }
+        }
+        if self.uses.len() > 2 * self.capacity {
+            let last_used = &self.last_used;
+            self.uses
+                .retain(|(key, tick)| last_used.get(key) == Some(tick));
+        }
+    }
+}
+
+/// Marks the messages redelivered by a relay, typically the overlap replayed after a
+/// reconnect-with-resume, with `Provenance::duplicate`, so that downstream consumers can process
+/// every message once.
+///
+/// A message is a duplicate if a message with the same sequence number and content was seen, or
+/// if it decoded into transactions that were all seen, e.g. when a transaction is sequenced
+/// again. A message re-sent with another content after a reorg is not a duplicate. Only the most
+/// recently seen messages and transaction hashes are remembered, up to the configured window
+/// each, and they are forgotten on a reorg, see `observe_event`.
+pub struct Deduplicator {
+    /// Keys the content hashes, so that a relay can't forge a message with the same hash.
+    hasher: RandomState,
+    /// The sequence numbers and content hashes of the messages seen.
+    messages: LruSet<(u64, u64)>,
+    tx_hashes: LruSet<H256>,
+    duplicates: u64,
+}
+
+impl Default for Deduplicator {
+    fn default() -> Self {
+        Self::new(DEFAULT_DEDUP_WINDOW)
+    }
+}
+
+impl Deduplicator {
+    /// # Arguments
+    ///
+    /// * `window` - How many messages and transaction hashes to remember. Must cover the
+    ///   messages a relay may redeliver, i.e. at least the resume overlap.
+    pub fn new(window: usize) -> Self {
+        Self {
+            hasher: RandomState::new(),
+            messages: LruSet::new(window),
+            tx_hashes: LruSet::new(window),
+            duplicates: 0,
+        }
+    }
+
+    /// Records `msg` as seen.
+    ///
+    /// # Returns
+    ///
+    /// `true` if `msg` is a duplicate of a message seen before.
+    pub fn is_duplicate(&mut self, msg: &FeedMessage) -> bool {
+        let hashes = match &msg.decoded {
+            Ok(Some(DecodedMsg::DecodedBatch(txs))) => txs.iter().map(|tx| tx.hash).collect(),
+            Ok(Some(DecodedMsg::DecodedSignedTx(tx))) => vec![tx.hash],
+            _ => Vec::new(),
+        };
+
+        let mut hasher = self.hasher.build_hasher();
+        msg.message.hash_content(&mut hasher);
+        let key = (msg.sequence_number(), hasher.finish());
+
+        let duplicate = self.messages.contains(&key)
+            || (!hashes.is_empty() && hashes.iter().all(|hash| self.tx_hashes.contains(hash)));
+
+        self.messages.touch(key);
+        for hash in hashes {
+            self.tx_hashes.touch(hash);
+        }
+        if duplicate {
+            self.duplicates += 1;
+        }
+        duplicate
+    }
+
+    /// Records `msg` as seen, and returns it with `Provenance::duplicate` set if it is a
+    /// duplicate.
+    pub fn mark(&mut self, mut msg: FeedMessage) -> FeedMessage {
+        if self.is_duplicate(&msg) {
+            msg.provenance.duplicate = true;
+        }
+        msg
+    }
+
+    /// Forgets every message and transaction seen on a `FeedEvent::Reorg`: the messages re-sent
+    /// after it supersede them.
+    pub fn observe_event(&mut self, event: &FeedEvent) {
+        if let FeedEvent::Reorg { .. } = event {
+            self.messages.clear();
+            self.tx_hashes.clear();
+        }
+    }
+
+    /// Returns how many duplicates were seen so far.
+    pub fn duplicates(&self) -> u64 {
+        self.duplicates
+    }
+
+    /// Forwards the messages received on `input` to `output` on a new thread, marking
+    /// duplicates, until either side is disconnected.
+    pub fn spawn(
+        mut self,
+        input: Receiver<FeedMessage>,
+        output: Sender<FeedMessage>,
+    ) -> JoinHandle<()> {
+        thread::spawn(move || {
+            for msg in input {
+                let msg = self.mark(msg);
+                if msg.provenance.duplicate {
+                    debug!("Marking duplicate message {}", msg.sequence_number());
+                }
+                if output.send(msg).is_err() {
+                    break;
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::networks::arbitrum::fixtures::feed_message;
+    use ethers::types::Transaction;
+
+    #[test]
+    fn marks_redelivered_messages_within_window() {
+        let with_hashes = |seq: u64, hashes: &[u64]| {
+            let txs = hashes
+                .iter()
+                .map(|hash| Transaction {
+                    hash: H256::from_low_u64_be(*hash),
+                    ..Default::default()
+                })
+                .collect();
+            feed_message(
+                seq,
+                0,
+                hashes.iter().map(|&h| h as u8).collect(),
+                Some(DecodedMsg::DecodedBatch(txs)),
+            )
+        };
+        let mut dedup = Deduplicator::new(3);
+
+        assert!(!dedup.is_duplicate(&with_hashes(1, &[10])));
+        assert!(!dedup.is_duplicate(&with_hashes(2, &[20, 21])));
+        // Redelivered after a reconnect.
+        assert!(dedup.is_duplicate(&with_hashes(2, &[20, 21])));
+        // Sequenced again under a new sequence number.
+        assert!(dedup.is_duplicate(&with_hashes(3, &[10])));
+        // Only partly seen.
+        assert!(!dedup.is_duplicate(&with_hashes(4, &[21, 40])));
+        assert_eq!(dedup.duplicates(), 2);
+
+        // Sequence number 1 was evicted by 2, 3 and 4.
+        assert!(!dedup.is_duplicate(&with_hashes(1, &[])));
+
+        // Re-sent with another content after a reorg.
+        assert!(!dedup.is_duplicate(&with_hashes(4, &[50])));
+        assert!(dedup.mark(with_hashes(4, &[50])).provenance.duplicate);
+        assert!(!dedup.mark(with_hashes(5, &[])).provenance.duplicate);
+        dedup.observe_event(&FeedEvent::Reorg { from: 3, to: 5 });
+        assert!(!dedup.is_duplicate(&with_hashes(4, &[50])));
+    }
+}
@@ -0,0 +1,232 @@
+use crate::networks::arbitrum::{
+    errors::DelayedMessageError, events::FeedEvent, network::ArbitrumNetwork,
+    types::BroadcastFeedMessage,
+};
+use ethers::{
+    abi::{self, ParamType, Token},
+    providers::Middleware,
+    types::{Address, Filter, Log, H256, U256},
+    utils::keccak256,
+};
+use std::{ops::Range, sync::Arc};
+
+/// The event emitted by the bridge contract for every message added to the delayed inbox.
+const MESSAGE_DELIVERED: &str =
+    "MessageDelivered(uint256,bytes32,address,uint8,address,bytes32,uint256,uint64)";
+/// How many L1 blocks before the feed message to search for delayed messages by default.
+const DEFAULT_LOOKBACK: u64 = 7200;
+
+/// Detects when the sequencer includes new messages from the delayed inbox.
+///
+/// Every feed message carries the number of delayed messages read so far, which only grows when
+/// the sequencer includes deposits or force-included transactions posted on L1.
+#[derive(Debug, Clone, Default)]
+pub struct DelayedMessageTracker {
+    delayed_messages_read: Option<u64>,
+}
+
+impl DelayedMessageTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of delayed messages read as of the last observed message.
+    pub fn delayed_messages_read(&self) -> Option<u64> {
+        self.delayed_messages_read
+    }
+
+    /// Records a message read from the feed.
+    ///
+    /// # Returns
+    ///
+    /// A `FeedEvent::DelayedMessagesAdvanced` if the message read new delayed messages. The first
+    /// observed message only sets the baseline.
+    pub fn observe(&mut self, msg: &BroadcastFeedMessage) -> Option<FeedEvent> {
+        let read = msg.message.delayed_messages_read;
+        match self.delayed_messages_read {
+            Some(previous) if read <= previous => None,
+            previous => {
+                self.delayed_messages_read = Some(read);
+                previous.map(|from| FeedEvent::DelayedMessagesAdvanced { from, to: read })
+            }
+        }
+    }
+}
+
+/// A message of the delayed inbox, as delivered on L1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DelayedMessage {
+    /// The index of the message in the delayed inbox.
+    pub index: u64,
+    /// The L1 message kind, as in `Header::kind`.
+    pub kind: u8,
+    pub sender: Address,
+    /// The keccak256 hash of the message data.
+    pub data_hash: H256,
+    pub base_fee_l1: U256,
+    pub timestamp: u64,
+    pub l1_block_number: Option<u64>,
+    pub l1_transaction_hash: Option<H256>,
+}
+
+/// Fetches delayed messages from the bridge contract on L1.
+#[derive(Debug, Clone)]
+pub struct DelayedMessageFetcher<M> {
+    provider: Arc<M>,
+    bridge: Address,
+    lookback: u64,
+}
+
+impl<M: Middleware> DelayedMessageFetcher<M> {
+    /// # Arguments
+    ///
+    /// * `provider` - A provider connected to the parent chain.
+    /// * `bridge` - The address of the rollup's bridge contract.
+    pub fn new(provider: Arc<M>, bridge: Address) -> Self {
+        Self {
+            provider,
+            bridge,
+            lookback: DEFAULT_LOOKBACK,
+        }
+    }
+
+    /// Creates a fetcher using the bridge contract of `network`.
+    pub fn for_network(provider: Arc<M>, network: ArbitrumNetwork) -> Self {
+        Self::new(provider, network.bridge_address())
+    }
+
+    /// Sets how many L1 blocks before the feed message are searched for delayed messages.
+    pub fn with_lookback(mut self, blocks: u64) -> Self {
+        self.lookback = blocks;
+        self
+    }
+
+    /// Fetches the delayed messages with the given indices.
+    ///
+    /// # Arguments
+    ///
+    /// * `indices` - The indices to fetch, e.g. `from..to` of a `DelayedMessagesAdvanced` event.
+    /// * `l1_block_number` - The L1 block number of the feed message that read the messages,
+    ///   which were delivered at most `lookback` blocks before it.
+    ///
+    /// # Returns
+    ///
+    /// The messages found, sorted by index. Messages delivered before the lookback window are
+    /// missing from the result.
+    pub async fn fetch(
+        &self,
+        indices: Range<u64>,
+        l1_block_number: u64,
+    ) -> Result<Vec<DelayedMessage>, DelayedMessageError> {
+        if indices.is_empty() {
+            return Ok(Vec::new());
+        }
+        let topics: Vec<H256> = indices.map(H256::from_low_u64_be).collect();
+        let filter = Filter::new()
+            .address(self.bridge)
+            .topic0(H256::from(keccak256(MESSAGE_DELIVERED)))
+            .topic1(topics)
+            .from_block(l1_block_number.saturating_sub(self.lookback))
+            .to_block(l1_block_number);
+
+        let logs = self
+            .provider
+            .get_logs(&filter)
+            .await
+            .map_err(|e| DelayedMessageError::Provider(e.to_string()))?;
+        let mut messages = logs
+            .iter()
+            .map(decode_message_delivered)
+            .collect::<Result<Vec<_>, _>>()?;
+        messages.sort_by_key(|msg| msg.index);
+        Ok(messages)
+    }
+}
+
+fn decode_message_delivered(log: &Log) -> Result<DelayedMessage, DelayedMessageError> {
+    let index = log.topics.get(1).ok_or(DelayedMessageError::MalformedLog)?;
+    let tokens = abi::decode(
+        &[
+            ParamType::Address,
+            ParamType::Uint(8),
+            ParamType::Address,
+            ParamType::FixedBytes(32),
+            ParamType::Uint(256),
+            ParamType::Uint(64),
+        ],
+        &log.data,
+    )?;
+    let [_inbox, Token::Uint(kind), Token::Address(sender), Token::FixedBytes(data_hash), Token::Uint(base_fee_l1), Token::Uint(timestamp)] =
+        tokens.as_slice()
+    else {
+        return Err(DelayedMessageError::MalformedLog);
+    };
+
+    Ok(DelayedMessage {
+        index: U256::from_big_endian(index.as_bytes()).low_u64(),
+        kind: kind.low_u32() as u8,
+        sender: *sender,
+        data_hash: H256::from_slice(data_hash),
+        base_fee_l1: *base_fee_l1,
+        timestamp: timestamp.low_u64(),
+        l1_block_number: log.block_number.map(|n| n.as_u64()),
+        l1_transaction_hash: log.transaction_hash,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::networks::arbitrum::fixtures::message_with;
+
+    #[test]
+    fn tracks_delayed_messages_read() {
+        let message = |seq: u64, delayed: u64| {
+            let mut msg = message_with(seq, 0, Vec::new());
+            msg.message.delayed_messages_read = delayed;
+            msg
+        };
+        let mut tracker = DelayedMessageTracker::new();
+
+        assert_eq!(tracker.observe(&message(1, 10)), None);
+        assert_eq!(tracker.observe(&message(2, 10)), None);
+        assert_eq!(
+            tracker.observe(&message(3, 12)),
+            Some(FeedEvent::DelayedMessagesAdvanced { from: 10, to: 12 })
+        );
+        // A retransmitted older message doesn't move the count back.
+        assert_eq!(tracker.observe(&message(2, 10)), None);
+        assert_eq!(tracker.delayed_messages_read(), Some(12));
+    }
+
+    #[test]
+    fn decodes_message_delivered_logs() {
+        let sender = Address::repeat_byte(0x11);
+        let log = Log {
+            topics: vec![
+                H256::from(keccak256(MESSAGE_DELIVERED)),
+                H256::from_low_u64_be(42),
+                H256::zero(),
+            ],
+            data: abi::encode(&[
+                Token::Address(Address::zero()),
+                Token::Uint(12.into()),
+                Token::Address(sender),
+                Token::FixedBytes(vec![0xab; 32]),
+                Token::Uint(7.into()),
+                Token::Uint(1_700_000_000u64.into()),
+            ])
+            .into(),
+            block_number: Some(100.into()),
+            ..Default::default()
+        };
+
+        let msg = decode_message_delivered(&log).unwrap();
+        assert_eq!(msg.index, 42);
+        assert_eq!(msg.kind, 12);
+        assert_eq!(msg.sender, sender);
+        assert_eq!(msg.data_hash, H256::repeat_byte(0xab));
+        assert_eq!(msg.timestamp, 1_700_000_000);
+        assert_eq!(msg.l1_block_number, Some(100));
+    }
+}
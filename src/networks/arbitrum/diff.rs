@@ -0,0 +1,151 @@
+use crate::networks::arbitrum::{
+    archive::Archive, errors::ArchiveError, types::BroadcastFeedMessage,
+};
+use std::collections::{btree_map::Entry, BTreeMap};
+
+/// The differences found between two recordings of the same feed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FeedDiff {
+    /// Sequence numbers present on both sides.
+    pub compared: usize,
+    /// Sequence numbers only present on the right side.
+    pub missing_left: Vec<u64>,
+    /// Sequence numbers only present on the left side.
+    pub missing_right: Vec<u64>,
+    /// Sequence numbers recorded more than once on the left side.
+    pub duplicated_left: Vec<u64>,
+    /// Sequence numbers recorded more than once on the right side.
+    pub duplicated_right: Vec<u64>,
+    /// Sequence numbers whose messages differ between both sides.
+    pub divergent: Vec<u64>,
+}
+
+impl FeedDiff {
+    /// Returns `true` if both sides hold exactly the same messages.
+    pub fn is_clean(&self) -> bool {
+        self.missing_left.is_empty()
+            && self.missing_right.is_empty()
+            && self.duplicated_left.is_empty()
+            && self.duplicated_right.is_empty()
+            && self.divergent.is_empty()
+    }
+}
+
+/// Compares two sequences of feed messages, e.g. an archive and the live cache.
+///
+/// Messages are compared by their serialized bytes. When a sequence number is duplicated, the
+/// first occurrence is used for the comparison.
+///
+/// # Arguments
+///
+/// * `left` - The messages of the reference recording.
+/// * `right` - The messages of the recording to validate.
+pub fn diff_messages<L, R>(left: L, right: R) -> FeedDiff
+where
+    L: IntoIterator<Item = BroadcastFeedMessage>,
+    R: IntoIterator<Item = BroadcastFeedMessage>,
+{
+    let mut diff = FeedDiff::default();
+    let left = index(left, &mut diff.duplicated_left);
+    let right = index(right, &mut diff.duplicated_right);
+
+    for (seq, bytes) in &left {
+        match right.get(seq) {
+            Some(other) => {
+                diff.compared += 1;
+                if bytes != other {
+                    diff.divergent.push(*seq);
+                }
+            }
+            None => diff.missing_right.push(*seq),
+        }
+    }
+    diff.missing_left = right
+        .keys()
+        .filter(|seq| !left.contains_key(seq))
+        .copied()
+        .collect();
+
+    diff
+}
+
+/// Compares the messages of two archives within the sequence range `[from, to]`.
+pub fn diff_archives(
+    left: &Archive,
+    right: &Archive,
+    from: u64,
+    to: u64,
+) -> Result<FeedDiff, ArchiveError> {
+    let left = left.read(from, to, |_| true)?;
+    let right = right.read(from, to, |_| true)?;
+    Ok(diff_messages(left, right))
+}
+
+fn index<I>(messages: I, duplicated: &mut Vec<u64>) -> BTreeMap<u64, Vec<u8>>
+where
+    I: IntoIterator<Item = BroadcastFeedMessage>,
+{
+    let mut indexed = BTreeMap::new();
+    for msg in messages {
+        match indexed.entry(msg.sequence_number) {
+            Entry::Vacant(entry) => {
+                entry.insert(serde_json::to_vec(&msg).unwrap_or_default());
+            }
+            Entry::Occupied(_) => {
+                if duplicated.last() != Some(&msg.sequence_number) {
+                    duplicated.push(msg.sequence_number);
+                }
+            }
+        }
+    }
+    duplicated.sort_unstable();
+    duplicated.dedup();
+
+    indexed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::networks::arbitrum::{archive::ArchiveWriter, fixtures::message_with};
+
+    #[test]
+    fn reports_missing_duplicated_and_divergent_messages() {
+        let message = |seq| message_with(seq, 1_000 + seq, vec![seq as u8]);
+        let left = [0, 1, 2, 3, 3, 5].map(message);
+        let mut right = [1, 2, 2, 3, 4, 5].map(message);
+        right[5].message.message.l2msg = vec![0xff].into();
+
+        let diff = diff_messages(left.clone(), right.clone());
+        assert_eq!(
+            diff,
+            FeedDiff {
+                compared: 4,
+                missing_left: vec![4],
+                missing_right: vec![0],
+                duplicated_left: vec![3],
+                duplicated_right: vec![2],
+                divergent: vec![5],
+            }
+        );
+        assert!(!diff.is_clean());
+        assert!(diff_messages((0..4).map(message), (0..4).map(message)).is_clean());
+
+        let dir = std::env::temp_dir().join(format!("sfr-diff-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        for (side, messages) in [("left", &left), ("right", &right)] {
+            let mut writer = ArchiveWriter::new(dir.join(side), 4).unwrap();
+            for msg in messages.iter() {
+                writer.append(msg).unwrap();
+            }
+            writer.close().unwrap();
+        }
+        let archive = |side: &str| Archive::open(dir.join(side)).unwrap();
+        let diff = diff_archives(&archive("left"), &archive("right"), 1, 4).unwrap();
+        assert_eq!(
+            (diff.compared, diff.missing_left, diff.missing_right),
+            (3, vec![4], vec![])
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,8 +1,12 @@
+use crate::networks::arbitrum::{decoder::rlp::RlpError, identity::RelayInfo};
+use std::io;
+use std::{sync::Arc, time::SystemTime};
 use thiserror::Error;
-use tokio::io;
 
+#[cfg(not(target_arch = "wasm32"))]
 pub type Result<T> = std::result::Result<T, RelayError>;
 
+#[cfg(not(target_arch = "wasm32"))]
 #[derive(Debug, Error)]
 pub enum RelayError {
     #[error(transparent)]
@@ -15,7 +19,7 @@ pub enum RelayError {
     HTTP(#[from] tungstenite::http::Error),
 
     #[error(transparent)]
-    Tungstenite(#[from] tungstenite::Error),
+    Tungstenite(#[from] Box<tungstenite::Error>),
 
     #[error(transparent)]
     Serde(#[from] serde_json::Error),
@@ -23,18 +27,564 @@ pub enum RelayError {
     #[error(transparent)]
     SendError(#[from] crossbeam_channel::SendError<ConnectionUpdate>),
 
+    #[error(transparent)]
+    Archive(#[from] ArchiveError),
+
     #[error("Invalid Url")]
     InvalidUrl,
 
     #[error("Sequencer feed is not for the given chain id")]
     InvalidChainId,
 
+    #[error("Consumer is too slow to keep up with the feed")]
+    ConsumerTooSlow,
+
+    #[error("Proxy error: {0}")]
+    Proxy(String),
+
+    #[error("Relay {stage} timed out after {after:?}")]
+    Timeout {
+        stage: &'static str,
+        after: std::time::Duration,
+    },
+
+    #[error(transparent)]
+    Tls(#[from] rustls::Error),
+
+    #[error("Relay started at sequence number {received} instead of {expected}")]
+    SequenceGap { expected: u64, received: u64 },
+
     #[error("Relay Error {0}")]
     Msg(String),
 }
 
-#[derive(Debug)]
+/// A stable identifier of the kind of a `RelayError`, for handling errors programmatically.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorCode {
+    Io,
+    InvalidUrl,
+    Http,
+    Websocket,
+    HandshakeRejected,
+    Serde,
+    ChannelClosed,
+    Archive,
+    ChainIdMismatch,
+    ConsumerTooSlow,
+    Proxy,
+    Timeout,
+    Tls,
+    SequenceGap,
+    Other,
+}
+
+impl ErrorCode {
+    /// The code as reported in logs and status output. Never changes once released.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::Io => "io",
+            ErrorCode::InvalidUrl => "invalid_url",
+            ErrorCode::Http => "http",
+            ErrorCode::Websocket => "websocket",
+            ErrorCode::HandshakeRejected => "handshake_rejected",
+            ErrorCode::Serde => "serde",
+            ErrorCode::ChannelClosed => "channel_closed",
+            ErrorCode::Archive => "archive",
+            ErrorCode::ChainIdMismatch => "chain_id_mismatch",
+            ErrorCode::ConsumerTooSlow => "consumer_too_slow",
+            ErrorCode::Proxy => "proxy",
+            ErrorCode::Timeout => "timeout",
+            ErrorCode::Tls => "tls",
+            ErrorCode::SequenceGap => "sequence_gap",
+            ErrorCode::Other => "other",
+        }
+    }
+}
+
+impl std::fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The broad cause of a `RelayError`, for supervisors deciding how to react to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorCategory {
+    /// The connection to the relay failed or timed out.
+    Network,
+    /// The relay answered in an unexpected way, e.g. rejecting the handshake or skipping
+    /// messages.
+    Protocol,
+    /// A frame from the relay could not be parsed.
+    Decode,
+    /// The client is misconfigured: invalid URL, wrong chain ID, rejected credentials or TLS
+    /// setup.
+    Configuration,
+    /// The consumer of the messages went away or can't keep up.
+    Consumer,
+    /// Reading or writing recorded messages failed.
+    Storage,
+    Other,
+}
+
+impl ErrorCategory {
+    /// The category as reported in logs and status output. Never changes once released.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCategory::Network => "network",
+            ErrorCategory::Protocol => "protocol",
+            ErrorCategory::Decode => "decode",
+            ErrorCategory::Configuration => "configuration",
+            ErrorCategory::Consumer => "consumer",
+            ErrorCategory::Storage => "storage",
+            ErrorCategory::Other => "other",
+        }
+    }
+}
+
+impl std::fmt::Display for ErrorCategory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl RelayError {
+    /// Returns the broad cause of the error.
+    ///
+    /// Within a category, `is_retryable` still tells transient errors apart, e.g. a relay
+    /// answering 503 from one answering 404.
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            RelayError::IO(_) | RelayError::Proxy(_) | RelayError::Timeout { .. } => {
+                ErrorCategory::Network
+            }
+            RelayError::Tungstenite(e) => match e.as_ref() {
+                tungstenite::Error::Http(response)
+                    if matches!(response.status().as_u16(), 401 | 403) =>
+                {
+                    ErrorCategory::Configuration
+                }
+                tungstenite::Error::Http(_)
+                | tungstenite::Error::Protocol(_)
+                | tungstenite::Error::Capacity(_)
+                | tungstenite::Error::Utf8 => ErrorCategory::Protocol,
+                tungstenite::Error::Url(_) | tungstenite::Error::Tls(_) => {
+                    ErrorCategory::Configuration
+                }
+                _ => ErrorCategory::Network,
+            },
+            RelayError::SequenceGap { .. } => ErrorCategory::Protocol,
+            RelayError::Serde(_) => ErrorCategory::Decode,
+            RelayError::UrlParse(_)
+            | RelayError::HTTP(_)
+            | RelayError::InvalidUrl
+            | RelayError::InvalidChainId
+            | RelayError::Tls(_) => ErrorCategory::Configuration,
+            RelayError::SendError(_) | RelayError::ConsumerTooSlow => ErrorCategory::Consumer,
+            RelayError::Archive(_) => ErrorCategory::Storage,
+            RelayError::Msg(_) => ErrorCategory::Other,
+        }
+    }
+
+    /// Returns the stable code of the error.
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            RelayError::IO(_) => ErrorCode::Io,
+            RelayError::UrlParse(_) | RelayError::InvalidUrl => ErrorCode::InvalidUrl,
+            RelayError::HTTP(_) => ErrorCode::Http,
+            RelayError::Tungstenite(e) => match e.as_ref() {
+                tungstenite::Error::Http(_) => ErrorCode::HandshakeRejected,
+                tungstenite::Error::Url(_) => ErrorCode::InvalidUrl,
+                tungstenite::Error::Io(_) => ErrorCode::Io,
+                tungstenite::Error::Tls(_) => ErrorCode::Tls,
+                _ => ErrorCode::Websocket,
+            },
+            RelayError::Serde(_) => ErrorCode::Serde,
+            RelayError::SendError(_) => ErrorCode::ChannelClosed,
+            RelayError::Archive(_) => ErrorCode::Archive,
+            RelayError::InvalidChainId => ErrorCode::ChainIdMismatch,
+            RelayError::ConsumerTooSlow => ErrorCode::ConsumerTooSlow,
+            RelayError::Proxy(_) => ErrorCode::Proxy,
+            RelayError::Timeout { .. } => ErrorCode::Timeout,
+            RelayError::Tls(_) => ErrorCode::Tls,
+            RelayError::SequenceGap { .. } => ErrorCode::SequenceGap,
+            RelayError::Msg(_) => ErrorCode::Other,
+        }
+    }
+
+    /// Returns `true` if reconnecting may succeed, e.g. after a network failure or a relay
+    /// restart.
+    ///
+    /// Errors caused by the configuration (invalid URL, wrong chain ID, rejected credentials,
+    /// TLS setup), by the consumer going away or by the relay no longer having the requested
+    /// messages are not retryable.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            RelayError::IO(_)
+            | RelayError::ConsumerTooSlow
+            | RelayError::Proxy(_)
+            | RelayError::Timeout { .. } => true,
+            RelayError::Tungstenite(e) => match e.as_ref() {
+                // Rate limiting and server errors are transient, other statuses are not.
+                tungstenite::Error::Http(response) => {
+                    response.status().as_u16() == 429 || response.status().is_server_error()
+                }
+                tungstenite::Error::Url(_) | tungstenite::Error::Tls(_) => false,
+                _ => true,
+            },
+            RelayError::Archive(ArchiveError::IO(_)) => true,
+            RelayError::UrlParse(_)
+            | RelayError::HTTP(_)
+            | RelayError::Serde(_)
+            | RelayError::SendError(_)
+            | RelayError::Archive(_)
+            | RelayError::InvalidUrl
+            | RelayError::InvalidChainId
+            | RelayError::Tls(_)
+            | RelayError::SequenceGap { .. }
+            | RelayError::Msg(_) => false,
+        }
+    }
+
+    /// Returns `true` if retrying is pointless, see `is_retryable`.
+    pub fn is_fatal(&self) -> bool {
+        !self.is_retryable()
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl From<tungstenite::Error> for RelayError {
+    fn from(e: tungstenite::Error) -> Self {
+        RelayError::Tungstenite(Box::new(e))
+    }
+}
+
+/// Updates about the state of a relay connection, each stamped with the time it happened at.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ConnectionUpdate {
-    StoppedSendingFrames(u32),
-    Unknown(u32),
+    /// The websocket handshake with the relay succeeded.
+    Connected {
+        relay: Arc<RelayInfo>,
+        at: SystemTime,
+    },
+    /// A supervisor is reconnecting to the relay.
+    Reconnecting {
+        relay: Arc<RelayInfo>,
+        attempt: u32,
+        at: SystemTime,
+    },
+    /// The relay closed the connection, with the close frame's code and reason if it sent one.
+    Closed {
+        relay: Arc<RelayInfo>,
+        code: Option<u16>,
+        reason: String,
+        at: SystemTime,
+    },
+    /// The relay violated the websocket protocol.
+    ProtocolError {
+        relay: Arc<RelayInfo>,
+        error: String,
+        at: SystemTime,
+    },
+    /// The connection failed while reading frames.
+    StoppedSendingFrames {
+        relay: Arc<RelayInfo>,
+        at: SystemTime,
+    },
+    Unknown {
+        relay: Arc<RelayInfo>,
+        at: SystemTime,
+    },
+}
+
+impl ConnectionUpdate {
+    /// Returns the ID of the relay the update is about.
+    pub fn id(&self) -> u32 {
+        self.relay().id
+    }
+
+    /// Returns the relay the update is about.
+    pub fn relay(&self) -> &RelayInfo {
+        match self {
+            ConnectionUpdate::Connected { relay, .. }
+            | ConnectionUpdate::Reconnecting { relay, .. }
+            | ConnectionUpdate::Closed { relay, .. }
+            | ConnectionUpdate::ProtocolError { relay, .. }
+            | ConnectionUpdate::StoppedSendingFrames { relay, .. }
+            | ConnectionUpdate::Unknown { relay, .. } => relay,
+        }
+    }
+
+    /// Returns the time the update happened at.
+    pub fn at(&self) -> SystemTime {
+        match self {
+            ConnectionUpdate::Connected { at, .. }
+            | ConnectionUpdate::Reconnecting { at, .. }
+            | ConnectionUpdate::Closed { at, .. }
+            | ConnectionUpdate::ProtocolError { at, .. }
+            | ConnectionUpdate::StoppedSendingFrames { at, .. }
+            | ConnectionUpdate::Unknown { at, .. } => *at,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ArchiveError {
+    #[error(transparent)]
+    IO(#[from] io::Error),
+
+    #[error(transparent)]
+    Serde(#[from] serde_json::Error),
+
+    #[error("Archive path {0} is not a directory")]
+    NotADirectory(std::path::PathBuf),
+
+    #[error("Invalid archive segment name {0}")]
+    InvalidSegmentName(std::path::PathBuf),
+
+    #[error("Invalid archive segment name template {0}")]
+    InvalidTemplate(String),
+
+    #[error("Archive segment {0} failed checksum verification")]
+    ChecksumMismatch(String),
+
+    #[error("Corrupt journal segment {0}")]
+    CorruptJournal(std::path::PathBuf),
+
+    #[cfg(feature = "s3")]
+    #[error(transparent)]
+    ObjectStore(#[from] object_store::Error),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum DecodeError {
+    #[error(transparent)]
+    Base64(#[from] base64::DecodeError),
+
+    #[error(transparent)]
+    Rlp(#[from] RlpError),
+
+    #[error("Unknown transaction type {0:#04x}")]
+    UnknownTxType(u8),
+
+    #[error("Empty L2 message")]
+    Empty,
+
+    #[error("L2 message of {size} bytes exceeds the limit of {max} bytes")]
+    MessageTooLarge { size: usize, max: usize },
+
+    #[error(
+        "Truncated batch entry at offset {offset}: {needed} bytes needed, {available} available"
+    )]
+    Truncated {
+        offset: usize,
+        needed: u64,
+        available: usize,
+    },
+
+    #[error("Batch nested deeper than {0} levels")]
+    BatchTooDeep(usize),
+
+    #[error("Truncated {field}: {needed} bytes needed, {available} available")]
+    TruncatedField {
+        field: &'static str,
+        needed: usize,
+        available: usize,
+    },
+
+    #[error("{0} overflows 64 bits")]
+    Overflow(&'static str),
+
+    #[error("Invalid message sender {0}")]
+    InvalidSender(String),
+
+    #[cfg(feature = "alloy")]
+    #[error("Invalid transaction envelope: {0}")]
+    Envelope(String),
+}
+
+#[derive(Debug, Error)]
+pub enum FrameError {
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+
+    #[error("Unsupported broadcast version {0}")]
+    UnsupportedVersion(u8),
+}
+
+#[derive(Debug, Error)]
+pub enum CheckpointError {
+    #[error(transparent)]
+    IO(#[from] io::Error),
+
+    #[cfg(feature = "sled")]
+    #[error(transparent)]
+    Sled(#[from] sled::Error),
+
+    #[cfg(feature = "redis")]
+    #[error(transparent)]
+    Redis(#[from] redis::RedisError),
+
+    #[error("Corrupt checkpoint {0:?}")]
+    Corrupt(String),
+}
+
+#[derive(Debug, Error)]
+pub enum DelayedMessageError {
+    #[error("L1 provider error: {0}")]
+    Provider(String),
+
+    #[error(transparent)]
+    Abi(#[from] ethers::abi::Error),
+
+    #[error("Malformed MessageDelivered log")]
+    MalformedLog,
+}
+
+/// The errors of the `FeedPubsub` ethers transport.
+#[derive(Debug, Error)]
+pub enum FeedPubsubError {
+    #[error("Request not served by the feed: {0}")]
+    Unsupported(String),
+
+    #[error("Unknown subscription {0}")]
+    UnknownSubscription(ethers::types::U256),
+
+    #[error(transparent)]
+    Serde(#[from] serde_json::Error),
+}
+
+impl ethers::providers::RpcError for FeedPubsubError {
+    fn as_error_response(&self) -> Option<&ethers::providers::JsonRpcError> {
+        None
+    }
+
+    fn as_serde_error(&self) -> Option<&serde_json::Error> {
+        match self {
+            FeedPubsubError::Serde(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<FeedPubsubError> for ethers::providers::ProviderError {
+    fn from(e: FeedPubsubError) -> Self {
+        ethers::providers::ProviderError::JsonRpcClientError(Box::new(e))
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum VerificationError {
+    #[error("L2 provider error: {0}")]
+    Provider(String),
+
+    #[error("Block {0} not found on chain")]
+    BlockNotFound(u64),
+}
+
+#[derive(Debug, Error)]
+pub enum SinkError {
+    #[error(transparent)]
+    IO(#[from] io::Error),
+
+    #[error(transparent)]
+    Serde(#[from] serde_json::Error),
+
+    #[cfg(feature = "kafka")]
+    #[error(transparent)]
+    Kafka(#[from] rdkafka::error::KafkaError),
+
+    #[cfg(feature = "postgres")]
+    #[error(transparent)]
+    Postgres(#[from] sqlx::Error),
+
+    #[error("Sink Error {0}")]
+    Msg(String),
+}
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error(transparent)]
+    IO(#[from] io::Error),
+
+    #[error(transparent)]
+    Toml(#[from] toml::de::Error),
+
+    #[error("Invalid environment variable {name}: {reason}")]
+    Env { name: String, reason: String },
+
+    #[error("Invalid configuration: {0}")]
+    Invalid(String),
+}
+
+#[derive(Debug, Error)]
+pub enum StartupError {
+    #[error(transparent)]
+    Checkpoint(#[from] CheckpointError),
+
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+
+    #[error("Sink {sink} failed to warm up: {source}")]
+    Sink { sink: String, source: SinkError },
+
+    #[error("Sinks did not warm up within {0:?}")]
+    Timeout(std::time::Duration),
+}
+
+#[cfg(feature = "otel")]
+#[derive(Debug, Error)]
+pub enum TelemetryError {
+    #[error(transparent)]
+    Trace(#[from] opentelemetry::trace::TraceError),
+
+    #[error(transparent)]
+    Metrics(#[from] opentelemetry::metrics::MetricsError),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_errors() {
+        let io = RelayError::from(io::Error::from(io::ErrorKind::ConnectionReset));
+        assert_eq!(io.code(), ErrorCode::Io);
+        assert!(io.is_retryable());
+
+        assert_eq!(
+            RelayError::InvalidChainId.code().as_str(),
+            "chain_id_mismatch"
+        );
+        assert!(RelayError::InvalidChainId.is_fatal());
+
+        let rejected = |status: u16| {
+            let response = tungstenite::http::Response::builder()
+                .status(status)
+                .body(None)
+                .unwrap();
+            RelayError::from(tungstenite::Error::Http(response))
+        };
+        assert_eq!(io.category(), ErrorCategory::Network);
+        assert_eq!(
+            RelayError::InvalidChainId.category(),
+            ErrorCategory::Configuration
+        );
+        assert_eq!(rejected(401).category(), ErrorCategory::Configuration);
+        assert_eq!(rejected(503).category(), ErrorCategory::Protocol);
+        assert_eq!(
+            RelayError::SequenceGap {
+                expected: 1,
+                received: 2
+            }
+            .category()
+            .as_str(),
+            "protocol"
+        );
+        assert_eq!(rejected(401).code(), ErrorCode::HandshakeRejected);
+        assert!(rejected(401).is_fatal());
+        assert!(rejected(429).is_retryable());
+        assert!(rejected(503).is_retryable());
+    }
 }
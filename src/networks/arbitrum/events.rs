@@ -0,0 +1,83 @@
+use crate::networks::arbitrum::{
+    failover::FailoverSignal, identity::RelayInfo, stats::FeedSummary,
+};
+use serde::Serialize;
+
+/// Notable events happening on the feed, besides the messages themselves.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum FeedEvent {
+    /// A replay caught up with its source and the messages now come from the live feed,
+    /// starting at `sequence_number`.
+    #[serde(rename_all = "camelCase")]
+    SwitchedToLive { sequence_number: u64 },
+    /// Every message up to `low` has been processed by every sink; `high` is the highest sequence
+    /// number processed by any sink.
+    Watermark { low: u64, high: u64 },
+    /// The feed behaved like it does while the sequencer fails over, starting at
+    /// `sequence_number`. Downstream systems may want to widen their tolerances for a while.
+    #[serde(rename_all = "camelCase")]
+    SequencerFailoverSuspected {
+        sequence_number: u64,
+        signal: FailoverSignal,
+        /// Whether the sequencer health check passed, if the detector cross-checks it, see
+        /// `FailoverDetector::with_health_check`.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        sequencer_healthy: Option<bool>,
+    },
+    /// The relay re-sent the messages from `from` on, superseding those up to `to` received
+    /// before: the sequencer reorged the feed.
+    Reorg { from: u64, to: u64 },
+    /// The standby `relay` replaced the failed relay `previous` as the source of the messages.
+    RelayPromoted {
+        relay: RelayInfo,
+        previous: RelayInfo,
+    },
+    /// Decoding fell behind and skips sender recovery, starting at `sequence_number`. Messages
+    /// decoded meanwhile are marked with `Provenance::degraded`.
+    #[serde(rename_all = "camelCase")]
+    DecodingDegraded {
+        sequence_number: u64,
+        queue_latency_ms: u64,
+    },
+    /// Decoding caught up and recovers senders again, starting at `sequence_number`.
+    #[serde(rename_all = "camelCase")]
+    DecodingRestored { sequence_number: u64 },
+    /// The relay switched to another broadcast format version.
+    BroadcastVersionChanged { from: u8, to: u8 },
+    /// The relay switched to a broadcast format version the reader can't parse. Its frames are
+    /// skipped until it switches back to a supported version.
+    UnsupportedBroadcastVersion { version: u8 },
+    /// The sequencer included the delayed inbox messages with indices `from..to`.
+    DelayedMessagesAdvanced { from: u64, to: u64 },
+    /// The L2 message `sequence_number`, or one of its batch entries, is `size` bytes long and
+    /// exceeds the decoding limit of `max` bytes: it was delivered undecoded. The limit may need
+    /// raising after an Arbitrum upgrade.
+    #[serde(rename_all = "camelCase")]
+    MessageTooLarge {
+        sequence_number: u64,
+        size: usize,
+        max: usize,
+    },
+    /// The `relay` delivered the message `sequence_number` with another content than the
+    /// `first_relay` did first: one of them may be misbehaving.
+    #[serde(rename_all = "camelCase")]
+    RelayMismatch {
+        sequence_number: u64,
+        relay: RelayInfo,
+        first_relay: RelayInfo,
+    },
+    /// The chain reports the block `block_number` differently than the feed message
+    /// `sequence_number` it was assembled from: `missing_txs` of its transactions are not in the
+    /// block, or its timestamp differs. The sequencer may have reorged the feed.
+    #[serde(rename_all = "camelCase")]
+    ChainDivergence {
+        sequence_number: u64,
+        block_number: u64,
+        missing_txs: usize,
+        timestamp_differs: bool,
+    },
+    /// The statistics of the feed over the last period.
+    Summary(FeedSummary),
+}
@@ -0,0 +1,281 @@
+use crate::networks::arbitrum::{events::FeedEvent, types::BroadcastFeedMessage};
+#[cfg(not(target_arch = "wasm32"))]
+use ethers::providers::{Http, JsonRpcClient};
+#[cfg(not(target_arch = "wasm32"))]
+use log::*;
+use serde::Serialize;
+use std::{
+    sync::{
+        atomic::{AtomicU8, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+#[cfg(not(target_arch = "wasm32"))]
+use url::Url;
+
+/// How long the feed may stay silent before a failover is suspected.
+const DEFAULT_MAX_SILENCE: Duration = Duration::from_secs(10);
+/// How long to wait after a suspected failover before reporting another one.
+const DEFAULT_COOLDOWN: Duration = Duration::from_secs(30);
+
+/// The JSON-RPC method of the Nitro node checking the health of the sequencer publishing the
+/// feed. It fails while the sequencer is unhealthy, e.g. during a coordinator failover.
+#[cfg(not(target_arch = "wasm32"))]
+const CHECK_PUBLISHER_HEALTH: &str = "arb_checkPublisherHealth";
+
+#[cfg(not(target_arch = "wasm32"))]
+const HEALTH_UNKNOWN: u8 = 0;
+const HEALTH_HEALTHY: u8 = 1;
+const HEALTH_UNHEALTHY: u8 = 2;
+
+/// The feed behavior that made a sequencer failover suspected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum FailoverSignal {
+    /// The feed skipped sequence numbers.
+    Gap { expected: u64, received: u64 },
+    /// No message was received for `silence_ms` milliseconds.
+    #[serde(rename_all = "camelCase")]
+    Stall { silence_ms: u64 },
+}
+
+/// Detects the feed behavior typical of a sequencer coordinator failover.
+///
+/// While the sequencer fails over, the feed briefly stalls and may skip sequence numbers. Both are
+/// also caused by network trouble, so the resulting events are heuristic: they are meant for
+/// downstream systems to widen their tolerances, not to act upon as facts.
+#[derive(Debug, Clone)]
+pub struct FailoverDetector {
+    max_silence: Duration,
+    cooldown: Duration,
+    last_sequence_number: Option<u64>,
+    last_message_at: Option<Instant>,
+    last_suspected_at: Option<Instant>,
+    health: Option<SequencerHealth>,
+}
+
+impl Default for FailoverDetector {
+    fn default() -> Self {
+        Self {
+            max_silence: DEFAULT_MAX_SILENCE,
+            cooldown: DEFAULT_COOLDOWN,
+            last_sequence_number: None,
+            last_message_at: None,
+            last_suspected_at: None,
+            health: None,
+        }
+    }
+}
+
+impl FailoverDetector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets how long the feed may stay silent before a failover is suspected.
+    pub fn with_max_silence(mut self, max_silence: Duration) -> Self {
+        self.max_silence = max_silence;
+        self
+    }
+
+    /// Sets how long to wait after a suspected failover before reporting another one.
+    pub fn with_cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = cooldown;
+        self
+    }
+
+    /// Cross-checks the suspected failovers with the health of the sequencer, reported in the
+    /// `sequencer_healthy` field of the events.
+    pub fn with_health_check(mut self, health: SequencerHealth) -> Self {
+        self.health = Some(health);
+        self
+    }
+
+    /// Records a message received at `now`.
+    ///
+    /// # Returns
+    ///
+    /// A `FeedEvent::SequencerFailoverSuspected` if the message arrived after a gap or a stall,
+    /// unless a failover was already suspected within the cooldown.
+    pub fn observe(&mut self, msg: &BroadcastFeedMessage, now: Instant) -> Option<FeedEvent> {
+        let signal = match (self.last_sequence_number, self.last_message_at) {
+            (Some(last), _) if msg.sequence_number > last + 1 => Some(FailoverSignal::Gap {
+                expected: last + 1,
+                received: msg.sequence_number,
+            }),
+            (_, Some(at)) if now.duration_since(at) > self.max_silence => {
+                Some(FailoverSignal::Stall {
+                    silence_ms: now.duration_since(at).as_millis() as u64,
+                })
+            }
+            _ => None,
+        };
+
+        self.last_sequence_number = Some(
+            self.last_sequence_number
+                .map_or(msg.sequence_number, |last| last.max(msg.sequence_number)),
+        );
+        self.last_message_at = Some(now);
+
+        let signal = signal?;
+        if self
+            .last_suspected_at
+            .is_some_and(|at| now.duration_since(at) < self.cooldown)
+        {
+            return None;
+        }
+        self.last_suspected_at = Some(now);
+        Some(FeedEvent::SequencerFailoverSuspected {
+            sequence_number: msg.sequence_number,
+            signal,
+            sequencer_healthy: self.health.as_ref().and_then(SequencerHealth::healthy),
+        })
+    }
+}
+
+/// The health of the sequencer, polled in the background with the `arb_checkPublisherHealth`
+/// method of a Nitro node.
+///
+/// Clones share the same health. Polling stops once every clone is dropped.
+#[derive(Debug, Clone)]
+pub struct SequencerHealth {
+    state: Arc<AtomicU8>,
+}
+
+impl SequencerHealth {
+    /// Polls the node at `url` every `interval`. Must be called within a tokio runtime.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn spawn(url: Url, interval: Duration) -> Self {
+        let state = Arc::new(AtomicU8::new(HEALTH_UNKNOWN));
+        let weak = Arc::downgrade(&state);
+        tokio::spawn(async move {
+            let client = Http::new(url);
+            let mut ticks = tokio::time::interval(interval);
+            loop {
+                ticks.tick().await;
+                let healthy = match client
+                    .request::<_, serde_json::Value>(CHECK_PUBLISHER_HEALTH, ())
+                    .await
+                {
+                    Ok(_) => HEALTH_HEALTHY,
+                    Err(e) => {
+                        debug!("Sequencer health check failed: {}", e);
+                        HEALTH_UNHEALTHY
+                    }
+                };
+                let Some(state) = weak.upgrade() else {
+                    return;
+                };
+                state.store(healthy, Ordering::Relaxed);
+            }
+        });
+        Self { state }
+    }
+
+    /// Returns whether the sequencer was healthy at the last check, `None` before the first one.
+    pub fn healthy(&self) -> Option<bool> {
+        match self.state.load(Ordering::Relaxed) {
+            HEALTH_HEALTHY => Some(true),
+            HEALTH_UNHEALTHY => Some(false),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::networks::arbitrum::{fixtures::message_with, server};
+    use serde_json::json;
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn detects_gaps_and_stalls_with_cooldown() {
+        let start = Instant::now();
+        let mut detector = FailoverDetector::new()
+            .with_max_silence(Duration::from_secs(5))
+            .with_cooldown(Duration::from_secs(30));
+
+        assert_eq!(
+            detector.observe(&message_with(1, 0, Vec::new()), start),
+            None
+        );
+        assert_eq!(
+            detector.observe(&message_with(2, 0, Vec::new()), start),
+            None
+        );
+        assert_eq!(
+            detector.observe(&message_with(5, 0, Vec::new()), start),
+            Some(FeedEvent::SequencerFailoverSuspected {
+                sequence_number: 5,
+                signal: FailoverSignal::Gap {
+                    expected: 3,
+                    received: 5
+                },
+                sequencer_healthy: None,
+            })
+        );
+        // Within the cooldown.
+        let later = start + Duration::from_secs(10);
+        assert_eq!(
+            detector.observe(&message_with(6, 0, Vec::new()), later),
+            None
+        );
+
+        let much_later = later + Duration::from_secs(40);
+        assert_eq!(
+            detector.observe(&message_with(7, 0, Vec::new()), much_later),
+            Some(FeedEvent::SequencerFailoverSuspected {
+                sequence_number: 7,
+                signal: FailoverSignal::Stall { silence_ms: 40_000 },
+                sequencer_healthy: None,
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn cross_checks_with_the_sequencer_health() {
+        // A node whose sequencer is failing over.
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!("http://{}", listener.local_addr().unwrap())).unwrap();
+        server::serve_http(listener, "rpc", |request| async move {
+            let body = hyper::body::to_bytes(request.into_body()).await.unwrap();
+            let request: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(request["method"], CHECK_PUBLISHER_HEALTH);
+            server::json(
+                200,
+                &json!({
+                    "jsonrpc": "2.0",
+                    "id": request["id"],
+                    "error": { "code": -32000, "message": "sequencer is not the active one" },
+                }),
+            )
+        });
+
+        let health = SequencerHealth::spawn(url, Duration::from_millis(10));
+        for _ in 0..100 {
+            if health.healthy().is_some() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(health.healthy(), Some(false));
+
+        let now = Instant::now();
+        let mut detector = FailoverDetector::new().with_health_check(health);
+        detector.observe(&message_with(1, 0, Vec::new()), now);
+        assert_eq!(
+            detector.observe(&message_with(3, 0, Vec::new()), now),
+            Some(FeedEvent::SequencerFailoverSuspected {
+                sequence_number: 3,
+                signal: FailoverSignal::Gap {
+                    expected: 2,
+                    received: 3
+                },
+                sequencer_healthy: Some(false),
+            })
+        );
+    }
+}
@@ -1,12 +1,36 @@
 use crate::networks::arbitrum::{
-    errors::{ConnectionUpdate, RelayError},
-    types::Root,
+    abi::{AbiRegistry, EnrichedTx},
+    backpressure::{Backpressure, BackpressurePolicy, Forwarded},
+    capture::{now_ms, CaptureMetadata, FrameCapture},
+    connect::{ConnectOptions, HandshakeInfo, RelayAuth},
+    decoder::{registry::DecoderRegistry, DecodeOptions},
+    errors::{ConnectionUpdate, DecodeError, FrameError, RelayError},
+    events::FeedEvent,
+    failover::FailoverDetector,
+    handle::{ControlMessage, RelayClientHandle},
+    health::{ConnectionState, HealthTracker, RelayHealth},
+    identity::RelayInfo,
+    message::{MalformedFrame, RawFrame},
+    metrics::RelayMetrics,
+    observer::EventObserver,
+    provenance::Provenance,
+    reorg::ReorgDetector,
+    types::{versioned::VersionedRoot, Root},
 };
 use crossbeam_channel::Sender;
 use ethers::providers::StreamExt;
 use log::*;
-use tokio::{net::TcpStream, task::JoinHandle};
-use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
+};
+use tokio::{
+    net::TcpStream,
+    sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
+    task::JoinHandle,
+};
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+use tungstenite::{protocol::CloseFrame, Message};
 use url::Url;
 
 /// A client for reading transactions from a Sequencer Feed on the Arbitrum network.
@@ -19,6 +43,62 @@ pub struct RelayClient {
     sender: Sender<Root>,
     /// The ID of the relay that this client is connected to.
     id: u32,
+    /// Identifies the relay in logs and statuses.
+    info: Arc<RelayInfo>,
+    /// The chain ID announced by the relay.
+    chain_id: u64,
+    /// What the relay announced in its handshake response.
+    handshake: HandshakeInfo,
+    /// An optional stage decoding transactions and their calldata before emitting them.
+    enrichment: Option<Enrichment>,
+    /// Control messages sent through `RelayClientHandle`s.
+    control: UnboundedReceiver<ControlMessage>,
+    control_sender: UnboundedSender<ControlMessage>,
+    /// The raw frame capture in progress, if any.
+    capture: Option<FrameCapture>,
+    /// Where every frame is forwarded as received, if anywhere.
+    raw_frames: Option<Sender<RawFrame>>,
+    /// Where the frames that could not be parsed are reported, if anywhere.
+    malformed_frames: Option<Sender<MalformedFrame>>,
+    /// Messages with a lower sequence number are dropped.
+    start_sequence_number: u64,
+    /// Whether no message has been forwarded yet.
+    awaiting_first: bool,
+    /// Whether starting after `start_sequence_number` is an error.
+    contiguous_start: bool,
+    /// What to do when the consumer can't keep up.
+    backpressure: Backpressure,
+    metrics: Arc<RelayMetrics>,
+    health: Arc<HealthTracker>,
+    /// Detects suspected sequencer failovers.
+    failover: Option<FailoverDetector>,
+    /// Detects the relay re-sending messages after a reorg.
+    reorg: ReorgDetector,
+    /// Where `FeedEvent`s are reported, if anywhere.
+    events: Option<Sender<FeedEvent>>,
+    /// The broadcast format version of the last frame parsed.
+    broadcast_version: Option<u8>,
+    /// How long to wait for a frame before giving up on the relay.
+    read_timeout: Option<Duration>,
+    /// How many times the caller reconnected before creating this client.
+    generation: u64,
+    /// Notified of the lifecycle of the client, if set.
+    observer: Option<Arc<dyn EventObserver>>,
+    /// The sequence number of the last message received, to notice gaps.
+    last_sequence_number: Option<u64>,
+    /// Whether frames are left unread until resumed.
+    paused: bool,
+    /// How the calldata decoding stage decodes messages.
+    decode_options: DecodeOptions,
+    /// The hooks the calldata decoding stage decodes messages with.
+    decoders: Arc<DecoderRegistry>,
+}
+
+/// The calldata decoding stage of the client pipeline.
+struct Enrichment {
+    registry: Arc<AbiRegistry>,
+    chain_id: u64,
+    sender: Sender<EnrichedTx>,
 }
 
 impl RelayClient {
@@ -42,18 +122,252 @@ impl RelayClient {
         sender: Sender<Root>,
         connection_update: Sender<ConnectionUpdate>,
     ) -> Result<Self, RelayError> {
-        let req = generate_websocket_request(url)?;
-        let (socket, resp) = connect_async(req).await?;
-        check_chain_id_header(resp, chain_id)?;
+        Self::new_from(url, chain_id, id, 0, sender, connection_update).await
+    }
+
+    /// Creates a new `FeedClient` instance reading the feed from a given sequence number.
+    ///
+    /// The relay is asked to start at `sequence_number`, and any message it sends with a lower
+    /// sequence number is dropped, so the client never emits messages the caller already has.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL of the websocket server to connect to.
+    /// * `chain_id` - The expected chain ID of the server.
+    /// * `id` - The ID of this client instance.
+    /// * `sequence_number` - The sequence number of the first message to emit.
+    /// * `sender` - The sender channel for sending `Root` messages.
+    /// * `connection_update` - The sender channel for sending `ConnectionUpdate` messages.
+    pub async fn new_from(
+        url: Url,
+        chain_id: u64,
+        id: u32,
+        sequence_number: u64,
+        sender: Sender<Root>,
+        connection_update: Sender<ConnectionUpdate>,
+    ) -> Result<Self, RelayError> {
+        let options = ConnectOptions::new().with_sequence_number(sequence_number);
+        Self::connect(url, chain_id, id, options, sender, connection_update).await
+    }
+
+    /// Creates a new `FeedClient` instance connected as described by `options`, e.g. through a
+    /// proxy.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL of the websocket server to connect to.
+    /// * `chain_id` - The expected chain ID of the server, ignored if `options` detect it.
+    /// * `id` - The ID of this client instance.
+    /// * `options` - How to connect to the server.
+    /// * `sender` - The sender channel for sending `Root` messages.
+    /// * `connection_update` - The sender channel for sending `ConnectionUpdate` messages.
+    pub async fn connect(
+        url: Url,
+        chain_id: u64,
+        id: u32,
+        options: ConnectOptions,
+        sender: Sender<Root>,
+        connection_update: Sender<ConnectionUpdate>,
+    ) -> Result<Self, RelayError> {
+        let sequence_number = options.sequence_number;
+        let info = Arc::new(options.relay_info(id, &url));
+        let req = generate_websocket_request(url.clone(), sequence_number, options.auth.as_ref())?;
+        let (socket, resp) = options.open(&url, req).await?;
+        let handshake = HandshakeInfo::from_response(&resp);
+        debug!("Relay {} handshake: {:?}", info, handshake);
+        let chain_id = if options.detect_chain_id {
+            let announced = handshake.chain_id.ok_or(RelayError::InvalidChainId)?;
+            if announced != chain_id {
+                info!("Relay {} announced chain ID {}", info, announced);
+            }
+            announced
+        } else {
+            check_chain_id_header(resp, chain_id)?;
+            chain_id
+        };
+        let (control_sender, control) = mpsc::unbounded_channel();
+        let _ = connection_update.send(ConnectionUpdate::Connected {
+            relay: info.clone(),
+            at: SystemTime::now(),
+        });
 
         Ok(Self {
             connection: socket,
             connection_update,
             sender,
             id,
+            info,
+            chain_id,
+            handshake,
+            enrichment: None,
+            control,
+            control_sender,
+            capture: None,
+            raw_frames: None,
+            malformed_frames: None,
+            start_sequence_number: sequence_number,
+            awaiting_first: true,
+            contiguous_start: options.contiguous_start,
+            backpressure: Backpressure::default(),
+            metrics: Arc::default(),
+            health: Arc::default(),
+            generation: 0,
+            failover: None,
+            reorg: ReorgDetector::new(),
+            events: None,
+            broadcast_version: None,
+            read_timeout: options.read_timeout,
+            observer: None,
+            last_sequence_number: None,
+            paused: false,
+            decode_options: DecodeOptions::default(),
+            decoders: Arc::default(),
         })
     }
 
+    /// Returns a handle that can be used to control the client once it runs.
+    pub fn handle(&self) -> RelayClientHandle {
+        RelayClientHandle::new(
+            self.control_sender.clone(),
+            self.metrics.clone(),
+            self.health.clone(),
+            self.info.clone(),
+            self.chain_id,
+        )
+    }
+
+    /// Returns the chain ID of the relay: the one it announced if `ConnectOptions` detect it, the
+    /// expected one otherwise.
+    pub fn chain_id(&self) -> u64 {
+        self.chain_id
+    }
+
+    /// Returns what the relay announced when the connection was established.
+    pub fn handshake(&self) -> &HandshakeInfo {
+        &self.handshake
+    }
+
+    /// Returns the current health of the client.
+    pub fn health(&self) -> RelayHealth {
+        self.health.snapshot()
+    }
+
+    /// Returns the counters of the client.
+    pub fn metrics(&self) -> Arc<RelayMetrics> {
+        self.metrics.clone()
+    }
+
+    /// Sets the connection generation reported in the provenance of the messages, i.e. how many
+    /// times the caller had to reconnect to the relay before.
+    pub fn with_generation(mut self, generation: u64) -> Self {
+        self.generation = generation;
+        self
+    }
+
+    /// Sets what the client does when its consumer can't keep up.
+    ///
+    /// # Arguments
+    ///
+    /// * `policy` - The policy applied once the consumer queue is full.
+    /// * `max_pending` - The queue length considered full. Defaults to the capacity of the
+    ///   `Root` channel, and must be set for unbounded channels.
+    pub fn with_backpressure(
+        mut self,
+        policy: BackpressurePolicy,
+        max_pending: Option<usize>,
+    ) -> Self {
+        self.backpressure = Backpressure {
+            policy,
+            max_pending,
+        };
+        self
+    }
+
+    /// Enables the calldata decoding stage.
+    ///
+    /// Every transaction received from the feed is decoded against `registry` and emitted as an
+    /// `EnrichedTx` on `sender`, in addition to the raw `Root` messages.
+    ///
+    /// # Arguments
+    ///
+    /// * `registry` - The ABIs used to decode calldata.
+    /// * `sender` - The sender channel for sending `EnrichedTx` messages.
+    pub fn with_abi_registry(
+        mut self,
+        registry: Arc<AbiRegistry>,
+        sender: Sender<EnrichedTx>,
+    ) -> Self {
+        self.enrichment = Some(Enrichment {
+            registry,
+            chain_id: self.chain_id,
+            sender,
+        });
+        self
+    }
+
+    /// Decodes messages with the hooks registered for the client's chain in the calldata
+    /// decoding stage, instead of the default decoder only, whether the stage is enabled with
+    /// `with_abi_registry` before or after.
+    pub fn with_decoder_registry(mut self, decoders: Arc<DecoderRegistry>) -> Self {
+        self.decoders = decoders;
+        self
+    }
+
+    /// Decodes messages with `options` in the calldata decoding stage, e.g. to raise the L2
+    /// message size limit. Larger messages are reported as `FeedEvent::MessageTooLarge`.
+    pub fn with_decode_options(mut self, options: DecodeOptions) -> Self {
+        self.decode_options = options;
+        self
+    }
+
+    /// Reports suspected sequencer failovers as `FeedEvent::SequencerFailoverSuspected`.
+    ///
+    /// # Arguments
+    ///
+    /// * `detector` - The detector applied to every message received.
+    /// * `events` - The sender channel for sending `FeedEvent`s.
+    pub fn with_failover_detection(
+        mut self,
+        detector: FailoverDetector,
+        events: Sender<FeedEvent>,
+    ) -> Self {
+        self.failover = Some(detector);
+        self.with_events(events)
+    }
+
+    /// Forwards every data frame received on `raw_frames` as a `RawFrame`, holding its payload
+    /// bytes together with the `Root` parsed from them, in addition to the `Root` messages.
+    ///
+    /// Frames that can't be parsed are forwarded too, without a `Root`. Forwarding stops once
+    /// the receiving side of the channel is dropped.
+    pub fn with_raw_frames(mut self, raw_frames: Sender<RawFrame>) -> Self {
+        self.raw_frames = Some(raw_frames);
+        self
+    }
+
+    /// Reports the frames that could not be parsed on `malformed_frames`, with their payload and
+    /// the parsing error, so that operators notice schema drift. Such frames are skipped either
+    /// way, and counted in `RelayMetrics::frames_malformed`.
+    pub fn with_malformed_frames(mut self, malformed_frames: Sender<MalformedFrame>) -> Self {
+        self.malformed_frames = Some(malformed_frames);
+        self
+    }
+
+    /// Reports the events noticed by the client on `events`, such as
+    /// `FeedEvent::BroadcastVersionChanged`, `FeedEvent::UnsupportedBroadcastVersion` or
+    /// `FeedEvent::Reorg`.
+    pub fn with_events(mut self, events: Sender<FeedEvent>) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Notifies `observer` of the lifecycle of the client: connection, messages, malformed frames,
+    /// sequence gaps, `FeedEvent`s and disconnection, as an alternative to a channel for each.
+    pub fn with_observer(mut self, observer: Arc<dyn EventObserver>) -> Self {
+        self.observer = Some(observer);
+        self
+    }
+
     /// Spawns a new Tokio task to run the feed client.
     ///
     /// # Returns
@@ -69,28 +383,405 @@ impl RelayClient {
     }
 
     pub async fn run(mut self) -> Result<(), RelayError> {
-        while let Some(msg) = self.connection.next().await {
-            match msg {
-                Ok(message) => {
-                    let decoded_root: Root = match serde_json::from_slice(&message.into_data()) {
-                        Ok(d) => d,
-                        Err(_) => continue,
-                    };
+        if let Some(observer) = &self.observer {
+            observer.on_connect(self.id, &self.handshake);
+        }
+        let result = self.read_frames().await;
+        self.health.set_state(ConnectionState::Closed);
+        if let Some(observer) = &self.observer {
+            observer.on_disconnect(self.id, result.as_ref().err());
+        }
+        result
+    }
 
-                    if self.sender.send(decoded_root).is_err() {
+    async fn read_frames(&mut self) -> Result<(), RelayError> {
+        let mut close_frame: Option<CloseFrame> = None;
+        let read_timeout = self.read_timeout.unwrap_or(Duration::MAX);
+        let read_deadline = tokio::time::sleep(read_timeout);
+        tokio::pin!(read_deadline);
+        loop {
+            tokio::select! {
+                () = &mut read_deadline, if self.read_timeout.is_some() && !self.paused => {
+                    warn!("Relay {} sent no frame for {:?}", self.info, read_timeout);
+                    let _ = self.connection.close(None).await;
+                    return Err(RelayError::Timeout { stage: "read", after: read_timeout });
+                }
+                msg = self.connection.next(), if !self.paused => {
+                    if self.read_timeout.is_some() {
+                        read_deadline.as_mut().reset(tokio::time::Instant::now() + read_timeout);
+                    }
+                    match msg {
+                        Some(Ok(Message::Close(frame))) => close_frame = frame,
+                        Some(Ok(message)) => match self.handle_message(message) {
+                            Ok(true) => (),
+                            Ok(false) => break,
+                            Err(e) => {
+                                let _ = self.connection.close(None).await;
+                                return Err(e);
+                            }
+                        },
+                        Some(Err(tungstenite::Error::Protocol(e))) => {
+                            let _ = self.connection_update.send(ConnectionUpdate::ProtocolError {
+                                relay: self.info.clone(),
+                                error: e.to_string(),
+                                at: SystemTime::now(),
+                            });
+                            error!("Connection closed with protocol error: {}", e);
+                            break;
+                        }
+                        Some(Err(e)) => {
+                            let _ = self.connection_update.send(
+                                ConnectionUpdate::StoppedSendingFrames {
+                                    relay: self.info.clone(),
+                                    at: SystemTime::now(),
+                                },
+                            );
+                            error!("Connection closed with error: {}", e);
+                            break;
+                        }
+                        None => {
+                            let (code, reason) = match close_frame.take() {
+                                Some(frame) => {
+                                    (Some(u16::from(frame.code)), frame.reason.into_owned())
+                                }
+                                None => (None, String::new()),
+                            };
+                            let _ = self.connection_update.send(ConnectionUpdate::Closed {
+                                relay: self.info.clone(),
+                                code,
+                                reason,
+                                at: SystemTime::now(),
+                            });
+                            break;
+                        }
+                    }
+                },
+                Some(control) = self.control.recv() => {
+                    if control == ControlMessage::Shutdown {
+                        self.health.set_state(ConnectionState::Closing);
+                        info!("Relay {} shutting down", self.info);
+                        if let Err(e) = self.connection.close(None).await {
+                            debug!("Relay {} failed to close connection: {}", self.info, e);
+                        }
                         break;
                     }
+                    let was_paused = self.paused;
+                    self.handle_control(control);
+                    if was_paused && !self.paused && self.read_timeout.is_some() {
+                        read_deadline.as_mut().reset(tokio::time::Instant::now() + read_timeout);
+                    }
+                }
+            }
+        }
+
+        if let Some(capture) = self.capture.take() {
+            capture.finish()?;
+        }
+
+        Ok(())
+    }
+
+    /// Processes a frame received from the relay.
+    ///
+    /// Returns `false` once the receiving side of the channel has been dropped,
+    /// `RelayError::ConsumerTooSlow` if the backpressure policy asks to disconnect, or
+    /// `RelayError::SequenceGap` if the feed doesn't start where required.
+    fn handle_message(&mut self, message: Message) -> Result<bool, RelayError> {
+        RelayMetrics::incr(&self.metrics.frames_received, 1);
+        if let Some(capture) = &mut self.capture {
+            match capture.record(&message) {
+                Ok(false) => (),
+                Ok(true) => {
+                    info!("Relay {} frame capture complete", self.info);
+                    self.capture = None;
                 }
                 Err(e) => {
-                    self.connection_update
-                        .send(ConnectionUpdate::StoppedSendingFrames(self.id))?;
-                    error!("Connection closed with error: {}", e);
-                    break;
+                    error!("Relay {} frame capture failed: {}", self.info, e);
+                    self.capture = None;
                 }
             }
         }
 
-        Ok(())
+        if !message.is_text() && !message.is_binary() {
+            return Ok(true);
+        }
+        let payload = message.into_data();
+        let provenance = Provenance::live(self.id, self.generation)
+            .with_relay(self.info.clone())
+            .with_received_at_ms(now_ms() as u64);
+        let versioned = match VersionedRoot::parse_borrowed(&payload) {
+            Ok(versioned) => {
+                if self.raw_frames.is_some() {
+                    let root = Root {
+                        provenance: provenance.clone(),
+                        ..versioned.clone().into_root()
+                    };
+                    self.forward_raw(payload, provenance.received_at_ms, Some(root));
+                }
+                versioned
+            }
+            Err(error) => {
+                RelayMetrics::incr(&self.metrics.frames_malformed, 1);
+                if let FrameError::UnsupportedVersion(version) = error {
+                    warn!(
+                        "Relay {} skipped a frame of unsupported broadcast version {}",
+                        self.info, version
+                    );
+                    if self.broadcast_version.replace(version) != Some(version) {
+                        self.emit(FeedEvent::UnsupportedBroadcastVersion { version });
+                    }
+                }
+                if self.raw_frames.is_some() {
+                    self.forward_raw(payload.clone(), provenance.received_at_ms, None);
+                }
+                if let Some(observer) = &self.observer {
+                    observer.on_decode_error(self.id, &error);
+                }
+                self.report_malformed(payload, provenance.received_at_ms, error);
+                return Ok(true);
+            }
+        };
+        let version = versioned.version();
+        if let Some(previous) = self.broadcast_version.replace(version) {
+            if previous != version {
+                info!(
+                    "Relay {} switched from broadcast version {} to {}",
+                    self.info, previous, version
+                );
+                self.emit(FeedEvent::BroadcastVersionChanged {
+                    from: previous,
+                    to: version,
+                });
+            }
+        }
+        let mut decoded_root = versioned.into_root();
+
+        if self.start_sequence_number > 0 {
+            decoded_root
+                .messages
+                .retain(|m| m.sequence_number >= self.start_sequence_number);
+        }
+        let (Some(first), Some(last)) =
+            (decoded_root.messages.first(), decoded_root.messages.last())
+        else {
+            return Ok(true);
+        };
+        self.health
+            .record(decoded_root.messages.len() as u64, last.sequence_number);
+        if self.awaiting_first {
+            if first.sequence_number > self.start_sequence_number && self.start_sequence_number > 0
+            {
+                if self.contiguous_start {
+                    return Err(RelayError::SequenceGap {
+                        expected: self.start_sequence_number,
+                        received: first.sequence_number,
+                    });
+                }
+                warn!(
+                    "Relay {} started at sequence number {} instead of {}",
+                    self.info, first.sequence_number, self.start_sequence_number
+                );
+            }
+            self.awaiting_first = false;
+        }
+        decoded_root.provenance = provenance;
+        if let Some(event) = self.reorg.observe(&decoded_root) {
+            warn!(
+                "Relay {} re-sent messages after a reorg: {:?}",
+                self.info, event
+            );
+            self.emit(event);
+        }
+        if self.observer.is_some() {
+            self.observe(&decoded_root);
+        }
+
+        if let Some(detector) = &mut self.failover {
+            let now = Instant::now();
+            let events: Vec<_> = decoded_root
+                .messages
+                .iter()
+                .filter_map(|msg| detector.observe(msg, now))
+                .collect();
+            for event in events {
+                warn!("Relay {}: {:?}", self.info, event);
+                self.emit(event);
+            }
+        }
+
+        if let Some(enrichment) = &self.enrichment {
+            let mut too_large = Vec::new();
+            let open = enrichment.emit(
+                &decoded_root,
+                &self.decoders,
+                self.decode_options,
+                &mut too_large,
+            );
+            for event in too_large {
+                warn!("Relay {}: {:?}", self.info, event);
+                self.emit(event);
+            }
+            if !open {
+                return Ok(false);
+            }
+        }
+
+        match self
+            .backpressure
+            .forward(&self.sender, decoded_root, &self.metrics)
+        {
+            Forwarded::Sent | Forwarded::Dropped => Ok(true),
+            Forwarded::Closed => Ok(false),
+            Forwarded::Overloaded => Err(RelayError::ConsumerTooSlow),
+        }
+    }
+
+    fn forward_raw(&mut self, payload: Vec<u8>, received_at_ms: u64, root: Option<Root>) {
+        let Some(raw_frames) = &self.raw_frames else {
+            return;
+        };
+        let frame = RawFrame {
+            relay_id: self.id,
+            received_at_ms,
+            payload,
+            root,
+        };
+        if raw_frames.send(frame).is_err() {
+            debug!("Relay {} stopped forwarding raw frames", self.info);
+            self.raw_frames = None;
+        }
+    }
+
+    fn report_malformed(&mut self, payload: Vec<u8>, received_at_ms: u64, error: FrameError) {
+        let Some(malformed_frames) = &self.malformed_frames else {
+            return;
+        };
+        let frame = MalformedFrame {
+            relay_id: self.id,
+            received_at_ms,
+            payload,
+            error,
+        };
+        if malformed_frames.send(frame).is_err() {
+            debug!("Relay {} stopped reporting malformed frames", self.info);
+            self.malformed_frames = None;
+        }
+    }
+
+    /// Notifies the observer of the messages of `root`, and of the gaps before them.
+    fn observe(&mut self, root: &Root) {
+        let Some(observer) = &self.observer else {
+            return;
+        };
+        for msg in &root.messages {
+            let expected = match self.last_sequence_number {
+                Some(last) => last + 1,
+                None => self.start_sequence_number,
+            };
+            if msg.sequence_number > expected
+                && (self.last_sequence_number.is_some() || expected > 0)
+            {
+                observer.on_gap(self.id, expected, msg.sequence_number);
+            }
+            if self.last_sequence_number < Some(msg.sequence_number) {
+                self.last_sequence_number = Some(msg.sequence_number);
+            }
+            observer.on_message(msg, &root.provenance);
+        }
+    }
+
+    fn emit(&self, event: FeedEvent) {
+        if let Some(observer) = &self.observer {
+            observer.on_event(self.id, &event);
+        }
+        if let Some(events) = &self.events {
+            let _ = events.send(event);
+        }
+    }
+
+    fn handle_control(&mut self, control: ControlMessage) {
+        match control {
+            ControlMessage::StartCapture { path, frames } => {
+                let metadata = CaptureMetadata {
+                    relay_id: self.id,
+                    url: self.info.url.clone(),
+                    chain_id: self.chain_id,
+                    started_at_ms: now_ms(),
+                    frames,
+                };
+                match FrameCapture::create(&path, metadata) {
+                    Ok(capture) => {
+                        info!(
+                            "Relay {} capturing {} frames to {:?}",
+                            self.info, frames, path
+                        );
+                        self.capture = Some(capture);
+                    }
+                    Err(e) => error!("Relay {} failed to start frame capture: {}", self.info, e),
+                }
+            }
+            ControlMessage::StopCapture => {
+                if let Some(capture) = self.capture.take() {
+                    if let Err(e) = capture.finish() {
+                        error!("Relay {} frame capture failed: {}", self.info, e);
+                    }
+                }
+            }
+            ControlMessage::Pause if !self.paused => {
+                info!("Relay {} paused", self.info);
+                self.paused = true;
+                self.health.set_state(ConnectionState::Paused);
+            }
+            ControlMessage::Resume if self.paused => {
+                info!("Relay {} resumed", self.info);
+                self.paused = false;
+                self.health.set_state(ConnectionState::Connected);
+            }
+            ControlMessage::Pause | ControlMessage::Resume | ControlMessage::Shutdown => (),
+        }
+    }
+}
+
+impl Enrichment {
+    /// Decodes the messages in `root` with `decoders` and enriches their transactions, adding a
+    /// `FeedEvent::MessageTooLarge` to `too_large` for every message exceeding the limit of
+    /// `options`.
+    ///
+    /// Returns `false` once the receiving side of the channel has been dropped.
+    fn emit(
+        &self,
+        root: &Root,
+        decoders: &DecoderRegistry,
+        options: DecodeOptions,
+        too_large: &mut Vec<FeedEvent>,
+    ) -> bool {
+        for msg in &root.messages {
+            let decoded = decoders.decode_with(self.chain_id, &msg.message.message, options);
+            let decoded = match decoded {
+                Ok(Some(decoded)) => decoded,
+                Ok(None) => continue,
+                Err(DecodeError::MessageTooLarge { size, max }) => {
+                    too_large.push(FeedEvent::MessageTooLarge {
+                        sequence_number: msg.sequence_number,
+                        size,
+                        max,
+                    });
+                    continue;
+                }
+                Err(e) => {
+                    debug!("Failed to decode message {}: {}", msg.sequence_number, e);
+                    continue;
+                }
+            };
+
+            for tx in self.registry.enrich_msg(decoded) {
+                if self.sender.send(tx).is_err() {
+                    return false;
+                }
+            }
+        }
+
+        true
     }
 }
 
@@ -105,39 +796,16 @@ impl RelayClient {
 ///
 /// Returns a `RelayError::InvalidChainId` error if the `arbitrum-chain-id` header is missing or
 /// does not match the expected chain ID.
-///
-/// # Examples
-///
-/// ```
-/// use tungstenite::http::Response;
-/// use sequencer_feed_reader::networks::arbitrum::feed_client::check_chain_id_header;
-///
-/// let resp = Response::builder()
-///     .header("arbitrum-chain-id", "123")
-///     .body(None)
-///     .unwrap();
-///
-/// // This should return Ok(())
-/// assert_eq!(check_chain_id_header(resp, 123), Ok(()));
-///
-/// // This should return Err(RelayError::InvalidChainId)
-/// assert!(check_chain_id_header(resp, 456).is_err());
-/// ```
 fn check_chain_id_header(
     resp: tungstenite::http::Response<Option<Vec<u8>>>,
     chain_id: u64,
 ) -> Result<(), RelayError> {
-    let chain_id_resp = resp
-        .headers()
-        .get("arbitrum-chain-id")
-        .ok_or(RelayError::InvalidChainId)?
-        .to_str()
-        .unwrap_or_default();
-    Ok(
-        if chain_id_resp.parse::<u64>().unwrap_or_default() != chain_id {
-            return Err(RelayError::InvalidChainId);
-        },
-    )
+    let announced = HandshakeInfo::from_response(&resp).chain_id;
+    if announced.ok_or(RelayError::InvalidChainId)? != chain_id {
+        return Err(RelayError::InvalidChainId);
+    }
+
+    Ok(())
 }
 
 /// Generates a WebSocket request for the given URL.
@@ -145,34 +813,20 @@ fn check_chain_id_header(
 /// # Arguments
 ///
 /// * `url` - The URL to generate the request for.
-///
-/// # Examples
-///
-/// ```
-/// use url::Url;
-/// use sequencer_feed_reader::networks::arbitrum::feed_client::generate_websocket_request;
-///
-/// let url = Url::parse("wss://example.com").unwrap();
-/// let request = generate_websocket_request(url).unwrap();
-///
-/// assert_eq!(request.method(), "GET");
-/// assert_eq!(request.uri().to_string(), "wss://example.com/");
-/// assert_eq!(request.headers()["Host"], "example.com");
-/// assert_eq!(request.headers()["Connection"], "Upgrade");
-/// assert_eq!(request.headers()["Upgrade"], "websocket");
-/// assert_eq!(request.headers()["Sec-WebSocket-Version"], "13");
-/// assert!(request.headers()["Sec-WebSocket-Key"].len() > 0);
-/// assert_eq!(request.headers()["Arbitrum-Feed-Client-Version"], "2");
-/// assert_eq!(request.headers()["Arbitrum-Requested-Sequence-number"], "0");
-/// ```
+/// * `requested_sequence_number` - The sequence number the relay should start the feed at.
+/// * `auth` - The credentials to send to the relay, if any.
 ///
 /// # Returns
 ///
 /// Returns a `Result` containing the generated WebSocket request if successful, or a `RelayError` if an error occurred.
-fn generate_websocket_request(url: Url) -> Result<tungstenite::http::Request<()>, RelayError> {
+fn generate_websocket_request(
+    url: Url,
+    requested_sequence_number: u64,
+    auth: Option<&RelayAuth>,
+) -> Result<tungstenite::http::Request<()>, RelayError> {
     let key = tungstenite::handshake::client::generate_key();
     let host = url.host_str().ok_or(RelayError::InvalidUrl)?;
-    let req = tungstenite::handshake::client::Request::builder()
+    let mut req = tungstenite::handshake::client::Request::builder()
         .method("GET")
         .uri(url.as_str())
         .header("Host", host)
@@ -181,7 +835,486 @@ fn generate_websocket_request(url: Url) -> Result<tungstenite::http::Request<()>
         .header("Sec-WebSocket-Version", "13")
         .header("Sec-WebSocket-Key", key)
         .header("Arbitrum-Feed-Client-Version", "2")
-        .header("Arbitrum-Requested-Sequence-number", "0")
-        .body(())?;
-    Ok(req)
+        .header(
+            "Arbitrum-Requested-Sequence-number",
+            requested_sequence_number.to_string(),
+        );
+    if let Some(auth) = auth {
+        let (name, value) = auth.header();
+        req = req.header(name, value);
+    }
+    Ok(req.body(())?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::networks::arbitrum::{
+        decoder::{registry::DecodeOutcome, DecodedMsg},
+        mock::{MockRelay, Scenario, SimEvent, SimulatedSequencer, Step},
+        types::{BroadcastFeedMessage, Header},
+    };
+    use base64::{engine::general_purpose, Engine as _};
+    use crossbeam_channel::unbounded;
+    use tokio::task;
+
+    #[tokio::test]
+    async fn forwards_raw_frames_with_parsed_root() {
+        let scenario = Scenario::new().then(Step::Blocks {
+            count: 2,
+            interval_ms: 10,
+        });
+        let events = SimulatedSequencer::new(0, 1_700_000_000, 1).generate(&scenario);
+        let relay = MockRelay::bind("127.0.0.1:0", 42161, events).await.unwrap();
+        let url = Url::parse(&format!("ws://{}", relay.local_addr().unwrap())).unwrap();
+        relay.spawn();
+
+        let (sender, roots) = unbounded();
+        let (raw_tx, raw_frames) = unbounded();
+        let (updates, _updates) = unbounded();
+        let client = RelayClient::connect(url, 42161, 7, ConnectOptions::new(), sender, updates)
+            .await
+            .unwrap()
+            .with_raw_frames(raw_tx);
+        let handle = client.handle();
+        client.spawn();
+
+        for sequence_number in 0..2 {
+            let frame = task::spawn_blocking({
+                let raw_frames = raw_frames.clone();
+                move || raw_frames.recv_timeout(Duration::from_secs(5))
+            })
+            .await
+            .unwrap()
+            .expect("no raw frame");
+            let root = roots.recv_timeout(Duration::from_secs(5)).unwrap();
+
+            assert_eq!(frame.relay_id, 7);
+            assert_eq!(frame.root.as_ref(), Some(&root));
+            assert_eq!(frame.received_at_ms, root.provenance.received_at_ms);
+            assert_eq!(root.messages[0].sequence_number, sequence_number);
+            let reparsed = VersionedRoot::parse(&frame.payload).unwrap().into_root();
+            assert_eq!(reparsed.messages, root.messages);
+        }
+        handle.shutdown();
+    }
+
+    #[tokio::test]
+    async fn reports_unsupported_broadcast_versions() {
+        let mut events = vec![
+            SimEvent::Frame(r#"{"version":2,"blocks":[]}"#.into()),
+            SimEvent::Frame(r#"{"version":2,"blocks":[]}"#.into()),
+        ];
+        events.extend(SimulatedSequencer::new(0, 1_700_000_000, 1).generate(
+            &Scenario::new().then(Step::Blocks {
+                count: 1,
+                interval_ms: 1,
+            }),
+        ));
+        events.push(SimEvent::Wait(Duration::from_secs(60)));
+        let relay = MockRelay::bind("127.0.0.1:0", 42161, events).await.unwrap();
+        let url = Url::parse(&format!("ws://{}", relay.local_addr().unwrap())).unwrap();
+        relay.spawn();
+
+        let (sender, roots) = unbounded();
+        let (events_tx, events_rx) = unbounded();
+        let (updates, _updates) = unbounded();
+        let client = RelayClient::connect(url, 42161, 0, ConnectOptions::new(), sender, updates)
+            .await
+            .unwrap()
+            .with_events(events_tx);
+        let handle = client.handle();
+        client.spawn();
+
+        let root = task::spawn_blocking(move || roots.recv_timeout(Duration::from_secs(5)))
+            .await
+            .unwrap()
+            .expect("no message after the unsupported frames");
+        assert_eq!(root.messages[0].sequence_number, 0);
+        assert_eq!(
+            events_rx.try_iter().collect::<Vec<_>>(),
+            [
+                FeedEvent::UnsupportedBroadcastVersion { version: 2 },
+                FeedEvent::BroadcastVersionChanged { from: 2, to: 1 },
+            ]
+        );
+        handle.shutdown();
+    }
+
+    #[tokio::test]
+    async fn reports_malformed_frames() {
+        let truncated = r#"{"version":1,"messages":["#;
+        let mut events = vec![
+            SimEvent::Frame(truncated.into()),
+            SimEvent::Frame(r#"{"version":2,"blocks":[]}"#.into()),
+        ];
+        events.extend(SimulatedSequencer::new(0, 1_700_000_000, 1).generate(
+            &Scenario::new().then(Step::Blocks {
+                count: 1,
+                interval_ms: 1,
+            }),
+        ));
+        events.push(SimEvent::Disconnect);
+        let relay = MockRelay::bind("127.0.0.1:0", 42161, events).await.unwrap();
+        let url = Url::parse(&format!("ws://{}", relay.local_addr().unwrap())).unwrap();
+        relay.spawn();
+
+        let (sender, roots) = unbounded();
+        let (malformed_tx, malformed) = unbounded();
+        let (updates, _updates) = unbounded();
+        let client = RelayClient::connect(url, 42161, 7, ConnectOptions::new(), sender, updates)
+            .await
+            .unwrap()
+            .with_malformed_frames(malformed_tx);
+        let metrics = client.metrics();
+        client.run().await.unwrap();
+
+        let malformed: Vec<MalformedFrame> = malformed.try_iter().collect();
+        assert_eq!(malformed.len(), 2);
+        assert!(malformed.iter().all(|frame| frame.relay_id == 7));
+        assert!(malformed[0].received_at_ms <= malformed[1].received_at_ms);
+        assert!(malformed[1].received_at_ms > 1_700_000_000_000);
+        assert_eq!(malformed[0].payload, truncated.as_bytes());
+        assert!(matches!(malformed[0].error, FrameError::Json(_)));
+        assert!(matches!(
+            malformed[1].error,
+            FrameError::UnsupportedVersion(2)
+        ));
+        assert_eq!(metrics.snapshot().frames_malformed, 2);
+        // The frames following the malformed ones are still read.
+        assert_eq!(roots.try_iter().count(), 1);
+    }
+
+    #[tokio::test]
+    async fn pauses_and_resumes_reading_frames() {
+        let scenario = Scenario::new()
+            .then(Step::Blocks {
+                count: 4,
+                interval_ms: 50,
+            })
+            .then(Step::Stall { ms: 60_000 });
+        let events = SimulatedSequencer::new(0, 1_700_000_000, 1).generate(&scenario);
+        let relay = MockRelay::bind("127.0.0.1:0", 42161, events).await.unwrap();
+        let url = Url::parse(&format!("ws://{}", relay.local_addr().unwrap())).unwrap();
+        relay.spawn();
+
+        let (sender, roots) = unbounded();
+        let (updates, _updates) = unbounded();
+        let client = RelayClient::connect(url, 42161, 0, ConnectOptions::new(), sender, updates)
+            .await
+            .unwrap();
+        let handle = client.handle();
+        client.spawn();
+
+        let recv = |roots: &crossbeam_channel::Receiver<Root>| {
+            let roots = roots.clone();
+            task::spawn_blocking(move || roots.recv_timeout(Duration::from_secs(5)))
+        };
+        recv(&roots).await.unwrap().expect("no root");
+        assert!(handle.pause());
+        while !handle.is_paused() {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        assert!(handle.health().state.is_connected());
+
+        // Frames sent while paused are left unread.
+        let read_before_pause = roots.try_iter().count();
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert_eq!(roots.try_iter().count(), 0);
+
+        assert!(handle.resume());
+        for _ in read_before_pause..3 {
+            recv(&roots).await.unwrap().expect("no root after resuming");
+        }
+        assert!(!handle.is_paused());
+        handle.shutdown();
+    }
+
+    #[tokio::test]
+    async fn detects_the_chain_id_of_the_relay() {
+        let relay = MockRelay::bind("127.0.0.1:0", 42170, vec![SimEvent::Disconnect; 2])
+            .await
+            .unwrap();
+        let url = Url::parse(&format!("ws://{}", relay.local_addr().unwrap())).unwrap();
+        relay.spawn();
+        let connect = |options: ConnectOptions| {
+            let (sender, _roots) = unbounded();
+            let (updates, _updates) = unbounded();
+            RelayClient::connect(url.clone(), 42161, 0, options, sender, updates)
+        };
+
+        assert!(matches!(
+            connect(ConnectOptions::new()).await,
+            Err(RelayError::InvalidChainId)
+        ));
+        let client = connect(ConnectOptions::new().with_chain_id_detection())
+            .await
+            .unwrap();
+        assert_eq!(client.chain_id(), 42170);
+        assert_eq!(client.handle().status().chain_id, 42170);
+        assert_eq!(client.handshake().feed_server_version, Some(2));
+        assert!(client.handshake().accepted_sequence_number(0));
+    }
+
+    #[tokio::test]
+    async fn reports_messages_too_large_to_decode() {
+        let scenario = Scenario::new()
+            .then(Step::Blocks {
+                count: 2,
+                interval_ms: 1,
+            })
+            .then(Step::Disconnect);
+        let events = SimulatedSequencer::new(0, 1_700_000_000, 1).generate(&scenario);
+        let relay = MockRelay::bind("127.0.0.1:0", 42161, events).await.unwrap();
+        let url = Url::parse(&format!("ws://{}", relay.local_addr().unwrap())).unwrap();
+        relay.spawn();
+
+        let (sender, roots) = unbounded();
+        let (updates, _updates) = unbounded();
+        let (enriched, _enriched) = unbounded();
+        let (events, feed_events) = unbounded();
+        // Simulated blocks are empty batches, a single byte long.
+        let options = DecodeOptions {
+            max_l2_message_size: 0,
+            ..Default::default()
+        };
+        RelayClient::connect(url, 42161, 0, ConnectOptions::new(), sender, updates)
+            .await
+            .unwrap()
+            .with_abi_registry(Arc::default(), enriched)
+            .with_decode_options(options)
+            .with_events(events)
+            .run()
+            .await
+            .unwrap();
+
+        // The messages are still forwarded, undecoded.
+        assert_eq!(roots.try_iter().count(), 2);
+        let too_large: Vec<_> = feed_events
+            .try_iter()
+            .filter_map(|event| match event {
+                FeedEvent::MessageTooLarge {
+                    sequence_number,
+                    max,
+                    ..
+                } => Some((sequence_number, max)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(too_large, [(0, 0), (1, 0)]);
+    }
+
+    #[test]
+    fn builds_the_handshake_and_checks_the_chain_id() {
+        let url = Url::parse("wss://example.com").unwrap();
+        let request = generate_websocket_request(url, 0, None).unwrap();
+        assert_eq!(request.method(), "GET");
+        assert_eq!(request.uri().to_string(), "wss://example.com/");
+        assert_eq!(request.headers()["Host"], "example.com");
+        assert_eq!(request.headers()["Connection"], "Upgrade");
+        assert_eq!(request.headers()["Upgrade"], "websocket");
+        assert_eq!(request.headers()["Sec-WebSocket-Version"], "13");
+        assert!(!request.headers()["Sec-WebSocket-Key"].is_empty());
+        assert_eq!(request.headers()["Arbitrum-Feed-Client-Version"], "2");
+        assert_eq!(request.headers()["Arbitrum-Requested-Sequence-number"], "0");
+
+        let response = || {
+            tungstenite::http::Response::builder()
+                .header("arbitrum-chain-id", "123")
+                .body(None)
+                .unwrap()
+        };
+        assert!(check_chain_id_header(response(), 123).is_ok());
+        assert!(matches!(
+            check_chain_id_header(response(), 456),
+            Err(RelayError::InvalidChainId)
+        ));
+    }
+
+    #[tokio::test]
+    async fn captures_frames_on_request() {
+        let scenario = Scenario::new()
+            .then(Step::Stall { ms: 200 })
+            .then(Step::Blocks {
+                count: 3,
+                interval_ms: 1,
+            })
+            .then(Step::Disconnect);
+        let events = SimulatedSequencer::new(0, 1_700_000_000, 1).generate(&scenario);
+        let relay = MockRelay::bind("127.0.0.1:0", 42161, events).await.unwrap();
+        let url = Url::parse(&format!("ws://{}", relay.local_addr().unwrap())).unwrap();
+        relay.spawn();
+
+        let (sender, roots) = unbounded();
+        let (updates, _updates) = unbounded();
+        let client = RelayClient::connect(url, 42161, 5, ConnectOptions::new(), sender, updates)
+            .await
+            .unwrap();
+        let path = std::env::temp_dir().join(format!("sfr-capture-{}.jsonl", std::process::id()));
+        assert!(client.handle().start_capture(&path, 2));
+        client.run().await.unwrap();
+        assert_eq!(roots.try_iter().count(), 3);
+
+        // The metadata, then only the requested number of frames.
+        let capture = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<serde_json::Value> = capture
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(
+            (&lines[0]["relayId"], &lines[0]["frames"]),
+            (&5.into(), &2.into())
+        );
+        assert_eq!(lines[1]["opcode"], "text");
+        let payload = general_purpose::STANDARD
+            .decode(lines[1]["payload"].as_str().unwrap())
+            .unwrap();
+        let root: Root = serde_json::from_slice(&payload).unwrap();
+        assert_eq!(root.messages[0].sequence_number, 0);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn decodes_with_the_registered_hooks() {
+        let scenario = Scenario::new()
+            .then(Step::Blocks {
+                count: 2,
+                interval_ms: 1,
+            })
+            .then(Step::Disconnect);
+        let events = SimulatedSequencer::new(0, 1_700_000_000, 1).generate(&scenario);
+        let relay = MockRelay::bind("127.0.0.1:0", 42161, events).await.unwrap();
+        let url = Url::parse(&format!("ws://{}", relay.local_addr().unwrap())).unwrap();
+        relay.spawn();
+
+        // Simulated blocks are empty batches: only the hook yields transactions.
+        let mut decoders = DecoderRegistry::new();
+        decoders.register(42161, |_: &Header, _: &[u8]| {
+            Ok::<_, DecodeError>(DecodeOutcome::Decoded(DecodedMsg::DecodedSignedTx(
+                Box::default(),
+            )))
+        });
+        let (sender, _roots) = unbounded();
+        let (updates, _updates) = unbounded();
+        let (enriched, enriched_txs) = unbounded();
+        // The registry applies whichever of the two is set first.
+        RelayClient::connect(url, 42161, 0, ConnectOptions::new(), sender, updates)
+            .await
+            .unwrap()
+            .with_decoder_registry(Arc::new(decoders))
+            .with_abi_registry(Arc::default(), enriched)
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(enriched_txs.try_iter().count(), 2);
+    }
+
+    #[derive(Default)]
+    struct Recorder(std::sync::Mutex<Vec<String>>);
+
+    impl EventObserver for Recorder {
+        fn on_connect(&self, relay_id: u32, _handshake: &HandshakeInfo) {
+            self.0.lock().unwrap().push(format!("connect {}", relay_id));
+        }
+
+        fn on_message(&self, msg: &BroadcastFeedMessage, provenance: &Provenance) {
+            self.0.lock().unwrap().push(format!(
+                "message {} from {}",
+                msg.sequence_number, provenance.relay_id
+            ));
+        }
+
+        fn on_gap(&self, _relay_id: u32, expected: u64, received: u64) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("gap {}..{}", expected, received));
+        }
+
+        fn on_disconnect(&self, _relay_id: u32, error: Option<&RelayError>) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("disconnect {}", error.is_some()));
+        }
+    }
+
+    #[tokio::test]
+    async fn notifies_the_observer() {
+        let scenario = Scenario::new()
+            .then(Step::Blocks {
+                count: 2,
+                interval_ms: 1,
+            })
+            .then(Step::Gap { count: 2 })
+            .then(Step::Blocks {
+                count: 1,
+                interval_ms: 1,
+            })
+            .then(Step::Disconnect);
+        let events = SimulatedSequencer::new(0, 1_700_000_000, 1).generate(&scenario);
+        let relay = MockRelay::bind("127.0.0.1:0", 42161, events).await.unwrap();
+        let url = Url::parse(&format!("ws://{}", relay.local_addr().unwrap())).unwrap();
+        relay.spawn();
+
+        let (sender, _roots) = unbounded();
+        let (updates, _updates) = unbounded();
+        let recorder = Arc::new(Recorder::default());
+        let client = RelayClient::connect(url, 42161, 3, ConnectOptions::new(), sender, updates)
+            .await
+            .unwrap()
+            .with_observer(recorder.clone());
+        client.run().await.unwrap();
+
+        assert_eq!(
+            *recorder.0.lock().unwrap(),
+            [
+                "connect 3",
+                "message 0 from 3",
+                "message 1 from 3",
+                "gap 2..4",
+                "message 4 from 3",
+                "disconnect false"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn reports_connection_updates_with_their_cause() {
+        let mut events = SimulatedSequencer::new(0, 1_700_000_000, 1).generate(
+            &Scenario::new().then(Step::Blocks {
+                count: 1,
+                interval_ms: 1,
+            }),
+        );
+        events.push(SimEvent::Close {
+            code: 4000,
+            reason: "shutting down".to_string(),
+        });
+        let relay = MockRelay::bind("127.0.0.1:0", 42161, events).await.unwrap();
+        let url = Url::parse(&format!("ws://{}", relay.local_addr().unwrap())).unwrap();
+        relay.spawn();
+
+        let (sender, _roots) = unbounded();
+        let (updates, received) = unbounded();
+        let before = SystemTime::now();
+        let client = RelayClient::connect(url, 42161, 5, ConnectOptions::new(), sender, updates)
+            .await
+            .unwrap();
+        client.run().await.unwrap();
+
+        let received: Vec<ConnectionUpdate> = received.try_iter().collect();
+        assert_eq!(received.len(), 2);
+        assert!(matches!(received[0], ConnectionUpdate::Connected { .. }));
+        match &received[1] {
+            ConnectionUpdate::Closed { code, reason, .. } => {
+                assert_eq!((*code, reason.as_str()), (Some(4000), "shutting down"));
+            }
+            update => panic!("unexpected update {:?}", update),
+        }
+        assert!(received.iter().all(|update| update.id() == 5));
+        assert!(received[0].at() >= before && received[1].at() >= received[0].at());
+    }
 }
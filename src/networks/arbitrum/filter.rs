@@ -0,0 +1,248 @@
+use crate::networks::arbitrum::{
+    config::{Config, FilterConfig},
+    errors::ConfigError,
+    message::{FeedMessage, FeedTransaction},
+    middleware::{FeedMiddleware, Next},
+};
+use log::*;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+    thread::{self, JoinHandle},
+    time::{Duration, SystemTime},
+};
+
+/// A `FilterConfig`, along with the `priority` filters of a `PriorityLane`, that can be replaced
+/// while the feed is being read, without reconnecting, e.g. to adjust the targeted addresses
+/// during volatile periods.
+///
+/// Clones share the same filters: replacing them through any clone applies to every stage
+/// matching messages against them, from the next message on.
+///
+/// # Example
+///
+/// ```no_run
+/// use sequencer_feed_reader::networks::arbitrum::service::FeedService;
+/// use std::time::Duration;
+///
+/// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+/// let service = FeedService::from_config("feed.toml")?.start().await?;
+/// // Applies the `[filter]` and `[[priority]]` sections of the file whenever it is saved.
+/// service.filter().clone().watch("feed.toml", Duration::from_secs(1));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct LiveFilter {
+    filters: Arc<RwLock<Filters>>,
+}
+
+#[derive(Debug, Default)]
+struct Filters {
+    filter: FilterConfig,
+    priority: Vec<FilterConfig>,
+}
+
+impl LiveFilter {
+    pub fn new(filter: FilterConfig) -> Self {
+        Self {
+            filters: Arc::new(RwLock::new(Filters {
+                filter,
+                priority: Vec::new(),
+            })),
+        }
+    }
+
+    /// Sets the high priority filters, see `PriorityLane`.
+    pub fn with_priority(self, priority: Vec<FilterConfig>) -> Self {
+        self.set_priority(priority);
+        self
+    }
+
+    /// Returns a copy of the current filter.
+    pub fn get(&self) -> FilterConfig {
+        self.filters.read().unwrap().filter.clone()
+    }
+
+    /// Replaces the filter.
+    pub fn set(&self, filter: FilterConfig) {
+        self.filters.write().unwrap().filter = filter;
+    }
+
+    /// Returns a copy of the current high priority filters.
+    pub fn priority(&self) -> Vec<FilterConfig> {
+        self.filters.read().unwrap().priority.clone()
+    }
+
+    /// Replaces the high priority filters.
+    pub fn set_priority(&self, priority: Vec<FilterConfig>) {
+        self.filters.write().unwrap().priority = priority;
+    }
+
+    /// Returns `true` if `msg` matches the current filter.
+    pub fn matches(&self, msg: &FeedMessage) -> bool {
+        self.filters.read().unwrap().filter.matches(msg)
+    }
+
+    /// Returns `true` if `tx` matches any of the current high priority filters.
+    pub fn matches_priority(&self, tx: &FeedTransaction) -> bool {
+        let filters = self.filters.read().unwrap();
+        filters.priority.iter().any(|f| f.matches_transaction(tx))
+    }
+
+    /// Replaces the filter and the high priority filters with the `[filter]` and `[[priority]]`
+    /// sections of the configuration file at `path`, overridden by the environment variables,
+    /// see `Config::load`.
+    ///
+    /// # Returns
+    ///
+    /// The new filter, or a `ConfigError` if the file is invalid, in which case the filters are
+    /// left unchanged.
+    pub fn reload(&self, path: impl AsRef<Path>) -> Result<FilterConfig, ConfigError> {
+        let config = Config::load(path)?;
+        *self.filters.write().unwrap() = Filters {
+            filter: config.filter.clone(),
+            priority: config.priority,
+        };
+        Ok(config.filter)
+    }
+
+    /// Reloads the filters from the configuration file at `path` whenever the file is modified,
+    /// checking every `interval` on a new thread.
+    ///
+    /// Invalid files are logged and leave the filters unchanged. The thread stops once every
+    /// other clone of the filter is dropped.
+    pub fn watch(self, path: impl Into<PathBuf>, interval: Duration) -> JoinHandle<()> {
+        let path = path.into();
+        let filters = Arc::downgrade(&self.filters);
+        drop(self);
+        thread::spawn(move || {
+            let mut modified = modified_at(&path);
+            loop {
+                thread::sleep(interval);
+                let Some(filters) = filters.upgrade() else {
+                    return;
+                };
+                let now = modified_at(&path);
+                if now == modified {
+                    continue;
+                }
+                modified = now;
+                match (LiveFilter { filters }).reload(&path) {
+                    Ok(_) => info!("Reloaded the filters from {}", path.display()),
+                    Err(e) => warn!(
+                        "Failed to reload the filters from {}: {}",
+                        path.display(),
+                        e
+                    ),
+                }
+            }
+        })
+    }
+}
+
+impl From<FilterConfig> for LiveFilter {
+    fn from(filter: FilterConfig) -> Self {
+        Self::new(filter)
+    }
+}
+
+/// Drops the messages not matching the current filter.
+impl FeedMiddleware for LiveFilter {
+    fn handle(&mut self, msg: FeedMessage, mut next: Next<'_>) -> bool {
+        !self.matches(&msg) || next.run(msg)
+    }
+}
+
+/// Returns when the file at `path` was last modified, `None` if unknown.
+fn modified_at(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::networks::arbitrum::{decoder::DecodedMsg, fixtures::feed_message};
+    use ethers::types::{Transaction, H160};
+
+    #[test]
+    fn reloads_the_filter_from_the_configuration_file() {
+        let msg = feed_message(
+            1,
+            0,
+            vec![],
+            Some(DecodedMsg::DecodedSignedTx(Box::new(Transaction {
+                to: Some(H160::repeat_byte(0x11)),
+                ..Default::default()
+            }))),
+        );
+        let live = LiveFilter::new(FilterConfig {
+            to: vec![H160::repeat_byte(0x22)],
+            ..Default::default()
+        });
+        let stage = live.clone();
+        assert!(!stage.matches(&msg));
+
+        let path = std::env::temp_dir().join(format!("sfr-filter-{}.toml", std::process::id()));
+        let to = format!("0x{}", "11".repeat(20));
+        fs::write(
+            &path,
+            format!(
+                "[filter]\nto = [\"{0}\"]\n\n[[priority]]\nto = [\"{0}\"]\n",
+                to
+            ),
+        )
+        .unwrap();
+        let reloaded = live.reload(&path).unwrap();
+        assert_eq!(reloaded.to, [H160::repeat_byte(0x11)]);
+        assert!(stage.matches(&msg));
+        assert!(msg
+            .transactions()
+            .iter()
+            .all(|tx| stage.matches_priority(tx)));
+
+        // An invalid file leaves the filters unchanged.
+        fs::write(&path, "[filter]\nto = [\"0x11\"]\n").unwrap();
+        assert!(live.reload(&path).is_err());
+        assert!(stage.matches(&msg));
+        assert_eq!(stage.priority().len(), 1);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn reloads_the_selectors() {
+        let transfer = [0xa9, 0x05, 0x9c, 0xbb];
+        let msg = feed_message(
+            1,
+            0,
+            vec![],
+            Some(DecodedMsg::DecodedSignedTx(Box::new(Transaction {
+                input: [&transfer[..], &[0; 64]].concat().into(),
+                ..Default::default()
+            }))),
+        );
+        let live = LiveFilter::default();
+        let path = std::env::temp_dir().join(format!("sfr-selectors-{}.toml", std::process::id()));
+
+        fs::write(
+            &path,
+            "[filter]\nselectors = [\"0xa9059cbb\"]\n\n[[priority]]\nselectors = [\"095ea7b3\"]\n",
+        )
+        .unwrap();
+        assert_eq!(live.reload(&path).unwrap().selectors, [transfer]);
+        assert!(live.matches(&msg));
+        assert!(!msg
+            .transactions()
+            .iter()
+            .any(|tx| live.matches_priority(tx)));
+
+        fs::write(&path, "[filter]\nselectors = [\"0x095ea7b3\"]\n").unwrap();
+        live.reload(&path).unwrap();
+        assert!(!live.matches(&msg));
+
+        fs::write(&path, "[filter]\nselectors = [\"0xa9059c\"]\n").unwrap();
+        assert!(live.reload(&path).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,51 @@
+//! Helpers building feed messages for unit tests.
+
+use crate::networks::arbitrum::{
+    decoder::DecodedMsg,
+    message::FeedMessage,
+    types::{BroadcastFeedMessage, Header, L1IncomingMessageHeader, MessageWithMetadata},
+};
+use serde_json::Value;
+
+/// Builds an L2 message (kind 3) with the given sequence number, timestamp and L2 bytes.
+pub fn message_with(sequence_number: u64, timestamp: u64, l2msg: Vec<u8>) -> BroadcastFeedMessage {
+    BroadcastFeedMessage {
+        sequence_number,
+        message: MessageWithMetadata {
+            message: L1IncomingMessageHeader {
+                header: Header {
+                    kind: 3,
+                    sender: String::new(),
+                    block_number: 0,
+                    timestamp,
+                    request_id: Value::Null,
+                    base_fee_l1: Value::Null,
+                },
+                l2msg: l2msg.into(),
+            },
+            delayed_messages_read: 0,
+        },
+        signature: Value::Null,
+    }
+}
+
+/// Wraps `message_with` in a `FeedMessage` decoded to `decoded`, with the default provenance.
+pub fn feed_message(
+    sequence_number: u64,
+    timestamp: u64,
+    l2msg: Vec<u8>,
+    decoded: Option<DecodedMsg>,
+) -> FeedMessage {
+    FeedMessage {
+        message: message_with(sequence_number, timestamp, l2msg),
+        decoded: Ok(decoded),
+        provenance: Default::default(),
+    }
+}
+
+/// Like `feed_message`, for an L1 message of the given kind.
+pub fn feed_message_of_kind(sequence_number: u64, kind: u8, l2msg: Vec<u8>) -> FeedMessage {
+    let mut msg = feed_message(sequence_number, 0, l2msg, None);
+    msg.message.message.message.header.kind = kind;
+    msg
+}
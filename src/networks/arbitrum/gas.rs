@@ -0,0 +1,166 @@
+use crate::networks::arbitrum::{
+    message::FeedMessage,
+    middleware::{FeedMiddleware, Next},
+};
+use crossbeam_channel::Sender;
+use ethers::types::{Transaction, U256};
+use serde::Serialize;
+use serde_json::Value;
+
+/// The gas pricing information of a feed message, for gas strategies that don't need the
+/// transactions themselves.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GasPriceUpdate {
+    pub sequence_number: u64,
+    pub timestamp: u64,
+    pub l1_block_number: u64,
+    /// The L1 base fee of the message header. Only set on the messages of the delayed inbox.
+    pub base_fee_l1: Option<U256>,
+    /// How many transactions of the message bid a gas price.
+    pub transactions: usize,
+    /// The lowest, median and highest gas price bid by the transactions of the message: the max
+    /// fee per gas of dynamic fee transactions, the gas price of the others.
+    pub min_gas_price: Option<U256>,
+    pub median_gas_price: Option<U256>,
+    pub max_gas_price: Option<U256>,
+    /// The highest priority fee bid by the dynamic fee transactions of the message. Arbitrum
+    /// ignores priority fees, so they mostly tell how bots bid.
+    pub max_priority_fee: Option<U256>,
+}
+
+impl GasPriceUpdate {
+    /// Extracts the gas pricing information of `msg`.
+    ///
+    /// # Returns
+    ///
+    /// The update, or `None` if `msg` has neither an L1 base fee nor transactions.
+    pub fn of(msg: &FeedMessage) -> Option<Self> {
+        let header = &msg.message.message.message.header;
+        let base_fee_l1 = parse_base_fee(&header.base_fee_l1);
+        let txs = msg.transactions();
+        let mut prices: Vec<U256> = txs.iter().filter_map(|tx| gas_price(&tx.tx)).collect();
+        if base_fee_l1.is_none() && prices.is_empty() {
+            return None;
+        }
+        prices.sort_unstable();
+
+        Some(Self {
+            sequence_number: msg.sequence_number(),
+            timestamp: header.timestamp,
+            l1_block_number: header.block_number,
+            base_fee_l1,
+            transactions: prices.len(),
+            min_gas_price: prices.first().copied(),
+            median_gas_price: prices.get(prices.len() / 2).copied(),
+            max_gas_price: prices.last().copied(),
+            max_priority_fee: txs
+                .iter()
+                .filter_map(|tx| tx.tx.max_priority_fee_per_gas)
+                .max(),
+        })
+    }
+}
+
+/// The gas price a transaction bids.
+fn gas_price(tx: &Transaction) -> Option<U256> {
+    tx.max_fee_per_gas.or(tx.gas_price)
+}
+
+/// Parses the `baseFeeL1` of a header, a JSON number, or a decimal or hex string.
+fn parse_base_fee(value: &Value) -> Option<U256> {
+    match value {
+        Value::Number(n) => n.as_u64().map(U256::from),
+        Value::String(s) => match s.strip_prefix("0x") {
+            Some(hex) => U256::from_str_radix(hex, 16).ok(),
+            None => U256::from_dec_str(s).ok(),
+        },
+        _ => None,
+    }
+}
+
+/// A middleware sending the `GasPriceUpdate` of every message to a channel, and passing the
+/// message on unchanged.
+///
+/// Updates are dropped while the channel is full, so that a slow gas strategy never holds the
+/// feed back. The pipeline stops once the receiving side of the channel is dropped.
+#[derive(Debug, Clone)]
+pub struct GasPriceFeed {
+    updates: Sender<GasPriceUpdate>,
+}
+
+impl GasPriceFeed {
+    pub fn new(updates: Sender<GasPriceUpdate>) -> Self {
+        Self { updates }
+    }
+
+    /// Sends the update of `msg`, if any.
+    ///
+    /// # Returns
+    ///
+    /// `false` once the receiving side of the channel is dropped.
+    pub fn publish(&self, msg: &FeedMessage) -> bool {
+        match GasPriceUpdate::of(msg) {
+            Some(update) => !matches!(
+                self.updates.try_send(update),
+                Err(crossbeam_channel::TrySendError::Disconnected(_))
+            ),
+            None => true,
+        }
+    }
+}
+
+impl FeedMiddleware for GasPriceFeed {
+    fn handle(&mut self, msg: FeedMessage, mut next: Next<'_>) -> bool {
+        self.publish(&msg) && next.run(msg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::networks::arbitrum::{decoder::DecodedMsg, fixtures::feed_message};
+    use crossbeam_channel::bounded;
+
+    #[test]
+    fn extracts_gas_prices_and_base_fee() {
+        let tx = |max_fee: Option<u64>, gas_price: Option<u64>| Transaction {
+            max_fee_per_gas: max_fee.map(U256::from),
+            max_priority_fee_per_gas: max_fee.map(|_| U256::one()),
+            gas_price: gas_price.map(U256::from),
+            ..Default::default()
+        };
+        let mut msg = feed_message(
+            5,
+            1_700_000_000,
+            vec![],
+            Some(DecodedMsg::DecodedBatch(vec![
+                tx(Some(300), Some(100)),
+                tx(None, Some(100)),
+                tx(Some(200), None),
+                tx(None, None),
+            ])),
+        );
+        msg.message.message.message.header.base_fee_l1 = Value::String("0x3b9aca00".into());
+
+        let update = GasPriceUpdate::of(&msg).unwrap();
+        assert_eq!(update.base_fee_l1, Some(1_000_000_000u64.into()));
+        assert_eq!(update.transactions, 3);
+        assert_eq!(update.min_gas_price, Some(100u64.into()));
+        assert_eq!(update.median_gas_price, Some(200u64.into()));
+        assert_eq!(update.max_gas_price, Some(300u64.into()));
+        assert_eq!(update.max_priority_fee, Some(U256::one()));
+
+        let (updates, received) = bounded(1);
+        let feed = GasPriceFeed::new(updates);
+        assert!(feed.publish(&msg));
+        // Updates are dropped while the channel is full.
+        assert!(feed.publish(&msg));
+        assert_eq!(received.len(), 1);
+        drop(received);
+        assert!(!feed.publish(&msg));
+
+        let empty = feed_message(6, 0, vec![], None);
+        assert_eq!(GasPriceUpdate::of(&empty), None);
+    }
+}
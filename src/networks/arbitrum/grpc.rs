@@ -0,0 +1,222 @@
+//! A gRPC server re-broadcasting feed messages to local services, see `proto/feed.proto`.
+
+use crate::networks::arbitrum::{decoder::DecodedMsg, message::FeedMessage, protobuf};
+use ethers::types::{Transaction, H160};
+use futures::{stream, Stream};
+use log::*;
+use std::{net::SocketAddr, pin::Pin, sync::Arc};
+use tokio::{
+    net::TcpListener,
+    sync::broadcast::{self, error::RecvError},
+};
+use tonic::{transport::Server, Request, Response, Status};
+
+pub use crate::networks::arbitrum::protobuf::proto;
+
+/// How many messages a subscriber may lag behind before it starts missing messages.
+const DEFAULT_BUFFER: usize = 4096;
+
+/// Re-broadcasts feed messages to gRPC subscribers, so that one process holds the relay
+/// connection and many local services consume it.
+///
+/// Messages published with `publish` are streamed to every subscriber whose `SubscribeRequest`
+/// filters match them. Subscribers falling more than `buffer` messages behind skip the messages
+/// they missed. Clones share the same subscribers.
+#[derive(Clone)]
+pub struct GrpcFeedService {
+    chain_id: u64,
+    sender: broadcast::Sender<Arc<FeedMessage>>,
+}
+
+impl GrpcFeedService {
+    /// # Arguments
+    ///
+    /// * `chain_id` - The chain ID reported in streamed messages.
+    pub fn new(chain_id: u64) -> Self {
+        Self::with_buffer(chain_id, DEFAULT_BUFFER)
+    }
+
+    /// Creates a service letting subscribers lag up to `buffer` messages behind.
+    pub fn with_buffer(chain_id: u64, buffer: usize) -> Self {
+        let (sender, _) = broadcast::channel(buffer.max(1));
+        Self { chain_id, sender }
+    }
+
+    /// Streams a message to the matching subscribers.
+    pub fn publish(&self, msg: Arc<FeedMessage>) {
+        // Sending only fails while nobody is subscribed.
+        let _ = self.sender.send(msg);
+    }
+
+    /// Returns the number of connected subscribers.
+    pub fn subscribers(&self) -> usize {
+        self.sender.receiver_count()
+    }
+
+    /// Serves the service on `addr` until the server fails.
+    pub async fn serve(self, addr: SocketAddr) -> Result<(), tonic::transport::Error> {
+        Server::builder()
+            .add_service(proto::feed_server::FeedServer::new(self))
+            .serve(addr)
+            .await
+    }
+
+    /// Serves the service on connections accepted from `listener`, e.g. bound to an ephemeral
+    /// port.
+    pub async fn serve_with_listener(
+        self,
+        listener: TcpListener,
+    ) -> Result<(), tonic::transport::Error> {
+        let incoming = stream::unfold(listener, |listener| async move {
+            let accepted = listener.accept().await.map(|(stream, _)| stream);
+            Some((accepted, listener))
+        });
+        Server::builder()
+            .add_service(proto::feed_server::FeedServer::new(self))
+            .serve_with_incoming(incoming)
+            .await
+    }
+}
+
+/// The parsed filters of a `SubscribeRequest`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SubscriptionFilter {
+    pub from_sequence_number: u64,
+    pub kinds: Vec<u32>,
+    pub from: Vec<H160>,
+    pub to: Vec<H160>,
+}
+
+impl SubscriptionFilter {
+    /// Returns `true` if `msg` matches every set filter, with `from` and `to` matching the same
+    /// transaction.
+    pub fn matches(&self, msg: &FeedMessage) -> bool {
+        if msg.sequence_number() < self.from_sequence_number {
+            return false;
+        }
+        let kind = msg.message.message.message.header.kind;
+        if !self.kinds.is_empty() && !self.kinds.contains(&kind.into()) {
+            return false;
+        }
+        if self.from.is_empty() && self.to.is_empty() {
+            return true;
+        }
+
+        let txs = transactions(msg);
+        txs.iter().any(|tx| {
+            (self.from.is_empty() || !msg.provenance.degraded && self.from.contains(&tx.from))
+                && (self.to.is_empty() || tx.to.is_some_and(|to| self.to.contains(&to)))
+        })
+    }
+}
+
+impl TryFrom<proto::SubscribeRequest> for SubscriptionFilter {
+    type Error = Status;
+
+    fn try_from(request: proto::SubscribeRequest) -> Result<Self, Status> {
+        // Fails with the length of the first invalid address.
+        let addresses = |addresses: Vec<Vec<u8>>| {
+            addresses
+                .into_iter()
+                .map(|address| match address.len() {
+                    20 => Ok(H160::from_slice(&address)),
+                    len => Err(len),
+                })
+                .collect::<Result<Vec<_>, _>>()
+        };
+        let invalid =
+            |len| Status::invalid_argument(format!("addresses are 20 bytes long, got {}", len));
+        Ok(Self {
+            from_sequence_number: request.from_sequence_number,
+            kinds: request.kinds,
+            from: addresses(request.from).map_err(invalid)?,
+            to: addresses(request.to).map_err(invalid)?,
+        })
+    }
+}
+
+type MessageStream = Pin<Box<dyn Stream<Item = Result<proto::FeedMessage, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl proto::feed_server::Feed for GrpcFeedService {
+    type SubscribeStream = MessageStream;
+
+    async fn subscribe(
+        &self,
+        request: Request<proto::SubscribeRequest>,
+    ) -> Result<Response<MessageStream>, Status> {
+        let filter = SubscriptionFilter::try_from(request.into_inner())?;
+        let chain_id = self.chain_id;
+        let receiver = self.sender.subscribe();
+        debug!("gRPC subscriber connected with {:?}", filter);
+
+        let messages = stream::unfold(receiver, move |mut receiver| {
+            let filter = filter.clone();
+            async move {
+                loop {
+                    match receiver.recv().await {
+                        Ok(msg) if filter.matches(&msg) => {
+                            return Some((Ok(protobuf::feed_message(chain_id, &msg)), receiver))
+                        }
+                        Ok(_) => (),
+                        Err(RecvError::Lagged(skipped)) => {
+                            warn!(
+                                "gRPC subscriber lagging behind, skipped {} messages",
+                                skipped
+                            )
+                        }
+                        Err(RecvError::Closed) => return None,
+                    }
+                }
+            }
+        });
+        Ok(Response::new(Box::pin(messages)))
+    }
+}
+
+fn transactions(msg: &FeedMessage) -> Vec<&Transaction> {
+    match &msg.decoded {
+        Ok(Some(DecodedMsg::DecodedBatch(txs))) => txs.iter().collect(),
+        Ok(Some(DecodedMsg::DecodedSignedTx(tx))) => vec![&**tx],
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{proto::feed_client::FeedClient, *};
+    use crate::networks::arbitrum::fixtures::feed_message;
+    use futures::StreamExt;
+
+    #[tokio::test]
+    async fn streams_matching_messages() {
+        let service = GrpcFeedService::new(42161);
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(service.clone().serve_with_listener(listener));
+
+        let mut client = FeedClient::connect(format!("http://{}", addr))
+            .await
+            .unwrap();
+        let request = proto::SubscribeRequest {
+            from_sequence_number: 2,
+            ..Default::default()
+        };
+        let mut messages = client.subscribe(request).await.unwrap().into_inner();
+        assert_eq!(service.subscribers(), 1);
+
+        for seq in 0..4 {
+            service.publish(Arc::new(feed_message(seq, 0, vec![3], None)));
+        }
+        let first = messages.next().await.unwrap().unwrap();
+        assert_eq!((first.chain_id, first.sequence_number), (42161, 2));
+        assert_eq!(messages.next().await.unwrap().unwrap().sequence_number, 3);
+
+        let request = proto::SubscribeRequest {
+            to: vec![vec![0; 19]],
+            ..Default::default()
+        };
+        let status = client.subscribe(request).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+}
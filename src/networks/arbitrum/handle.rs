@@ -0,0 +1,125 @@
+use crate::networks::arbitrum::{
+    health::{ConnectionState, HealthTracker, RelayHealth},
+    identity::RelayInfo,
+    metrics::RelayMetrics,
+    status::RelayStatus,
+};
+use std::{path::PathBuf, sync::Arc};
+use tokio::sync::mpsc::UnboundedSender;
+
+/// Commands sent to a running `RelayClient` through its `RelayClientHandle`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ControlMessage {
+    /// Dumps the next `frames` raw frames to `path`, replacing any capture in progress.
+    StartCapture { path: PathBuf, frames: usize },
+    /// Stops the capture in progress, if any.
+    StopCapture,
+    /// Stops reading frames, closes the connection and ends `RelayClient::run`.
+    Shutdown,
+    /// Stops reading frames while keeping the connection open, until `Resume`.
+    Pause,
+    /// Reads frames again after `Pause`.
+    Resume,
+}
+
+/// A cloneable handle used to control a `RelayClient` while it runs.
+#[derive(Debug, Clone)]
+pub struct RelayClientHandle {
+    control: UnboundedSender<ControlMessage>,
+    metrics: Arc<RelayMetrics>,
+    health: Arc<HealthTracker>,
+    info: Arc<RelayInfo>,
+    chain_id: u64,
+}
+
+impl RelayClientHandle {
+    pub(crate) fn new(
+        control: UnboundedSender<ControlMessage>,
+        metrics: Arc<RelayMetrics>,
+        health: Arc<HealthTracker>,
+        info: Arc<RelayInfo>,
+        chain_id: u64,
+    ) -> Self {
+        Self {
+            control,
+            metrics,
+            health,
+            info,
+            chain_id,
+        }
+    }
+
+    /// Returns the identity of the relay the client reads from.
+    pub fn info(&self) -> &RelayInfo {
+        &self.info
+    }
+
+    /// Returns the counters of the client.
+    pub fn metrics(&self) -> &RelayMetrics {
+        &self.metrics
+    }
+
+    /// Returns the current health of the client.
+    pub fn health(&self) -> RelayHealth {
+        self.health.snapshot()
+    }
+
+    /// Returns the status of the client, combining its health and counters.
+    pub fn status(&self) -> RelayStatus {
+        RelayStatus {
+            relay_id: self.info.id,
+            url: self.info.url.clone(),
+            name: self.info.name.clone(),
+            region: self.info.region.clone(),
+            chain_id: self.chain_id,
+            health: self.health(),
+            metrics: self.metrics.snapshot(),
+        }
+    }
+
+    /// Sends a raw control message to the client.
+    ///
+    /// # Returns
+    ///
+    /// `false` if the client has stopped.
+    pub fn send(&self, msg: ControlMessage) -> bool {
+        self.control.send(msg).is_ok()
+    }
+
+    /// Starts capturing the next `frames` raw frames to `path`.
+    pub fn start_capture(&self, path: impl Into<PathBuf>, frames: usize) -> bool {
+        self.send(ControlMessage::StartCapture {
+            path: path.into(),
+            frames,
+        })
+    }
+
+    /// Stops the capture in progress.
+    pub fn stop_capture(&self) -> bool {
+        self.send(ControlMessage::StopCapture)
+    }
+
+    /// Stops reading frames until `resume` is called, e.g. during downstream maintenance.
+    ///
+    /// The connection stays open: unread frames are left to TCP backpressure, so the relay holds
+    /// them until the client resumes. A relay may drop a client paused for too long, since pings
+    /// aren't answered either.
+    pub fn pause(&self) -> bool {
+        self.send(ControlMessage::Pause)
+    }
+
+    /// Reads frames again after `pause`.
+    pub fn resume(&self) -> bool {
+        self.send(ControlMessage::Resume)
+    }
+
+    /// Returns `true` if the client is paused.
+    pub fn is_paused(&self) -> bool {
+        self.health().state == ConnectionState::Paused
+    }
+
+    /// Asks the client to stop accepting new frames and close its connection.
+    pub fn shutdown(&self) -> bool {
+        self.send(ControlMessage::Shutdown)
+    }
+}
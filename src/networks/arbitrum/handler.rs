@@ -0,0 +1,121 @@
+//! Feeds decoded messages to a `tower::Service`, so that handlers can be wrapped in ready-made
+//! tower layers such as rate limits, retries, timeouts or buffers.
+
+use crate::networks::arbitrum::message::FeedMessage;
+use crossbeam_channel::Receiver;
+use log::*;
+use std::thread;
+use tokio::{sync::mpsc, task::JoinHandle};
+use tower::{BoxError, Service, ServiceExt};
+
+/// How many messages may wait for the service before the feed blocks.
+const QUEUE_DEPTH: usize = 1024;
+
+/// Calls a `tower::Service<FeedMessage>` with every message of the feed, in sequence order.
+///
+/// The service is called once it is ready, with a single message in flight at a time: layers
+/// limiting the rate or the concurrency of the service hold the feed back, while a
+/// `tower::buffer` layer decouples them from it. Errors returned by the service are logged, and
+/// the message is skipped; wrap the service in a retry layer to retry it instead.
+///
+/// # Example
+///
+/// ```no_run
+/// use crossbeam_channel::Receiver;
+/// use sequencer_feed_reader::networks::arbitrum::{handler::ServiceDriver, message::FeedMessage};
+/// use std::convert::Infallible;
+/// use tower::{service_fn, ServiceBuilder};
+///
+/// # fn run(messages: Receiver<FeedMessage>) {
+/// let service = ServiceBuilder::new()
+///     .map_request(|msg: FeedMessage| msg.sequence_number())
+///     .service(service_fn(|seq: u64| async move {
+///         println!("{}", seq);
+///         Ok::<_, Infallible>(())
+///     }));
+/// ServiceDriver::new(service).spawn(messages);
+/// # }
+/// ```
+pub struct ServiceDriver<S> {
+    service: S,
+}
+
+impl<S> ServiceDriver<S>
+where
+    S: Service<FeedMessage> + Send + 'static,
+    S::Future: Send,
+    S::Error: Into<BoxError>,
+{
+    pub fn new(service: S) -> Self {
+        Self { service }
+    }
+
+    /// Calls the service with every message received on `input`, until `input` is disconnected
+    /// or the service fails to get ready. Must be called within a Tokio runtime.
+    pub fn spawn(mut self, input: Receiver<FeedMessage>) -> JoinHandle<()> {
+        let (queue, mut messages) = mpsc::channel(QUEUE_DEPTH);
+        thread::spawn(move || {
+            for msg in input {
+                if queue.blocking_send(msg).is_err() {
+                    return;
+                }
+            }
+        });
+
+        tokio::spawn(async move {
+            while let Some(msg) = messages.recv().await {
+                let sequence_number = msg.sequence_number();
+                let service = match self.service.ready().await {
+                    Ok(service) => service,
+                    Err(e) => {
+                        error!("Message handler failed: {}", e.into());
+                        return;
+                    }
+                };
+                if let Err(e) = service.call(msg).await {
+                    warn!(
+                        "Message handler failed on message {}: {}",
+                        sequence_number,
+                        e.into()
+                    );
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::networks::arbitrum::fixtures::feed_message;
+    use crossbeam_channel::unbounded;
+    use std::sync::{Arc, Mutex};
+    use tower::service_fn;
+
+    #[tokio::test]
+    async fn calls_the_service_in_order() {
+        let handled = Arc::new(Mutex::new(Vec::new()));
+        let service = service_fn({
+            let handled = handled.clone();
+            move |msg: FeedMessage| {
+                let handled = handled.clone();
+                async move {
+                    if msg.sequence_number() == 2 {
+                        return Err("rejected");
+                    }
+                    handled.lock().unwrap().push(msg.sequence_number());
+                    Ok(())
+                }
+            }
+        });
+
+        let (input, messages) = unbounded();
+        for seq in 0..4 {
+            input.send(feed_message(seq, 0, vec![], None)).unwrap();
+        }
+        drop(input);
+        ServiceDriver::new(service).spawn(messages).await.unwrap();
+
+        assert_eq!(*handled.lock().unwrap(), vec![0, 1, 3]);
+    }
+}
@@ -0,0 +1,145 @@
+use serde::Serialize;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The state of the websocket connection of a `RelayClient`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ConnectionState {
+    /// Connected and reading frames.
+    Connected,
+    /// A shutdown was requested and the connection is being closed.
+    Closing,
+    /// The connection is closed and the client stopped.
+    Closed,
+    /// Connected, but not reading frames until resumed.
+    Paused,
+}
+
+impl ConnectionState {
+    /// Returns `true` if the connection is open, whether frames are read or not.
+    pub fn is_connected(&self) -> bool {
+        matches!(self, ConnectionState::Connected | ConnectionState::Paused)
+    }
+}
+
+/// A point-in-time view of the health of a `RelayClient`, e.g. for readiness probes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RelayHealth {
+    pub state: ConnectionState,
+    /// When the last feed message was received, if any. Serialized as milliseconds since the
+    /// UNIX epoch.
+    #[serde(rename = "lastMessageAtMs", serialize_with = "serialize_unix_ms")]
+    pub last_message_at: Option<SystemTime>,
+    /// Feed messages received from the relay, including those later dropped by backpressure.
+    pub messages_received: u64,
+    /// The sequence number of the last feed message received, if any.
+    pub sequence_number: Option<u64>,
+}
+
+impl RelayHealth {
+    /// Returns `true` if the client is connected and received a message within `max_silence`.
+    pub fn is_ready(&self, max_silence: Duration) -> bool {
+        self.state == ConnectionState::Connected
+            && self
+                .last_message_at
+                .and_then(|at| at.elapsed().ok())
+                .is_some_and(|silence| silence <= max_silence)
+    }
+}
+
+fn serialize_unix_ms<S: serde::Serializer>(
+    at: &Option<SystemTime>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let ms = at
+        .and_then(|at| at.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as u64);
+    ms.serialize(serializer)
+}
+
+/// The health of a `RelayClient`, shared with its handles.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug)]
+pub(crate) struct HealthTracker {
+    state: AtomicU8,
+    /// Milliseconds since the UNIX epoch, 0 until the first message.
+    last_message_ms: AtomicU64,
+    messages_received: AtomicU64,
+    /// `u64::MAX` until the first message.
+    sequence_number: AtomicU64,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Default for HealthTracker {
+    fn default() -> Self {
+        Self {
+            state: AtomicU8::new(ConnectionState::Connected as u8),
+            last_message_ms: AtomicU64::new(0),
+            messages_received: AtomicU64::new(0),
+            sequence_number: AtomicU64::new(u64::MAX),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl HealthTracker {
+    pub(crate) fn set_state(&self, state: ConnectionState) {
+        self.state.store(state as u8, Ordering::Release);
+    }
+
+    /// Records the receipt of `count` messages, the last of which has `sequence_number`.
+    pub(crate) fn record(&self, count: u64, sequence_number: u64) {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        self.last_message_ms.store(now, Ordering::Relaxed);
+        self.messages_received.fetch_add(count, Ordering::Relaxed);
+        self.sequence_number
+            .store(sequence_number, Ordering::Release);
+    }
+
+    pub(crate) fn snapshot(&self) -> RelayHealth {
+        let state = match self.state.load(Ordering::Acquire) {
+            s if s == ConnectionState::Connected as u8 => ConnectionState::Connected,
+            s if s == ConnectionState::Closing as u8 => ConnectionState::Closing,
+            s if s == ConnectionState::Paused as u8 => ConnectionState::Paused,
+            _ => ConnectionState::Closed,
+        };
+        let last_message_ms = self.last_message_ms.load(Ordering::Relaxed);
+        let sequence_number = self.sequence_number.load(Ordering::Acquire);
+
+        RelayHealth {
+            state,
+            last_message_at: (last_message_ms > 0)
+                .then(|| UNIX_EPOCH + Duration::from_millis(last_message_ms)),
+            messages_received: self.messages_received.load(Ordering::Relaxed),
+            sequence_number: (sequence_number != u64::MAX).then_some(sequence_number),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tracks_messages_and_state() {
+        let tracker = HealthTracker::default();
+        let health = tracker.snapshot();
+        assert_eq!(health.sequence_number, None);
+        assert!(!health.is_ready(Duration::from_secs(60)));
+
+        tracker.record(3, 42);
+        let health = tracker.snapshot();
+        assert_eq!(health.messages_received, 3);
+        assert_eq!(health.sequence_number, Some(42));
+        assert!(health.is_ready(Duration::from_secs(60)));
+
+        tracker.set_state(ConnectionState::Closed);
+        assert!(!tracker.snapshot().is_ready(Duration::from_secs(60)));
+    }
+}
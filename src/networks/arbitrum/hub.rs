@@ -0,0 +1,187 @@
+use crate::networks::arbitrum::{config::FilterConfig, message::FeedMessage};
+use crossbeam_channel::{bounded, Receiver, Sender, TrySendError};
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+};
+
+/// How many messages a subscriber may lag behind by default.
+const DEFAULT_BUFFER: usize = 1024;
+
+struct Subscriber {
+    filter: FilterConfig,
+    sender: Sender<FeedMessage>,
+    missed: Arc<AtomicU64>,
+}
+
+/// Broadcasts feed messages to any number of independent subscribers within the application,
+/// each with its own filter.
+///
+/// A subscriber falling more than its buffer behind misses the messages that don't fit, so that
+/// a slow subscriber never holds the others back. Subscribers whose `Subscription` was dropped
+/// are removed on the next message matching their filter. Clones share the same subscribers.
+///
+/// # Example
+///
+/// ```no_run
+/// use crossbeam_channel::Receiver;
+/// use ethers::types::H160;
+/// use sequencer_feed_reader::networks::arbitrum::{
+///     config::FilterConfig, hub::FeedHub, message::FeedMessage,
+/// };
+///
+/// # fn run(messages: Receiver<FeedMessage>, router_address: H160) {
+/// let hub = FeedHub::new();
+/// let everything = hub.subscribe();
+/// let router = hub.subscribe_with(FilterConfig {
+///     to: vec![router_address],
+///     ..Default::default()
+/// });
+/// hub.clone().spawn(messages);
+/// for msg in router.receiver() {
+///     println!("{}", msg.sequence_number());
+/// }
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct FeedHub {
+    subscribers: Arc<Mutex<Vec<Subscriber>>>,
+    buffer: usize,
+}
+
+/// The receiving side of a `FeedHub` subscriber.
+#[derive(Debug)]
+pub struct Subscription {
+    receiver: Receiver<FeedMessage>,
+    missed: Arc<AtomicU64>,
+}
+
+impl FeedHub {
+    pub fn new() -> Self {
+        Self::with_buffer(DEFAULT_BUFFER)
+    }
+
+    /// Creates a hub letting subscribers lag up to `buffer` messages behind.
+    pub fn with_buffer(buffer: usize) -> Self {
+        Self {
+            subscribers: Arc::default(),
+            buffer: buffer.max(1),
+        }
+    }
+
+    /// Subscribes to every message published from now on.
+    pub fn subscribe(&self) -> Subscription {
+        self.subscribe_with(FilterConfig::default())
+    }
+
+    /// Subscribes to the messages matching `filter` published from now on.
+    pub fn subscribe_with(&self, filter: FilterConfig) -> Subscription {
+        let (sender, receiver) = bounded(self.buffer);
+        let missed = Arc::new(AtomicU64::new(0));
+        self.subscribers.lock().unwrap().push(Subscriber {
+            filter,
+            sender,
+            missed: missed.clone(),
+        });
+        Subscription { receiver, missed }
+    }
+
+    /// Returns the number of subscribers.
+    pub fn subscribers(&self) -> usize {
+        self.subscribers.lock().unwrap().len()
+    }
+
+    /// Sends `msg` to the matching subscribers. Dropped subscribers are removed once a message
+    /// matches their filter.
+    ///
+    /// # Returns
+    ///
+    /// The number of subscribers the message was sent to.
+    pub fn publish(&self, msg: &FeedMessage) -> usize {
+        let mut delivered = 0;
+        self.subscribers.lock().unwrap().retain(|subscriber| {
+            if !subscriber.filter.matches(msg) {
+                return true;
+            }
+            match subscriber.sender.try_send(msg.clone()) {
+                Ok(()) => {
+                    delivered += 1;
+                    true
+                }
+                Err(TrySendError::Full(_)) => {
+                    subscriber.missed.fetch_add(1, Ordering::Relaxed);
+                    true
+                }
+                Err(TrySendError::Disconnected(_)) => false,
+            }
+        });
+        delivered
+    }
+
+    /// Publishes the messages received on `input` on a new thread, until `input` is
+    /// disconnected.
+    pub fn spawn(self, input: Receiver<FeedMessage>) -> JoinHandle<()> {
+        thread::spawn(move || {
+            for msg in input {
+                self.publish(&msg);
+            }
+        })
+    }
+}
+
+impl Default for FeedHub {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Subscription {
+    /// Returns the channel the messages are received on.
+    pub fn receiver(&self) -> &Receiver<FeedMessage> {
+        &self.receiver
+    }
+
+    /// Returns how many messages were missed because the subscriber lagged behind.
+    pub fn missed(&self) -> u64 {
+        self.missed.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::networks::arbitrum::fixtures::feed_message_of_kind;
+
+    #[test]
+    fn broadcasts_to_independent_subscribers() {
+        let hub = FeedHub::with_buffer(2);
+        let everything = hub.subscribe();
+        let deposits = hub.subscribe_with(FilterConfig {
+            kinds: vec![12],
+            ..Default::default()
+        });
+        let dropped = hub.subscribe();
+        drop(dropped);
+
+        assert_eq!(hub.publish(&feed_message_of_kind(1, 3, vec![])), 1);
+        assert_eq!(hub.subscribers(), 2);
+        assert_eq!(hub.publish(&feed_message_of_kind(2, 12, vec![])), 2);
+        // The first subscriber lags behind and misses the next message.
+        assert_eq!(hub.publish(&feed_message_of_kind(3, 12, vec![])), 1);
+
+        let seqs = |subscription: &Subscription| {
+            subscription
+                .receiver()
+                .try_iter()
+                .map(|msg| msg.sequence_number())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(seqs(&everything), [1, 2]);
+        assert_eq!(everything.missed(), 1);
+        assert_eq!(seqs(&deposits), [2, 3]);
+        assert_eq!(deposits.missed(), 0);
+    }
+}
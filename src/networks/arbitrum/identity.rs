@@ -0,0 +1,108 @@
+use serde::Serialize;
+use std::fmt;
+use url::Url;
+
+/// Identifies a relay in logs, statuses and metrics, beyond the numeric ID of its client.
+///
+/// The name and region are set with `ConnectOptions::with_name` and
+/// `ConnectOptions::with_region`, so that deployments reading from several relays can tell
+/// which endpoint misbehaved from logs alone.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct RelayInfo {
+    /// The ID of the client reading from the relay.
+    pub id: u32,
+    /// A name given to the relay, e.g. `arb1-primary`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub url: String,
+    /// Where the relay is hosted, e.g. `eu-west`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+}
+
+impl RelayInfo {
+    pub fn new(id: u32, url: &Url) -> Self {
+        Self {
+            id,
+            name: None,
+            url: url.to_string(),
+            region: None,
+        }
+    }
+
+    /// A relay known only by the ID of its client, e.g. in messages replayed from an archive.
+    pub fn from_id(id: u32) -> Self {
+        Self {
+            id,
+            name: None,
+            url: String::new(),
+            region: None,
+        }
+    }
+
+    pub fn with_name(mut self, name: Option<String>) -> Self {
+        self.name = name;
+        self
+    }
+
+    pub fn with_region(mut self, region: Option<String>) -> Self {
+        self.region = region;
+        self
+    }
+
+    /// Returns the Prometheus labels of the relay: `relay_id`, followed by `relay_name` and
+    /// `region` when set.
+    pub fn prometheus_labels(&self) -> String {
+        let mut labels = format!("relay_id=\"{}\"", self.id);
+        for (label, value) in [("relay_name", &self.name), ("region", &self.region)] {
+            if let Some(value) = value {
+                labels.push_str(&format!(",{}=\"{}\"", label, escape_label(value)));
+            }
+        }
+        labels
+    }
+}
+
+/// Formats as the ID, followed by the name and region when set, e.g. `2 (arb1-backup, eu-west)`.
+impl fmt::Display for RelayInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.id)?;
+        match (&self.name, &self.region) {
+            (Some(name), Some(region)) => write!(f, " ({}, {})", name, region),
+            (Some(label), None) | (None, Some(label)) => write!(f, " ({})", label),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+/// Escapes a Prometheus label value.
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn labels_relays() {
+        let url = Url::parse("wss://arb1.arbitrum.io/feed").unwrap();
+        let info = RelayInfo::new(2, &url);
+        assert_eq!(info.to_string(), "2");
+        assert_eq!(info.prometheus_labels(), "relay_id=\"2\"");
+
+        let info = info
+            .with_name(Some("arb1 \"backup\"".into()))
+            .with_region(Some("eu-west".into()));
+        assert_eq!(info.to_string(), "2 (arb1 \"backup\", eu-west)");
+        assert_eq!(
+            info.prometheus_labels(),
+            "relay_id=\"2\",relay_name=\"arb1 \\\"backup\\\"\",region=\"eu-west\""
+        );
+    }
+}
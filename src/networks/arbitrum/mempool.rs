@@ -0,0 +1,464 @@
+//! A JSON-RPC server exposing the transactions of the feed as pending transactions, so that
+//! tooling expecting a mempool subscription can consume the feed unchanged.
+//!
+//! Arbitrum has no public mempool: the sequencer orders transactions first-come first-served and
+//! broadcasts them on the feed before they are executed, which makes the feed the closest thing to
+//! one. Supported methods:
+//!
+//! * Over websockets, `eth_subscribe("newPendingTransactions", [full])` and `eth_unsubscribe`.
+//!   Notifications carry the transaction hash, or the whole transaction if `full` is `true`.
+//! * Over HTTP `POST`, `eth_newPendingTransactionFilter`, `eth_getFilterChanges` and
+//!   `eth_uninstallFilter`.
+//! * Over both, `eth_chainId` and `net_version`.
+
+use crate::networks::arbitrum::{decoder::DecodedMsg, message::FeedMessage, server};
+use ethers::types::{Transaction, H256};
+use futures::{SinkExt, StreamExt};
+use hyper::{
+    body::HttpBody,
+    header::{HeaderValue, CONNECTION, SEC_WEBSOCKET_ACCEPT, SEC_WEBSOCKET_KEY, UPGRADE},
+    upgrade::Upgraded,
+    Body, Method, Request, Response, StatusCode,
+};
+use log::*;
+use serde_json::{json, Value};
+use std::{
+    collections::{HashMap, VecDeque},
+    io,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+use tokio::{
+    net::TcpListener,
+    sync::broadcast::{self, error::RecvError},
+    task::JoinHandle,
+};
+use tokio_tungstenite::{
+    tungstenite::{handshake::derive_accept_key, protocol::Role, Message},
+    WebSocketStream,
+};
+
+/// How many transactions a websocket subscriber may lag behind before it misses some.
+const SUBSCRIBER_BUFFER: usize = 16 * 1024;
+/// How many transaction hashes a filter keeps between two polls.
+const MAX_FILTER_CHANGES: usize = 10_000;
+/// How long a filter lives without being polled, like in geth.
+const FILTER_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+/// The largest HTTP request accepted.
+const MAX_REQUEST_SIZE: usize = 1024 * 1024;
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// Serves the transactions published through its `PendingTxPublisher`s over JSON-RPC, on a single
+/// port accepting both websocket and HTTP clients.
+pub struct PendingTxServer {
+    listener: TcpListener,
+    state: Arc<State>,
+}
+
+/// Publishes the transactions of feed messages to the clients of a `PendingTxServer`.
+#[derive(Clone)]
+pub struct PendingTxPublisher {
+    state: Arc<State>,
+}
+
+struct State {
+    chain_id: u64,
+    sender: broadcast::Sender<Arc<Transaction>>,
+    filters: Mutex<HashMap<u64, PendingFilter>>,
+    next_id: AtomicU64,
+}
+
+/// A filter installed by `eth_newPendingTransactionFilter`.
+struct PendingFilter {
+    hashes: VecDeque<H256>,
+    last_poll: Instant,
+}
+
+impl PendingTxServer {
+    /// Binds the server of the chain `chain_id` to `addr`, e.g. `127.0.0.1:8546`.
+    pub async fn bind(addr: &str, chain_id: u64) -> io::Result<Self> {
+        let (sender, _) = broadcast::channel(SUBSCRIBER_BUFFER);
+        Ok(Self {
+            listener: TcpListener::bind(addr).await?,
+            state: Arc::new(State {
+                chain_id,
+                sender,
+                filters: Mutex::default(),
+                next_id: AtomicU64::new(1),
+            }),
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Returns a publisher of transactions to the clients of the server.
+    pub fn publisher(&self) -> PendingTxPublisher {
+        PendingTxPublisher {
+            state: self.state.clone(),
+        }
+    }
+
+    /// Serves clients until the task is aborted.
+    pub fn spawn(self) -> JoinHandle<()> {
+        let state = self.state;
+        server::serve_http(self.listener, "JSON-RPC", move |request| {
+            let state = state.clone();
+            async move { state.serve(request).await }
+        })
+    }
+}
+
+impl PendingTxPublisher {
+    /// Publishes the transactions of a feed message, in order.
+    pub fn publish(&self, msg: &FeedMessage) {
+        let txs = match &msg.decoded {
+            Ok(Some(DecodedMsg::DecodedBatch(txs))) => txs.iter().collect(),
+            Ok(Some(DecodedMsg::DecodedSignedTx(tx))) => vec![&**tx],
+            _ => Vec::new(),
+        };
+        if txs.is_empty() {
+            return;
+        }
+
+        let mut filters = self.state.filters.lock().unwrap();
+        filters.retain(|_, filter| filter.last_poll.elapsed() < FILTER_TIMEOUT);
+        for tx in txs {
+            for filter in filters.values_mut() {
+                if filter.hashes.len() >= MAX_FILTER_CHANGES {
+                    filter.hashes.pop_front();
+                }
+                filter.hashes.push_back(tx.hash);
+            }
+            // Sending only fails while nobody is subscribed.
+            let _ = self.state.sender.send(Arc::new(tx.clone()));
+        }
+    }
+}
+
+/// The subscriptions of a websocket connection, by ID, and whether they want full transactions.
+type Subscriptions = HashMap<String, bool>;
+
+impl State {
+    async fn serve(self: Arc<Self>, mut request: Request<Body>) -> Response<Body> {
+        // Websocket handshakes are upgraded GET requests, JSON-RPC calls over HTTP are POST
+        // requests.
+        if let Some(key) = request
+            .headers()
+            .get(UPGRADE)
+            .filter(|upgrade| upgrade.as_bytes().eq_ignore_ascii_case(b"websocket"))
+            .and(request.headers().get(SEC_WEBSOCKET_KEY))
+        {
+            let accept = derive_accept_key(key.as_bytes());
+            let upgrade = hyper::upgrade::on(&mut request);
+            tokio::spawn(async move {
+                let socket = match upgrade.await {
+                    Ok(upgraded) => WebSocketStream::from_raw_socket(upgraded, Role::Server, None),
+                    Err(e) => return debug!("JSON-RPC websocket upgrade failed: {}", e),
+                };
+                if let Err(e) = self.serve_websocket(socket.await).await {
+                    debug!("JSON-RPC websocket connection failed: {}", e);
+                }
+            });
+            let mut response = Response::new(Body::empty());
+            *response.status_mut() = StatusCode::SWITCHING_PROTOCOLS;
+            let headers = response.headers_mut();
+            headers.insert(CONNECTION, HeaderValue::from_static("upgrade"));
+            headers.insert(UPGRADE, HeaderValue::from_static("websocket"));
+            // The accept key is base64, always a valid header value.
+            headers.insert(SEC_WEBSOCKET_ACCEPT, accept.parse().unwrap());
+            return response;
+        }
+        if request.method() != Method::POST {
+            return server::error(405, "only POST and websocket requests are supported");
+        }
+
+        let body = match server::with_read_timeout(read_body(request.into_body())).await {
+            Ok(Some(body)) => body,
+            Ok(None) => return server::error(413, "request too large"),
+            Err(e) => return server::error(400, e.to_string()),
+        };
+        let response = match serde_json::from_slice::<Value>(&body) {
+            Ok(request) => self.handle_batch(request, None),
+            Err(e) => Some(error(Value::Null, PARSE_ERROR, e.to_string())),
+        };
+        match response {
+            Some(response) => server::json(200, &response),
+            // Notifications get an empty response.
+            None => Response::new(Body::empty()),
+        }
+    }
+
+    async fn serve_websocket(
+        &self,
+        mut socket: WebSocketStream<Upgraded>,
+    ) -> Result<(), tokio_tungstenite::tungstenite::Error> {
+        let mut subscriptions = Subscriptions::new();
+        let mut transactions = self.sender.subscribe();
+
+        loop {
+            tokio::select! {
+                message = socket.next() => {
+                    let text = match message {
+                        Some(Ok(Message::Text(text))) => text,
+                        Some(Ok(Message::Close(_))) | None => return Ok(()),
+                        Some(Ok(_)) => continue,
+                        Some(Err(e)) => return Err(e),
+                    };
+                    let response = match serde_json::from_str::<Value>(&text) {
+                        Ok(request) => self.handle_batch(request, Some(&mut subscriptions)),
+                        Err(e) => Some(error(Value::Null, PARSE_ERROR, e.to_string())),
+                    };
+                    if let Some(response) = response {
+                        socket.send(Message::Text(response.to_string())).await?;
+                    }
+                }
+                tx = transactions.recv(), if !subscriptions.is_empty() => match tx {
+                    Ok(tx) => {
+                        for (id, full) in &subscriptions {
+                            let result = if *full { json!(*tx) } else { json!(tx.hash) };
+                            let notification = json!({
+                                "jsonrpc": "2.0",
+                                "method": "eth_subscription",
+                                "params": { "subscription": id, "result": result },
+                            });
+                            socket.send(Message::Text(notification.to_string())).await?;
+                        }
+                    }
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!("JSON-RPC subscriber lagging behind, skipped {} transactions", skipped)
+                    }
+                    Err(RecvError::Closed) => return Ok(()),
+                },
+            }
+        }
+    }
+
+    /// Handles a request or a batch of requests, returning `None` if only notifications were
+    /// sent.
+    fn handle_batch(&self, request: Value, mut ws: Option<&mut Subscriptions>) -> Option<Value> {
+        match request {
+            Value::Array(requests) if requests.is_empty() => Some(error(
+                Value::Null,
+                INVALID_REQUEST,
+                "empty batch".to_string(),
+            )),
+            Value::Array(requests) => {
+                let responses: Vec<_> = requests
+                    .into_iter()
+                    .filter_map(|request| self.handle(request, ws.as_deref_mut()))
+                    .collect();
+                (!responses.is_empty()).then_some(Value::Array(responses))
+            }
+            request => self.handle(request, ws),
+        }
+    }
+
+    fn handle(&self, request: Value, ws: Option<&mut Subscriptions>) -> Option<Value> {
+        let id = request.get("id").cloned();
+        let Some(method) = request.get("method").and_then(Value::as_str) else {
+            return Some(error(
+                id.unwrap_or_default(),
+                INVALID_REQUEST,
+                "missing method".to_string(),
+            ));
+        };
+        let params = request.get("params").cloned().unwrap_or(json!([]));
+
+        let result = match (method, ws) {
+            ("eth_chainId", _) => Ok(json!(format!("{:#x}", self.chain_id))),
+            ("net_version", _) => Ok(json!(self.chain_id.to_string())),
+            ("eth_subscribe", Some(subscriptions)) => match params[0].as_str() {
+                Some("newPendingTransactions") => {
+                    let id = format!("{:#x}", self.next_id.fetch_add(1, Ordering::Relaxed));
+                    subscriptions.insert(id.clone(), params[1].as_bool().unwrap_or(false));
+                    Ok(json!(id))
+                }
+                _ => Err((INVALID_PARAMS, "unsupported subscription".to_string())),
+            },
+            ("eth_unsubscribe", Some(subscriptions)) => match params[0].as_str() {
+                Some(id) => Ok(json!(subscriptions.remove(id).is_some())),
+                None => Err((INVALID_PARAMS, "missing subscription ID".to_string())),
+            },
+            ("eth_newPendingTransactionFilter", _) => {
+                let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+                self.filters.lock().unwrap().insert(
+                    id,
+                    PendingFilter {
+                        hashes: VecDeque::new(),
+                        last_poll: Instant::now(),
+                    },
+                );
+                Ok(json!(format!("{:#x}", id)))
+            }
+            ("eth_getFilterChanges", _) => {
+                let mut filters = self.filters.lock().unwrap();
+                match filter_id(&params).and_then(|id| filters.get_mut(&id)) {
+                    Some(filter) => {
+                        filter.last_poll = Instant::now();
+                        Ok(json!(std::mem::take(&mut filter.hashes)))
+                    }
+                    None => Err((INVALID_PARAMS, "filter not found".to_string())),
+                }
+            }
+            ("eth_uninstallFilter", _) => {
+                let removed = filter_id(&params)
+                    .and_then(|id| self.filters.lock().unwrap().remove(&id))
+                    .is_some();
+                Ok(json!(removed))
+            }
+            (method, _) => Err((
+                METHOD_NOT_FOUND,
+                format!("the method {} does not exist/is not available", method),
+            )),
+        };
+
+        // Requests without an ID are notifications, which get no response.
+        let id = id?;
+        Some(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => error(id, code, message),
+        })
+    }
+}
+
+/// Reads the body of an HTTP request.
+///
+/// # Returns
+///
+/// The body, or `None` if it exceeds the size limit.
+async fn read_body(mut body: Body) -> io::Result<Option<Vec<u8>>> {
+    let mut bytes = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.map_err(io::Error::other)?;
+        if bytes.len() + chunk.len() > MAX_REQUEST_SIZE {
+            return Ok(None);
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(Some(bytes))
+}
+
+fn filter_id(params: &Value) -> Option<u64> {
+    let id = params[0].as_str()?;
+    u64::from_str_radix(id.strip_prefix("0x")?, 16).ok()
+}
+
+fn error(id: Value, code: i64, message: String) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::networks::arbitrum::fixtures::feed_message;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+    };
+    use tokio_tungstenite::MaybeTlsStream;
+
+    type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+    async fn call(socket: &mut Socket, request: Value) -> Value {
+        socket
+            .send(Message::Text(request.to_string()))
+            .await
+            .unwrap();
+        let Some(Ok(Message::Text(text))) = socket.next().await else {
+            panic!("connection closed");
+        };
+        serde_json::from_str(&text).unwrap()
+    }
+
+    async fn post(addr: SocketAddr, request: Value) -> Value {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let body = request.to_string();
+        let request = format!(
+            "POST / HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        serde_json::from_str(response.split_once("\r\n\r\n").unwrap().1).unwrap()
+    }
+
+    #[tokio::test]
+    async fn streams_pending_transactions() {
+        let server = PendingTxServer::bind("127.0.0.1:0", 42161).await.unwrap();
+        let addr = server.local_addr().unwrap();
+        let publisher = server.publisher();
+        server.spawn();
+
+        let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://{}", addr))
+            .await
+            .unwrap();
+        let response = call(
+            &mut socket,
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "eth_chainId" }),
+        )
+        .await;
+        assert_eq!(response["result"], "0xa4b1");
+        let response = call(
+            &mut socket,
+            json!({
+                "jsonrpc": "2.0",
+                "id": 2,
+                "method": "eth_subscribe",
+                "params": ["newPendingTransactions"],
+            }),
+        )
+        .await;
+        let subscription = response["result"].clone();
+        let filter = post(
+            addr,
+            json!({ "jsonrpc": "2.0", "id": 3, "method": "eth_newPendingTransactionFilter" }),
+        )
+        .await["result"]
+            .clone();
+
+        let tx = Transaction {
+            hash: H256::repeat_byte(7),
+            ..Default::default()
+        };
+        publisher.publish(&feed_message(
+            1,
+            0,
+            Vec::new(),
+            Some(DecodedMsg::DecodedBatch(vec![tx])),
+        ));
+        let Some(Ok(Message::Text(text))) = socket.next().await else {
+            panic!("connection closed");
+        };
+        let notification: Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(notification["method"], "eth_subscription");
+        assert_eq!(notification["params"]["subscription"], subscription);
+        assert_eq!(
+            notification["params"]["result"],
+            json!(H256::repeat_byte(7))
+        );
+
+        let changes = json!({
+            "jsonrpc": "2.0",
+            "id": 4,
+            "method": "eth_getFilterChanges",
+            "params": [filter],
+        });
+        assert_eq!(
+            post(addr, changes.clone()).await["result"],
+            json!([H256::repeat_byte(7)])
+        );
+        assert_eq!(post(addr, changes).await["result"], json!([]));
+    }
+}
@@ -0,0 +1,127 @@
+use crate::networks::arbitrum::{
+    decoder::DecodedMsg,
+    errors::{DecodeError, FrameError},
+    provenance::Provenance,
+    types::{BroadcastFeedMessage, Header, Root},
+};
+use ethers::types::{Transaction, H160};
+
+/// A feed message together with the result of decoding its L2 message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeedMessage {
+    pub message: BroadcastFeedMessage,
+    /// `Ok(None)` if the message is of a kind the decoder doesn't support.
+    pub decoded: Result<Option<DecodedMsg>, DecodeError>,
+    pub provenance: Provenance,
+}
+
+impl FeedMessage {
+    pub fn sequence_number(&self) -> u64 {
+        self.message.sequence_number
+    }
+
+    /// Returns the decoded transactions of the message, each with the context of the message.
+    pub fn transactions(&self) -> Vec<FeedTransaction> {
+        let txs = match &self.decoded {
+            Ok(Some(DecodedMsg::DecodedBatch(txs))) => txs.iter().collect(),
+            Ok(Some(DecodedMsg::DecodedSignedTx(tx))) => vec![tx.as_ref()],
+            _ => Vec::new(),
+        };
+        txs.into_iter()
+            .enumerate()
+            .map(|(index, tx)| FeedTransaction {
+                tx: tx.clone(),
+                sequence_number: self.sequence_number(),
+                index,
+                header: self.message.message.message.header.clone(),
+                received_at_ms: self.provenance.received_at_ms,
+                degraded: self.provenance.degraded,
+            })
+            .collect()
+    }
+}
+
+/// A transaction of a feed message, together with the context it was sequenced in, so that
+/// consumers don't have to zip transactions back with their message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeedTransaction {
+    pub tx: Transaction,
+    /// The sequence number of the message holding the transaction.
+    pub sequence_number: u64,
+    /// The position of the transaction in its message.
+    pub index: usize,
+    /// The L1 header of the message: L1 block number, timestamp and sender.
+    pub header: Header,
+    /// When the message was received, in milliseconds since the UNIX epoch, 0 if unknown.
+    pub received_at_ms: u64,
+    /// `true` if the sender of the transaction wasn't recovered, see `Provenance::degraded`.
+    pub degraded: bool,
+}
+
+impl FeedTransaction {
+    /// Returns the sender of the transaction, `None` if it wasn't recovered.
+    pub fn sender(&self) -> Option<H160> {
+        (!self.degraded).then_some(self.tx.from)
+    }
+}
+
+/// A websocket frame exactly as received from a relay, for archiving frames or parsing them
+/// differently when the typed path fails.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawFrame {
+    pub relay_id: u32,
+    /// When the frame was received, in milliseconds since the UNIX epoch, as in the
+    /// `Provenance` of its `root`.
+    pub received_at_ms: u64,
+    /// The payload of the frame, before any parsing.
+    pub payload: Vec<u8>,
+    /// The frame parsed into a `Root`, with every message it holds, or `None` if it could not be
+    /// parsed.
+    pub root: Option<Root>,
+}
+
+/// A websocket frame from a relay that could not be parsed, e.g. because the relay changed its
+/// schema.
+#[derive(Debug)]
+pub struct MalformedFrame {
+    pub relay_id: u32,
+    /// When the frame was received, in milliseconds since the UNIX epoch.
+    pub received_at_ms: u64,
+    /// The payload of the frame.
+    pub payload: Vec<u8>,
+    pub error: FrameError,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::networks::arbitrum::fixtures::feed_message;
+    use ethers::types::H256;
+
+    #[test]
+    fn transactions_carry_their_message_context() {
+        let txs = (1..=2)
+            .map(|hash| Transaction {
+                hash: H256::from_low_u64_be(hash),
+                ..Default::default()
+            })
+            .collect();
+        let msg = FeedMessage {
+            provenance: Provenance::live(0, 0).with_received_at_ms(1_700_000_000_250),
+            ..feed_message(
+                7,
+                1_700_000_000,
+                Vec::new(),
+                Some(DecodedMsg::DecodedBatch(txs)),
+            )
+        };
+
+        let txs = msg.transactions();
+        assert_eq!(txs.len(), 2);
+        assert_eq!(txs[1].tx.hash, H256::from_low_u64_be(2));
+        assert_eq!(txs[1].index, 1);
+        assert!(txs.iter().all(|tx| tx.sequence_number == 7
+            && tx.header.timestamp == 1_700_000_000
+            && tx.received_at_ms == 1_700_000_000_250));
+    }
+}
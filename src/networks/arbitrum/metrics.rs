@@ -0,0 +1,141 @@
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// The prefix of every Prometheus metric exported by the crate.
+pub const NAMESPACE: &str = "sequencer_feed";
+
+/// The type of a Prometheus metric.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MetricKind {
+    Counter,
+    Gauge,
+}
+
+impl MetricKind {
+    /// The name of the type in the Prometheus exposition format.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MetricKind::Counter => "counter",
+            MetricKind::Gauge => "gauge",
+        }
+    }
+}
+
+/// Describes a metric exported by the crate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct MetricDescriptor {
+    /// The name of the metric, without `NAMESPACE`.
+    pub name: &'static str,
+    pub help: &'static str,
+    pub kind: MetricKind,
+}
+
+impl MetricDescriptor {
+    /// The full name of the metric, prefixed with `NAMESPACE`.
+    pub fn full_name(&self) -> String {
+        format!("{}_{}", NAMESPACE, self.name)
+    }
+}
+
+pub const FRAMES_RECEIVED: MetricDescriptor = MetricDescriptor {
+    name: "frames_received_total",
+    help: "Websocket frames received from the relay.",
+    kind: MetricKind::Counter,
+};
+pub const FRAMES_MALFORMED: MetricDescriptor = MetricDescriptor {
+    name: "frames_malformed_total",
+    help: "Websocket frames from the relay that could not be parsed.",
+    kind: MetricKind::Counter,
+};
+pub const MESSAGES_RECEIVED: MetricDescriptor = MetricDescriptor {
+    name: "messages_received_total",
+    help: "Feed messages received from the relay.",
+    kind: MetricKind::Counter,
+};
+pub const MESSAGES_FORWARDED: MetricDescriptor = MetricDescriptor {
+    name: "messages_forwarded_total",
+    help: "Feed messages forwarded to the consumer.",
+    kind: MetricKind::Counter,
+};
+pub const DROPPED_OLDEST: MetricDescriptor = MetricDescriptor {
+    name: "dropped_oldest_total",
+    help: "Queued messages dropped to make room for newer ones.",
+    kind: MetricKind::Counter,
+};
+pub const DROPPED_NEWEST: MetricDescriptor = MetricDescriptor {
+    name: "dropped_newest_total",
+    help: "Received messages dropped because the consumer queue was full.",
+    kind: MetricKind::Counter,
+};
+pub const SEQUENCE_NUMBER: MetricDescriptor = MetricDescriptor {
+    name: "sequence_number",
+    help: "Sequence number of the last feed message received.",
+    kind: MetricKind::Gauge,
+};
+pub const LAST_MESSAGE_TIMESTAMP: MetricDescriptor = MetricDescriptor {
+    name: "last_message_timestamp_seconds",
+    help: "UNIX time at which the last feed message was received.",
+    kind: MetricKind::Gauge,
+};
+pub const CONNECTED: MetricDescriptor = MetricDescriptor {
+    name: "connected",
+    help: "1 if the client is connected to the relay, 0 otherwise.",
+    kind: MetricKind::Gauge,
+};
+
+/// Every metric exported per relay, labelled with `relay_id`.
+pub const RELAY_METRICS: &[MetricDescriptor] = &[
+    FRAMES_RECEIVED,
+    FRAMES_MALFORMED,
+    MESSAGES_RECEIVED,
+    MESSAGES_FORWARDED,
+    DROPPED_OLDEST,
+    DROPPED_NEWEST,
+    SEQUENCE_NUMBER,
+    LAST_MESSAGE_TIMESTAMP,
+    CONNECTED,
+];
+
+/// Counters describing the activity of a `RelayClient`.
+#[derive(Debug, Default)]
+pub struct RelayMetrics {
+    /// Websocket frames received from the relay.
+    pub frames_received: AtomicU64,
+    /// Websocket frames from the relay that could not be parsed.
+    pub frames_malformed: AtomicU64,
+    /// Feed messages forwarded to the consumer.
+    pub messages_forwarded: AtomicU64,
+    /// Queued messages dropped to make room for newer ones.
+    pub dropped_oldest: AtomicU64,
+    /// Received messages dropped because the consumer queue was full.
+    pub dropped_newest: AtomicU64,
+}
+
+/// A point-in-time copy of `RelayMetrics`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RelayMetricsSnapshot {
+    pub frames_received: u64,
+    pub frames_malformed: u64,
+    pub messages_forwarded: u64,
+    pub dropped_oldest: u64,
+    pub dropped_newest: u64,
+}
+
+impl RelayMetrics {
+    pub fn snapshot(&self) -> RelayMetricsSnapshot {
+        RelayMetricsSnapshot {
+            frames_received: self.frames_received.load(Ordering::Relaxed),
+            frames_malformed: self.frames_malformed.load(Ordering::Relaxed),
+            messages_forwarded: self.messages_forwarded.load(Ordering::Relaxed),
+            dropped_oldest: self.dropped_oldest.load(Ordering::Relaxed),
+            dropped_newest: self.dropped_newest.load(Ordering::Relaxed),
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn incr(counter: &AtomicU64, by: u64) {
+        counter.fetch_add(by, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,205 @@
+//! Composable stages processing feed messages, as an alternative to wiring fixed stages together
+//! with channels.
+
+use crate::networks::arbitrum::{
+    config::FilterConfig, dedup::Deduplicator, message::FeedMessage, router::Router,
+};
+use crossbeam_channel::{Receiver, Sender};
+use log::*;
+use std::thread::{self, JoinHandle};
+
+/// A stage of a `MiddlewarePipeline`, such as deduplication, filtering, enrichment or recording.
+///
+/// A stage passes messages on to the rest of the pipeline with `next`: it may pass them
+/// unchanged, modified, split in several messages, or not at all to drop them.
+///
+/// Closures taking a message and the `Next` stages are middlewares too.
+pub trait FeedMiddleware: Send {
+    /// Processes `msg`.
+    ///
+    /// # Returns
+    ///
+    /// `false` once the pipeline must stop, typically because `next.run` returned `false`.
+    fn handle(&mut self, msg: FeedMessage, next: Next<'_>) -> bool;
+}
+
+/// The stages following a middleware in its pipeline.
+pub struct Next<'a> {
+    stages: &'a mut [Box<dyn FeedMiddleware>],
+    endpoint: &'a mut dyn FnMut(FeedMessage) -> bool,
+}
+
+impl Next<'_> {
+    /// Passes `msg` on to the next stage, or to the end of the pipeline.
+    ///
+    /// # Returns
+    ///
+    /// `false` once the pipeline must stop.
+    pub fn run(&mut self, msg: FeedMessage) -> bool {
+        match self.stages.split_first_mut() {
+            Some((stage, stages)) => stage.handle(
+                msg,
+                Next {
+                    stages,
+                    endpoint: &mut *self.endpoint,
+                },
+            ),
+            None => (self.endpoint)(msg),
+        }
+    }
+}
+
+impl<F> FeedMiddleware for F
+where
+    F: FnMut(FeedMessage, Next<'_>) -> bool + Send,
+{
+    fn handle(&mut self, msg: FeedMessage, next: Next<'_>) -> bool {
+        self(msg, next)
+    }
+}
+
+/// Marks duplicate messages, see `Provenance::duplicate`.
+impl FeedMiddleware for Deduplicator {
+    fn handle(&mut self, msg: FeedMessage, mut next: Next<'_>) -> bool {
+        next.run(self.mark(msg))
+    }
+}
+
+/// Drops the messages not matching the filter.
+impl FeedMiddleware for FilterConfig {
+    fn handle(&mut self, msg: FeedMessage, mut next: Next<'_>) -> bool {
+        !self.matches(&msg) || next.run(msg)
+    }
+}
+
+/// Routes messages by kind. The end of a pipeline: no message is passed on.
+impl FeedMiddleware for Router {
+    fn handle(&mut self, msg: FeedMessage, _next: Next<'_>) -> bool {
+        self.dispatch(msg);
+        true
+    }
+}
+
+/// Forwards a copy of every message to the channel, and passes it on. Stops the pipeline once
+/// the receiving side of the channel is dropped.
+impl FeedMiddleware for Sender<FeedMessage> {
+    fn handle(&mut self, msg: FeedMessage, mut next: Next<'_>) -> bool {
+        self.send(msg.clone()).is_ok() && next.run(msg)
+    }
+}
+
+/// Runs feed messages through a sequence of `FeedMiddleware` stages, in the order they were
+/// added.
+///
+/// # Example
+///
+/// ```
+/// use sequencer_feed_reader::networks::arbitrum::{
+///     dedup::Deduplicator,
+///     message::FeedMessage,
+///     middleware::{MiddlewarePipeline, Next},
+/// };
+///
+/// let pipeline = MiddlewarePipeline::new()
+///     .layer(Deduplicator::default())
+///     .layer(|msg: FeedMessage, mut next: Next<'_>| {
+///         msg.provenance.duplicate || msg.transactions().is_empty() || next.run(msg)
+///     });
+/// ```
+#[derive(Default)]
+pub struct MiddlewarePipeline {
+    stages: Vec<Box<dyn FeedMiddleware>>,
+}
+
+impl MiddlewarePipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `stage` at the end of the pipeline.
+    pub fn layer(mut self, stage: impl FeedMiddleware + 'static) -> Self {
+        self.stages.push(Box::new(stage));
+        self
+    }
+
+    /// Runs `msg` through the stages, then passes what they let through to `endpoint`.
+    ///
+    /// # Returns
+    ///
+    /// `false` once the pipeline must stop.
+    pub fn process(
+        &mut self,
+        msg: FeedMessage,
+        endpoint: &mut dyn FnMut(FeedMessage) -> bool,
+    ) -> bool {
+        Next {
+            stages: &mut self.stages,
+            endpoint,
+        }
+        .run(msg)
+    }
+
+    /// Runs the messages received on `input` through the stages on a new thread, sending what
+    /// they let through to `output`, until either side is disconnected or a stage stops the
+    /// pipeline.
+    pub fn spawn(
+        mut self,
+        input: Receiver<FeedMessage>,
+        output: Sender<FeedMessage>,
+    ) -> JoinHandle<()> {
+        thread::spawn(move || {
+            let mut send = |msg| output.send(msg).is_ok();
+            for msg in input {
+                if !self.process(msg, &mut send) {
+                    debug!("Middleware pipeline stopped");
+                    break;
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::networks::arbitrum::fixtures::feed_message;
+    use crossbeam_channel::unbounded;
+
+    #[test]
+    fn runs_messages_through_stages_in_order() {
+        let (recorded_tx, recorded) = unbounded();
+        let mut pipeline = MiddlewarePipeline::new()
+            .layer(Deduplicator::new(16))
+            .layer(|msg: FeedMessage, mut next: Next<'_>| msg.provenance.duplicate || next.run(msg))
+            .layer(recorded_tx)
+            .layer(|msg: FeedMessage, mut next: Next<'_>| {
+                msg.sequence_number() % 2 == 1 || next.run(msg)
+            })
+            .layer(|mut msg: FeedMessage, mut next: Next<'_>| {
+                msg.message.sequence_number *= 10;
+                next.run(msg)
+            });
+
+        let mut output = Vec::new();
+        for seq in [1, 2, 2, 3, 4] {
+            assert!(
+                pipeline.process(feed_message(seq, 0, vec![], None), &mut |msg| {
+                    output.push(msg.sequence_number());
+                    true
+                })
+            );
+        }
+
+        assert_eq!(output, vec![20, 40]);
+        assert_eq!(
+            recorded
+                .try_iter()
+                .map(|m| m.sequence_number())
+                .collect::<Vec<_>>(),
+            vec![1, 2, 3, 4]
+        );
+        // The recording stage stops the pipeline once its channel is dropped.
+        drop(recorded);
+        assert!(!pipeline.process(feed_message(6, 0, vec![], None), &mut |_| true));
+    }
+}
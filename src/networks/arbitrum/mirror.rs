@@ -0,0 +1,299 @@
+//! A websocket server re-broadcasting the feed in the native Arbitrum format, so that the crate
+//! can act as a lightweight relay for other feed clients, including Nitro nodes.
+
+use crate::networks::arbitrum::{
+    server,
+    types::{BroadcastFeedMessage, Root},
+};
+use futures::{SinkExt, StreamExt};
+use log::*;
+use std::{
+    collections::VecDeque,
+    io,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+use tokio::{
+    net::{TcpListener, TcpStream},
+    sync::broadcast::{self, error::RecvError},
+    task::JoinHandle,
+};
+use tokio_tungstenite::tungstenite::{
+    handshake::server::{ErrorResponse, Request, Response},
+    http::{HeaderValue, StatusCode},
+    Message,
+};
+
+/// How many recent messages are kept by default, for clients resuming from a sequence number.
+const DEFAULT_BACKLOG: usize = 1000;
+/// How many frames a client may lag behind before it is disconnected.
+const CLIENT_BUFFER: usize = 4096;
+const BROADCAST_VERSION: u8 = 1;
+
+/// A serialized frame, with the sequence number of its last message.
+type Frame = (u64, Arc<str>);
+
+/// Accepts feed clients and re-broadcasts the frames published through its `MirrorPublisher`s.
+///
+/// Like a relay, the mirror sends the `arbitrum-chain-id` header and honors the
+/// `Arbitrum-Requested-Sequence-number` header of clients, first sending them the requested
+/// messages still in its backlog. Clients requesting messages older than the backlog are refused
+/// with a `410 Gone` response instead of silently missing them, and clients falling too far
+/// behind are disconnected, to reconnect from where they stopped.
+pub struct FeedMirror {
+    listener: TcpListener,
+    chain_id: u64,
+    publisher: MirrorPublisher,
+}
+
+/// Publishes frames to the clients of a `FeedMirror`. Clones publish to the same clients.
+#[derive(Clone)]
+pub struct MirrorPublisher {
+    sender: broadcast::Sender<Frame>,
+    backlog: Arc<Mutex<VecDeque<BroadcastFeedMessage>>>,
+    backlog_size: usize,
+}
+
+impl FeedMirror {
+    /// Binds the mirror of the chain `chain_id` to `addr`, e.g. `0.0.0.0:9642`.
+    pub async fn bind(addr: &str, chain_id: u64) -> io::Result<Self> {
+        let (sender, _) = broadcast::channel(CLIENT_BUFFER);
+        Ok(Self {
+            listener: TcpListener::bind(addr).await?,
+            chain_id,
+            publisher: MirrorPublisher {
+                sender,
+                backlog: Arc::default(),
+                backlog_size: DEFAULT_BACKLOG,
+            },
+        })
+    }
+
+    /// Sets how many recent messages are kept for clients resuming from a sequence number.
+    pub fn with_backlog(mut self, backlog: usize) -> Self {
+        self.publisher.backlog_size = backlog;
+        self
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Returns a publisher of frames to the clients of the mirror.
+    pub fn publisher(&self) -> MirrorPublisher {
+        self.publisher.clone()
+    }
+
+    /// Serves clients until the task is aborted.
+    pub fn spawn(self) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                let (stream, peer) = server::accept(&self.listener, "mirror").await;
+                let publisher = self.publisher.clone();
+                let chain_id = self.chain_id;
+                tokio::spawn(async move {
+                    if let Err(e) = serve_client(stream, chain_id, publisher).await {
+                        debug!("Mirror client {} failed: {}", peer, e);
+                    }
+                });
+            }
+        })
+    }
+}
+
+impl MirrorPublisher {
+    /// Broadcasts the messages of a frame received from a relay.
+    pub fn publish_root(&self, root: &Root) {
+        let Some(last) = root.messages.last() else {
+            return;
+        };
+        let frame = match serde_json::to_string(root) {
+            Ok(frame) => frame,
+            Err(e) => {
+                error!("Failed to serialize frame: {}", e);
+                return;
+            }
+        };
+
+        let mut backlog = self.backlog.lock().unwrap();
+        backlog.extend(root.messages.iter().cloned());
+        while backlog.len() > self.backlog_size {
+            backlog.pop_front();
+        }
+        // Sending only fails while no client is connected.
+        let _ = self.sender.send((last.sequence_number, frame.into()));
+    }
+
+    /// Broadcasts a single message.
+    pub fn publish(&self, msg: &BroadcastFeedMessage) {
+        self.publish_root(&Root {
+            version: BROADCAST_VERSION,
+            messages: vec![msg.clone()],
+            provenance: Default::default(),
+        });
+    }
+
+    /// Returns the number of connected clients.
+    pub fn clients(&self) -> usize {
+        self.sender.receiver_count()
+    }
+}
+
+async fn serve_client(
+    stream: TcpStream,
+    chain_id: u64,
+    publisher: MirrorPublisher,
+) -> Result<(), tokio_tungstenite::tungstenite::Error> {
+    let mut requested = None;
+    // The signature of the callback is imposed by tungstenite.
+    #[allow(clippy::result_large_err)]
+    let callback = |request: &Request, mut response: Response| {
+        requested = request
+            .headers()
+            .get("Arbitrum-Requested-Sequence-number")
+            .and_then(|v| v.to_str().ok()?.parse::<u64>().ok())
+            .filter(|requested| *requested > 0);
+        if let Some(requested) = requested {
+            let oldest = publisher
+                .backlog
+                .lock()
+                .unwrap()
+                .front()
+                .map(|msg| msg.sequence_number);
+            if let Some(oldest) = oldest.filter(|oldest| requested < *oldest) {
+                let mut error = ErrorResponse::new(Some(format!(
+                    "message {} is no longer available, the oldest is {}",
+                    requested, oldest
+                )));
+                *error.status_mut() = StatusCode::GONE;
+                return Err(error);
+            }
+        }
+        let headers = response.headers_mut();
+        headers.insert("arbitrum-chain-id", HeaderValue::from(chain_id));
+        headers.insert("arbitrum-feed-server-version", HeaderValue::from(2));
+        if let Some(requested) = requested {
+            headers.insert(
+                "arbitrum-requested-sequence-number",
+                HeaderValue::from(requested),
+            );
+        }
+        Ok(response)
+    };
+    let mut socket =
+        server::with_read_timeout(tokio_tungstenite::accept_hdr_async(stream, callback)).await?;
+
+    // Subscribe before reading the backlog, so that no message is missed in between.
+    let mut frames = publisher.sender.subscribe();
+    let mut sent = 0;
+    if let Some(requested) = requested {
+        let messages: Vec<_> = publisher
+            .backlog
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|msg| msg.sequence_number >= requested)
+            .cloned()
+            .collect();
+        if let Some(last) = messages.last() {
+            sent = last.sequence_number;
+            let root = Root {
+                version: BROADCAST_VERSION,
+                messages,
+                provenance: Default::default(),
+            };
+            let frame = serde_json::to_string(&root).unwrap_or_default();
+            socket.send(Message::Text(frame)).await?;
+        }
+    }
+
+    loop {
+        tokio::select! {
+            frame = frames.recv() => match frame {
+                Ok((last, _)) if last <= sent => (),
+                Ok((_, frame)) => socket.send(Message::Text(frame.to_string())).await?,
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!("Mirror client lagging behind by {} frames, disconnecting", skipped);
+                    break;
+                }
+                Err(RecvError::Closed) => break,
+            },
+            // Reading answers pings; clients aren't expected to send anything else.
+            message = socket.next() => match message {
+                Some(Ok(Message::Close(_))) | None => return Ok(()),
+                Some(Ok(_)) => (),
+                Some(Err(e)) => return Err(e),
+            },
+        }
+    }
+    socket.close(None).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::networks::arbitrum::fixtures::message_with;
+    use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+
+    #[tokio::test]
+    async fn resumes_clients_from_backlog() {
+        let mirror = FeedMirror::bind("127.0.0.1:0", 42161)
+            .await
+            .unwrap()
+            .with_backlog(2);
+        let addr = mirror.local_addr().unwrap();
+        let publisher = mirror.publisher();
+        mirror.spawn();
+        for seq in 0..3 {
+            publisher.publish(&message_with(seq, 0, vec![3]));
+        }
+
+        let mut request = format!("ws://{}", addr).into_client_request().unwrap();
+        request.headers_mut().insert(
+            "Arbitrum-Requested-Sequence-number",
+            HeaderValue::from(1u64),
+        );
+        let (mut socket, response) = tokio_tungstenite::connect_async(request).await.unwrap();
+        assert_eq!(response.headers()["arbitrum-chain-id"], "42161");
+
+        let mut received = Vec::new();
+        while received.len() < 3 {
+            // The client is subscribed once it received the backlog.
+            if received.len() == 2 {
+                publisher.publish(&message_with(3, 0, vec![3]));
+            }
+            let Some(Ok(Message::Text(frame))) = socket.next().await else {
+                panic!("connection closed");
+            };
+            let root: Root = serde_json::from_str(&frame).unwrap();
+            received.extend(root.messages.iter().map(|msg| msg.sequence_number));
+        }
+        assert_eq!(received, vec![1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn refuses_clients_resuming_before_backlog() {
+        let mirror = FeedMirror::bind("127.0.0.1:0", 42161)
+            .await
+            .unwrap()
+            .with_backlog(2);
+        let addr = mirror.local_addr().unwrap();
+        let publisher = mirror.publisher();
+        mirror.spawn();
+        for seq in 1..4 {
+            publisher.publish(&message_with(seq, 0, vec![3]));
+        }
+
+        let mut request = format!("ws://{}", addr).into_client_request().unwrap();
+        request.headers_mut().insert(
+            "Arbitrum-Requested-Sequence-number",
+            HeaderValue::from(1u64),
+        );
+        match tokio_tungstenite::connect_async(request).await {
+            Err(tokio_tungstenite::tungstenite::Error::Http(response)) => {
+                assert_eq!(response.status(), StatusCode::GONE)
+            }
+            other => panic!("unexpected handshake result: {:?}", other.map(|_| ())),
+        }
+    }
+}
@@ -0,0 +1,409 @@
+//! A simulated sequencer and relay for full-system tests.
+//!
+//! `SimulatedSequencer` turns a `Scenario` into an internally consistent stream of feed messages:
+//! sequence numbers, L2 timestamps, L1 block numbers and delayed message counts all advance the
+//! way they do on a real chain. `MockRelay` serves that stream over a websocket, pacing it in real
+//! time, so clients are exercised with realistic temporal patterns rather than random frames.
+//!
+//! Only built for the tests of the crate, or with the `mock` feature.
+
+use crate::networks::arbitrum::{
+    decoder::{
+        l1::{L1_MESSAGE_KIND_L2_FUNDED_BY_L1, L1_MESSAGE_KIND_L2_MESSAGE},
+        L2_MESSAGE_KIND_BATCH,
+    },
+    types::{BroadcastFeedMessage, Header, L1IncomingMessageHeader, MessageWithMetadata, Root},
+};
+use futures::SinkExt;
+use log::*;
+use serde::Deserialize;
+use serde_json::Value;
+use std::{net::SocketAddr, time::Duration};
+use tokio::{net::TcpListener, task::JoinHandle};
+use tokio_tungstenite::tungstenite::{
+    handshake::server::{Request, Response},
+    http::HeaderValue,
+    protocol::CloseFrame,
+    Message,
+};
+
+/// The address the sequencer posts its own messages from.
+const SEQUENCER_ADDRESS: &str = "0xa4b000000000000000000073657175656e636572";
+/// An L2 batch without transactions.
+const EMPTY_BATCH: [u8; 1] = [L2_MESSAGE_KIND_BATCH];
+/// An L2 contract transaction, a kind the decoder doesn't support.
+const CONTRACT_TX: [u8; 1] = [1];
+/// The L1 block time.
+const L1_BLOCK_TIME_MS: u64 = 12_000;
+const BROADCAST_VERSION: u8 = 1;
+
+/// A step of a `Scenario`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "step", rename_all = "camelCase")]
+pub enum Step {
+    /// The sequencer produces `count` blocks, one every `interval_ms`.
+    #[serde(rename_all = "camelCase")]
+    Blocks { count: u64, interval_ms: u64 },
+    /// `count` delayed messages from L1 are sequenced, one every `interval_ms`.
+    #[serde(rename_all = "camelCase")]
+    DelayedMessages { count: u64, interval_ms: u64 },
+    /// `count` sequence numbers are never broadcast.
+    Gap { count: u64 },
+    /// The sequencer produces nothing for `ms` milliseconds, e.g. while failing over.
+    Stall { ms: u64 },
+    /// The relay drops the connection.
+    Disconnect,
+}
+
+/// A script describing how a simulated sequencer behaves over time, deserializable from JSON such
+/// as `[{"step": "blocks", "count": 100, "intervalMs": 250}, {"step": "stall", "ms": 5000}]`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(transparent)]
+pub struct Scenario {
+    pub steps: Vec<Step>,
+}
+
+impl Scenario {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a step to the scenario.
+    pub fn then(mut self, step: Step) -> Self {
+        self.steps.push(step);
+        self
+    }
+}
+
+/// What a `MockRelay` does next.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SimEvent {
+    Message(BroadcastFeedMessage),
+    /// A text frame sent as is, e.g. to check how clients handle malformed frames.
+    Frame(String),
+    Wait(Duration),
+    Disconnect,
+    /// Disconnects with a close frame carrying `code` and `reason`.
+    Close {
+        code: u16,
+        reason: String,
+    },
+}
+
+/// Generates internally consistent feed messages.
+#[derive(Debug, Clone)]
+pub struct SimulatedSequencer {
+    sequence_number: u64,
+    /// L2 time in milliseconds since the UNIX epoch.
+    now_ms: u64,
+    /// L2 time at which `l1_block_number` was produced.
+    l1_block_at_ms: u64,
+    l1_block_number: u64,
+    delayed_messages_read: u64,
+}
+
+impl SimulatedSequencer {
+    /// # Arguments
+    ///
+    /// * `sequence_number` - The sequence number of the first message.
+    /// * `timestamp` - The L2 timestamp of the first message, in seconds.
+    /// * `l1_block_number` - The L1 block number of the first message.
+    pub fn new(sequence_number: u64, timestamp: u64, l1_block_number: u64) -> Self {
+        Self {
+            sequence_number,
+            now_ms: timestamp * 1000,
+            l1_block_at_ms: timestamp * 1000,
+            l1_block_number,
+            delayed_messages_read: 0,
+        }
+    }
+
+    /// Plays `scenario`, returning what the relay should do.
+    pub fn generate(&mut self, scenario: &Scenario) -> Vec<SimEvent> {
+        let mut events = Vec::new();
+        for step in &scenario.steps {
+            match *step {
+                Step::Blocks { count, interval_ms } => {
+                    for _ in 0..count {
+                        self.advance(interval_ms, &mut events);
+                        events.push(SimEvent::Message(self.message(
+                            L1_MESSAGE_KIND_L2_MESSAGE,
+                            SEQUENCER_ADDRESS.to_string(),
+                            Value::Null,
+                            &EMPTY_BATCH,
+                        )));
+                    }
+                }
+                Step::DelayedMessages { count, interval_ms } => {
+                    for _ in 0..count {
+                        self.advance(interval_ms, &mut events);
+                        let request_id = format!("0x{:064x}", self.delayed_messages_read);
+                        self.delayed_messages_read += 1;
+                        events.push(SimEvent::Message(self.message(
+                            L1_MESSAGE_KIND_L2_FUNDED_BY_L1,
+                            format!("0x{:040x}", self.delayed_messages_read),
+                            Value::String(request_id),
+                            &CONTRACT_TX,
+                        )));
+                    }
+                }
+                Step::Gap { count } => self.sequence_number += count,
+                Step::Stall { ms } => self.advance(ms, &mut events),
+                Step::Disconnect => events.push(SimEvent::Disconnect),
+            }
+        }
+        events
+    }
+
+    fn advance(&mut self, ms: u64, events: &mut Vec<SimEvent>) {
+        if ms > 0 {
+            events.push(SimEvent::Wait(Duration::from_millis(ms)));
+        }
+        self.now_ms += ms;
+        let l1_blocks = (self.now_ms - self.l1_block_at_ms) / L1_BLOCK_TIME_MS;
+        self.l1_block_number += l1_blocks;
+        self.l1_block_at_ms += l1_blocks * L1_BLOCK_TIME_MS;
+    }
+
+    fn message(
+        &mut self,
+        kind: u8,
+        sender: String,
+        request_id: Value,
+        l2msg: &[u8],
+    ) -> BroadcastFeedMessage {
+        let message = BroadcastFeedMessage {
+            sequence_number: self.sequence_number,
+            message: MessageWithMetadata {
+                message: L1IncomingMessageHeader {
+                    header: Header {
+                        kind,
+                        sender,
+                        block_number: self.l1_block_number,
+                        timestamp: self.now_ms / 1000,
+                        request_id,
+                        base_fee_l1: Value::Null,
+                    },
+                    l2msg: l2msg.to_vec().into(),
+                },
+                delayed_messages_read: self.delayed_messages_read,
+            },
+            signature: Value::Null,
+        };
+        self.sequence_number += 1;
+        message
+    }
+}
+
+/// A websocket relay playing `SimEvent`s to its clients, one connection at a time.
+///
+/// Every connection resumes where the previous one stopped, skipping the messages below the
+/// sequence number requested by the client.
+pub struct MockRelay {
+    listener: TcpListener,
+    chain_id: u64,
+    events: Vec<SimEvent>,
+    /// Waits are divided by this factor, to run long scenarios faster than real time.
+    speedup: u32,
+}
+
+impl MockRelay {
+    /// Binds a relay for the chain `chain_id` to `addr`, e.g. `127.0.0.1:0`.
+    pub async fn bind(addr: &str, chain_id: u64, events: Vec<SimEvent>) -> std::io::Result<Self> {
+        Ok(Self {
+            listener: TcpListener::bind(addr).await?,
+            chain_id,
+            events,
+            speedup: 1,
+        })
+    }
+
+    /// Plays the scenario `speedup` times faster than real time.
+    pub fn with_speedup(mut self, speedup: u32) -> Self {
+        self.speedup = speedup.max(1);
+        self
+    }
+
+    pub fn local_addr(&self) -> std::io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Serves clients until every event has been played.
+    pub fn spawn(self) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut events = self.events.into_iter().peekable();
+            while events.peek().is_some() {
+                let Ok((stream, _)) = self.listener.accept().await else {
+                    return;
+                };
+                let mut requested = 0;
+                let chain_id = self.chain_id;
+                // The signature of the callback is imposed by tungstenite.
+                #[allow(clippy::result_large_err)]
+                let callback = |request: &Request, mut response: Response| {
+                    requested = request
+                        .headers()
+                        .get("Arbitrum-Requested-Sequence-number")
+                        .and_then(|v| v.to_str().ok()?.parse().ok())
+                        .unwrap_or(0);
+                    let headers = response.headers_mut();
+                    headers.insert("arbitrum-chain-id", HeaderValue::from(chain_id));
+                    headers.insert("arbitrum-feed-server-version", HeaderValue::from(2));
+                    headers.insert(
+                        "arbitrum-requested-sequence-number",
+                        HeaderValue::from(requested),
+                    );
+                    Ok(response)
+                };
+                let mut socket = match tokio_tungstenite::accept_hdr_async(stream, callback).await {
+                    Ok(socket) => socket,
+                    Err(e) => {
+                        warn!("Mock relay handshake failed: {}", e);
+                        continue;
+                    }
+                };
+
+                let mut close_frame = None;
+                for event in events.by_ref() {
+                    match event {
+                        SimEvent::Message(msg) if msg.sequence_number < requested => (),
+                        SimEvent::Message(msg) => {
+                            let root = Root {
+                                version: BROADCAST_VERSION,
+                                messages: vec![msg],
+                                provenance: Default::default(),
+                            };
+                            let frame = serde_json::to_string(&root).unwrap_or_default();
+                            if socket.send(Message::Text(frame)).await.is_err() {
+                                break;
+                            }
+                        }
+                        SimEvent::Frame(frame) => {
+                            if socket.send(Message::Text(frame)).await.is_err() {
+                                break;
+                            }
+                        }
+                        SimEvent::Wait(duration) => {
+                            tokio::time::sleep(duration / self.speedup).await
+                        }
+                        SimEvent::Disconnect => break,
+                        SimEvent::Close { code, reason } => {
+                            close_frame = Some(CloseFrame {
+                                code: code.into(),
+                                reason: reason.into(),
+                            });
+                            break;
+                        }
+                    }
+                }
+                let _ = socket.close(close_frame).await;
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::networks::arbitrum::{
+        connect::ConnectOptions, errors::RelayError, feed_client::RelayClient,
+    };
+    use url::Url;
+
+    #[test]
+    fn sequencer_advances_consistently() {
+        let scenario: Scenario = serde_json::from_str(
+            r#"[
+                {"step": "blocks", "count": 60, "intervalMs": 250},
+                {"step": "delayedMessages", "count": 2, "intervalMs": 1000},
+                {"step": "gap", "count": 3},
+                {"step": "blocks", "count": 1, "intervalMs": 250}
+            ]"#,
+        )
+        .unwrap();
+        let messages: Vec<_> = SimulatedSequencer::new(100, 1_700_000_000, 18_000_000)
+            .generate(&scenario)
+            .into_iter()
+            .filter_map(|event| match event {
+                SimEvent::Message(msg) => Some(msg),
+                _ => None,
+            })
+            .collect();
+
+        assert_eq!(messages.len(), 63);
+        assert_eq!(messages[0].sequence_number, 100);
+        assert_eq!(messages[62].sequence_number, 165);
+        // 60 blocks of 250ms then 2s of delayed messages: 17.25s, an L1 block later.
+        let last = &messages[62].message;
+        assert_eq!(last.message.header.timestamp, 1_700_000_017);
+        assert_eq!(last.message.header.block_number, 18_000_001);
+        assert_eq!(last.delayed_messages_read, 2);
+        assert!(messages.windows(2).all(
+            |w| w[0].message.message.header.timestamp <= w[1].message.message.header.timestamp
+        ));
+    }
+
+    #[tokio::test]
+    async fn client_resumes_after_disconnect() {
+        let scenario = Scenario::new()
+            .then(Step::Blocks {
+                count: 3,
+                interval_ms: 10,
+            })
+            .then(Step::Disconnect)
+            .then(Step::Blocks {
+                count: 2,
+                interval_ms: 10,
+            });
+        let events = SimulatedSequencer::new(0, 1_700_000_000, 1).generate(&scenario);
+        let relay = MockRelay::bind("127.0.0.1:0", 42161, events).await.unwrap();
+        let url = Url::parse(&format!("ws://{}", relay.local_addr().unwrap())).unwrap();
+        let server = relay.spawn();
+
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        let (update, _updates) = crossbeam_channel::unbounded();
+        for from in [0, 3] {
+            RelayClient::new_from(url.clone(), 42161, 0, from, sender.clone(), update.clone())
+                .await
+                .unwrap()
+                .run()
+                .await
+                .unwrap();
+        }
+        server.await.unwrap();
+
+        let sequence_numbers: Vec<_> = receiver
+            .try_iter()
+            .flat_map(|root| root.messages)
+            .map(|msg| msg.sequence_number)
+            .collect();
+        assert_eq!(sequence_numbers, vec![0, 1, 2, 3, 4]);
+    }
+
+    #[tokio::test]
+    async fn read_timeout_fails_on_stalled_relay() {
+        let scenario = Scenario::new()
+            .then(Step::Blocks {
+                count: 1,
+                interval_ms: 0,
+            })
+            .then(Step::Stall { ms: 1_000 });
+        let events = SimulatedSequencer::new(0, 1_700_000_000, 1).generate(&scenario);
+        let relay = MockRelay::bind("127.0.0.1:0", 42161, events).await.unwrap();
+        let url = Url::parse(&format!("ws://{}", relay.local_addr().unwrap())).unwrap();
+        relay.spawn();
+
+        let (sender, _receiver) = crossbeam_channel::unbounded();
+        let (update, _updates) = crossbeam_channel::unbounded();
+        let options = ConnectOptions::new().with_read_timeout(Duration::from_millis(100));
+        let result = RelayClient::connect(url, 42161, 0, options, sender, update)
+            .await
+            .unwrap()
+            .run()
+            .await;
+        assert!(matches!(
+            result,
+            Err(RelayError::Timeout { stage: "read", .. })
+        ));
+    }
+}
@@ -0,0 +1,87 @@
+use ethers::types::Address;
+use serde::Deserialize;
+use std::str::FromStr;
+use url::Url;
+
+/// The public Arbitrum chains and their official sequencer feeds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(try_from = "String")]
+pub enum ArbitrumNetwork {
+    One,
+    Nova,
+    Sepolia,
+}
+
+impl ArbitrumNetwork {
+    /// Returns the network of `chain_id`, `None` if it is not a public Arbitrum chain.
+    pub fn from_chain_id(chain_id: u64) -> Option<Self> {
+        [
+            ArbitrumNetwork::One,
+            ArbitrumNetwork::Nova,
+            ArbitrumNetwork::Sepolia,
+        ]
+        .into_iter()
+        .find(|network| network.chain_id() == chain_id)
+    }
+
+    pub fn chain_id(&self) -> u64 {
+        match self {
+            ArbitrumNetwork::One => 42161,
+            ArbitrumNetwork::Nova => 42170,
+            ArbitrumNetwork::Sepolia => 421614,
+        }
+    }
+
+    /// The L2 block number produced from the feed message with sequence number 0.
+    ///
+    /// Arbitrum One was migrated to Nitro with its classic history preserved, so its Nitro
+    /// messages start after the last classic block.
+    pub fn genesis_block_number(&self) -> u64 {
+        match self {
+            ArbitrumNetwork::One => 22207817,
+            ArbitrumNetwork::Nova | ArbitrumNetwork::Sepolia => 0,
+        }
+    }
+
+    /// The address of the bridge contract holding the delayed inbox on the parent chain.
+    pub fn bridge_address(&self) -> Address {
+        let address = match self {
+            ArbitrumNetwork::One => "0x8315177aB297bA92A06054cE80a67Ed4DBd7ed3a",
+            ArbitrumNetwork::Nova => "0xC1Ebd02f738644983b6C4B2d440b8e77DdE276Bd",
+            ArbitrumNetwork::Sepolia => "0x38f918D0E9F1b721EDaA41302E399fa1B79333a9",
+        };
+        address.parse().expect("valid bridge address")
+    }
+
+    /// The URL of the public sequencer feed relay.
+    pub fn feed_url(&self) -> Url {
+        let url = match self {
+            ArbitrumNetwork::One => "wss://arb1.arbitrum.io/feed",
+            ArbitrumNetwork::Nova => "wss://nova.arbitrum.io/feed",
+            ArbitrumNetwork::Sepolia => "wss://sepolia-rollup.arbitrum.io/feed",
+        };
+        Url::parse(url).expect("valid feed url")
+    }
+}
+
+impl FromStr for ArbitrumNetwork {
+    type Err = String;
+
+    /// Parses a network name: `one`, `nova` or `sepolia`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "one" | "arb1" => Ok(ArbitrumNetwork::One),
+            "nova" => Ok(ArbitrumNetwork::Nova),
+            "sepolia" => Ok(ArbitrumNetwork::Sepolia),
+            _ => Err(format!("unknown network {}", s)),
+        }
+    }
+}
+
+impl TryFrom<String> for ArbitrumNetwork {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
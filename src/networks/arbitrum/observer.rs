@@ -0,0 +1,53 @@
+//! Callbacks on the lifecycle of a relay client, as an alternative to a channel per kind of
+//! notification.
+
+use crate::networks::arbitrum::{
+    connect::HandshakeInfo,
+    errors::{FrameError, RelayError},
+    events::FeedEvent,
+    provenance::Provenance,
+    types::BroadcastFeedMessage,
+};
+
+/// Observes a `RelayClient`, registered with `RelayClient::with_observer`.
+///
+/// Every method does nothing by default, so implementations only override the notifications
+/// they need. The methods are called on the task of the client, before the messages are
+/// forwarded: they must return quickly and never block.
+///
+/// # Example
+///
+/// ```
+/// use sequencer_feed_reader::networks::arbitrum::{
+///     observer::EventObserver, provenance::Provenance, types::BroadcastFeedMessage,
+/// };
+/// use std::sync::atomic::{AtomicU64, Ordering};
+///
+/// #[derive(Default)]
+/// struct Gaps(AtomicU64);
+///
+/// impl EventObserver for Gaps {
+///     fn on_gap(&self, _relay_id: u32, expected: u64, received: u64) {
+///         self.0.fetch_add(received - expected, Ordering::Relaxed);
+///     }
+/// }
+/// ```
+pub trait EventObserver: Send + Sync {
+    /// The client is connected and starts reading frames.
+    fn on_connect(&self, _relay_id: u32, _handshake: &HandshakeInfo) {}
+
+    /// A message was received from the relay.
+    fn on_message(&self, _msg: &BroadcastFeedMessage, _provenance: &Provenance) {}
+
+    /// A frame could not be parsed and was skipped.
+    fn on_decode_error(&self, _relay_id: u32, _error: &FrameError) {}
+
+    /// The relay skipped messages: `received` arrived while `expected` was the next one.
+    fn on_gap(&self, _relay_id: u32, _expected: u64, _received: u64) {}
+
+    /// The client noticed a `FeedEvent`, also sent to `RelayClient::with_events`, if set.
+    fn on_event(&self, _relay_id: u32, _event: &FeedEvent) {}
+
+    /// The client stopped, because of `error` if it failed.
+    fn on_disconnect(&self, _relay_id: u32, _error: Option<&RelayError>) {}
+}
@@ -0,0 +1,354 @@
+use crate::networks::arbitrum::{
+    errors::TelemetryError,
+    handle::RelayClientHandle,
+    message::FeedMessage,
+    metrics::{self, MetricKind},
+    middleware::{FeedMiddleware, Next},
+    status::RelayStatus,
+};
+use opentelemetry::{
+    metrics::{Counter, Histogram, Meter, MeterProvider},
+    trace::{Span, Status, TraceContextExt, Tracer},
+    Context, KeyValue,
+};
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::{metrics::SdkMeterProvider, runtime, trace, Resource};
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// The service name reported by default.
+const DEFAULT_SERVICE_NAME: &str = "sequencer-feed-reader";
+
+/// How often metrics are exported by default.
+const DEFAULT_METRICS_PERIOD: Duration = Duration::from_secs(15);
+
+/// Exports traces and metrics over OTLP (gRPC) to an OpenTelemetry collector.
+///
+/// # Example
+///
+/// ```no_run
+/// use sequencer_feed_reader::networks::arbitrum::{
+///     handle::RelayClientHandle, middleware::MiddlewarePipeline, otel::OtlpExporter,
+/// };
+///
+/// # async fn run(handles: Vec<RelayClientHandle>) -> Result<(), Box<dyn std::error::Error>> {
+/// let telemetry = OtlpExporter::new("http://localhost:4317")
+///     .with_service_name("arb-feed")
+///     .install()?;
+/// telemetry.observe_relays(handles);
+/// let pipeline = MiddlewarePipeline::new().layer(telemetry.message_tracer());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct OtlpExporter {
+    endpoint: String,
+    service_name: String,
+    metrics_period: Duration,
+}
+
+impl OtlpExporter {
+    /// # Arguments
+    ///
+    /// * `endpoint` - The gRPC endpoint of the collector, e.g. `http://localhost:4317`.
+    pub fn new(endpoint: impl Into<String>) -> Self {
+        Self {
+            endpoint: endpoint.into(),
+            service_name: DEFAULT_SERVICE_NAME.to_string(),
+            metrics_period: DEFAULT_METRICS_PERIOD,
+        }
+    }
+
+    /// Sets the `service.name` resource attribute of the exported traces and metrics.
+    pub fn with_service_name(mut self, name: impl Into<String>) -> Self {
+        self.service_name = name.into();
+        self
+    }
+
+    /// Sets how often metrics are exported.
+    pub fn with_metrics_period(mut self, period: Duration) -> Self {
+        self.metrics_period = period;
+        self
+    }
+
+    /// Starts exporting traces and metrics in the background. Must be called within a tokio
+    /// runtime.
+    ///
+    /// # Returns
+    ///
+    /// The `Telemetry` recording what is exported.
+    pub fn install(self) -> Result<Telemetry, TelemetryError> {
+        let resource = Resource::new([KeyValue::new("service.name", self.service_name)]);
+        let tracer = opentelemetry_otlp::new_pipeline()
+            .tracing()
+            .with_exporter(
+                opentelemetry_otlp::new_exporter()
+                    .tonic()
+                    .with_endpoint(&self.endpoint),
+            )
+            .with_trace_config(trace::config().with_resource(resource.clone()))
+            .install_batch(runtime::Tokio)?;
+        let meter_provider = opentelemetry_otlp::new_pipeline()
+            .metrics(runtime::Tokio)
+            .with_exporter(
+                opentelemetry_otlp::new_exporter()
+                    .tonic()
+                    .with_endpoint(&self.endpoint),
+            )
+            .with_resource(resource)
+            .with_period(self.metrics_period)
+            .build()?;
+        Ok(Telemetry {
+            tracer,
+            meter_provider,
+        })
+    }
+}
+
+/// The tracer and meter provider of an installed `OtlpExporter`.
+#[derive(Debug, Clone)]
+pub struct Telemetry {
+    tracer: trace::Tracer,
+    meter_provider: SdkMeterProvider,
+}
+
+impl Telemetry {
+    pub fn tracer(&self) -> &trace::Tracer {
+        &self.tracer
+    }
+
+    /// Returns the meter the crate records its metrics with.
+    pub fn meter(&self) -> Meter {
+        self.meter_provider.meter(DEFAULT_SERVICE_NAME)
+    }
+
+    /// Returns a middleware tracing every message, see `MessageTracer`.
+    pub fn message_tracer(&self) -> MessageTracer<trace::Tracer> {
+        MessageTracer::new(self.tracer.clone(), &self.meter())
+    }
+
+    /// Exports the metrics of `metrics::RELAY_METRICS` for every relay client.
+    pub fn observe_relays(&self, handles: Vec<RelayClientHandle>) {
+        observe_statuses(&self.meter(), move || {
+            handles.iter().map(RelayClientHandle::status).collect()
+        });
+    }
+
+    /// Exports the pending traces and metrics, and stops exporting.
+    pub fn shutdown(&self) -> Result<(), TelemetryError> {
+        opentelemetry::global::shutdown_tracer_provider();
+        self.meter_provider.shutdown()?;
+        Ok(())
+    }
+}
+
+/// Registers an instrument for every metric of `metrics::RELAY_METRICS`, observing the relay
+/// statuses returned by `statuses` whenever metrics are collected.
+pub fn observe_statuses<F>(meter: &Meter, statuses: F)
+where
+    F: Fn() -> Vec<RelayStatus> + Send + Sync + 'static,
+{
+    let statuses = Arc::new(statuses);
+    for descriptor in metrics::RELAY_METRICS {
+        let statuses = statuses.clone();
+        let callback = move |observer: &dyn opentelemetry::metrics::AsyncInstrument<f64>| {
+            for status in statuses() {
+                if let Some(value) = status.value(descriptor) {
+                    observer.observe(value, &relay_attributes(&status));
+                }
+            }
+        };
+        let name = descriptor.full_name();
+        match descriptor.kind {
+            MetricKind::Counter => {
+                meter
+                    .f64_observable_counter(name)
+                    .with_description(descriptor.help)
+                    .with_callback(callback)
+                    .init();
+            }
+            MetricKind::Gauge => {
+                meter
+                    .f64_observable_gauge(name)
+                    .with_description(descriptor.help)
+                    .with_callback(callback)
+                    .init();
+            }
+        }
+    }
+}
+
+/// The attributes identifying a relay, the OpenTelemetry equivalent of its Prometheus labels, see
+/// `RelayInfo::prometheus_labels`.
+fn relay_attributes(status: &RelayStatus) -> Vec<KeyValue> {
+    let mut attributes = vec![
+        KeyValue::new("relay_id", i64::from(status.relay_id)),
+        KeyValue::new("url", status.url.clone()),
+    ];
+    if let Some(name) = &status.name {
+        attributes.push(KeyValue::new("relay_name", name.clone()));
+    }
+    if let Some(region) = &status.region {
+        attributes.push(KeyValue::new("region", region.clone()));
+    }
+    attributes
+}
+
+/// A middleware recording a `feed.message` span for every message, and passing it on.
+///
+/// The span starts when the message was received, records a `decoded` event when the message
+/// reaches the middleware, and ends once the rest of the pipeline is done with it, with a
+/// `feed.dispatch` child span covering the rest of the pipeline. Place it first in the pipeline
+/// to cover every stage.
+///
+/// It also counts the messages and transactions, and records the time from receipt to the end
+/// of the dispatch in a histogram.
+pub struct MessageTracer<T> {
+    tracer: T,
+    messages: Counter<u64>,
+    transactions: Counter<u64>,
+    latency: Histogram<f64>,
+}
+
+impl<T: Tracer> MessageTracer<T> {
+    /// # Arguments
+    ///
+    /// * `tracer` - The tracer recording the spans.
+    /// * `meter` - The meter recording the counters and the latency histogram.
+    pub fn new(tracer: T, meter: &Meter) -> Self {
+        let name = |name: &str| format!("{}_{}", metrics::NAMESPACE, name);
+        Self {
+            tracer,
+            messages: meter
+                .u64_counter(name("messages_traced_total"))
+                .with_description("Feed messages that went through the pipeline.")
+                .init(),
+            transactions: meter
+                .u64_counter(name("transactions_traced_total"))
+                .with_description(
+                    "Transactions of the feed messages that went through the pipeline.",
+                )
+                .init(),
+            latency: meter
+                .f64_histogram(name("dispatch_latency_milliseconds"))
+                .with_description(
+                    "Time from the receipt of a feed message to the end of its dispatch.",
+                )
+                .with_unit(opentelemetry::metrics::Unit::new("ms"))
+                .init(),
+        }
+    }
+}
+
+impl<T> FeedMiddleware for MessageTracer<T>
+where
+    T: Tracer + Send,
+    T::Span: Send + Sync + 'static,
+{
+    fn handle(&mut self, msg: FeedMessage, mut next: Next<'_>) -> bool {
+        let received_at_ms = msg.provenance.received_at_ms;
+        let relay = [KeyValue::new(
+            "relay_id",
+            i64::from(msg.provenance.relay_id),
+        )];
+        let transactions = msg.transactions().len() as u64;
+        let attributes = vec![
+            KeyValue::new("sequence_number", msg.sequence_number() as i64),
+            relay[0].clone(),
+            KeyValue::new("transactions", transactions as i64),
+        ];
+
+        let mut builder = self
+            .tracer
+            .span_builder("feed.message")
+            .with_attributes(attributes);
+        if received_at_ms > 0 {
+            builder = builder.with_start_time(UNIX_EPOCH + Duration::from_millis(received_at_ms));
+        }
+        let mut span = builder.start(&self.tracer);
+        match &msg.decoded {
+            Ok(_) => span.add_event("decoded", Vec::new()),
+            Err(e) => {
+                span.add_event("decoded", vec![KeyValue::new("error", e.to_string())]);
+                span.set_status(Status::error(e.to_string()));
+            }
+        }
+
+        let cx = Context::current_with_span(span);
+        let mut dispatch = self.tracer.start_with_context("feed.dispatch", &cx);
+        let running = next.run(msg);
+        dispatch.end();
+        cx.span().end();
+
+        self.messages.add(1, &relay);
+        self.transactions.add(transactions, &relay);
+        if received_at_ms > 0 {
+            let now_ms = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_millis() as u64);
+            self.latency
+                .record(now_ms.saturating_sub(received_at_ms) as f64, &relay);
+        }
+        running
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::networks::arbitrum::{
+        fixtures::feed_message, middleware::MiddlewarePipeline, provenance::Provenance,
+    };
+    use crossbeam_channel::{unbounded, Sender};
+    use futures::future::BoxFuture;
+    use opentelemetry::trace::TracerProvider as _;
+    use opentelemetry_sdk::export::trace::{ExportResult, SpanData, SpanExporter};
+
+    #[derive(Debug)]
+    struct ChannelExporter(Sender<SpanData>);
+
+    impl SpanExporter for ChannelExporter {
+        fn export(&mut self, batch: Vec<SpanData>) -> BoxFuture<'static, ExportResult> {
+            for span in batch {
+                let _ = self.0.send(span);
+            }
+            Box::pin(async { Ok(()) })
+        }
+    }
+
+    #[test]
+    fn traces_messages_from_receipt_to_dispatch() {
+        let (exported, spans) = unbounded();
+        let provider = trace::TracerProvider::builder()
+            .with_simple_exporter(ChannelExporter(exported))
+            .build();
+        let meter = SdkMeterProvider::builder().build().meter("test");
+        let mut pipeline =
+            MiddlewarePipeline::new().layer(MessageTracer::new(provider.tracer("test"), &meter));
+
+        let received_at_ms = 1_700_000_000_250;
+        let msg = FeedMessage {
+            provenance: Provenance::live(3, 0).with_received_at_ms(received_at_ms),
+            ..feed_message(42, 0, vec![], None)
+        };
+        assert!(pipeline.process(msg, &mut |_| true));
+        provider.force_flush();
+
+        let spans: Vec<SpanData> = spans.try_iter().collect();
+        assert_eq!(spans.len(), 2);
+        let (dispatch, message) = (&spans[0], &spans[1]);
+        assert_eq!(dispatch.name, "feed.dispatch");
+        assert_eq!(dispatch.parent_span_id, message.span_context.span_id());
+        assert_eq!(message.name, "feed.message");
+        assert_eq!(
+            message.start_time,
+            UNIX_EPOCH + Duration::from_millis(received_at_ms)
+        );
+        assert!(message
+            .attributes
+            .contains(&KeyValue::new("sequence_number", 42i64)));
+        assert_eq!(message.events.iter().next().unwrap().name, "decoded");
+    }
+}
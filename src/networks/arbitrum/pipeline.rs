@@ -0,0 +1,470 @@
+use crate::networks::arbitrum::{
+    decoder::{registry::DecoderRegistry, DecodeOptions},
+    errors::DecodeError,
+    events::FeedEvent,
+    message::FeedMessage,
+    priority::PriorityLane,
+    provenance::Provenance,
+    types::{BroadcastFeedMessage, Root},
+};
+use crossbeam_channel::{bounded, Receiver, Sender};
+use log::*;
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+/// How many messages may wait for a worker before the dispatcher blocks.
+const QUEUE_DEPTH_PER_WORKER: usize = 64;
+
+/// When a `DecodePool` considers itself overloaded and skips sender recovery.
+///
+/// Load is measured as the time messages wait in the queue before a worker picks them up.
+/// Decoding degrades once that latency stayed above `max_queue_latency` for `sustain`, and is
+/// restored once it stayed below half of it for `sustain` again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Degradation {
+    pub max_queue_latency: Duration,
+    pub sustain: Duration,
+}
+
+impl Default for Degradation {
+    fn default() -> Self {
+        Self {
+            max_queue_latency: Duration::from_millis(100),
+            sustain: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Tracks the queue latency seen by a worker against a `Degradation` policy.
+///
+/// Every worker has its own monitor, so that tracking the load never contends on a lock: the
+/// workers only share whether decoding is degraded, see `sync`.
+#[derive(Debug)]
+struct LoadMonitor {
+    policy: Degradation,
+    degraded: bool,
+    /// Since when the latency has been on the other side of the threshold.
+    crossed_at: Option<Instant>,
+}
+
+impl LoadMonitor {
+    fn new(policy: Degradation) -> Self {
+        Self {
+            policy,
+            degraded: false,
+            crossed_at: None,
+        }
+    }
+
+    /// Adopts the state `degraded` set by any worker, restarting the tracking if it changed.
+    fn sync(&mut self, degraded: bool) {
+        if degraded != self.degraded {
+            self.degraded = degraded;
+            self.crossed_at = None;
+        }
+    }
+
+    /// Records the queue latency of a message picked up at `now`.
+    ///
+    /// # Returns
+    ///
+    /// `Some(degraded)` if decoding switched between degraded and full.
+    fn observe(&mut self, latency: Duration, now: Instant) -> Option<bool> {
+        let crossed = if self.degraded {
+            latency < self.policy.max_queue_latency / 2
+        } else {
+            latency > self.policy.max_queue_latency
+        };
+        if !crossed {
+            self.crossed_at = None;
+            return None;
+        }
+
+        let since = *self.crossed_at.get_or_insert(now);
+        if now.duration_since(since) < self.policy.sustain {
+            return None;
+        }
+        self.degraded = !self.degraded;
+        self.crossed_at = None;
+        Some(self.degraded)
+    }
+}
+
+/// A pool of worker threads decoding feed messages in parallel.
+///
+/// Messages are emitted in the order they were received, regardless of which worker decoded
+/// them, so a slow decode only delays its own output and never the websocket reads.
+pub struct DecodePool {
+    workers: usize,
+    chain_id: u64,
+    decoders: Arc<DecoderRegistry>,
+    options: DecodeOptions,
+    degradation: Option<(Degradation, Sender<FeedEvent>)>,
+    events: Option<Sender<FeedEvent>>,
+    priority: Option<PriorityLane>,
+}
+
+/// The threads of a running `DecodePool`.
+pub struct DecodePoolHandle {
+    threads: Vec<JoinHandle<()>>,
+    degraded: Arc<AtomicBool>,
+}
+
+impl DecodePool {
+    /// Creates a pool of `workers` decoding threads (at least one).
+    pub fn new(workers: usize) -> Self {
+        Self {
+            workers: workers.max(1),
+            chain_id: 0,
+            decoders: Arc::default(),
+            options: DecodeOptions::default(),
+            degradation: None,
+            events: None,
+            priority: None,
+        }
+    }
+
+    /// Decodes messages with the hooks registered for `chain_id` in `decoders`.
+    pub fn with_decoder_registry(mut self, chain_id: u64, decoders: Arc<DecoderRegistry>) -> Self {
+        self.chain_id = chain_id;
+        self.decoders = decoders;
+        self
+    }
+
+    /// Decodes messages as described by `options`, e.g. with a larger message size limit.
+    pub fn with_decode_options(mut self, options: DecodeOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Reports the messages too large to be decoded to `events`, as
+    /// `FeedEvent::MessageTooLarge`.
+    pub fn with_events(mut self, events: Sender<FeedEvent>) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Delivers the transactions matching the high priority filters of `lane` as soon as they
+    /// are decoded, without waiting for the earlier messages.
+    pub fn with_priority_lane(mut self, lane: PriorityLane) -> Self {
+        self.priority = Some(lane);
+        self
+    }
+
+    /// Skips sender recovery while the pool can't keep up, the most expensive step of decoding.
+    ///
+    /// Messages are never dropped, and are otherwise decoded in full. Those decoded without
+    /// recovering their senders are marked with `Provenance::degraded`.
+    ///
+    /// # Arguments
+    ///
+    /// * `policy` - When the pool is considered overloaded.
+    /// * `events` - The sender channel for `FeedEvent::DecodingDegraded` and
+    ///   `FeedEvent::DecodingRestored` events, marking the degraded periods.
+    pub fn with_degradation(mut self, policy: Degradation, events: Sender<FeedEvent>) -> Self {
+        self.degradation = Some((policy, events));
+        self
+    }
+
+    /// Starts decoding the messages received on `input`.
+    ///
+    /// The pool stops, and drops `output`, once `input` is disconnected and every pending message
+    /// has been emitted.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - The receiver channel of `Root` messages, e.g. fed by a `RelayClient`.
+    /// * `output` - The sender channel for sending decoded `FeedMessage`s in order.
+    pub fn spawn(self, input: Receiver<Root>, output: Sender<FeedMessage>) -> DecodePoolHandle {
+        let (work_tx, work_rx) = bounded::<(u64, BroadcastFeedMessage, Provenance, Instant)>(
+            self.workers * QUEUE_DEPTH_PER_WORKER,
+        );
+        let degraded = Arc::new(AtomicBool::new(false));
+        let (done_tx, done_rx) =
+            bounded::<(u64, FeedMessage)>(self.workers * QUEUE_DEPTH_PER_WORKER);
+        let mut threads = Vec::with_capacity(self.workers + 2);
+
+        threads.push(thread::spawn(move || {
+            let mut index = 0;
+            for root in input {
+                for msg in root.messages {
+                    if work_tx
+                        .send((index, msg, root.provenance.clone(), Instant::now()))
+                        .is_err()
+                    {
+                        return;
+                    }
+                    index += 1;
+                }
+            }
+        }));
+
+        for _ in 0..self.workers {
+            let work_rx = work_rx.clone();
+            let done_tx = done_tx.clone();
+            let decoders = self.decoders.clone();
+            let chain_id = self.chain_id;
+            let base_options = self.options;
+            let events = self.events.clone();
+            let priority = self.priority.clone();
+            let degraded = degraded.clone();
+            let mut monitor = self
+                .degradation
+                .clone()
+                .map(|(policy, events)| (LoadMonitor::new(policy), events));
+            threads.push(thread::spawn(move || {
+                for (index, message, mut provenance, queued_at) in work_rx {
+                    if let Some((monitor, events)) = &mut monitor {
+                        let now = Instant::now();
+                        let latency = now.duration_since(queued_at);
+                        monitor.sync(degraded.load(Ordering::Relaxed));
+                        let switched = monitor.observe(latency, now).filter(|&now_degraded| {
+                            // Only the worker switching the state first reports it.
+                            degraded
+                                .compare_exchange(
+                                    !now_degraded,
+                                    now_degraded,
+                                    Ordering::Relaxed,
+                                    Ordering::Relaxed,
+                                )
+                                .is_ok()
+                        });
+                        if let Some(now_degraded) = switched {
+                            let sequence_number = message.sequence_number;
+                            let event = if now_degraded {
+                                warn!("Decoding degraded, queue latency {:?}", latency);
+                                FeedEvent::DecodingDegraded {
+                                    sequence_number,
+                                    queue_latency_ms: latency.as_millis() as u64,
+                                }
+                            } else {
+                                info!("Decoding restored");
+                                FeedEvent::DecodingRestored { sequence_number }
+                            };
+                            let _ = events.send(event);
+                        }
+                    }
+
+                    let skip_senders =
+                        base_options.recover_senders && degraded.load(Ordering::Relaxed);
+                    let options = DecodeOptions {
+                        recover_senders: base_options.recover_senders && !skip_senders,
+                        ..base_options
+                    };
+                    provenance.degraded = skip_senders;
+                    let decoded = decoders.decode_with(chain_id, &message.message.message, options);
+                    if let Err(DecodeError::MessageTooLarge { size, max }) = decoded {
+                        warn!(
+                            "Message {} of {} bytes exceeds the limit of {} bytes",
+                            message.sequence_number, size, max
+                        );
+                        if let Some(events) = &events {
+                            let _ = events.send(FeedEvent::MessageTooLarge {
+                                sequence_number: message.sequence_number,
+                                size,
+                                max,
+                            });
+                        }
+                    }
+                    let msg = FeedMessage {
+                        message,
+                        decoded,
+                        provenance,
+                    };
+                    if let Some(priority) = &priority {
+                        priority.deliver(&msg);
+                    }
+                    if done_tx.send((index, msg)).is_err() {
+                        return;
+                    }
+                }
+            }));
+        }
+        drop(done_tx);
+
+        threads.push(thread::spawn(move || {
+            let mut next = 0;
+            let mut pending = BTreeMap::new();
+            for (index, msg) in done_rx {
+                pending.insert(index, msg);
+                while let Some(msg) = pending.remove(&next) {
+                    if output.send(msg).is_err() {
+                        return;
+                    }
+                    next += 1;
+                }
+            }
+        }));
+
+        DecodePoolHandle { threads, degraded }
+    }
+}
+
+impl DecodePoolHandle {
+    /// Returns `true` while the pool skips sender recovery. Later stages may consult it to skip
+    /// their own optional work, such as calldata enrichment.
+    pub fn is_degraded(&self) -> bool {
+        self.degraded.load(Ordering::Relaxed)
+    }
+
+    /// Waits for the pool to emit every pending message and stop.
+    pub fn join(self) {
+        for thread in self.threads {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::networks::arbitrum::{
+        decoder::registry::DecodeOutcome, fixtures::message_with, types::Header,
+    };
+    use crossbeam_channel::unbounded;
+    use std::time::Duration;
+
+    #[test]
+    fn preserves_order_with_uneven_decode_times() {
+        let mut decoders = DecoderRegistry::new();
+        decoders.register(1, |_: &Header, l2_bytes: &[u8]| {
+            // Early messages are the slowest to decode.
+            thread::sleep(Duration::from_millis(
+                20u64.saturating_sub(l2_bytes[1] as u64),
+            ));
+            Ok::<_, DecodeError>(DecodeOutcome::Ignore)
+        });
+
+        let (input_tx, input_rx) = unbounded();
+        let (output_tx, output_rx) = unbounded();
+        let handle = DecodePool::new(4)
+            .with_decoder_registry(1, Arc::new(decoders))
+            .spawn(input_rx, output_tx);
+
+        for chunk in (0..40).collect::<Vec<_>>().chunks(3) {
+            input_tx
+                .send(Root {
+                    version: 1,
+                    messages: chunk
+                        .iter()
+                        .map(|&seq| message_with(seq, 0, vec![0xff, seq as u8]))
+                        .collect(),
+                    provenance: Provenance::live(7, 2),
+                })
+                .unwrap();
+        }
+        drop(input_tx);
+        handle.join();
+
+        let output: Vec<_> = output_rx.iter().collect();
+        let sequence_numbers: Vec<_> = output.iter().map(|m| m.sequence_number()).collect();
+        assert_eq!(sequence_numbers, (0..40).collect::<Vec<_>>());
+        assert!(output
+            .iter()
+            .all(|m| m.provenance == Provenance::live(7, 2)));
+    }
+
+    #[test]
+    fn reports_messages_over_the_size_limit() {
+        let (input_tx, input_rx) = unbounded();
+        let (output_tx, output_rx) = unbounded();
+        let (events_tx, events_rx) = unbounded();
+        let handle = DecodePool::new(1)
+            .with_decode_options(DecodeOptions {
+                max_l2_message_size: 16,
+                ..DecodeOptions::default()
+            })
+            .with_events(events_tx)
+            .spawn(input_rx, output_tx);
+
+        input_tx
+            .send(Root {
+                version: 1,
+                messages: vec![message_with(1, 0, vec![6]), message_with(2, 0, vec![6; 17])],
+                provenance: Provenance::default(),
+            })
+            .unwrap();
+        drop(input_tx);
+        handle.join();
+
+        let output: Vec<_> = output_rx.iter().collect();
+        assert_eq!(output.len(), 2);
+        assert_eq!(
+            output[1].decoded,
+            Err(DecodeError::MessageTooLarge { size: 17, max: 16 })
+        );
+        assert_eq!(
+            events_rx.try_iter().collect::<Vec<_>>(),
+            [FeedEvent::MessageTooLarge {
+                sequence_number: 2,
+                size: 17,
+                max: 16
+            }]
+        );
+    }
+
+    #[test]
+    fn marks_the_messages_decoded_while_degraded() {
+        let (input_tx, input_rx) = unbounded();
+        let (output_tx, output_rx) = unbounded();
+        let (events_tx, events_rx) = unbounded();
+        let handle = DecodePool::new(2)
+            .with_degradation(
+                Degradation {
+                    max_queue_latency: Duration::ZERO,
+                    sustain: Duration::ZERO,
+                },
+                events_tx,
+            )
+            .spawn(input_rx, output_tx);
+
+        input_tx
+            .send(Root {
+                version: 1,
+                messages: (1..=8).map(|seq| message_with(seq, 0, vec![])).collect(),
+                provenance: Provenance::default(),
+            })
+            .unwrap();
+        drop(input_tx);
+        handle.join();
+
+        // Any queue latency degrades, and only the first worker noticing reports it.
+        let events: Vec<_> = events_rx.try_iter().collect();
+        assert!(matches!(events[..], [FeedEvent::DecodingDegraded { .. }]));
+        let output: Vec<_> = output_rx.iter().collect();
+        assert_eq!(output.len(), 8);
+        assert!(output.last().unwrap().provenance.degraded);
+    }
+
+    #[test]
+    fn degrades_under_sustained_queue_latency() {
+        let mut monitor = LoadMonitor::new(Degradation {
+            max_queue_latency: Duration::from_millis(100),
+            sustain: Duration::from_secs(5),
+        });
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let slow = Duration::from_millis(200);
+        let fast = Duration::from_millis(10);
+
+        // A short spike doesn't degrade.
+        assert_eq!(monitor.observe(slow, at(0)), None);
+        assert_eq!(monitor.observe(fast, at(1)), None);
+        assert_eq!(monitor.observe(slow, at(6)), None);
+
+        assert_eq!(monitor.observe(slow, at(11)), Some(true));
+        // Below the threshold, but not below half of it.
+        assert_eq!(monitor.observe(Duration::from_millis(80), at(12)), None);
+        assert_eq!(monitor.observe(Duration::from_millis(80), at(20)), None);
+
+        assert_eq!(monitor.observe(fast, at(21)), None);
+        assert_eq!(monitor.observe(fast, at(26)), Some(false));
+    }
+}
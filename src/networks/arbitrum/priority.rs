@@ -0,0 +1,145 @@
+use crate::networks::arbitrum::{
+    filter::LiveFilter,
+    message::{FeedMessage, FeedTransaction},
+};
+use crossbeam_channel::{Sender, TrySendError};
+use log::*;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+/// Delivers the transactions matching high priority filters on their own channel, as soon as
+/// they are decoded and ahead of the ordered stream, for bots reacting to specific contracts.
+///
+/// Messages decoded in parallel are held back until every earlier message is decoded too: the
+/// priority lane skips that wait, so its transactions may arrive out of sequence order. They are
+/// still delivered on the ordered stream as well.
+///
+/// Transactions are only sent while the channel has room, so that a slow consumer never holds
+/// decoding back: those that don't fit are missed, and counted, see `missed`.
+///
+/// Filters with `from` rules don't match the transactions decoded while a `DecodePool` is
+/// degraded, since their senders aren't recovered, see `Provenance::degraded`.
+#[derive(Debug, Clone)]
+pub struct PriorityLane {
+    filter: LiveFilter,
+    sender: Sender<FeedTransaction>,
+    missed: Arc<AtomicU64>,
+}
+
+impl PriorityLane {
+    /// # Arguments
+    ///
+    /// * `filter` - The filter holding the high priority filters, see `LiveFilter::priority`. A
+    ///   transaction matching any of them is delivered.
+    /// * `sender` - The sender channel for the matching transactions.
+    pub fn new(filter: LiveFilter, sender: Sender<FeedTransaction>) -> Self {
+        Self {
+            filter,
+            sender,
+            missed: Arc::default(),
+        }
+    }
+
+    /// Counts the missed transactions in `missed`, e.g. to report them along with other
+    /// statistics.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn with_missed_counter(mut self, missed: Arc<AtomicU64>) -> Self {
+        self.missed = missed;
+        self
+    }
+
+    /// Returns the number of matching transactions missed because the channel was full.
+    pub fn missed(&self) -> u64 {
+        self.missed.load(Ordering::Relaxed)
+    }
+
+    /// Sends the transactions of `msg` matching a high priority filter, without waiting for
+    /// room in the channel.
+    ///
+    /// # Returns
+    ///
+    /// The number of transactions sent, 0 once the receiving side is dropped.
+    pub fn deliver(&self, msg: &FeedMessage) -> usize {
+        let mut sent = 0;
+        for tx in msg.transactions() {
+            if !self.filter.matches_priority(&tx) {
+                continue;
+            }
+            match self.sender.try_send(tx) {
+                Ok(()) => sent += 1,
+                Err(TrySendError::Full(tx)) => {
+                    debug!(
+                        "Priority channel full, missing transaction {} of message {}",
+                        tx.index, tx.sequence_number
+                    );
+                    self.missed.fetch_add(1, Ordering::Relaxed);
+                }
+                Err(TrySendError::Disconnected(_)) => return 0,
+            }
+        }
+        sent
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::networks::arbitrum::{
+        config::FilterConfig, decoder::DecodedMsg, fixtures::feed_message,
+    };
+    use crossbeam_channel::{bounded, unbounded};
+    use ethers::types::{Transaction, H160};
+
+    #[test]
+    fn delivers_transactions_matching_a_filter() {
+        let to = |byte| Transaction {
+            to: Some(H160::repeat_byte(byte)),
+            ..Default::default()
+        };
+        let msg = feed_message(
+            7,
+            0,
+            vec![],
+            Some(DecodedMsg::DecodedBatch(vec![to(1), to(2), to(3)])),
+        );
+        let filter = |byte| FilterConfig {
+            to: vec![H160::repeat_byte(byte)],
+            ..Default::default()
+        };
+        let (sender, priority) = unbounded();
+        let live = LiveFilter::default().with_priority(vec![filter(3), filter(1)]);
+        let lane = PriorityLane::new(live, sender);
+
+        assert_eq!(lane.deliver(&msg), 2);
+        assert_eq!(
+            priority.try_iter().map(|tx| tx.index).collect::<Vec<_>>(),
+            vec![0, 2]
+        );
+    }
+
+    #[test]
+    fn counts_the_transactions_missed_while_the_channel_is_full() {
+        let msg = feed_message(
+            7,
+            0,
+            vec![],
+            Some(DecodedMsg::DecodedBatch(vec![
+                Transaction::default(),
+                Transaction::default(),
+                Transaction::default(),
+            ])),
+        );
+        let (sender, priority) = bounded(1);
+        let live = LiveFilter::default().with_priority(vec![FilterConfig::default()]);
+        let lane = PriorityLane::new(live, sender);
+
+        assert_eq!(lane.deliver(&msg), 1);
+        assert_eq!(lane.missed(), 2);
+        assert_eq!(priority.recv().unwrap().index, 0);
+
+        drop(priority);
+        assert_eq!(lane.deliver(&msg), 0);
+    }
+}
@@ -0,0 +1,137 @@
+//! Protocol Buffers encodings of the feed, see `proto/feed.proto`, for services written in
+//! other languages to consume binary-encoded feed output.
+//!
+//! The conversions return the generated `prost` messages, encoded with `prost::Message`.
+
+use crate::networks::arbitrum::{
+    decoder::DecodedMsg,
+    message::{FeedMessage, FeedTransaction},
+    types::{BroadcastFeedMessage, Header, Root},
+};
+use serde_json::Value;
+
+pub mod proto {
+    include!(concat!(env!("OUT_DIR"), "/sequencer_feed.rs"));
+}
+
+/// Serializes a JSON field of the feed, empty if null.
+fn json_field(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        value => value.to_string(),
+    }
+}
+
+impl From<&Header> for proto::Header {
+    fn from(header: &Header) -> Self {
+        Self {
+            kind: header.kind.into(),
+            sender: header.sender.clone(),
+            l1_block_number: header.block_number,
+            timestamp: header.timestamp,
+            request_id: json_field(&header.request_id),
+            base_fee_l1: json_field(&header.base_fee_l1),
+        }
+    }
+}
+
+impl From<&BroadcastFeedMessage> for proto::BroadcastFeedMessage {
+    fn from(msg: &BroadcastFeedMessage) -> Self {
+        Self {
+            sequence_number: msg.sequence_number,
+            header: Some((&msg.message.message.header).into()),
+            l2_msg: msg.message.message.l2msg.to_vec(),
+            delayed_messages_read: msg.message.delayed_messages_read,
+            signature: json_field(&msg.signature),
+        }
+    }
+}
+
+impl From<&Root> for proto::Root {
+    fn from(root: &Root) -> Self {
+        Self {
+            version: root.version.into(),
+            messages: root.messages.iter().map(Into::into).collect(),
+        }
+    }
+}
+
+/// Converts a feed message of chain `chain_id`, along with its decoded transactions.
+pub fn feed_message(chain_id: u64, msg: &FeedMessage) -> proto::FeedMessage {
+    let header = &msg.message.message.message.header;
+    let transactions = match &msg.decoded {
+        Ok(Some(DecodedMsg::DecodedBatch(txs))) => txs.iter().map(|tx| tx.rlp().to_vec()).collect(),
+        Ok(Some(DecodedMsg::DecodedSignedTx(tx))) => vec![tx.rlp().to_vec()],
+        _ => Vec::new(),
+    };
+    proto::FeedMessage {
+        chain_id,
+        sequence_number: msg.sequence_number(),
+        kind: header.kind.into(),
+        sender: header.sender.clone(),
+        l1_block_number: header.block_number,
+        timestamp: header.timestamp,
+        delayed_messages_read: msg.message.message.delayed_messages_read,
+        l2_msg: msg.message.message.message.l2msg.to_vec(),
+        transactions,
+    }
+}
+
+impl From<&FeedTransaction> for proto::FeedTransaction {
+    fn from(tx: &FeedTransaction) -> Self {
+        Self {
+            transaction: tx.tx.rlp().to_vec(),
+            hash: tx.tx.hash.as_bytes().to_vec(),
+            from: tx
+                .sender()
+                .map_or_else(Vec::new, |from| from.as_bytes().to_vec()),
+            sequence_number: tx.sequence_number,
+            index: tx.index as u32,
+            header: Some((&tx.header).into()),
+            received_at_ms: tx.received_at_ms,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::networks::arbitrum::fixtures::message_with;
+    use ethers::types::{Transaction, H160, H256};
+    use prost::Message;
+
+    #[test]
+    fn encodes_roots_and_transactions() {
+        let root = Root {
+            version: 1,
+            messages: vec![message_with(300, 1_700_000_000, vec![0xaa])],
+            provenance: Default::default(),
+        };
+        let decoded = proto::Root::decode(&*proto::Root::from(&root).encode_to_vec()).unwrap();
+        assert_eq!(decoded.version, 1);
+        assert_eq!(decoded.messages[0].sequence_number, 300);
+        assert_eq!(decoded.messages[0].l2_msg, [0xaa]);
+        let header = decoded.messages[0].header.as_ref().unwrap();
+        assert_eq!((header.kind, header.timestamp), (3, 1_700_000_000));
+
+        let tx = FeedTransaction {
+            tx: Transaction {
+                hash: H256::repeat_byte(1),
+                from: H160::repeat_byte(2),
+                ..Default::default()
+            },
+            sequence_number: 300,
+            index: 4,
+            header: root.messages[0].message.message.header.clone(),
+            received_at_ms: 1_700_000_000_250,
+            degraded: false,
+        };
+        let decoded =
+            proto::FeedTransaction::decode(&*proto::FeedTransaction::from(&tx).encode_to_vec())
+                .unwrap();
+        assert_eq!(decoded.hash, H256::repeat_byte(1).as_bytes());
+        assert_eq!(decoded.from, H160::repeat_byte(2).as_bytes());
+        assert_eq!((decoded.sequence_number, decoded.index), (300, 4));
+        assert_eq!(decoded.transaction, tx.tx.rlp().to_vec());
+    }
+}
@@ -0,0 +1,86 @@
+use crate::networks::arbitrum::identity::RelayInfo;
+use serde::Serialize;
+use std::sync::Arc;
+
+/// Where a feed message was read from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub enum Origin {
+    /// Received from a relay's live websocket feed.
+    #[default]
+    Live,
+    /// Replayed from recorded history, such as an archive.
+    Replay,
+}
+
+/// Describes where a feed message came from, so that storage downstream can tell data sources
+/// apart when debugging discrepancies.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct Provenance {
+    /// The ID of the relay client the message was read by.
+    pub relay_id: u32,
+    /// The relay the message was read from, when known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub relay: Option<Arc<RelayInfo>>,
+    /// How many times the client had reconnected before reading the message.
+    pub generation: u64,
+    pub origin: Origin,
+    /// `true` if a deduplicating stage saw the message from another source first.
+    pub duplicate: bool,
+    /// `true` if the message was decoded while its `DecodePool` was degraded, without recovering
+    /// the senders of its transactions: their `from` is then the zero address rather than the
+    /// actual sender.
+    pub degraded: bool,
+    /// When the message was received, in milliseconds since the UNIX epoch, 0 if unknown.
+    pub received_at_ms: u64,
+}
+
+impl Provenance {
+    /// The provenance of a message read from the live feed by relay `relay_id`.
+    pub fn live(relay_id: u32, generation: u64) -> Self {
+        Self {
+            relay_id,
+            relay: None,
+            generation,
+            origin: Origin::Live,
+            duplicate: false,
+            degraded: false,
+            received_at_ms: 0,
+        }
+    }
+
+    /// The provenance of a message read from a historical source of the given `origin`.
+    pub fn historical(relay_id: u32, origin: Origin) -> Self {
+        Self {
+            relay_id,
+            relay: None,
+            generation: 0,
+            origin,
+            duplicate: false,
+            degraded: false,
+            received_at_ms: 0,
+        }
+    }
+
+    /// Returns the relay the message was read from, or one known only by `relay_id`.
+    pub fn relay(&self) -> Arc<RelayInfo> {
+        self.relay
+            .clone()
+            .unwrap_or_else(|| Arc::new(RelayInfo::from_id(self.relay_id)))
+    }
+
+    /// Sets the relay the message was read from.
+    pub fn with_relay(mut self, relay: Arc<RelayInfo>) -> Self {
+        self.relay = Some(relay);
+        self
+    }
+
+    /// Sets when the message was received, in milliseconds since the UNIX epoch.
+    pub fn with_received_at_ms(mut self, received_at_ms: u64) -> Self {
+        self.received_at_ms = received_at_ms;
+        self
+    }
+}
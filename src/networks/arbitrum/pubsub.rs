@@ -0,0 +1,242 @@
+//! An ethers transport serving pending transaction subscriptions from the sequencer feed, so that
+//! bots built on `Provider::subscribe_pending_txs` can read the feed without changes.
+
+use crate::networks::arbitrum::{errors::FeedPubsubError, message::FeedMessage};
+use async_trait::async_trait;
+use crossbeam_channel::Receiver;
+use ethers::{
+    providers::{JsonRpcClient, PubsubClient},
+    types::{Transaction, U256},
+};
+use futures::channel::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{value::RawValue, Value};
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    sync::{Arc, Mutex},
+    thread::{self, JoinHandle},
+};
+
+/// A subscription opened with `eth_subscribe`.
+#[derive(Debug)]
+struct Subscription {
+    /// Whether full transactions are sent instead of their hashes.
+    full: bool,
+    sender: UnboundedSender<Box<RawValue>>,
+    /// The notification stream, until ethers takes it with `PubsubClient::subscribe`.
+    receiver: Option<UnboundedReceiver<Box<RawValue>>>,
+}
+
+#[derive(Debug, Default)]
+struct Subscriptions {
+    next_id: u64,
+    by_id: HashMap<U256, Subscription>,
+}
+
+/// A `JsonRpcClient` and `PubsubClient` answering `newPendingTransactions` subscriptions with the
+/// transactions of the feed, as soon as the sequencer orders them.
+///
+/// Only `eth_subscribe` to `newPendingTransactions`, with or without full transactions, and
+/// `eth_unsubscribe` are supported: other requests fail with `FeedPubsubError::Unsupported` and
+/// belong to a regular provider.
+///
+/// # Example
+///
+/// ```no_run
+/// use crossbeam_channel::Receiver;
+/// use ethers::providers::{Middleware, Provider, StreamExt};
+/// use sequencer_feed_reader::networks::arbitrum::{message::FeedMessage, pubsub::FeedPubsub};
+///
+/// # async fn run(messages: Receiver<FeedMessage>) -> Result<(), Box<dyn std::error::Error>> {
+/// let pubsub = FeedPubsub::new();
+/// pubsub.clone().spawn(messages);
+/// let provider = Provider::new(pubsub);
+/// let mut txs = provider.subscribe_full_pending_txs().await?;
+/// while let Some(tx) = txs.next().await {
+///     println!("{:?}", tx.hash);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct FeedPubsub {
+    subscriptions: Arc<Mutex<Subscriptions>>,
+}
+
+impl FeedPubsub {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Notifies the subscriptions of the transactions of `msg`.
+    pub fn publish(&self, msg: &FeedMessage) {
+        let txs = msg.transactions();
+        if txs.is_empty() {
+            return;
+        }
+        let mut subscriptions = self.subscriptions.lock().unwrap();
+        subscriptions
+            .by_id
+            .retain(|_, subscription| txs.iter().all(|tx| subscription.notify(&tx.tx).is_ok()));
+    }
+
+    /// Publishes the messages received on `input` on a new thread, until `input` is
+    /// disconnected.
+    pub fn spawn(self, input: Receiver<FeedMessage>) -> JoinHandle<()> {
+        thread::spawn(move || {
+            for msg in input {
+                self.publish(&msg);
+            }
+        })
+    }
+
+    /// Opens a subscription for the `eth_subscribe` parameters `params`.
+    fn open(&self, params: Value) -> Result<U256, FeedPubsubError> {
+        let full = match params.as_array().map(Vec::as_slice) {
+            Some([kind]) if kind == "newPendingTransactions" => false,
+            Some([kind, full]) if kind == "newPendingTransactions" => full.as_bool() == Some(true),
+            _ => {
+                return Err(FeedPubsubError::Unsupported(format!(
+                    "eth_subscribe {}",
+                    params
+                )))
+            }
+        };
+        let (sender, receiver) = mpsc::unbounded();
+        let mut subscriptions = self.subscriptions.lock().unwrap();
+        subscriptions.next_id += 1;
+        let id = U256::from(subscriptions.next_id);
+        subscriptions.by_id.insert(
+            id,
+            Subscription {
+                full,
+                sender,
+                receiver: Some(receiver),
+            },
+        );
+        Ok(id)
+    }
+}
+
+impl Subscription {
+    /// Sends `tx`, or its hash, to the subscriber. Fails once the subscriber is gone.
+    ///
+    /// Until the notification stream is taken, nobody reads the notifications: they are dropped
+    /// instead of piling up in the stream.
+    fn notify(&self, tx: &Transaction) -> Result<(), ()> {
+        if self.receiver.is_some() {
+            return Ok(());
+        }
+        let notification = if self.full {
+            serde_json::value::to_raw_value(tx)
+        } else {
+            serde_json::value::to_raw_value(&tx.hash)
+        };
+        let notification = notification.map_err(|_| ())?;
+        self.sender.unbounded_send(notification).map_err(|_| ())
+    }
+}
+
+#[async_trait]
+impl JsonRpcClient for FeedPubsub {
+    type Error = FeedPubsubError;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, Self::Error>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        let params = serde_json::to_value(params)?;
+        let result = match method {
+            "eth_subscribe" => serde_json::to_value(self.open(params)?)?,
+            "eth_unsubscribe" => {
+                let id: [U256; 1] = serde_json::from_value(params)?;
+                Value::Bool(self.unsubscribe(id[0]).is_ok())
+            }
+            _ => return Err(FeedPubsubError::Unsupported(method.to_string())),
+        };
+        Ok(serde_json::from_value(result)?)
+    }
+}
+
+impl PubsubClient for FeedPubsub {
+    type NotificationStream = UnboundedReceiver<Box<RawValue>>;
+
+    fn subscribe<T: Into<U256>>(&self, id: T) -> Result<Self::NotificationStream, Self::Error> {
+        let id = id.into();
+        self.subscriptions
+            .lock()
+            .unwrap()
+            .by_id
+            .get_mut(&id)
+            .and_then(|subscription| subscription.receiver.take())
+            .ok_or(FeedPubsubError::UnknownSubscription(id))
+    }
+
+    fn unsubscribe<T: Into<U256>>(&self, id: T) -> Result<(), Self::Error> {
+        let id = id.into();
+        match self.subscriptions.lock().unwrap().by_id.remove(&id) {
+            Some(_) => Ok(()),
+            None => Err(FeedPubsubError::UnknownSubscription(id)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::networks::arbitrum::{decoder::DecodedMsg, fixtures::feed_message};
+    use ethers::{
+        providers::{Middleware, Provider, StreamExt},
+        types::H256,
+    };
+
+    #[tokio::test]
+    async fn serves_pending_transactions_from_the_feed() {
+        let pubsub = FeedPubsub::new();
+        let provider = Provider::new(pubsub.clone());
+        let mut hashes = provider.subscribe_pending_txs().await.unwrap();
+        let mut txs = provider.subscribe_full_pending_txs().await.unwrap();
+
+        let tx = Transaction {
+            hash: H256::repeat_byte(7),
+            nonce: 3.into(),
+            ..Default::default()
+        };
+        pubsub.publish(&feed_message(
+            1,
+            0,
+            vec![],
+            Some(DecodedMsg::DecodedSignedTx(Box::new(tx.clone()))),
+        ));
+
+        assert_eq!(hashes.next().await, Some(tx.hash));
+        assert_eq!(txs.next().await, Some(tx));
+        assert!(provider.get_block_number().await.is_err());
+
+        drop(hashes);
+        assert_eq!(pubsub.subscriptions.lock().unwrap().by_id.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn drops_notifications_until_subscribed() {
+        let pubsub = FeedPubsub::new();
+        let id: U256 = pubsub
+            .request("eth_subscribe", ["newPendingTransactions"])
+            .await
+            .unwrap();
+        let msg = feed_message(
+            1,
+            0,
+            vec![],
+            Some(DecodedMsg::DecodedSignedTx(Box::default())),
+        );
+        pubsub.publish(&msg);
+
+        let mut notifications = PubsubClient::subscribe(&pubsub, id).unwrap();
+        assert!(notifications.try_recv().is_err());
+        pubsub.publish(&msg);
+        assert!(notifications.try_recv().is_ok());
+    }
+}
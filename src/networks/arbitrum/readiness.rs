@@ -0,0 +1,250 @@
+#[cfg(not(target_arch = "wasm32"))]
+use crate::networks::arbitrum::errors::RelayError;
+use crate::networks::arbitrum::{errors::ConnectionUpdate, events::FeedEvent};
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// How long the feed may stay silent before the reader is no longer caught up, by default.
+const DEFAULT_MAX_SILENCE: Duration = Duration::from_secs(10);
+/// How far the slowest sink may lag behind the fastest one, in messages, by default.
+const DEFAULT_MAX_SINK_LAG: u64 = 10_000;
+/// How long a fatal error keeps the reader unready, by default.
+const DEFAULT_FATAL_WINDOW: Duration = Duration::from_secs(60);
+
+/// A condition contributing to readiness.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Condition {
+    pub name: &'static str,
+    pub ok: bool,
+    /// Why the condition holds or not, for debugging.
+    pub detail: String,
+}
+
+/// The readiness of the reader, together with each condition it is made of.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadinessReport {
+    /// `true` if every condition holds.
+    pub ready: bool,
+    pub conditions: Vec<Condition>,
+}
+
+#[derive(Debug, Default)]
+struct Observations {
+    /// Whether each relay is connected, by relay ID, according to its last update.
+    relays: HashMap<u32, bool>,
+    last_message: Option<(Instant, u64)>,
+    /// `true` while a replay hasn't switched to the live feed yet.
+    replaying: bool,
+    /// The last `FeedEvent::Watermark`, as `(low, high)`.
+    watermarks: Option<(u64, u64)>,
+    last_fatal: Option<(Instant, String)>,
+}
+
+/// Computes a composite readiness condition, e.g. for Kubernetes readiness probes, from what it
+/// observes of the reader. The reader is ready when:
+///
+/// * `connected` - A relay is connected. Without connection updates, e.g. when reading through
+///   `subscribe`, a message received within `max_silence` counts as connected.
+/// * `caughtUp` - A message was received within `max_silence`, and a replay, if any, switched to
+///   the live feed.
+/// * `sinksHealthy` - The slowest sink lags at most `max_sink_lag` messages behind the fastest,
+///   according to the last `FeedEvent::Watermark`.
+/// * `noRecentFatal` - No fatal error happened within `fatal_window`.
+#[derive(Debug)]
+pub struct ReadinessMonitor {
+    max_silence: Duration,
+    max_sink_lag: u64,
+    fatal_window: Duration,
+    observations: Mutex<Observations>,
+}
+
+impl Default for ReadinessMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ReadinessMonitor {
+    pub fn new() -> Self {
+        Self {
+            max_silence: DEFAULT_MAX_SILENCE,
+            max_sink_lag: DEFAULT_MAX_SINK_LAG,
+            fatal_window: DEFAULT_FATAL_WINDOW,
+            observations: Mutex::default(),
+        }
+    }
+
+    pub fn with_max_silence(mut self, max_silence: Duration) -> Self {
+        self.max_silence = max_silence;
+        self
+    }
+
+    pub fn with_max_sink_lag(mut self, max_sink_lag: u64) -> Self {
+        self.max_sink_lag = max_sink_lag;
+        self
+    }
+
+    pub fn with_fatal_window(mut self, fatal_window: Duration) -> Self {
+        self.fatal_window = fatal_window;
+        self
+    }
+
+    /// Keeps the reader unready until a `FeedEvent::SwitchedToLive` is observed.
+    pub fn with_replay(self) -> Self {
+        self.observations.lock().unwrap().replaying = true;
+        self
+    }
+
+    /// Records the receipt of the message with `sequence_number`.
+    pub fn observe_message(&self, sequence_number: u64) {
+        self.observations.lock().unwrap().last_message = Some((Instant::now(), sequence_number));
+    }
+
+    pub fn observe_update(&self, update: &ConnectionUpdate) {
+        let mut observations = self.observations.lock().unwrap();
+        let connected = matches!(update, ConnectionUpdate::Connected { .. });
+        observations.relays.insert(update.id(), connected);
+        if let ConnectionUpdate::ProtocolError { relay, error, .. } = update {
+            observations.last_fatal = Some((
+                Instant::now(),
+                format!("relay {} violated the protocol: {}", relay, error),
+            ));
+        }
+    }
+
+    pub fn observe_event(&self, event: &FeedEvent) {
+        let mut observations = self.observations.lock().unwrap();
+        match event {
+            FeedEvent::SwitchedToLive { .. } => observations.replaying = false,
+            FeedEvent::Watermark { low, high } => observations.watermarks = Some((*low, *high)),
+            _ => (),
+        }
+    }
+
+    /// Records an error, which keeps the reader unready for a while if it is fatal.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn observe_error(&self, error: &RelayError) {
+        if error.is_fatal() {
+            self.observations.lock().unwrap().last_fatal =
+                Some((Instant::now(), error.to_string()));
+        }
+    }
+
+    /// Evaluates every condition.
+    pub fn report(&self) -> ReadinessReport {
+        let observations = self.observations.lock().unwrap();
+        let silence = observations.last_message.map(|(at, _)| at.elapsed());
+        let recent_message = silence.is_some_and(|silence| silence <= self.max_silence);
+        let last_message = match observations.last_message {
+            Some((at, seq)) => format!("last message {} received {:?} ago", seq, at.elapsed()),
+            None => "no message received".to_string(),
+        };
+
+        let connected = if observations.relays.is_empty() {
+            Condition {
+                name: "connected",
+                ok: recent_message,
+                detail: last_message.clone(),
+            }
+        } else {
+            let connected = observations.relays.values().filter(|c| **c).count();
+            Condition {
+                name: "connected",
+                ok: connected > 0,
+                detail: format!(
+                    "{} of {} relays connected",
+                    connected,
+                    observations.relays.len()
+                ),
+            }
+        };
+
+        let caught_up = Condition {
+            name: "caughtUp",
+            ok: recent_message && !observations.replaying,
+            detail: if observations.replaying {
+                "replaying history".to_string()
+            } else {
+                last_message
+            },
+        };
+
+        let sinks_healthy = match observations.watermarks {
+            Some((low, high)) => Condition {
+                name: "sinksHealthy",
+                ok: high.saturating_sub(low) <= self.max_sink_lag,
+                detail: format!("sink watermarks {}..{}", low, high),
+            },
+            None => Condition {
+                name: "sinksHealthy",
+                ok: true,
+                detail: "no sink watermark reported".to_string(),
+            },
+        };
+
+        let recent_fatal = observations
+            .last_fatal
+            .as_ref()
+            .filter(|(at, _)| at.elapsed() <= self.fatal_window);
+        let no_recent_fatal = Condition {
+            name: "noRecentFatal",
+            ok: recent_fatal.is_none(),
+            detail: match recent_fatal {
+                Some((at, error)) => format!("{:?} ago: {}", at.elapsed(), error),
+                None => "no fatal error".to_string(),
+            },
+        };
+
+        let conditions = vec![connected, caught_up, sinks_healthy, no_recent_fatal];
+        ReadinessReport {
+            ready: conditions.iter().all(|c| c.ok),
+            conditions,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::networks::arbitrum::identity::RelayInfo;
+    use std::{sync::Arc, time::SystemTime};
+
+    #[test]
+    fn ready_when_every_condition_holds() {
+        let failing = |monitor: &ReadinessMonitor| {
+            let report = monitor.report();
+            let failing: Vec<_> = report
+                .conditions
+                .iter()
+                .filter(|c| !c.ok)
+                .map(|c| c.name)
+                .collect();
+            assert_eq!(report.ready, failing.is_empty());
+            failing
+        };
+
+        let monitor = ReadinessMonitor::new().with_replay().with_max_sink_lag(10);
+        assert_eq!(failing(&monitor), ["connected", "caughtUp"]);
+
+        monitor.observe_update(&ConnectionUpdate::Connected {
+            relay: Arc::new(RelayInfo::from_id(0)),
+            at: SystemTime::now(),
+        });
+        monitor.observe_message(5);
+        monitor.observe_event(&FeedEvent::Watermark { low: 0, high: 20 });
+        assert_eq!(failing(&monitor), ["caughtUp", "sinksHealthy"]);
+
+        monitor.observe_event(&FeedEvent::SwitchedToLive { sequence_number: 5 });
+        monitor.observe_event(&FeedEvent::Watermark { low: 15, high: 20 });
+        assert!(failing(&monitor).is_empty());
+
+        monitor.observe_error(&RelayError::InvalidChainId);
+        assert_eq!(failing(&monitor), ["noRecentFatal"]);
+    }
+}
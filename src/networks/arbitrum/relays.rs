@@ -0,0 +1,921 @@
+pub use crate::networks::arbitrum::config::RelayStrategy;
+use crate::networks::arbitrum::{
+    connect::ConnectOptions, consistency::ConsistencyChecker, errors::ConnectionUpdate,
+    events::FeedEvent, feed_client::RelayClient, handle::RelayClientHandle, identity::RelayInfo,
+    reorg::ReorgDetector, status::RelayStatus as ClientStatus, types::Root,
+};
+use crossbeam_channel::{select, unbounded, Receiver, Sender};
+use log::*;
+use std::{
+    collections::{BTreeMap, VecDeque},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant, SystemTime},
+};
+use tokio::task;
+use url::Url;
+
+/// How long the active relay may stay silent while a standby keeps delivering.
+const DEFAULT_STALL_TIMEOUT: Duration = Duration::from_secs(2);
+/// How many frames are kept per standby, to fill the gap left by a failed relay on promotion.
+const DEFAULT_STANDBY_BUFFER: usize = 1024;
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+/// A relay connection going up or down, or the relay being given up on.
+enum RelayStatus {
+    Up(usize),
+    Down(usize),
+    Failed(usize),
+}
+
+/// How many messages were forwarded from each relay, by relay ID. With
+/// `RelayStrategy::FirstWins`, how many messages each relay delivered first.
+#[derive(Debug, Default)]
+pub struct RelayWins {
+    wins: Vec<AtomicU64>,
+}
+
+impl RelayWins {
+    fn new(relays: usize) -> Self {
+        Self {
+            wins: (0..relays).map(|_| AtomicU64::new(0)).collect(),
+        }
+    }
+
+    fn record(&self, relay_id: usize, messages: u64) {
+        if let Some(wins) = self.wins.get(relay_id) {
+            wins.fetch_add(messages, Ordering::Relaxed);
+        }
+    }
+
+    /// Returns the number of messages won by each relay.
+    pub fn wins(&self) -> Vec<u64> {
+        self.wins
+            .iter()
+            .map(|wins| wins.load(Ordering::Relaxed))
+            .collect()
+    }
+
+    /// Returns the share of the forwarded messages won by each relay, between 0 and 1.
+    pub fn win_rates(&self) -> Vec<f64> {
+        let wins = self.wins();
+        let total = wins.iter().sum::<u64>().max(1) as f64;
+        wins.into_iter().map(|wins| wins as f64 / total).collect()
+    }
+}
+
+/// The clients currently connected to the relays of a `RelayFailover`, by relay ID, to monitor
+/// and control them while they run. Clones share the same clients.
+///
+/// While paused, the clients connecting after a reconnection are paused too.
+#[derive(Debug, Clone, Default)]
+pub struct ConnectedRelays {
+    clients: Arc<Mutex<Clients>>,
+}
+
+/// The connected clients and whether they are paused, kept under the same lock so that a client
+/// connecting while the relays are paused or resumed ends up in the requested state.
+#[derive(Debug, Default)]
+struct Clients {
+    handles: Vec<Option<RelayClientHandle>>,
+    paused: bool,
+}
+
+impl ConnectedRelays {
+    /// Returns the handles of the connected clients.
+    pub fn handles(&self) -> Vec<RelayClientHandle> {
+        self.clients
+            .lock()
+            .unwrap()
+            .handles
+            .iter()
+            .flatten()
+            .cloned()
+            .collect()
+    }
+
+    /// Returns the status of the connected clients.
+    pub fn statuses(&self) -> Vec<ClientStatus> {
+        self.handles()
+            .iter()
+            .map(RelayClientHandle::status)
+            .collect()
+    }
+
+    /// Disconnects every connected client. Each relay is reconnected right after, resuming after
+    /// the last forwarded message.
+    ///
+    /// # Returns
+    ///
+    /// The number of clients disconnected.
+    pub fn reconnect(&self) -> usize {
+        self.handles()
+            .iter()
+            .filter(|handle| handle.shutdown())
+            .count()
+    }
+
+    /// Stops reading frames from every relay, keeping the connections open, see
+    /// `RelayClientHandle::pause`.
+    ///
+    /// # Returns
+    ///
+    /// The number of clients paused.
+    pub fn pause(&self) -> usize {
+        let mut clients = self.clients.lock().unwrap();
+        clients.paused = true;
+        clients
+            .handles
+            .iter()
+            .flatten()
+            .filter(|handle| handle.pause())
+            .count()
+    }
+
+    /// Reads frames from every relay again after `pause`.
+    ///
+    /// # Returns
+    ///
+    /// The number of clients resumed.
+    pub fn resume(&self) -> usize {
+        let mut clients = self.clients.lock().unwrap();
+        clients.paused = false;
+        clients
+            .handles
+            .iter()
+            .flatten()
+            .filter(|handle| handle.resume())
+            .count()
+    }
+
+    /// Returns `true` if the relays are paused.
+    pub fn is_paused(&self) -> bool {
+        self.clients.lock().unwrap().paused
+    }
+
+    fn reset(&self, relays: usize) {
+        self.clients.lock().unwrap().handles = vec![None; relays];
+    }
+
+    fn set(&self, id: usize, client: Option<RelayClientHandle>) {
+        let mut clients = self.clients.lock().unwrap();
+        if let Some(client) = client.as_ref().filter(|_| clients.paused) {
+            client.pause();
+        }
+        if let Some(slot) = clients.handles.get_mut(id) {
+            *slot = client;
+        }
+    }
+}
+
+/// Reads the feed from a primary relay, keeping the others connected as hot standbys.
+///
+/// Only the messages of the active relay are forwarded. When it disconnects, or stalls while a
+/// standby keeps delivering, the first connected standby is promoted: its buffered messages fill
+/// the gap left by the failed relay, and messages already forwarded are never forwarded again.
+/// A promoted relay stays active until it fails in turn.
+///
+/// With `RelayStrategy::FirstWins`, every relay is raced instead: each message is forwarded
+/// from the first relay to deliver it, recorded in the provenance of its `Root`, and the later
+/// copies are dropped. Messages are forwarded in order: those delivered after a gap are held
+/// until another relay fills it, or the stall timeout elapses.
+///
+/// Either way, the failover stops once every relay is given up on.
+pub struct RelayFailover {
+    chain_id: u64,
+    strategy: RelayStrategy,
+    relays: Vec<(Url, ConnectOptions)>,
+    stall_timeout: Duration,
+    standby_buffer: usize,
+    sequence_number: u64,
+    reconnect_delay: (Duration, Duration),
+    /// The number of sequence numbers cross-checked between relays, if enabled.
+    consistency_window: Option<usize>,
+    replay_reorgs: bool,
+    connected: ConnectedRelays,
+    updates: Option<Sender<ConnectionUpdate>>,
+}
+
+/// The tasks of a running `RelayFailover`.
+pub struct RelayFailoverHandle {
+    relays: Vec<task::JoinHandle<()>>,
+    manager: JoinHandle<()>,
+    wins: Arc<RelayWins>,
+    connected: ConnectedRelays,
+}
+
+impl RelayFailover {
+    /// # Arguments
+    ///
+    /// * `chain_id` - The expected chain ID of every relay.
+    /// * `primary` - The URL of the relay read from first, with ID 0.
+    pub fn new(chain_id: u64, primary: Url) -> Self {
+        Self {
+            chain_id,
+            strategy: RelayStrategy::default(),
+            relays: vec![(primary, ConnectOptions::new())],
+            stall_timeout: DEFAULT_STALL_TIMEOUT,
+            standby_buffer: DEFAULT_STANDBY_BUFFER,
+            sequence_number: 0,
+            reconnect_delay: (RECONNECT_DELAY, MAX_RECONNECT_DELAY),
+            consistency_window: None,
+            replay_reorgs: false,
+            connected: ConnectedRelays::default(),
+            updates: None,
+        }
+    }
+
+    /// Sets how the relays whose messages are forwarded are picked.
+    pub fn with_strategy(mut self, strategy: RelayStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Adds a standby relay, with the next ID.
+    pub fn with_standby(mut self, url: Url) -> Self {
+        self.relays.push((url, ConnectOptions::new()));
+        self
+    }
+
+    /// Connects to the last added relay with `options`. The sequence number is managed by the
+    /// failover and overridden.
+    pub fn with_connect_options(mut self, options: ConnectOptions) -> Self {
+        if let Some(relay) = self.relays.last_mut() {
+            relay.1 = options;
+        }
+        self
+    }
+
+    /// Sets how long the active relay may stay silent while a standby keeps delivering before
+    /// the standby is promoted. This bounds how long a failover takes.
+    pub fn with_stall_timeout(mut self, stall_timeout: Duration) -> Self {
+        self.stall_timeout = stall_timeout;
+        self
+    }
+
+    /// Starts the feed at `sequence_number`, e.g. to resume after the last processed message.
+    pub fn with_sequence_number(mut self, sequence_number: u64) -> Self {
+        self.sequence_number = sequence_number;
+        self
+    }
+
+    /// Sets how long to wait before reconnecting to a relay. The delay doubles after every failed
+    /// attempt, up to `max_delay`, and is reset once a connection succeeds.
+    pub fn with_reconnect_delay(mut self, delay: Duration, max_delay: Duration) -> Self {
+        self.reconnect_delay = (delay, max_delay.max(delay));
+        self
+    }
+
+    /// Sets how many frames are kept per standby.
+    pub fn with_standby_buffer(mut self, frames: usize) -> Self {
+        self.standby_buffer = frames.max(1);
+        self
+    }
+
+    /// Compares the messages of every relay, standbys included, and reports those delivered with
+    /// different contents by different relays as `FeedEvent::RelayMismatch`.
+    ///
+    /// # Arguments
+    ///
+    /// * `window` - How many recent sequence numbers are remembered for the comparison, e.g.
+    ///   `consistency::DEFAULT_CONSISTENCY_WINDOW`.
+    pub fn with_consistency_check(mut self, window: usize) -> Self {
+        self.consistency_window = Some(window);
+        self
+    }
+
+    /// Forwards the messages re-sent by a relay after a sequencer reorg, superseding those
+    /// forwarded before, instead of dropping them as duplicates. Reorgs are reported as
+    /// `FeedEvent::Reorg` either way.
+    pub fn with_reorg_replay(mut self) -> Self {
+        self.replay_reorgs = true;
+        self
+    }
+
+    /// Registers the connected clients in `connected`, e.g. to keep monitoring the relays across
+    /// failovers restarted with the same `ConnectedRelays`.
+    pub fn with_connected_relays(mut self, connected: ConnectedRelays) -> Self {
+        self.connected = connected;
+        self
+    }
+
+    /// Sends the `ConnectionUpdate`s of every relay to `updates`, including a
+    /// `ConnectionUpdate::Reconnecting` before every reconnection attempt.
+    pub fn with_connection_updates(mut self, updates: Sender<ConnectionUpdate>) -> Self {
+        self.updates = Some(updates);
+        self
+    }
+
+    /// Connects to every relay and starts forwarding the messages of the primary.
+    ///
+    /// Must be called from within a Tokio runtime.
+    ///
+    /// # Arguments
+    ///
+    /// * `sender` - The sender channel for sending `Root` messages.
+    /// * `events` - The sender channel for `FeedEvent::RelayPromoted` events.
+    pub fn spawn(self, sender: Sender<Root>, events: Sender<FeedEvent>) -> RelayFailoverHandle {
+        let (roots_tx, roots_rx) = unbounded();
+        let (status_tx, status_rx) = unbounded();
+        let next_sequence_number = Arc::new(AtomicU64::new(self.sequence_number));
+        let infos = self
+            .relays
+            .iter()
+            .enumerate()
+            .map(|(id, (url, options))| options.relay_info(id as u32, url))
+            .collect();
+        self.connected.reset(self.relays.len());
+        // Without a receiver, the updates are dropped as they are sent.
+        let updates = self.updates.unwrap_or_else(|| unbounded().0);
+
+        let relays = self
+            .relays
+            .into_iter()
+            .enumerate()
+            .map(|(id, relay)| {
+                task::spawn(supervise(
+                    id,
+                    relay,
+                    self.chain_id,
+                    self.reconnect_delay,
+                    next_sequence_number.clone(),
+                    roots_tx.clone(),
+                    (status_tx.clone(), self.connected.clone(), updates.clone()),
+                ))
+            })
+            .collect::<Vec<_>>();
+
+        let wins = Arc::new(RelayWins::new(relays.len()));
+        let mut manager = Manager {
+            infos,
+            strategy: self.strategy,
+            wins: wins.clone(),
+            consistency: self.consistency_window.map(ConsistencyChecker::new),
+            reorgs: vec![ReorgDetector::new(); relays.len()],
+            last_reorg: None,
+            replay_reorgs: self.replay_reorgs,
+            active: 0,
+            up: vec![false; relays.len()],
+            live: relays.len(),
+            buffers: vec![VecDeque::new(); relays.len()],
+            pending: BTreeMap::new(),
+            gap_since: None,
+            last_forwarded: None,
+            last_active_frame: Instant::now(),
+            stall_timeout: self.stall_timeout,
+            standby_buffer: self.standby_buffer,
+            next_sequence_number,
+            sender,
+            events,
+        };
+        let manager = thread::spawn(move || manager.run(roots_rx, status_rx));
+
+        RelayFailoverHandle {
+            relays,
+            manager,
+            wins,
+            connected: self.connected,
+        }
+    }
+}
+
+impl RelayFailoverHandle {
+    /// Returns how many messages were forwarded from each relay.
+    pub fn wins(&self) -> &RelayWins {
+        &self.wins
+    }
+
+    /// Returns the clients currently connected to the relays.
+    pub fn connected(&self) -> &ConnectedRelays {
+        &self.connected
+    }
+
+    /// Disconnects from every relay and waits for the pending messages to be forwarded.
+    pub async fn stop(self) {
+        for relay in self.relays {
+            relay.abort();
+        }
+        self.connected.reset(0);
+        let manager = self.manager;
+        let _ = task::spawn_blocking(move || manager.join()).await;
+    }
+}
+
+/// Keeps a relay connected, resuming after the last forwarded message.
+async fn supervise(
+    id: usize,
+    (url, options): (Url, ConnectOptions),
+    chain_id: u64,
+    (reconnect_delay, max_reconnect_delay): (Duration, Duration),
+    next_sequence_number: Arc<AtomicU64>,
+    roots: Sender<Root>,
+    (status, connected, update): (
+        Sender<RelayStatus>,
+        ConnectedRelays,
+        Sender<ConnectionUpdate>,
+    ),
+) {
+    let info = Arc::new(options.relay_info(id as u32, &url));
+    let mut generation = 0;
+    let mut attempt = 0;
+    let mut delay = reconnect_delay;
+    loop {
+        let options = options
+            .clone()
+            .with_sequence_number(next_sequence_number.load(Ordering::Acquire));
+        match RelayClient::connect(
+            url.clone(),
+            chain_id,
+            id as u32,
+            options,
+            roots.clone(),
+            update.clone(),
+        )
+        .await
+        {
+            Ok(client) => {
+                delay = reconnect_delay;
+                attempt = 0;
+                let _ = status.send(RelayStatus::Up(id));
+                connected.set(id, Some(client.handle()));
+                let result = client.with_generation(generation).run().await;
+                connected.set(id, None);
+                if let Err(e) = result {
+                    warn!(
+                        "Relay {} stopped [{}/{}]: {}",
+                        info,
+                        e.category(),
+                        e.code(),
+                        e
+                    );
+                }
+            }
+            Err(e) => {
+                debug!(
+                    "Relay {} failed to connect [{}/{}]: {}",
+                    info,
+                    e.category(),
+                    e.code(),
+                    e
+                );
+                if e.is_fatal() {
+                    error!("Giving up on relay {}: {}", info, e);
+                    let _ = status.send(RelayStatus::Failed(id));
+                    return;
+                }
+            }
+        }
+        if status.send(RelayStatus::Down(id)).is_err() {
+            return;
+        }
+        generation += 1;
+        attempt += 1;
+        let _ = update.send(ConnectionUpdate::Reconnecting {
+            relay: info.clone(),
+            attempt,
+            at: SystemTime::now(),
+        });
+        tokio::time::sleep(delay).await;
+        delay = (delay * 2).min(max_reconnect_delay);
+    }
+}
+
+struct Manager {
+    infos: Vec<RelayInfo>,
+    strategy: RelayStrategy,
+    wins: Arc<RelayWins>,
+    consistency: Option<ConsistencyChecker>,
+    /// Detects reorgs in the frames of every relay.
+    reorgs: Vec<ReorgDetector>,
+    /// The last reorg reported, so that racing relays report it once.
+    last_reorg: Option<FeedEvent>,
+    replay_reorgs: bool,
+    active: usize,
+    up: Vec<bool>,
+    /// The number of relays not given up on.
+    live: usize,
+    /// The recent frames of every standby.
+    buffers: Vec<VecDeque<Root>>,
+    /// With `RelayStrategy::FirstWins`, the frames delivered after a gap, by first sequence
+    /// number.
+    pending: BTreeMap<u64, Root>,
+    /// When the oldest pending frame was held.
+    gap_since: Option<Instant>,
+    last_forwarded: Option<u64>,
+    last_active_frame: Instant,
+    stall_timeout: Duration,
+    standby_buffer: usize,
+    next_sequence_number: Arc<AtomicU64>,
+    sender: Sender<Root>,
+    events: Sender<FeedEvent>,
+}
+
+impl Manager {
+    fn run(&mut self, roots: Receiver<Root>, status: Receiver<RelayStatus>) {
+        loop {
+            select! {
+                recv(roots) -> root => match root {
+                    Ok(root) => {
+                        if !self.receive(root) {
+                            return;
+                        }
+                    }
+                    Err(_) => return,
+                },
+                recv(status) -> status => match status {
+                    Ok(RelayStatus::Up(id)) => self.up[id] = true,
+                    Ok(RelayStatus::Down(id)) => {
+                        if !self.relay_down(id) {
+                            return;
+                        }
+                    }
+                    Ok(RelayStatus::Failed(id)) => {
+                        self.live -= 1;
+                        if !self.relay_down(id) || self.live == 0 {
+                            warn!("Every relay was given up on");
+                            return;
+                        }
+                    }
+                    Err(_) => return,
+                },
+                default(self.stall_timeout) => (),
+            }
+
+            if self
+                .gap_since
+                .is_some_and(|since| since.elapsed() > self.stall_timeout)
+                && !self.skip_gap()
+            {
+                return;
+            }
+
+            if self.last_active_frame.elapsed() > self.stall_timeout && self.standby_is_ahead() {
+                warn!(
+                    "Relay {} stalled for {:?}",
+                    self.infos[self.active],
+                    self.last_active_frame.elapsed()
+                );
+                if !self.promote() {
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Handles a frame, returning `false` once the consumer is gone.
+    fn receive(&mut self, root: Root) -> bool {
+        let id = root.provenance.relay_id as usize;
+        let reorg = self
+            .reorgs
+            .get_mut(id)
+            .and_then(|reorgs| reorgs.observe(&root));
+        if let (Some(checker), Some(FeedEvent::Reorg { from, .. })) =
+            (&mut self.consistency, &reorg)
+        {
+            checker.reorg(*from);
+        }
+        if let Some(checker) = &mut self.consistency {
+            for event in checker.check(&root) {
+                let _ = self.events.send(event);
+            }
+        }
+
+        if let Some(reorg) = reorg {
+            if (self.strategy == RelayStrategy::FirstWins || id == self.active)
+                && self.last_reorg.as_ref() != Some(&reorg)
+            {
+                self.handle_reorg(id, reorg);
+            }
+        }
+
+        if self.strategy == RelayStrategy::FirstWins {
+            return self.forward_in_order(root);
+        }
+        if id == self.active {
+            self.last_active_frame = Instant::now();
+            return self.forward(root);
+        }
+
+        if let Some(buffer) = self.buffers.get_mut(id) {
+            if buffer.len() == self.standby_buffer {
+                buffer.pop_front();
+            }
+            buffer.push_back(root);
+        }
+        true
+    }
+
+    /// Reports a reorg of the feed of relay `id`, and replays the superseded range if enabled.
+    fn handle_reorg(&mut self, id: usize, reorg: FeedEvent) {
+        warn!(
+            "Relay {} re-sent messages after a reorg: {:?}",
+            self.infos[id], reorg
+        );
+        if let (true, FeedEvent::Reorg { from, .. }) = (self.replay_reorgs, &reorg) {
+            self.last_forwarded = from.checked_sub(1);
+        }
+        self.last_reorg = Some(reorg.clone());
+        let _ = self.events.send(reorg);
+    }
+
+    /// Forwards the messages of `root` that haven't been forwarded yet.
+    fn forward(&mut self, mut root: Root) -> bool {
+        if let Some(last) = self.last_forwarded {
+            root.messages.retain(|m| m.sequence_number > last);
+        }
+        let Some(last) = root.messages.last().map(|m| m.sequence_number) else {
+            return true;
+        };
+        self.last_forwarded = Some(last);
+        self.wins.record(
+            root.provenance.relay_id as usize,
+            root.messages.len() as u64,
+        );
+        self.next_sequence_number.store(last + 1, Ordering::Release);
+        self.sender.send(root).is_ok()
+    }
+
+    /// Forwards `root` if it follows the last forwarded message, along with the pending frames it
+    /// makes contiguous, or holds it until the gap before it is filled.
+    fn forward_in_order(&mut self, mut root: Root) -> bool {
+        if let Some(last) = self.last_forwarded {
+            root.messages.retain(|m| m.sequence_number > last);
+        }
+        let Some(first) = root.messages.first().map(|m| m.sequence_number) else {
+            return true;
+        };
+        if self.last_forwarded.is_some_and(|last| first > last + 1) {
+            self.gap_since.get_or_insert_with(Instant::now);
+            self.pending.entry(first).or_insert(root);
+            return true;
+        }
+        self.forward(root) && self.forward_pending()
+    }
+
+    /// Forwards the pending frames following the last forwarded message.
+    fn forward_pending(&mut self) -> bool {
+        while let Some(entry) = self.pending.first_entry() {
+            let last = self.last_forwarded.unwrap_or(0);
+            let Some(end) = entry.get().messages.last().map(|m| m.sequence_number) else {
+                entry.remove();
+                continue;
+            };
+            if end <= last {
+                entry.remove();
+            } else if *entry.key() <= last + 1 {
+                let root = entry.remove();
+                if !self.forward(root) {
+                    return false;
+                }
+            } else {
+                return true;
+            }
+        }
+        self.gap_since = None;
+        true
+    }
+
+    /// Forwards the pending frames after a gap no relay filled in time.
+    fn skip_gap(&mut self) -> bool {
+        let Some((&first, _)) = self.pending.first_key_value() else {
+            self.gap_since = None;
+            return true;
+        };
+        warn!(
+            "No relay delivered messages {:?} within {:?}, skipping them",
+            self.last_forwarded.map_or(0, |last| last + 1)..first,
+            self.stall_timeout
+        );
+        self.last_forwarded = first.checked_sub(1);
+        self.gap_since = Some(Instant::now());
+        self.forward_pending()
+    }
+
+    /// Handles relay `id` disconnecting, returning `false` once the consumer is gone.
+    fn relay_down(&mut self, id: usize) -> bool {
+        self.up[id] = false;
+        self.strategy == RelayStrategy::FirstWins || id != self.active || self.promote()
+    }
+
+    fn standby_is_ahead(&self) -> bool {
+        self.buffers
+            .iter()
+            .enumerate()
+            .filter(|&(id, _)| id != self.active)
+            .filter_map(|(_, buffer)| buffer.back()?.messages.last())
+            .any(|m| {
+                self.last_forwarded
+                    .is_none_or(|last| m.sequence_number > last)
+            })
+    }
+
+    /// Promotes the first connected standby and forwards its buffered messages, returning `false`
+    /// once the consumer is gone.
+    fn promote(&mut self) -> bool {
+        let Some(standby) = (0..self.up.len()).find(|&id| id != self.active && self.up[id]) else {
+            debug!(
+                "No standby to promote after relay {} failed",
+                self.infos[self.active]
+            );
+            return true;
+        };
+
+        info!(
+            "Promoting relay {} after relay {} failed",
+            self.infos[standby], self.infos[self.active]
+        );
+        let _ = self.events.send(FeedEvent::RelayPromoted {
+            relay: self.infos[standby].clone(),
+            previous: self.infos[self.active].clone(),
+        });
+        self.active = standby;
+        self.last_active_frame = Instant::now();
+        let buffered = std::mem::take(&mut self.buffers[standby]);
+        buffered.into_iter().all(|root| self.forward(root))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::networks::arbitrum::mock::{MockRelay, Scenario, SimulatedSequencer, Step};
+    use crossbeam_channel::RecvTimeoutError;
+
+    async fn relay(scenario: Scenario) -> Url {
+        let events = SimulatedSequencer::new(0, 1_700_000_000, 1).generate(&scenario);
+        let relay = MockRelay::bind("127.0.0.1:0", 42161, events).await.unwrap();
+        let url = Url::parse(&format!("ws://{}", relay.local_addr().unwrap())).unwrap();
+        relay.spawn();
+        url
+    }
+
+    #[tokio::test]
+    async fn promotes_standby_without_duplicates() {
+        let blocks = |count| Step::Blocks {
+            count,
+            interval_ms: 20,
+        };
+        let primary = relay(Scenario::new().then(blocks(3)).then(Step::Disconnect)).await;
+        let standby = relay(Scenario::new().then(blocks(6))).await;
+
+        let (sender, receiver) = unbounded();
+        let (events_tx, events_rx) = unbounded();
+        let (updates_tx, updates_rx) = unbounded();
+        let handle = RelayFailover::new(42161, primary.clone())
+            .with_standby(standby.clone())
+            .with_connection_updates(updates_tx)
+            .spawn(sender, events_tx);
+
+        let mut sequence_numbers = Vec::new();
+        while sequence_numbers.len() < 6 {
+            let root = task::spawn_blocking({
+                let receiver = receiver.clone();
+                move || receiver.recv_timeout(Duration::from_secs(5))
+            })
+            .await
+            .unwrap()
+            .expect("no failover");
+            sequence_numbers.extend(root.messages.iter().map(|m| m.sequence_number));
+        }
+        let reconnecting = task::spawn_blocking(move || loop {
+            match updates_rx.recv_timeout(Duration::from_secs(5)) {
+                Ok(ConnectionUpdate::Reconnecting { relay, attempt, .. }) => {
+                    return Some((relay.id, attempt))
+                }
+                Ok(_) => continue,
+                Err(_) => return None,
+            }
+        })
+        .await
+        .unwrap();
+        handle.stop().await;
+
+        assert_eq!(reconnecting, Some((0, 1)));
+        assert_eq!(sequence_numbers, vec![0, 1, 2, 3, 4, 5]);
+        assert_eq!(
+            events_rx.try_recv(),
+            Ok(FeedEvent::RelayPromoted {
+                relay: RelayInfo::new(1, &standby),
+                previous: RelayInfo::new(0, &primary),
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn forwards_messages_from_the_first_relay() {
+        let blocks = |count| Step::Blocks {
+            count,
+            interval_ms: 10,
+        };
+        // The first relay delivers 0 and 1 first, the second one 2 and 3.
+        let first = relay(
+            Scenario::new()
+                .then(blocks(2))
+                .then(Step::Stall { ms: 400 })
+                .then(blocks(2)),
+        )
+        .await;
+        let second = relay(
+            Scenario::new()
+                .then(Step::Stall { ms: 200 })
+                .then(blocks(4)),
+        )
+        .await;
+
+        let (sender, receiver) = unbounded();
+        let (events_tx, _events_rx) = unbounded();
+        let handle = RelayFailover::new(42161, first)
+            .with_standby(second)
+            .with_strategy(RelayStrategy::FirstWins)
+            .spawn(sender, events_tx);
+
+        let mut winners = Vec::new();
+        while winners.len() < 4 {
+            let root = task::spawn_blocking({
+                let receiver = receiver.clone();
+                move || receiver.recv_timeout(Duration::from_secs(5))
+            })
+            .await
+            .unwrap()
+            .expect("no message");
+            winners.extend(
+                root.messages
+                    .iter()
+                    .map(|m| (m.sequence_number, root.provenance.relay_id)),
+            );
+        }
+        assert_eq!(winners, vec![(0, 0), (1, 0), (2, 1), (3, 1)]);
+        assert_eq!(handle.wins().wins(), vec![2, 2]);
+        assert_eq!(handle.wins().win_rates(), vec![0.5, 0.5]);
+        handle.stop().await;
+    }
+
+    #[tokio::test]
+    async fn forwards_messages_in_order_across_relays() {
+        let blocks = |count| Step::Blocks {
+            count,
+            interval_ms: 10,
+        };
+        // The first relay skips 2, which the second one delivers later.
+        let first = relay(
+            Scenario::new()
+                .then(blocks(2))
+                .then(Step::Gap { count: 1 })
+                .then(blocks(2))
+                .then(Step::Stall { ms: 60_000 }),
+        )
+        .await;
+        let second = relay(
+            Scenario::new()
+                .then(Step::Stall { ms: 300 })
+                .then(blocks(5))
+                .then(Step::Stall { ms: 60_000 }),
+        )
+        .await;
+
+        let (sender, receiver) = unbounded();
+        let (events_tx, _events_rx) = unbounded();
+        let handle = RelayFailover::new(42161, first)
+            .with_standby(second)
+            .with_strategy(RelayStrategy::FirstWins)
+            .spawn(sender, events_tx);
+
+        let mut sequence_numbers = Vec::new();
+        while sequence_numbers.len() < 5 {
+            let root = task::spawn_blocking({
+                let receiver = receiver.clone();
+                move || receiver.recv_timeout(Duration::from_secs(5))
+            })
+            .await
+            .unwrap()
+            .expect("no message");
+            sequence_numbers.extend(root.messages.iter().map(|m| m.sequence_number));
+        }
+        assert_eq!(sequence_numbers, vec![0, 1, 2, 3, 4]);
+        handle.stop().await;
+    }
+
+    #[tokio::test]
+    async fn stops_once_every_relay_is_given_up_on() {
+        let first = relay(Scenario::new().then(Step::Stall { ms: 60_000 })).await;
+        let second = relay(Scenario::new().then(Step::Stall { ms: 60_000 })).await;
+
+        // Both relays serve another chain.
+        let (sender, receiver) = unbounded();
+        let (events_tx, _events_rx) = unbounded();
+        let handle = RelayFailover::new(1, first)
+            .with_standby(second)
+            .with_strategy(RelayStrategy::FirstWins)
+            .spawn(sender, events_tx);
+
+        let received = task::spawn_blocking(move || receiver.recv_timeout(Duration::from_secs(5)))
+            .await
+            .unwrap();
+        assert_eq!(received, Err(RecvTimeoutError::Disconnected));
+        handle.stop().await;
+    }
+}
@@ -0,0 +1,64 @@
+use crate::networks::arbitrum::{events::FeedEvent, types::Root};
+
+/// Detects sequencer reorgs of the feed.
+///
+/// After a reorg, Nitro relays re-send the messages from the first superseded sequence number
+/// on, with their new contents: the sequence numbers of a relay going backwards mark a reorg.
+#[derive(Debug, Clone, Default)]
+pub struct ReorgDetector {
+    last_sequence_number: Option<u64>,
+}
+
+impl ReorgDetector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Observes the next frame of a relay.
+    ///
+    /// # Returns
+    ///
+    /// A `FeedEvent::Reorg` if `root` re-sends messages received before, from its first message
+    /// to the last message received before it.
+    pub fn observe(&mut self, root: &Root) -> Option<FeedEvent> {
+        let (first, last) = (root.messages.first()?, root.messages.last()?);
+        let reorg = match self.last_sequence_number {
+            Some(previous) if first.sequence_number <= previous => Some(FeedEvent::Reorg {
+                from: first.sequence_number,
+                to: previous,
+            }),
+            _ => None,
+        };
+        // The feed goes on from the new messages, even if they end before the superseded ones.
+        self.last_sequence_number = Some(last.sequence_number);
+        reorg
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::networks::arbitrum::fixtures::message_with;
+
+    #[test]
+    fn detects_sequence_numbers_going_backwards() {
+        let root = |seqs: &[u64]| Root {
+            version: 1,
+            messages: seqs
+                .iter()
+                .map(|&seq| message_with(seq, 0, vec![]))
+                .collect(),
+            provenance: Default::default(),
+        };
+        let mut detector = ReorgDetector::new();
+
+        assert_eq!(detector.observe(&root(&[1, 2])), None);
+        assert_eq!(detector.observe(&root(&[3])), None);
+        assert_eq!(detector.observe(&root(&[])), None);
+        assert_eq!(
+            detector.observe(&root(&[2])),
+            Some(FeedEvent::Reorg { from: 2, to: 3 })
+        );
+        assert_eq!(detector.observe(&root(&[3, 4])), None);
+    }
+}
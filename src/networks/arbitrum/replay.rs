@@ -0,0 +1,281 @@
+use crate::networks::arbitrum::{
+    archive::{history::FeedArchive, Archive},
+    connect::ConnectOptions,
+    errors::{ArchiveError, ConnectionUpdate, RelayError},
+    events::FeedEvent,
+    feed_client::RelayClient,
+    provenance::{Origin, Provenance},
+    store::QueryRange,
+    types::{BroadcastFeedMessage, Root},
+};
+use crossbeam_channel::Sender;
+use log::*;
+use url::Url;
+
+/// The broadcast format version used for replayed messages.
+const REPLAY_ROOT_VERSION: u8 = 1;
+
+/// A source of historical feed messages, such as an archive or a backfill service.
+pub trait ReplaySource {
+    /// Returns the messages with a sequence number of at least `from`, in sequence order.
+    fn read_from(&self, from: u64) -> Result<Vec<BroadcastFeedMessage>, ArchiveError>;
+}
+
+impl ReplaySource for Archive {
+    fn read_from(&self, from: u64) -> Result<Vec<BroadcastFeedMessage>, ArchiveError> {
+        self.read(from, u64::MAX, |_| true)
+    }
+}
+
+impl ReplaySource for FeedArchive {
+    fn read_from(&self, from: u64) -> Result<Vec<BroadcastFeedMessage>, ArchiveError> {
+        self.messages(QueryRange::Sequence(from..=u64::MAX))?
+            .map(|msg| msg.map(|msg| msg.message))
+            .collect()
+    }
+}
+
+/// Replays a source of historical messages, then switches to the live feed.
+///
+/// The live connection requests the sequence number following the last replayed message and
+/// drops anything older, so consumers see every message exactly once across the switchover. If
+/// the relay no longer has that message, the live client fails with `RelayError::SequenceGap`
+/// rather than leaving a gap at the seam.
+pub struct ReplayToLive<S> {
+    source: S,
+    from: u64,
+    url: Url,
+    chain_id: u64,
+    id: u32,
+    options: ConnectOptions,
+}
+
+impl<S: ReplaySource> ReplayToLive<S> {
+    /// # Arguments
+    ///
+    /// * `source` - The source of historical messages.
+    /// * `from` - The sequence number of the first message to emit.
+    /// * `url` - The URL of the relay to switch to.
+    /// * `chain_id` - The expected chain ID of the relay.
+    /// * `id` - The ID of the live client.
+    pub fn new(source: S, from: u64, url: Url, chain_id: u64, id: u32) -> Self {
+        Self {
+            source,
+            from,
+            url,
+            chain_id,
+            id,
+            options: ConnectOptions::new(),
+        }
+    }
+
+    /// Connects to the relay as described by `options`, e.g. through a proxy. The sequence
+    /// number of the options is replaced by the one following the replay.
+    pub fn with_connect_options(mut self, options: ConnectOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Replays the source and returns a live client picking up where it ended.
+    ///
+    /// The source is read again until it has no newer messages, so an archive that is still being
+    /// recorded to is replayed to its very end. `FeedEvent::SwitchedToLive` is emitted once the
+    /// live client is connected.
+    ///
+    /// # Arguments
+    ///
+    /// * `sender` - The sender channel for sending `Root` messages, replayed and live.
+    /// * `connection_update` - The sender channel for the live client's `ConnectionUpdate`s.
+    /// * `events` - The sender channel for sending `FeedEvent`s.
+    pub async fn connect(
+        self,
+        sender: Sender<Root>,
+        connection_update: Sender<ConnectionUpdate>,
+        events: Sender<FeedEvent>,
+    ) -> Result<RelayClient, RelayError> {
+        let mut next = self.from;
+        let provenance = Provenance::historical(self.id, Origin::Replay);
+        loop {
+            let messages = self.source.read_from(next)?;
+            let Some(last) = messages.last() else {
+                break;
+            };
+            next = last.sequence_number + 1;
+
+            for msg in messages {
+                let root = Root {
+                    version: REPLAY_ROOT_VERSION,
+                    messages: vec![msg],
+                    provenance: provenance.clone(),
+                };
+                if sender.send(root).is_err() {
+                    return Err(RelayError::Msg(
+                        "Receiver dropped during replay".to_string(),
+                    ));
+                }
+            }
+        }
+
+        info!(
+            "Replay done, switching to live feed at sequence number {}",
+            next
+        );
+        let options = self
+            .options
+            .with_sequence_number(next)
+            .with_contiguous_start();
+        let client = RelayClient::connect(
+            self.url,
+            self.chain_id,
+            self.id,
+            options,
+            sender,
+            connection_update,
+        )
+        .await?;
+        let _ = events.send(FeedEvent::SwitchedToLive {
+            sequence_number: next,
+        });
+
+        Ok(client)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::networks::arbitrum::{
+        archive::ArchiveWriter,
+        mock::{MockRelay, Scenario, SimEvent, SimulatedSequencer, Step},
+    };
+    use std::{cell::RefCell, fs};
+
+    /// A source growing between reads, like an archive still being recorded to.
+    struct Recording(RefCell<Vec<Vec<BroadcastFeedMessage>>>);
+
+    impl ReplaySource for Recording {
+        fn read_from(&self, from: u64) -> Result<Vec<BroadcastFeedMessage>, ArchiveError> {
+            let mut batches = self.0.borrow_mut();
+            if batches.is_empty() {
+                return Ok(Vec::new());
+            }
+            let mut batch = batches.remove(0);
+            batch.retain(|msg| msg.sequence_number >= from);
+            Ok(batch)
+        }
+    }
+
+    fn recorded(count: u64) -> Vec<BroadcastFeedMessage> {
+        SimulatedSequencer::new(0, 1_700_000_000, 1)
+            .generate(&Scenario::new().then(Step::Blocks {
+                count,
+                interval_ms: 10,
+            }))
+            .into_iter()
+            .filter_map(|event| match event {
+                SimEvent::Message(msg) => Some(msg),
+                _ => None,
+            })
+            .collect()
+    }
+
+    async fn replay_then_live(
+        source: impl ReplaySource,
+        live_from: u64,
+    ) -> (Vec<Root>, Vec<FeedEvent>, Result<(), RelayError>) {
+        let scenario = Scenario::new().then(Step::Blocks {
+            count: 10 - live_from,
+            interval_ms: 10,
+        });
+        let mut events = SimulatedSequencer::new(live_from, 1_700_000_000, 1).generate(&scenario);
+        events.push(SimEvent::Disconnect);
+        let relay = MockRelay::bind("127.0.0.1:0", 42161, events).await.unwrap();
+        let url = Url::parse(&format!("ws://{}", relay.local_addr().unwrap())).unwrap();
+        let server = relay.spawn();
+
+        let (sender, roots) = crossbeam_channel::unbounded();
+        let (updates, _) = crossbeam_channel::unbounded();
+        let (events, feed_events) = crossbeam_channel::unbounded();
+        let client = ReplayToLive::new(source, 0, url, 42161, 0)
+            .connect(sender, updates, events)
+            .await
+            .unwrap();
+        let result = client.run().await;
+        server.abort();
+        (
+            roots.try_iter().collect(),
+            feed_events.try_iter().collect(),
+            result,
+        )
+    }
+
+    fn sequence_numbers(roots: &[Root]) -> Vec<(u64, Origin)> {
+        roots
+            .iter()
+            .flat_map(|root| {
+                root.messages
+                    .iter()
+                    .map(|msg| (msg.sequence_number, root.provenance.origin))
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn stitches_archive_and_live_feed_without_gap_or_duplicate() {
+        let dir = std::env::temp_dir().join(format!("sfr-replay-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let mut writer = ArchiveWriter::new(&dir, 2).unwrap();
+        for msg in recorded(5) {
+            writer.append(&msg).unwrap();
+        }
+        writer.close().unwrap();
+
+        // The relay overlaps the archive: the live client resumes right after it.
+        let (roots, events, result) = replay_then_live(FeedArchive::open(&dir).unwrap(), 0).await;
+        result.unwrap();
+        assert_eq!(events, [FeedEvent::SwitchedToLive { sequence_number: 5 }]);
+        let expected: Vec<_> = (0..10)
+            .map(|seq| {
+                let origin = if seq < 5 {
+                    Origin::Replay
+                } else {
+                    Origin::Live
+                };
+                (seq, origin)
+            })
+            .collect();
+        assert_eq!(sequence_numbers(&roots), expected);
+
+        // The relay no longer has the messages following the archive.
+        let (roots, _, result) = replay_then_live(FeedArchive::open(&dir).unwrap(), 8).await;
+        assert_eq!(roots.len(), 5);
+        assert!(matches!(
+            result,
+            Err(RelayError::SequenceGap {
+                expected: 5,
+                received: 8
+            })
+        ));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn replays_a_source_to_its_end_before_switching() {
+        let mut messages = recorded(6);
+        let later = messages.split_off(2);
+        let source = Recording(RefCell::new(vec![messages, later]));
+
+        let (roots, events, result) = replay_then_live(source, 0).await;
+        result.unwrap();
+        assert_eq!(events, [FeedEvent::SwitchedToLive { sequence_number: 6 }]);
+        let seen = sequence_numbers(&roots);
+        assert_eq!(
+            seen.iter().map(|&(seq, _)| seq).collect::<Vec<_>>(),
+            (0..10).collect::<Vec<_>>()
+        );
+        assert!(seen[..6]
+            .iter()
+            .all(|&(_, origin)| origin == Origin::Replay));
+    }
+}
@@ -0,0 +1,224 @@
+use crate::networks::arbitrum::{
+    decoder::{
+        l1::{
+            L1_MESSAGE_KIND_BATCH_POSTING_REPORT, L1_MESSAGE_KIND_ETH_DEPOSIT,
+            L1_MESSAGE_KIND_INITIALIZE, L1_MESSAGE_KIND_L2_FUNDED_BY_L1,
+            L1_MESSAGE_KIND_L2_MESSAGE, L1_MESSAGE_KIND_SUBMIT_RETRYABLE,
+        },
+        DecodedMsg, L2_MESSAGE_KIND_BATCH, L2_MESSAGE_KIND_SIGNED_COMPRESSED_TX,
+        L2_MESSAGE_KIND_SIGNED_TX,
+    },
+    message::FeedMessage,
+};
+use crossbeam_channel::{Receiver, SendError, Sender};
+use log::*;
+use std::{
+    collections::HashMap,
+    thread::{self, JoinHandle},
+};
+
+/// What a feed message carries, as routed by a `Router`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MessageKind {
+    /// A single signed transaction posted to the sequencer.
+    SignedTx,
+    /// A batch of L2 messages, usually signed transactions.
+    Batch,
+    /// Another L2 message, such as an unsigned or contract transaction.
+    OtherL2Message,
+    /// An L2 message funded by L1, from the delayed inbox.
+    L2FundedByL1,
+    /// A retryable ticket submitted on L1.
+    Retryable,
+    /// An ETH deposit from L1.
+    Deposit,
+    /// The report of a batch posted to L1, used to charge the batch poster.
+    BatchPostingReport,
+    /// The initialization message of the chain.
+    Initialize,
+    /// Any other L1 message kind.
+    Other(u8),
+}
+
+impl MessageKind {
+    /// Returns the kind of `msg`, from what it was decoded to. Messages that weren't decoded are
+    /// told apart by their L1 message kind and, for L2 messages, their first byte.
+    pub fn of(msg: &FeedMessage) -> Self {
+        match &msg.decoded {
+            Ok(Some(DecodedMsg::DecodedSignedTx(_))) => return MessageKind::SignedTx,
+            Ok(Some(DecodedMsg::DecodedBatch(_))) => return MessageKind::Batch,
+            Ok(Some(DecodedMsg::BatchPostingReport(_))) => return MessageKind::BatchPostingReport,
+            Ok(Some(DecodedMsg::EthDeposit(_))) => return MessageKind::Deposit,
+            _ => {}
+        }
+        let message = &msg.message.message.message;
+        match message.header.kind {
+            L1_MESSAGE_KIND_L2_MESSAGE => match message.l2msg.first().copied() {
+                Some(L2_MESSAGE_KIND_SIGNED_TX | L2_MESSAGE_KIND_SIGNED_COMPRESSED_TX) => {
+                    MessageKind::SignedTx
+                }
+                Some(L2_MESSAGE_KIND_BATCH) => MessageKind::Batch,
+                _ => MessageKind::OtherL2Message,
+            },
+            L1_MESSAGE_KIND_L2_FUNDED_BY_L1 => MessageKind::L2FundedByL1,
+            L1_MESSAGE_KIND_SUBMIT_RETRYABLE => MessageKind::Retryable,
+            L1_MESSAGE_KIND_ETH_DEPOSIT => MessageKind::Deposit,
+            L1_MESSAGE_KIND_BATCH_POSTING_REPORT => MessageKind::BatchPostingReport,
+            L1_MESSAGE_KIND_INITIALIZE => MessageKind::Initialize,
+            kind => MessageKind::Other(kind),
+        }
+    }
+}
+
+/// Routes decoded feed messages to separate channels by `MessageKind`, e.g. signed transactions
+/// to one consumer and retryables to another.
+///
+/// A message is sent to every channel registered for its kind, or to the default channel if
+/// there is none, or if none of them is connected anymore. Messages without a channel are
+/// dropped. Channels whose receiver was dropped are removed.
+#[derive(Default)]
+pub struct Router {
+    routes: HashMap<MessageKind, Vec<Sender<FeedMessage>>>,
+    default: Option<Sender<FeedMessage>>,
+}
+
+impl Router {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sends the messages of `kind` to `sender`. A kind may be routed to several channels.
+    pub fn route(mut self, kind: MessageKind, sender: Sender<FeedMessage>) -> Self {
+        self.routes.entry(kind).or_default().push(sender);
+        self
+    }
+
+    /// Sends the messages of every kind without a route to `sender`.
+    pub fn with_default(mut self, sender: Sender<FeedMessage>) -> Self {
+        self.default = Some(sender);
+        self
+    }
+
+    /// Sends `msg` to the channels of its kind, or to the default channel.
+    ///
+    /// # Returns
+    ///
+    /// The number of channels the message was sent to.
+    pub fn dispatch(&mut self, msg: FeedMessage) -> usize {
+        let kind = MessageKind::of(&msg);
+        let msg = match self.routes.get_mut(&kind) {
+            Some(senders) => {
+                let routes = senders.len();
+                let unsent = send_all(senders, msg);
+                let delivered = senders.len();
+                if delivered < routes {
+                    warn!("Route of {:?} disconnected, removing it", kind);
+                }
+                match unsent {
+                    Some(msg) => {
+                        self.routes.remove(&kind);
+                        msg
+                    }
+                    None => return delivered,
+                }
+            }
+            None => msg,
+        };
+
+        let Some(default) = &self.default else {
+            return 0;
+        };
+        if default.send(msg).is_ok() {
+            return 1;
+        }
+        warn!("Default route disconnected, removing it");
+        self.default = None;
+        0
+    }
+
+    /// Routes the messages received on `input` on a new thread, until `input` is disconnected.
+    pub fn spawn(mut self, input: Receiver<FeedMessage>) -> JoinHandle<()> {
+        thread::spawn(move || {
+            for msg in input {
+                self.dispatch(msg);
+            }
+        })
+    }
+}
+
+/// Sends `msg` to every channel of `senders`, removing those whose receiver was dropped.
+///
+/// # Returns
+///
+/// The message back if it wasn't sent to any channel, in which case `senders` is left empty.
+fn send_all(senders: &mut Vec<Sender<FeedMessage>>, msg: FeedMessage) -> Option<FeedMessage> {
+    let last = senders.len().saturating_sub(1);
+    let mut pending = Some(msg);
+    let mut index = 0;
+    senders.retain(|sender| {
+        // Only the last channel takes the message without cloning it.
+        let msg = if index == last {
+            pending.take()
+        } else {
+            pending.clone()
+        };
+        index += 1;
+        match sender.send(msg.expect("kept until the last channel")) {
+            Ok(()) => true,
+            Err(SendError(msg)) => {
+                pending.get_or_insert(msg);
+                false
+            }
+        }
+    });
+    pending.filter(|_| senders.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::networks::arbitrum::fixtures::feed_message_of_kind;
+    use crossbeam_channel::unbounded;
+
+    #[test]
+    fn routes_by_kind() {
+        let (txs, signed_txs) = unbounded();
+        let (reports, batch_posting_reports) = unbounded();
+        let (other, others) = unbounded();
+        let mut router = Router::new()
+            .route(MessageKind::SignedTx, txs)
+            .route(MessageKind::BatchPostingReport, reports)
+            .with_default(other);
+
+        assert_eq!(
+            router.dispatch(feed_message_of_kind(0, 3, vec![4, 0xaa])),
+            1
+        );
+        assert_eq!(router.dispatch(feed_message_of_kind(1, 13, Vec::new())), 1);
+        assert_eq!(router.dispatch(feed_message_of_kind(2, 9, Vec::new())), 1);
+        assert_eq!(router.dispatch(feed_message_of_kind(3, 3, vec![3])), 1);
+
+        let seqs = |rx: &Receiver<FeedMessage>| {
+            rx.try_iter()
+                .map(|msg| msg.sequence_number())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(seqs(&signed_txs), [0]);
+        assert_eq!(seqs(&batch_posting_reports), [1]);
+        assert_eq!(seqs(&others), [2, 3]);
+
+        // A message whose only route just disconnected goes to the default route.
+        drop(batch_posting_reports);
+        assert_eq!(router.dispatch(feed_message_of_kind(4, 13, Vec::new())), 1);
+        assert_eq!(seqs(&others), [4]);
+
+        // Messages are routed on what they were decoded to, rather than their kind byte.
+        let mut batch = feed_message_of_kind(5, 3, vec![4, 0xaa]);
+        batch.decoded = Ok(Some(DecodedMsg::DecodedBatch(Vec::new())));
+        assert_eq!(MessageKind::of(&batch), MessageKind::Batch);
+
+        // Without any connected route, messages are dropped.
+        drop(others);
+        assert_eq!(router.dispatch(feed_message_of_kind(6, 9, Vec::new())), 0);
+    }
+}
@@ -0,0 +1,94 @@
+//! Machine-readable definitions of the messages produced by the crate, generated from its types
+//! so that they never drift from the serialized forms.
+
+use crate::networks::arbitrum::{
+    events::FeedEvent,
+    sinks::encoding::{JsonMessage, PROTO_SCHEMA},
+    types::Root,
+};
+use schemars::{schema::RootSchema, schema_for};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// The JSON Schema of the frames sent by relays, as read and archived by the crate.
+pub fn feed_frame() -> RootSchema {
+    schema_for!(Root)
+}
+
+/// The JSON Schema of the events reported besides the messages.
+pub fn feed_event() -> RootSchema {
+    schema_for!(FeedEvent)
+}
+
+/// The JSON Schema of the messages published by sinks using `Encoding::Json`.
+pub fn sink_message() -> RootSchema {
+    schema_for!(JsonMessage)
+}
+
+/// Writes every schema into `dir`, creating the directory if needed.
+///
+/// # Returns
+///
+/// The paths of the written files: one JSON Schema per message type, and the Protocol Buffers
+/// schema of the crate, `PROTO_SCHEMA`.
+pub fn write_all(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    fs::create_dir_all(dir)?;
+    let schemas = [
+        ("feed-frame.schema.json", feed_frame()),
+        ("feed-event.schema.json", feed_event()),
+        ("sink-message.schema.json", sink_message()),
+    ];
+
+    let mut paths = Vec::new();
+    for (name, schema) in schemas {
+        let path = dir.join(name);
+        fs::write(&path, serde_json::to_vec_pretty(&schema)?)?;
+        paths.push(path);
+    }
+    let path = dir.join("feed.proto");
+    fs::write(&path, PROTO_SCHEMA)?;
+    paths.push(path);
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::networks::arbitrum::fixtures::message_with;
+    use serde_json::Value;
+
+    /// Returns the property names of the definition `name`, sorted.
+    fn properties(schema: &Value, name: &str) -> Vec<String> {
+        let mut properties: Vec<_> = schema["definitions"][name]["properties"]
+            .as_object()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect();
+        properties.sort();
+        properties
+    }
+
+    #[test]
+    fn schema_matches_serialized_messages() {
+        let schema = serde_json::to_value(feed_frame()).unwrap();
+        let msg = serde_json::to_value(message_with(1, 2, vec![3])).unwrap();
+
+        let keys = |value: &Value| {
+            let mut keys: Vec<_> = value.as_object().unwrap().keys().cloned().collect();
+            keys.sort();
+            keys
+        };
+        assert_eq!(properties(&schema, "BroadcastFeedMessage"), keys(&msg));
+        assert_eq!(
+            properties(&schema, "Header"),
+            keys(&msg["message"]["message"]["header"])
+        );
+        assert_eq!(
+            schema["definitions"]["L1IncomingMessageHeader"]["properties"]["l2Msg"]["type"],
+            "string"
+        );
+    }
+}
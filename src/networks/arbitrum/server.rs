@@ -0,0 +1,106 @@
+//! Connection handling shared by the servers of the crate.
+
+use hyper::{
+    header::{HeaderValue, CONTENT_TYPE},
+    server::conn::Http,
+    service::service_fn,
+    Body, Request, Response, StatusCode,
+};
+use log::*;
+use serde::Serialize;
+use serde_json::json;
+use std::{convert::Infallible, future::Future, io, net::SocketAddr, time::Duration};
+use tokio::{
+    net::{TcpListener, TcpStream},
+    task::JoinHandle,
+};
+
+/// How long to wait before accepting connections again after a failure, e.g. when the process
+/// runs out of file descriptors, instead of retrying in a tight loop.
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
+/// How long a client may take to send its request, or to complete its websocket handshake.
+pub(crate) const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Accepts the next connection on `listener`, backing off after failures.
+///
+/// # Arguments
+///
+/// * `server` - The name of the server, for the logs.
+pub(crate) async fn accept(listener: &TcpListener, server: &str) -> (TcpStream, SocketAddr) {
+    loop {
+        match listener.accept().await {
+            Ok(accepted) => return accepted,
+            Err(e) => {
+                warn!("Failed to accept {} connection: {}", server, e);
+                tokio::time::sleep(ACCEPT_BACKOFF).await;
+            }
+        }
+    }
+}
+
+/// Runs `read`, failing with `io::ErrorKind::TimedOut` if it takes longer than `READ_TIMEOUT`,
+/// so that idle clients don't hold their connection forever.
+pub(crate) async fn with_read_timeout<T, E>(
+    read: impl Future<Output = Result<T, E>>,
+) -> Result<T, E>
+where
+    E: From<io::Error>,
+{
+    match tokio::time::timeout(READ_TIMEOUT, read).await {
+        Ok(result) => result,
+        Err(_) => Err(io::Error::new(io::ErrorKind::TimedOut, "request timed out").into()),
+    }
+}
+
+/// Serves HTTP/1 requests with `handler` until the task is aborted, closing each connection
+/// after its response, unless the handler upgrades it.
+///
+/// # Arguments
+///
+/// * `server` - The name of the server, for the logs.
+pub(crate) fn serve_http<H, F>(
+    listener: TcpListener,
+    server: &'static str,
+    handler: H,
+) -> JoinHandle<()>
+where
+    H: Fn(Request<Body>) -> F + Clone + Send + 'static,
+    F: Future<Output = Response<Body>> + Send + 'static,
+{
+    tokio::spawn(async move {
+        let mut http = Http::new();
+        http.http1_only(true)
+            .http1_keep_alive(false)
+            .http1_header_read_timeout(READ_TIMEOUT);
+        loop {
+            let (stream, _) = accept(&listener, server).await;
+            let handler = handler.clone();
+            let service = service_fn(move |request| {
+                let response = handler(request);
+                async move { Ok::<_, Infallible>(response.await) }
+            });
+            let connection = http.serve_connection(stream, service).with_upgrades();
+            tokio::spawn(async move {
+                if let Err(e) = connection.await {
+                    debug!("{} connection failed: {}", server, e);
+                }
+            });
+        }
+    })
+}
+
+/// Returns a response with `body` serialized to JSON.
+pub(crate) fn json(status: u16, body: &impl Serialize) -> Response<Body> {
+    let mut response = Response::new(Body::from(serde_json::to_vec(body).unwrap_or_default()));
+    *response.status_mut() =
+        StatusCode::from_u16(status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    response
+        .headers_mut()
+        .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    response
+}
+
+/// Returns a JSON error response, `{ "error": message }`.
+pub(crate) fn error(status: u16, message: impl Into<String>) -> Response<Body> {
+    json(status, &json!({ "error": message.into() }))
+}
@@ -0,0 +1,669 @@
+use crate::networks::arbitrum::{
+    config::{Config, SinkConfig},
+    dedup::Deduplicator,
+    errors::{ConfigError, StartupError},
+    events::FeedEvent,
+    failover::{FailoverDetector, SequencerHealth},
+    filter::LiveFilter,
+    gas::{GasPriceFeed, GasPriceUpdate},
+    message::{FeedMessage, FeedTransaction},
+    pipeline::DecodePool,
+    priority::PriorityLane,
+    relays::{ConnectedRelays, RelayFailover, RelayFailoverHandle},
+    sinks::{
+        fanout::{SinkFanOut, Watermarks},
+        Sink,
+    },
+    stats::FeedStats,
+};
+use crossbeam_channel::{bounded, unbounded, RecvTimeoutError, Sender};
+use log::*;
+use std::{
+    path::Path,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use tokio::{
+    sync::oneshot,
+    task::{self, JoinHandle},
+};
+use url::Url;
+
+/// How many messages may be queued between two stages of the pipeline.
+const QUEUE_DEPTH: usize = 1024;
+const DEFAULT_WORKERS: usize = 2;
+/// How often the period of the summaries is checked while no message arrives.
+const STATS_TICK: Duration = Duration::from_secs(1);
+/// How often the health of the sequencer is checked, when `Config::health_rpc` is set.
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Reads the feed as described by a `Config`, wiring together the relays, decoding, gap
+/// detection, filtering and sinks.
+///
+/// The relays are reconnected and failed over by a `RelayFailover`. If the pipeline reading them
+/// stops anyway, e.g. because every relay failed fatally or a stage panicked, it is restarted
+/// with the `reconnect` backoff, resuming after the last message it read. The sinks keep running
+/// across restarts.
+///
+/// # Example
+///
+/// ```no_run
+/// use sequencer_feed_reader::networks::arbitrum::service::FeedService;
+///
+/// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+/// let service = FeedService::from_config("feed.toml")?.start().await?;
+/// tokio::signal::ctrl_c().await?;
+/// let watermarks = service.stop().await;
+/// # Ok(())
+/// # }
+/// ```
+pub struct FeedService {
+    config: Config,
+    workers: usize,
+    sinks: Vec<Arc<dyn Sink>>,
+    output: Option<Sender<FeedMessage>>,
+    priority_output: Option<Sender<FeedTransaction>>,
+    gas_prices: Option<Sender<GasPriceUpdate>>,
+    events: Option<Sender<FeedEvent>>,
+}
+
+/// A running `FeedService`.
+pub struct FeedServiceHandle {
+    stop: oneshot::Sender<()>,
+    supervisor: JoinHandle<Watermarks>,
+    control: FeedServiceControl,
+}
+
+/// Monitors and controls a running `FeedService`, e.g. from an `AdminServer`. Unlike the
+/// `FeedServiceHandle`, it can be cloned and doesn't stop the service.
+#[derive(Debug, Clone)]
+pub struct FeedServiceControl {
+    restarts: Arc<AtomicU64>,
+    next_sequence_number: Arc<AtomicU64>,
+    filter: LiveFilter,
+    relays: ConnectedRelays,
+    missed_priority: Arc<AtomicU64>,
+}
+
+impl FeedService {
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            workers: DEFAULT_WORKERS,
+            sinks: Vec::new(),
+            output: None,
+            priority_output: None,
+            gas_prices: None,
+            events: None,
+        }
+    }
+
+    /// Creates a service from the TOML configuration file at `path`, overridden by the
+    /// environment variables, see `Config::load`.
+    pub fn from_config(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        Ok(Self::new(Config::load(path)?))
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Sets how many threads decode messages.
+    pub fn with_workers(mut self, workers: usize) -> Self {
+        self.workers = workers.max(1);
+        self
+    }
+
+    /// Delivers messages to `sink`, besides the sinks of the configuration.
+    pub fn with_sink(mut self, sink: Arc<dyn Sink>) -> Self {
+        self.sinks.push(sink);
+        self
+    }
+
+    /// Sends the messages matching the filter to `output` too, for in-process consumers.
+    pub fn with_output(mut self, output: Sender<FeedMessage>) -> Self {
+        self.output = Some(output);
+        self
+    }
+
+    /// Sends the transactions matching the `priority` filters of the configuration to `output`
+    /// as soon as they are decoded, ahead of the ordered stream. Transactions are missed while
+    /// `output` is full, see `PriorityLane`.
+    pub fn with_priority_output(mut self, output: Sender<FeedTransaction>) -> Self {
+        self.priority_output = Some(output);
+        self
+    }
+
+    /// Sends the `GasPriceUpdate` of every message to `output`, filtered or not, for gas
+    /// strategies that don't need the transactions. Updates are dropped while `output` is full.
+    pub fn with_gas_price_output(mut self, output: Sender<GasPriceUpdate>) -> Self {
+        self.gas_prices = Some(output);
+        self
+    }
+
+    /// Reports relay promotions, suspected sequencer failovers, watermarks and other
+    /// `FeedEvent`s to `events`.
+    pub fn with_events(mut self, events: Sender<FeedEvent>) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Connects the sinks, warms them up and starts reading the feed. Must be called within a
+    /// Tokio runtime.
+    ///
+    /// # Returns
+    ///
+    /// A handle stopping the service, or a `StartupError` if the configuration is invalid or a
+    /// sink could not be set up.
+    pub async fn start(self) -> Result<FeedServiceHandle, StartupError> {
+        let chain_id = self.config.chain_id()?;
+        let relays = self.config.relays()?;
+
+        let mut fanout = SinkFanOut::new(chain_id);
+        for sink in &self.config.sinks {
+            fanout = fanout.with_sink(connect_sink(&self.config, sink).await?);
+        }
+        for sink in self.sinks {
+            fanout = fanout.with_sink(sink);
+        }
+        fanout.warm_up().await?;
+
+        let events = self.events.unwrap_or_else(|| unbounded().0);
+        let (sink_tx, sink_rx) = bounded(QUEUE_DEPTH);
+        let fanout = fanout.spawn(sink_rx, events.clone());
+
+        let (stop, mut stopped) = oneshot::channel();
+        let restarts = Arc::new(AtomicU64::new(0));
+        let next_sequence_number = Arc::new(AtomicU64::new(0));
+        let control = FeedServiceControl {
+            restarts: restarts.clone(),
+            next_sequence_number: next_sequence_number.clone(),
+            filter: LiveFilter::new(self.config.filter.clone())
+                .with_priority(self.config.priority.clone()),
+            relays: ConnectedRelays::default(),
+            missed_priority: Arc::default(),
+        };
+        let health = self
+            .config
+            .health_rpc
+            .clone()
+            .map(|url| SequencerHealth::spawn(url, HEALTH_CHECK_INTERVAL));
+        let pipeline = Pipeline {
+            config: self.config,
+            chain_id,
+            relays,
+            workers: self.workers,
+            next_sequence_number: next_sequence_number.clone(),
+            filter: control.filter.clone(),
+            connected: control.relays.clone(),
+            output: self.output,
+            priority: self.priority_output.map(|output| {
+                PriorityLane::new(control.filter.clone(), output)
+                    .with_missed_counter(control.missed_priority.clone())
+            }),
+            gas_prices: self.gas_prices.map(GasPriceFeed::new),
+            health,
+            sinks: sink_tx,
+            events,
+        };
+        let supervisor = task::spawn({
+            let restarts = restarts.clone();
+            async move {
+                let mut attempt = 0;
+                loop {
+                    let started = Instant::now();
+                    let mut run = pipeline.spawn();
+                    tokio::select! {
+                        _ = &mut stopped => {
+                            run.stop().await;
+                            break;
+                        }
+                        _ = &mut run.done => run.relays.stop().await,
+                    }
+
+                    // The backoff only grows while restarts fail in a row.
+                    let max_delay = Duration::from_millis(pipeline.config.reconnect.max_delay_ms);
+                    if started.elapsed() > max_delay {
+                        attempt = 0;
+                    }
+                    let delay = pipeline.config.reconnect.backoff(attempt);
+                    attempt += 1;
+                    restarts.fetch_add(1, Ordering::Relaxed);
+                    warn!("Feed pipeline stopped, restarting in {:?}", delay);
+                    tokio::select! {
+                        _ = &mut stopped => break,
+                        _ = tokio::time::sleep(delay) => (),
+                    }
+                }
+
+                drop(pipeline);
+                fanout.join().await
+            }
+        });
+
+        Ok(FeedServiceHandle {
+            stop,
+            supervisor,
+            control,
+        })
+    }
+}
+
+impl FeedServiceHandle {
+    /// Returns how many times the pipeline was restarted after failing.
+    pub fn restarts(&self) -> u64 {
+        self.control.restarts()
+    }
+
+    /// Returns the sequence number following the last message read.
+    pub fn next_sequence_number(&self) -> u64 {
+        self.control.next_sequence_number()
+    }
+
+    /// Returns the filter of the messages delivered, which can be replaced while the service
+    /// runs, see `LiveFilter`.
+    pub fn filter(&self) -> &LiveFilter {
+        self.control.filter()
+    }
+
+    /// Stops reading the relays while keeping them connected, e.g. during downstream
+    /// maintenance, see `ConnectedRelays::pause`.
+    pub fn pause(&self) {
+        self.control.relays().pause();
+    }
+
+    /// Reads the relays again after `pause`.
+    pub fn resume(&self) {
+        self.control.relays().resume();
+    }
+
+    pub fn control(&self) -> &FeedServiceControl {
+        &self.control
+    }
+
+    /// Disconnects from the relays, delivers the pending messages to the sinks and waits for
+    /// them to flush.
+    ///
+    /// # Returns
+    ///
+    /// The final watermarks of the sinks.
+    ///
+    /// # Panics
+    ///
+    /// If the task supervising the service panicked.
+    pub async fn stop(self) -> Watermarks {
+        let _ = self.stop.send(());
+        match self.supervisor.await {
+            Ok(watermarks) => watermarks,
+            Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+            // Only cancelled if the runtime shuts down, in which case nothing was flushed.
+            Err(_) => Watermarks::default(),
+        }
+    }
+}
+
+impl FeedServiceControl {
+    /// Returns how many times the pipeline was restarted after failing.
+    pub fn restarts(&self) -> u64 {
+        self.restarts.load(Ordering::Relaxed)
+    }
+
+    /// Returns the sequence number following the last message read.
+    pub fn next_sequence_number(&self) -> u64 {
+        self.next_sequence_number.load(Ordering::Acquire)
+    }
+
+    /// Returns the filter of the messages delivered, see `LiveFilter`.
+    pub fn filter(&self) -> &LiveFilter {
+        &self.filter
+    }
+
+    /// Returns the clients currently connected to the relays.
+    pub fn relays(&self) -> &ConnectedRelays {
+        &self.relays
+    }
+
+    /// Returns the number of priority transactions missed because the priority output was
+    /// full, see `PriorityLane::missed`.
+    pub fn missed_priority_transactions(&self) -> u64 {
+        self.missed_priority.load(Ordering::Relaxed)
+    }
+}
+
+/// Sets up the sink described by `sink`.
+#[cfg_attr(not(feature = "postgres"), allow(unused_variables))]
+async fn connect_sink(config: &Config, sink: &SinkConfig) -> Result<Arc<dyn Sink>, StartupError> {
+    match sink {
+        #[cfg(feature = "kafka")]
+        SinkConfig::Kafka {
+            brokers,
+            topic,
+            protobuf,
+        } => {
+            use crate::networks::arbitrum::sinks::kafka::KafkaSink;
+            let encoding = encoding(*protobuf)?;
+            let sink = KafkaSink::new(brokers, topic.clone()).map_err(failed("kafka"))?;
+            Ok(Arc::new(sink.with_encoding(encoding)))
+        }
+        #[cfg(feature = "nats")]
+        SinkConfig::Nats {
+            url,
+            subject,
+            jetstream,
+            protobuf,
+        } => {
+            use crate::networks::arbitrum::sinks::nats::{NatsSink, SubjectTemplate};
+            let encoding = encoding(*protobuf)?;
+            let subject = SubjectTemplate::new(subject.clone()).map_err(failed("nats"))?;
+            let mut sink = NatsSink::connect(url, subject)
+                .await
+                .map_err(failed("nats"))?;
+            if *jetstream {
+                sink = sink.with_jetstream();
+            }
+            Ok(Arc::new(sink.with_encoding(encoding)))
+        }
+        #[cfg(feature = "postgres")]
+        SinkConfig::Postgres {
+            url,
+            table,
+            create_table,
+            genesis_block_number,
+        } => {
+            use crate::networks::arbitrum::{
+                network::ArbitrumNetwork, sinks::postgres::PostgresSink,
+            };
+            let chain_id = config.chain_id()?;
+            let genesis_block_number = genesis_block_number
+                .or(ArbitrumNetwork::from_chain_id(chain_id).map(|n| n.genesis_block_number()))
+                .ok_or_else(|| {
+                    ConfigError::Invalid(format!(
+                        "the postgres sink needs the genesis_block_number of chain {}",
+                        chain_id
+                    ))
+                })?;
+            let sink = PostgresSink::connect(url, table.clone(), genesis_block_number)
+                .await
+                .map_err(failed("postgres"))?;
+            if *create_table {
+                sink.create_table().await.map_err(failed("postgres"))?;
+            }
+            Ok(Arc::new(sink))
+        }
+        #[cfg(not(feature = "kafka"))]
+        SinkConfig::Kafka { .. } => Err(unavailable("kafka")),
+        #[cfg(not(feature = "nats"))]
+        SinkConfig::Nats { .. } => Err(unavailable("nats")),
+        #[cfg(not(feature = "postgres"))]
+        SinkConfig::Postgres { .. } => Err(unavailable("postgres")),
+    }
+}
+
+#[cfg(any(feature = "kafka", feature = "nats", feature = "postgres"))]
+fn failed(sink: &str) -> impl FnOnce(crate::networks::arbitrum::errors::SinkError) -> StartupError {
+    let sink = sink.to_string();
+    move |source| StartupError::Sink { sink, source }
+}
+
+/// Returns the encoding of the messages published by a streaming sink.
+#[cfg(any(feature = "kafka", feature = "nats"))]
+fn encoding(
+    protobuf: bool,
+) -> Result<crate::networks::arbitrum::sinks::encoding::Encoding, StartupError> {
+    use crate::networks::arbitrum::sinks::encoding::Encoding;
+    match protobuf {
+        #[cfg(feature = "protobuf")]
+        true => Ok(Encoding::Protobuf),
+        #[cfg(not(feature = "protobuf"))]
+        true => Err(StartupError::Config(ConfigError::Invalid(
+            "the protobuf encoding needs the protobuf feature".into(),
+        ))),
+        false => Ok(Encoding::Json),
+    }
+}
+
+#[cfg(not(all(feature = "kafka", feature = "nats", feature = "postgres")))]
+fn unavailable(feature: &str) -> StartupError {
+    StartupError::Config(ConfigError::Invalid(format!(
+        "the {} sink needs the {} feature",
+        feature, feature
+    )))
+}
+
+/// Everything needed to (re)start reading the feed.
+struct Pipeline {
+    config: Config,
+    chain_id: u64,
+    relays: Vec<Url>,
+    workers: usize,
+    next_sequence_number: Arc<AtomicU64>,
+    filter: LiveFilter,
+    connected: ConnectedRelays,
+    output: Option<Sender<FeedMessage>>,
+    priority: Option<PriorityLane>,
+    gas_prices: Option<GasPriceFeed>,
+    health: Option<SequencerHealth>,
+    sinks: Sender<FeedMessage>,
+    events: Sender<FeedEvent>,
+}
+
+/// A running `Pipeline`.
+struct PipelineRun {
+    relays: RelayFailoverHandle,
+    /// Closed once every message read has been handed to the sinks.
+    done: oneshot::Receiver<()>,
+}
+
+impl PipelineRun {
+    async fn stop(self) {
+        self.relays.stop().await;
+        let _ = self.done.await;
+    }
+}
+
+impl Pipeline {
+    /// Starts reading the relays after the last message read.
+    fn spawn(&self) -> PipelineRun {
+        let options = self.config.connection.connect_options();
+        let reconnect = &self.config.reconnect;
+        let mut failover = RelayFailover::new(self.chain_id, self.relays[0].clone())
+            .with_connect_options(options.clone());
+        for url in &self.relays[1..] {
+            failover = failover
+                .with_standby(url.clone())
+                .with_connect_options(options.clone());
+        }
+        failover = failover.with_connected_relays(self.connected.clone());
+        if let Some(window) = reconnect.consistency_window {
+            failover = failover.with_consistency_check(window);
+        }
+        let failover = failover
+            .with_sequence_number(self.next_sequence_number.load(Ordering::Acquire))
+            .with_strategy(reconnect.strategy)
+            .with_stall_timeout(Duration::from_millis(reconnect.stall_timeout_ms))
+            .with_reconnect_delay(
+                Duration::from_millis(reconnect.delay_ms),
+                Duration::from_millis(reconnect.max_delay_ms),
+            );
+
+        let (roots_tx, roots_rx) = bounded(QUEUE_DEPTH);
+        let (decoded_tx, decoded_rx) = bounded(QUEUE_DEPTH);
+        let relays = failover.spawn(roots_tx, self.events.clone());
+        let mut pool = DecodePool::new(self.workers)
+            .with_decode_options(self.config.decode.decode_options())
+            .with_events(self.events.clone());
+        if let Some(lane) = &self.priority {
+            pool = pool.with_priority_lane(lane.clone());
+        }
+        pool.spawn(roots_rx, decoded_tx);
+
+        let (done_tx, done) = oneshot::channel::<()>();
+        let filter = self.filter.clone();
+        let next_sequence_number = self.next_sequence_number.clone();
+        let output = self.output.clone();
+        let sinks = self.sinks.clone();
+        let events = self.events.clone();
+        let gas_prices = self.gas_prices.clone();
+        let mut stats = self
+            .config
+            .summary_period_secs
+            .map(|secs| FeedStats::new(Duration::from_secs(secs)));
+        let mut dedup = self.config.dedup_window.map(Deduplicator::new);
+        let mut gaps = FailoverDetector::new();
+        if let Some(health) = &self.health {
+            gaps = gaps.with_health_check(health.clone());
+        }
+        thread::spawn(move || {
+            let _done = done_tx;
+            let mut last_sequence_number = None;
+            loop {
+                let mut msg = match decoded_rx.recv_timeout(STATS_TICK) {
+                    Ok(msg) => msg,
+                    Err(RecvTimeoutError::Timeout) => {
+                        let now = SystemTime::now()
+                            .duration_since(UNIX_EPOCH)
+                            .map_or(0, |d| d.as_secs());
+                        if let Some(event) = stats.as_mut().and_then(|stats| stats.tick(now)) {
+                            let _ = events.send(event);
+                        }
+                        continue;
+                    }
+                    Err(RecvTimeoutError::Disconnected) => return,
+                };
+                if let Some(dedup) = &mut dedup {
+                    // The failover only forwards messages again after a reorg.
+                    if let Some(to) = last_sequence_number.filter(|&to| msg.sequence_number() <= to)
+                    {
+                        dedup.observe_event(&FeedEvent::Reorg {
+                            from: msg.sequence_number(),
+                            to,
+                        });
+                    }
+                    last_sequence_number = Some(msg.sequence_number());
+                    msg = dedup.mark(msg);
+                }
+                next_sequence_number.store(msg.sequence_number() + 1, Ordering::Release);
+                if let Some(event) = gaps.observe(&msg.message, Instant::now()) {
+                    let _ = events.send(event);
+                }
+                if msg.provenance.duplicate {
+                    continue;
+                }
+                if let Some(event) = stats.as_mut().and_then(|stats| stats.observe(&msg)) {
+                    let _ = events.send(event);
+                }
+                if let Some(gas_prices) = &gas_prices {
+                    gas_prices.publish(&msg);
+                }
+                if !filter.matches(&msg) {
+                    continue;
+                }
+                if let Some(output) = &output {
+                    let _ = output.send(msg.clone());
+                }
+                if sinks.send(msg).is_err() {
+                    return;
+                }
+            }
+        });
+
+        PipelineRun { relays, done }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::networks::arbitrum::{
+        bench,
+        mock::{MockRelay, Scenario, SimEvent, SimulatedSequencer, Step},
+    };
+
+    async fn next_messages(
+        messages: &crossbeam_channel::Receiver<FeedMessage>,
+        count: usize,
+    ) -> Vec<u64> {
+        let mut sequence_numbers = Vec::new();
+        while sequence_numbers.len() < count {
+            let msg = task::spawn_blocking({
+                let messages = messages.clone();
+                move || messages.recv_timeout(Duration::from_secs(5))
+            })
+            .await
+            .unwrap()
+            .expect("no message");
+            sequence_numbers.push(msg.sequence_number());
+        }
+        sequence_numbers
+    }
+
+    #[tokio::test]
+    async fn reads_configured_relays_until_stopped() {
+        let scenario = Scenario::new().then(Step::Blocks {
+            count: 5,
+            interval_ms: 10,
+        });
+        let events = SimulatedSequencer::new(0, 1_700_000_000, 1).generate(&scenario);
+        let relay = MockRelay::bind("127.0.0.1:0", 42161, events).await.unwrap();
+        let url = format!("ws://{}", relay.local_addr().unwrap());
+        relay.spawn();
+
+        let config =
+            Config::from_toml(&format!("chain_id = 42161\nrelays = [\"{}\"]", url)).unwrap();
+        let (output, messages) = unbounded();
+        let service = FeedService::new(config)
+            .with_output(output)
+            .start()
+            .await
+            .unwrap();
+
+        let sequence_numbers = next_messages(&messages, 5).await;
+        assert_eq!(service.next_sequence_number(), 5);
+        service.stop().await;
+
+        assert_eq!(sequence_numbers, [0, 1, 2, 3, 4]);
+    }
+
+    #[tokio::test]
+    async fn delivers_every_message_once() {
+        let txs = bench::signed_transactions(2);
+        let signed =
+            |seq, tx: &[u8]| SimEvent::Message(bench::message(seq, bench::signed_tx_l2msg(tx)));
+        let events = vec![
+            signed(0, &txs[0]),
+            signed(1, &txs[1]),
+            // The overlap sent again, e.g. on resume.
+            signed(0, &txs[0]),
+            signed(1, &txs[1]),
+            // A transaction sequenced again.
+            signed(2, &txs[0]),
+            SimEvent::Message(bench::message(3, bench::batch_l2msg(&[]))),
+            SimEvent::Wait(Duration::from_secs(60)),
+        ];
+        let relay = MockRelay::bind("127.0.0.1:0", 42161, events).await.unwrap();
+        let url = format!("ws://{}", relay.local_addr().unwrap());
+        relay.spawn();
+
+        let config = Config::from_toml(&format!(
+            "chain_id = 42161\nrelays = [\"{}\"]\ndedup_window = 100",
+            url
+        ))
+        .unwrap();
+        let (output, messages) = unbounded();
+        let service = FeedService::new(config)
+            .with_output(output)
+            .start()
+            .await
+            .unwrap();
+
+        assert_eq!(next_messages(&messages, 3).await, [0, 1, 3]);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(messages.is_empty());
+        assert_eq!(service.next_sequence_number(), 4);
+        service.stop().await;
+    }
+}
@@ -0,0 +1,242 @@
+use futures::future::join_all;
+use log::*;
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, HashMap},
+    error::Error,
+    fmt,
+    future::Future,
+    pin::Pin,
+    time::{Duration, Instant},
+};
+
+pub type HookResult = Result<(), Box<dyn Error + Send + Sync>>;
+
+type Hook = Box<dyn FnOnce() -> Pin<Box<dyn Future<Output = HookResult> + Send>> + Send>;
+
+const DEFAULT_STAGE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The stages of a graceful shutdown, run in declaration order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShutdownStage {
+    /// Stop reading new frames from the relays.
+    StopIntake,
+    /// Let in-flight messages finish decoding.
+    FlushDecoders,
+    /// Deliver buffered messages to the sinks.
+    DrainSinks,
+    /// Persist the last processed sequence numbers.
+    WriteCheckpoints,
+    /// Write the shutdown summary.
+    WriteSummary,
+}
+
+impl ShutdownStage {
+    pub const ALL: [ShutdownStage; 5] = [
+        ShutdownStage::StopIntake,
+        ShutdownStage::FlushDecoders,
+        ShutdownStage::DrainSinks,
+        ShutdownStage::WriteCheckpoints,
+        ShutdownStage::WriteSummary,
+    ];
+}
+
+/// How a shutdown hook ended.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HookOutcome {
+    Completed,
+    Failed(String),
+    TimedOut,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HookReport {
+    pub stage: ShutdownStage,
+    pub name: String,
+    pub outcome: HookOutcome,
+    pub elapsed_ms: u128,
+}
+
+/// The summary of a shutdown, listing every hook in the order it ran.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ShutdownReport {
+    pub hooks: Vec<HookReport>,
+    pub elapsed_ms: u128,
+}
+
+impl ShutdownReport {
+    /// Returns `true` if every hook completed within its stage deadline.
+    pub fn is_clean(&self) -> bool {
+        self.hooks
+            .iter()
+            .all(|h| h.outcome == HookOutcome::Completed)
+    }
+}
+
+impl fmt::Display for ShutdownReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "shutdown completed in {}ms", self.elapsed_ms)?;
+        for hook in &self.hooks {
+            writeln!(
+                f,
+                "  {:?}/{}: {:?} ({}ms)",
+                hook.stage, hook.name, hook.outcome, hook.elapsed_ms
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs shutdown hooks registered by the subsystems in a defined order.
+///
+/// Stages run one after the other. The hooks of a stage run concurrently and share the stage
+/// deadline; hooks still running when it expires are abandoned and reported as timed out.
+pub struct ShutdownCoordinator {
+    hooks: BTreeMap<ShutdownStage, Vec<(String, Hook)>>,
+    timeouts: HashMap<ShutdownStage, Duration>,
+    default_timeout: Duration,
+}
+
+impl Default for ShutdownCoordinator {
+    fn default() -> Self {
+        Self {
+            hooks: BTreeMap::new(),
+            timeouts: HashMap::new(),
+            default_timeout: DEFAULT_STAGE_TIMEOUT,
+        }
+    }
+}
+
+impl ShutdownCoordinator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the deadline of `stage`.
+    pub fn with_timeout(mut self, stage: ShutdownStage, timeout: Duration) -> Self {
+        self.timeouts.insert(stage, timeout);
+        self
+    }
+
+    /// Sets the deadline of stages without an explicit timeout.
+    pub fn with_default_timeout(mut self, timeout: Duration) -> Self {
+        self.default_timeout = timeout;
+        self
+    }
+
+    /// Registers a hook to run during `stage`.
+    ///
+    /// # Arguments
+    ///
+    /// * `stage` - The stage the hook belongs to.
+    /// * `name` - A name identifying the hook in the `ShutdownReport`.
+    /// * `hook` - A function returning the future to await.
+    pub fn register<F, Fut>(&mut self, stage: ShutdownStage, name: impl Into<String>, hook: F)
+    where
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future<Output = HookResult> + Send + 'static,
+    {
+        let hook: Hook = Box::new(move || Box::pin(hook()));
+        self.hooks
+            .entry(stage)
+            .or_default()
+            .push((name.into(), hook));
+    }
+
+    /// Runs every registered hook, stage by stage.
+    pub async fn shutdown(mut self) -> ShutdownReport {
+        let started = Instant::now();
+        let mut report = ShutdownReport::default();
+
+        for stage in ShutdownStage::ALL {
+            let Some(hooks) = self.hooks.remove(&stage) else {
+                continue;
+            };
+            let timeout = *self.timeouts.get(&stage).unwrap_or(&self.default_timeout);
+            let deadline = tokio::time::Instant::now() + timeout;
+            debug!("Running shutdown stage {:?}", stage);
+
+            let runs = hooks.into_iter().map(|(name, hook)| async move {
+                let started = Instant::now();
+                let outcome = match tokio::time::timeout_at(deadline, hook()).await {
+                    Ok(Ok(())) => HookOutcome::Completed,
+                    Ok(Err(e)) => HookOutcome::Failed(e.to_string()),
+                    Err(_) => HookOutcome::TimedOut,
+                };
+                if outcome != HookOutcome::Completed {
+                    warn!("Shutdown hook {:?}/{}: {:?}", stage, name, outcome);
+                }
+
+                HookReport {
+                    stage,
+                    name,
+                    outcome,
+                    elapsed_ms: started.elapsed().as_millis(),
+                }
+            });
+            report.hooks.extend(join_all(runs).await);
+        }
+
+        report.elapsed_ms = started.elapsed().as_millis();
+        info!("{}", report);
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[tokio::test]
+    async fn stages_run_in_order_with_deadlines() {
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let mut coordinator = ShutdownCoordinator::new()
+            .with_timeout(ShutdownStage::DrainSinks, Duration::from_millis(20));
+
+        for stage in [
+            ShutdownStage::WriteCheckpoints,
+            ShutdownStage::StopIntake,
+            ShutdownStage::DrainSinks,
+        ] {
+            let order = order.clone();
+            coordinator.register(stage, format!("{:?}", stage), move || async move {
+                order.lock().unwrap().push(stage);
+                Ok(())
+            });
+        }
+        coordinator.register(ShutdownStage::DrainSinks, "stuck", || async {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            Ok(())
+        });
+        coordinator.register(ShutdownStage::WriteSummary, "failing", || async {
+            Err("disk full".into())
+        });
+
+        let report = coordinator.shutdown().await;
+
+        assert_eq!(
+            *order.lock().unwrap(),
+            vec![
+                ShutdownStage::StopIntake,
+                ShutdownStage::DrainSinks,
+                ShutdownStage::WriteCheckpoints
+            ]
+        );
+        assert!(!report.is_clean());
+        let outcome = |name: &str| {
+            report
+                .hooks
+                .iter()
+                .find(|h| h.name == name)
+                .map(|h| h.outcome.clone())
+        };
+        assert_eq!(outcome("stuck"), Some(HookOutcome::TimedOut));
+        assert_eq!(
+            outcome("failing"),
+            Some(HookOutcome::Failed("disk full".to_string()))
+        );
+    }
+}
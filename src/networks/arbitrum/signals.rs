@@ -0,0 +1,227 @@
+use crate::networks::arbitrum::shutdown::{HookResult, ShutdownCoordinator, ShutdownReport};
+use futures::{stream, Stream, StreamExt};
+use log::*;
+use std::{future::Future, io, pin::Pin};
+
+type Handler = Box<dyn FnMut() -> Pin<Box<dyn Future<Output = HookResult> + Send>> + Send>;
+
+/// What the process was asked to do by the operating system or the service manager.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LifecycleSignal {
+    /// Shut down gracefully: `SIGTERM` or `SIGINT` on Unix, and Ctrl-C, closing the console or
+    /// shutting down the system on Windows.
+    Shutdown,
+    /// Reload the configuration: `SIGHUP` on Unix. Windows has no equivalent.
+    Reload,
+    /// Persist the checkpoints without stopping: `SIGUSR1` on Unix and Ctrl-Break on Windows.
+    Flush,
+}
+
+/// Listens for the signals sent to the process, such as the `SIGTERM` sent by systemd or
+/// Kubernetes before killing it.
+///
+/// The handlers are installed when the listener is created. From then on, the default action
+/// of these signals (terminating the process) no longer applies.
+pub struct SignalListener {
+    #[cfg(unix)]
+    terminate: tokio::signal::unix::Signal,
+    #[cfg(unix)]
+    interrupt: tokio::signal::unix::Signal,
+    #[cfg(unix)]
+    hangup: tokio::signal::unix::Signal,
+    #[cfg(unix)]
+    user_defined1: tokio::signal::unix::Signal,
+    #[cfg(windows)]
+    ctrl_c: tokio::signal::windows::CtrlC,
+    #[cfg(windows)]
+    ctrl_break: tokio::signal::windows::CtrlBreak,
+    #[cfg(windows)]
+    ctrl_close: tokio::signal::windows::CtrlClose,
+    #[cfg(windows)]
+    ctrl_shutdown: tokio::signal::windows::CtrlShutdown,
+}
+
+impl SignalListener {
+    /// Installs the signal handlers. Must be called within a Tokio runtime.
+    #[cfg(unix)]
+    pub fn new() -> io::Result<Self> {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        Ok(Self {
+            terminate: signal(SignalKind::terminate())?,
+            interrupt: signal(SignalKind::interrupt())?,
+            hangup: signal(SignalKind::hangup())?,
+            user_defined1: signal(SignalKind::user_defined1())?,
+        })
+    }
+
+    /// Installs the console control handlers. Must be called within a Tokio runtime.
+    #[cfg(windows)]
+    pub fn new() -> io::Result<Self> {
+        use tokio::signal::windows;
+
+        Ok(Self {
+            ctrl_c: windows::ctrl_c()?,
+            ctrl_break: windows::ctrl_break()?,
+            ctrl_close: windows::ctrl_close()?,
+            ctrl_shutdown: windows::ctrl_shutdown()?,
+        })
+    }
+
+    /// Waits for the next signal.
+    #[cfg(unix)]
+    pub async fn recv(&mut self) -> LifecycleSignal {
+        tokio::select! {
+            _ = self.terminate.recv() => LifecycleSignal::Shutdown,
+            _ = self.interrupt.recv() => LifecycleSignal::Shutdown,
+            _ = self.hangup.recv() => LifecycleSignal::Reload,
+            _ = self.user_defined1.recv() => LifecycleSignal::Flush,
+        }
+    }
+
+    /// Waits for the next console control event.
+    #[cfg(windows)]
+    pub async fn recv(&mut self) -> LifecycleSignal {
+        tokio::select! {
+            _ = self.ctrl_c.recv() => LifecycleSignal::Shutdown,
+            _ = self.ctrl_close.recv() => LifecycleSignal::Shutdown,
+            _ = self.ctrl_shutdown.recv() => LifecycleSignal::Shutdown,
+            _ = self.ctrl_break.recv() => LifecycleSignal::Flush,
+        }
+    }
+
+    /// Turns the listener into an endless stream of signals.
+    pub fn into_stream(self) -> impl Stream<Item = LifecycleSignal> {
+        stream::unfold(self, |mut listener| async move {
+            Some((listener.recv().await, listener))
+        })
+    }
+}
+
+/// Ties the lifecycle of a daemon to the signals it receives: reloads its configuration and
+/// flushes its checkpoints on request, and runs its `ShutdownCoordinator` when asked to stop.
+pub struct Lifecycle {
+    shutdown: ShutdownCoordinator,
+    reload: Vec<(String, Handler)>,
+    flush: Vec<(String, Handler)>,
+}
+
+impl Lifecycle {
+    /// # Arguments
+    ///
+    /// * `shutdown` - The coordinator to run on `LifecycleSignal::Shutdown`.
+    pub fn new(shutdown: ShutdownCoordinator) -> Self {
+        Self {
+            shutdown,
+            reload: Vec::new(),
+            flush: Vec::new(),
+        }
+    }
+
+    /// Registers a handler to run on every `LifecycleSignal::Reload`.
+    pub fn on_reload<F, Fut>(mut self, name: impl Into<String>, mut handler: F) -> Self
+    where
+        F: FnMut() -> Fut + Send + 'static,
+        Fut: Future<Output = HookResult> + Send + 'static,
+    {
+        let handler: Handler = Box::new(move || Box::pin(handler()));
+        self.reload.push((name.into(), handler));
+        self
+    }
+
+    /// Registers a handler to run on every `LifecycleSignal::Flush`, typically flushing a
+    /// `Checkpointer`.
+    pub fn on_flush<F, Fut>(mut self, name: impl Into<String>, mut handler: F) -> Self
+    where
+        F: FnMut() -> Fut + Send + 'static,
+        Fut: Future<Output = HookResult> + Send + 'static,
+    {
+        let handler: Handler = Box::new(move || Box::pin(handler()));
+        self.flush.push((name.into(), handler));
+        self
+    }
+
+    /// Handles the signals sent to the process until it is asked to shut down.
+    ///
+    /// # Returns
+    ///
+    /// The report of the shutdown, or an error if the signal handlers could not be installed.
+    pub async fn run(self) -> io::Result<ShutdownReport> {
+        let signals = SignalListener::new()?.into_stream();
+        Ok(self.run_with(signals).await)
+    }
+
+    /// Handles the signals of `signals` until a `LifecycleSignal::Shutdown` or the end of the
+    /// stream, then shuts down.
+    ///
+    /// Failing reload and flush handlers are logged and don't stop the daemon.
+    pub async fn run_with(
+        mut self,
+        signals: impl Stream<Item = LifecycleSignal>,
+    ) -> ShutdownReport {
+        let mut signals = Box::pin(signals);
+        while let Some(signal) = signals.next().await {
+            info!("Received {:?} signal", signal);
+            let handlers = match signal {
+                LifecycleSignal::Shutdown => break,
+                LifecycleSignal::Reload => &mut self.reload,
+                LifecycleSignal::Flush => &mut self.flush,
+            };
+            for (name, handler) in handlers.iter_mut() {
+                if let Err(e) = handler().await {
+                    warn!("{:?} handler {} failed: {}", signal, name, e);
+                }
+            }
+        }
+        self.shutdown.shutdown().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::networks::arbitrum::shutdown::ShutdownStage;
+    use std::sync::{Arc, Mutex};
+
+    #[tokio::test]
+    async fn dispatches_signals_until_shutdown() {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let record = |name: &'static str| {
+            let calls = calls.clone();
+            move || {
+                let calls = calls.clone();
+                async move {
+                    calls.lock().unwrap().push(name);
+                    Ok(())
+                }
+            }
+        };
+
+        let mut coordinator = ShutdownCoordinator::new();
+        coordinator.register(
+            ShutdownStage::WriteCheckpoints,
+            "checkpoint",
+            record("shutdown"),
+        );
+        let lifecycle = Lifecycle::new(coordinator)
+            .on_reload("config", record("reload"))
+            .on_flush("checkpoint", record("flush"))
+            .on_flush("failing", || async { Err("disk full".into()) });
+
+        let report = lifecycle
+            .run_with(stream::iter([
+                LifecycleSignal::Flush,
+                LifecycleSignal::Reload,
+                LifecycleSignal::Flush,
+                LifecycleSignal::Shutdown,
+                LifecycleSignal::Reload,
+            ]))
+            .await;
+
+        assert!(report.is_clean());
+        assert_eq!(
+            *calls.lock().unwrap(),
+            ["flush", "reload", "flush", "shutdown"]
+        );
+    }
+}
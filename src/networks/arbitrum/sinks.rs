@@ -0,0 +1,143 @@
+pub mod encoding;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod fanout;
+#[cfg(feature = "kafka")]
+pub mod kafka;
+#[cfg(feature = "nats")]
+pub mod nats;
+#[cfg(feature = "postgres")]
+pub mod postgres;
+
+use crate::networks::arbitrum::{errors::SinkError, message::FeedMessage};
+use async_trait::async_trait;
+use std::{fmt, time::Duration};
+
+/// The HTTP header carrying the `IdempotencyKey` of webhook deliveries.
+pub const IDEMPOTENCY_HEADER: &str = "Idempotency-Key";
+/// The columns of the unique constraint deduplicating rows in database sinks.
+pub const IDEMPOTENCY_COLUMNS: [&str; 2] = ["chain_id", "sequence_number"];
+
+/// Uniquely identifies a delivery, so that sinks and their consumers can deduplicate redelivered
+/// messages.
+///
+/// Deliveries are retried and relays replay messages after a reconnect, so the same message may
+/// reach a sink several times. Every sink derives its deduplication key from this one, in the
+/// form native to its destination:
+///
+/// * Kafka: the message key, `kafka_key`.
+/// * Redis streams: the entry ID, `redis_stream_id`.
+/// * NATS JetStream: the `Nats-Msg-Id` header, set to the `Display` form.
+/// * Databases: a unique constraint on `IDEMPOTENCY_COLUMNS`.
+/// * Webhooks: the `IDEMPOTENCY_HEADER` header, set to the `Display` form.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct IdempotencyKey {
+    pub chain_id: u64,
+    pub sequence_number: u64,
+}
+
+impl IdempotencyKey {
+    pub fn new(chain_id: u64, sequence_number: u64) -> Self {
+        Self {
+            chain_id,
+            sequence_number,
+        }
+    }
+
+    /// The key of the message in Kafka, which also makes compacted topics keep one copy.
+    pub fn kafka_key(&self) -> Vec<u8> {
+        self.to_string().into_bytes()
+    }
+
+    /// The explicit ID of the entry in a Redis stream.
+    ///
+    /// Redis rejects IDs not greater than the last one of the stream, so redelivered messages
+    /// fail to be added instead of being duplicated, provided each chain has its own stream.
+    pub fn redis_stream_id(&self) -> String {
+        format!("{}-{}", self.sequence_number, self.chain_id)
+    }
+}
+
+/// Formats the key as `<chain_id>:<sequence_number>`.
+impl fmt::Display for IdempotencyKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.chain_id, self.sequence_number)
+    }
+}
+
+/// A destination feed messages are delivered to, such as a message queue or a database.
+#[async_trait]
+pub trait Sink: Send + Sync {
+    /// A name identifying the sink in logs, events and metrics.
+    fn name(&self) -> &str;
+
+    /// Delivers a message. Messages are delivered one at a time, in sequence order.
+    ///
+    /// Errors are retried according to the `RetryPolicy` of the fan-out driving the sink, so
+    /// implementations should be idempotent, deduplicating deliveries by `key`.
+    async fn deliver(&self, key: IdempotencyKey, msg: &FeedMessage) -> Result<(), SinkError>;
+
+    /// Establishes the connections of the sink and checks its destination is reachable, so that
+    /// the first messages don't hit a cold sink. Called before messages flow, see `Startup`.
+    async fn warm_up(&self) -> Result<(), SinkError> {
+        Ok(())
+    }
+
+    /// Flushes messages buffered by the sink.
+    async fn flush(&self) -> Result<(), SinkError> {
+        Ok(())
+    }
+}
+
+/// How failed deliveries are retried.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// The number of attempts, including the first one, before a message is given up on.
+    pub max_attempts: u32,
+    /// The delay before the first retry. Doubles after each failed retry.
+    pub initial_backoff: Duration,
+    /// The upper bound of the delay between retries.
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(5),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Returns the delay before retry number `attempt` (starting at 1).
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(self.max_backoff)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn derives_the_native_key_of_every_destination() {
+        let key = IdempotencyKey::new(42161, 7);
+        assert_eq!(key.to_string(), "42161:7");
+        assert_eq!(key.kafka_key(), b"42161:7");
+        assert_eq!(key.redis_stream_id(), "7-42161");
+        assert_eq!(IDEMPOTENCY_HEADER, "Idempotency-Key");
+
+        // Redis orders entry IDs by their first part, then their second: a redelivered message
+        // has an ID no greater than the last entry of the stream, and is rejected.
+        let id = |seq| {
+            let id = IdempotencyKey::new(42161, seq).redis_stream_id();
+            let (ms, seq) = id.split_once('-').unwrap();
+            (ms.parse::<u64>().unwrap(), seq.parse::<u64>().unwrap())
+        };
+        assert!(id(8) > id(7));
+        assert!(id(10) > id(9));
+    }
+}
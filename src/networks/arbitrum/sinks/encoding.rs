@@ -0,0 +1,119 @@
+//! Wire formats of the messages published by the streaming sinks.
+
+use super::IdempotencyKey;
+#[cfg(feature = "protobuf")]
+use crate::networks::arbitrum::protobuf;
+use crate::networks::arbitrum::{
+    decoder::DecodedMsg, errors::SinkError, message::FeedMessage, provenance::Provenance,
+    types::BroadcastFeedMessage,
+};
+use ethers::types::Transaction;
+use serde::Serialize;
+
+/// The Protocol Buffers schema of the crate, `proto/feed.proto`, for consumers to generate code
+/// from: the `FeedMessage` message of `Encoding::Protobuf`, the messages of `protobuf` and the
+/// service of `grpc`.
+pub const PROTO_SCHEMA: &str = include_str!("../../../../proto/feed.proto");
+
+/// The JSON object of `Encoding::Json`.
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct JsonMessage<'a> {
+    /// The `IdempotencyKey` of the delivery, as `<chain_id>:<sequence_number>`.
+    pub key: String,
+    pub chain_id: u64,
+    pub sequence_number: u64,
+    pub message: &'a BroadcastFeedMessage,
+    /// The transactions decoded from the L2 message, as returned by `eth_getTransactionByHash`.
+    #[cfg_attr(feature = "schema", schemars(with = "Vec<serde_json::Value>"))]
+    pub transactions: Vec<Transaction>,
+    /// Why the L2 message could not be decoded, if it couldn't.
+    pub decode_error: Option<String>,
+    pub provenance: &'a Provenance,
+}
+
+/// How a sink serializes feed messages.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Encoding {
+    /// A JSON object holding the feed message, its decoded transactions and its provenance.
+    #[default]
+    Json,
+    /// The `FeedMessage` message of `PROTO_SCHEMA`.
+    #[cfg(feature = "protobuf")]
+    Protobuf,
+}
+
+impl Encoding {
+    /// The MIME type of the encoded messages.
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Encoding::Json => "application/json",
+            #[cfg(feature = "protobuf")]
+            Encoding::Protobuf => "application/x-protobuf",
+        }
+    }
+
+    /// Serializes `msg`, delivered under `key`.
+    pub fn encode(&self, key: IdempotencyKey, msg: &FeedMessage) -> Result<Vec<u8>, SinkError> {
+        match self {
+            Encoding::Json => {
+                let (transactions, decode_error) = match &msg.decoded {
+                    Ok(decoded) => (transactions(decoded.as_ref()), None),
+                    Err(e) => (Vec::new(), Some(e.to_string())),
+                };
+                Ok(serde_json::to_vec(&JsonMessage {
+                    key: key.to_string(),
+                    chain_id: key.chain_id,
+                    sequence_number: key.sequence_number,
+                    message: &msg.message,
+                    transactions,
+                    decode_error,
+                    provenance: &msg.provenance,
+                })?)
+            }
+            #[cfg(feature = "protobuf")]
+            Encoding::Protobuf => {
+                use prost::Message;
+                Ok(protobuf::feed_message(key.chain_id, msg).encode_to_vec())
+            }
+        }
+    }
+}
+
+fn transactions(decoded: Option<&DecodedMsg>) -> Vec<Transaction> {
+    match decoded {
+        Some(DecodedMsg::DecodedBatch(txs)) => txs.clone(),
+        Some(DecodedMsg::DecodedSignedTx(tx)) => vec![(**tx).clone()],
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::networks::arbitrum::fixtures::feed_message;
+
+    #[test]
+    fn encodes_json_and_protobuf() {
+        let msg = feed_message(300, 1, vec![0xaa], None);
+        let key = IdempotencyKey::new(42161, 300);
+
+        let json: serde_json::Value =
+            serde_json::from_slice(&Encoding::Json.encode(key, &msg).unwrap()).unwrap();
+        assert_eq!(json["key"], "42161:300");
+        assert_eq!(json["message"]["sequenceNumber"], 300);
+
+        #[cfg(feature = "protobuf")]
+        assert_eq!(
+            Encoding::Protobuf.encode(key, &msg).unwrap(),
+            vec![
+                0x08, 0xb1, 0xc9, 0x02, // chain_id = 42161
+                0x10, 0xac, 0x02, // sequence_number = 300
+                0x18, 0x03, // kind = 3
+                0x30, 0x01, // timestamp = 1
+                0x42, 0x01, 0xaa, // l2_msg
+            ]
+        );
+    }
+}
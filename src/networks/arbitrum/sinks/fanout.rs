@@ -0,0 +1,508 @@
+use super::{IdempotencyKey, RetryPolicy, Sink};
+use crate::networks::arbitrum::{
+    errors::{SinkError, StartupError},
+    events::FeedEvent,
+    message::FeedMessage,
+};
+use crossbeam_channel::{Receiver, Sender};
+use futures::FutureExt;
+use log::*;
+use std::{
+    any::Any,
+    future::Future,
+    panic::AssertUnwindSafe,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+use tokio::{sync::mpsc, task::JoinHandle};
+
+const DEFAULT_QUEUE_DEPTH: usize = 1024;
+const DEFAULT_WATERMARK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Sequence numbers delivered across all the sinks of a fan-out.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Watermarks {
+    /// Every message up to this sequence number has been processed by every sink.
+    pub low: Option<u64>,
+    /// The highest sequence number processed by any sink.
+    pub high: Option<u64>,
+}
+
+/// Tracks the last sequence number processed by each sink.
+///
+/// Values are stored offset by one so that zero means "nothing processed yet".
+#[derive(Debug)]
+struct Progress(Vec<AtomicU64>);
+
+impl Progress {
+    fn watermarks(&self) -> Watermarks {
+        let processed: Vec<u64> = self.0.iter().map(|p| p.load(Ordering::Acquire)).collect();
+        let low = processed.iter().min().copied().unwrap_or(0);
+        let high = processed.iter().max().copied().unwrap_or(0);
+        Watermarks {
+            low: low.checked_sub(1),
+            high: high.checked_sub(1),
+        }
+    }
+}
+
+/// Delivers every message to several sinks, each with its own queue and retries.
+///
+/// A slow or failing sink only delays its own queue. Because sinks progress independently, the
+/// fan-out computes watermarks: every message up to the low watermark has been processed by
+/// every sink, which external systems can use as a safe commit point. A message is processed
+/// once it was delivered, or sent to the dead-letter sink after `RetryPolicy::max_attempts`.
+/// A sink panicking while delivering or flushing fails that attempt, so that it can't take its
+/// queue down with it.
+/// A sink failing to deliver a message otherwise stops, holding the low watermark back.
+pub struct SinkFanOut {
+    chain_id: u64,
+    sinks: Vec<Arc<dyn Sink>>,
+    dead_letter: Option<Arc<dyn Sink>>,
+    retry: RetryPolicy,
+    queue_depth: usize,
+    watermark_interval: Duration,
+}
+
+/// A running `SinkFanOut`.
+pub struct SinkFanOutHandle {
+    progress: Arc<Progress>,
+    tasks: Vec<JoinHandle<()>>,
+    watermark_task: JoinHandle<()>,
+}
+
+impl SinkFanOut {
+    /// # Arguments
+    ///
+    /// * `chain_id` - The chain the messages come from, part of their `IdempotencyKey`.
+    pub fn new(chain_id: u64) -> Self {
+        Self {
+            chain_id,
+            sinks: Vec::new(),
+            dead_letter: None,
+            retry: RetryPolicy::default(),
+            queue_depth: DEFAULT_QUEUE_DEPTH,
+            watermark_interval: DEFAULT_WATERMARK_INTERVAL,
+        }
+    }
+
+    pub fn with_sink(mut self, sink: Arc<dyn Sink>) -> Self {
+        self.sinks.push(sink);
+        self
+    }
+
+    /// Sets the sink receiving the messages another sink failed to deliver, retried according to
+    /// the retry policy too.
+    pub fn with_dead_letter(mut self, sink: Arc<dyn Sink>) -> Self {
+        self.dead_letter = Some(sink);
+        self
+    }
+
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Sets how many messages each sink may lag behind before the fan-out blocks.
+    pub fn with_queue_depth(mut self, queue_depth: usize) -> Self {
+        self.queue_depth = queue_depth.max(1);
+        self
+    }
+
+    /// Sets how often `FeedEvent::Watermark` events are emitted, if the watermarks moved.
+    pub fn with_watermark_interval(mut self, interval: Duration) -> Self {
+        self.watermark_interval = interval;
+        self
+    }
+
+    /// Warms every sink up concurrently, retrying according to the retry policy.
+    ///
+    /// # Returns
+    ///
+    /// A `StartupError::Sink` with the last error of the first sink that failed every attempt.
+    pub async fn warm_up(&self) -> Result<(), StartupError> {
+        let warm_ups = self.sinks.iter().map(|sink| async move {
+            let mut attempt = 1;
+            loop {
+                match sink.warm_up().await {
+                    Ok(()) => return Ok(()),
+                    Err(e) if attempt >= self.retry.max_attempts => {
+                        return Err(StartupError::Sink {
+                            sink: sink.name().to_string(),
+                            source: e,
+                        })
+                    }
+                    Err(e) => {
+                        warn!(
+                            "Sink {} failed to warm up (attempt {}): {}",
+                            sink.name(),
+                            attempt,
+                            e
+                        );
+                        tokio::time::sleep(self.retry.backoff(attempt)).await;
+                        attempt += 1;
+                    }
+                }
+            }
+        });
+        futures::future::try_join_all(warm_ups).await?;
+        Ok(())
+    }
+
+    /// Starts delivering the messages received on `input`. Must be called within a Tokio runtime.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - The receiver channel of `FeedMessage`s, e.g. fed by a `DecodePool`.
+    /// * `events` - The sender channel for sending `FeedEvent::Watermark` events.
+    pub fn spawn(
+        self,
+        input: Receiver<FeedMessage>,
+        events: Sender<FeedEvent>,
+    ) -> SinkFanOutHandle {
+        let progress = Arc::new(Progress(
+            self.sinks.iter().map(|_| AtomicU64::new(0)).collect(),
+        ));
+        let mut queues = Vec::with_capacity(self.sinks.len());
+        let mut tasks = Vec::with_capacity(self.sinks.len());
+
+        for (i, sink) in self.sinks.into_iter().enumerate() {
+            let (tx, rx) = mpsc::channel(self.queue_depth);
+            queues.push(tx);
+            tasks.push(tokio::spawn(run_sink(
+                (sink, self.dead_letter.clone()),
+                self.chain_id,
+                rx,
+                self.retry.clone(),
+                progress.clone(),
+                i,
+            )));
+        }
+
+        thread::spawn(move || {
+            for msg in input {
+                let msg = Arc::new(msg);
+                for queue in &queues {
+                    // A closed queue means its sink task ended; keep feeding the others.
+                    let _ = queue.blocking_send(msg.clone());
+                }
+            }
+        });
+
+        let watermark_task = tokio::spawn(emit_watermarks(
+            progress.clone(),
+            self.watermark_interval,
+            events,
+        ));
+
+        SinkFanOutHandle {
+            progress,
+            tasks,
+            watermark_task,
+        }
+    }
+}
+
+impl SinkFanOutHandle {
+    /// Returns the current watermarks.
+    pub fn watermarks(&self) -> Watermarks {
+        self.progress.watermarks()
+    }
+
+    /// Waits for every sink to process and flush its queue, once the input is disconnected.
+    pub async fn join(self) -> Watermarks {
+        for task in self.tasks {
+            let _ = task.await;
+        }
+        self.watermark_task.abort();
+        self.progress.watermarks()
+    }
+}
+
+async fn run_sink(
+    (sink, dead_letter): (Arc<dyn Sink>, Option<Arc<dyn Sink>>),
+    chain_id: u64,
+    mut queue: mpsc::Receiver<Arc<FeedMessage>>,
+    retry: RetryPolicy,
+    progress: Arc<Progress>,
+    index: usize,
+) {
+    while let Some(msg) = queue.recv().await {
+        let key = IdempotencyKey::new(chain_id, msg.sequence_number());
+        if let Err(e) = deliver(&sink, key, &msg, &retry).await {
+            error!(
+                "Sink {} gave up on message {} after {} attempts: {}",
+                sink.name(),
+                msg.sequence_number(),
+                retry.max_attempts,
+                e
+            );
+            let Some(dead_letter) = &dead_letter else {
+                error!(
+                    "Sink {} stopped, holding the low watermark back",
+                    sink.name()
+                );
+                break;
+            };
+            if let Err(e) = deliver(dead_letter, key, &msg, &retry).await {
+                error!(
+                    "Dead-letter sink {} failed to deliver message {}: {}",
+                    dead_letter.name(),
+                    msg.sequence_number(),
+                    e
+                );
+                error!(
+                    "Sink {} stopped, holding the low watermark back",
+                    sink.name()
+                );
+                break;
+            }
+        }
+
+        progress.0[index].fetch_max(msg.sequence_number() + 1, Ordering::AcqRel);
+    }
+
+    if let Err(e) = supervised(sink.flush()).await {
+        error!("Sink {} failed to flush: {}", sink.name(), e);
+    }
+}
+
+/// Runs a sink operation, turning a panic into a `SinkError`.
+async fn supervised(
+    operation: impl Future<Output = Result<(), SinkError>>,
+) -> Result<(), SinkError> {
+    AssertUnwindSafe(operation)
+        .catch_unwind()
+        .await
+        .unwrap_or_else(|panic| {
+            Err(SinkError::Msg(format!(
+                "panicked: {}",
+                panic_message(&panic)
+            )))
+        })
+}
+
+fn panic_message(panic: &Box<dyn Any + Send>) -> &str {
+    panic
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic")
+}
+
+/// Delivers `msg` to `sink`, retrying according to `retry`.
+///
+/// # Returns
+///
+/// The error of the last attempt, if every attempt failed.
+async fn deliver(
+    sink: &Arc<dyn Sink>,
+    key: IdempotencyKey,
+    msg: &FeedMessage,
+    retry: &RetryPolicy,
+) -> Result<(), SinkError> {
+    let mut attempt = 1;
+    loop {
+        match supervised(sink.deliver(key, msg)).await {
+            Ok(()) => return Ok(()),
+            Err(e) if attempt >= retry.max_attempts => return Err(e),
+            Err(e) => {
+                warn!(
+                    "Sink {} failed to deliver message {} (attempt {}): {}",
+                    sink.name(),
+                    msg.sequence_number(),
+                    attempt,
+                    e
+                );
+                tokio::time::sleep(retry.backoff(attempt)).await;
+                attempt += 1;
+            }
+        }
+    }
+}
+
+async fn emit_watermarks(progress: Arc<Progress>, interval: Duration, events: Sender<FeedEvent>) {
+    let mut ticker = tokio::time::interval(interval);
+    let mut last = Watermarks::default();
+    loop {
+        ticker.tick().await;
+        let current = progress.watermarks();
+        if current == last {
+            continue;
+        }
+        last = current;
+
+        if let (Some(low), Some(high)) = (current.low, current.high) {
+            if events.send(FeedEvent::Watermark { low, high }).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::networks::arbitrum::{errors::SinkError, fixtures::feed_message};
+    use async_trait::async_trait;
+    use crossbeam_channel::unbounded;
+    use std::sync::Mutex;
+
+    struct RecordingSink {
+        failures_left: Mutex<u32>,
+        delivered: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl Sink for RecordingSink {
+        fn name(&self) -> &str {
+            "recording"
+        }
+
+        async fn deliver(&self, key: IdempotencyKey, _msg: &FeedMessage) -> Result<(), SinkError> {
+            let mut failures_left = self.failures_left.lock().unwrap();
+            if *failures_left > 0 {
+                *failures_left -= 1;
+                return Err(SinkError::Msg("unavailable".to_string()));
+            }
+            self.delivered.lock().unwrap().push(key.to_string());
+            Ok(())
+        }
+    }
+
+    /// Panics on its first delivery, then delivers to `inner`.
+    struct PanickingSink {
+        panicked: std::sync::atomic::AtomicBool,
+        inner: Arc<RecordingSink>,
+    }
+
+    #[async_trait]
+    impl Sink for PanickingSink {
+        fn name(&self) -> &str {
+            "panicking"
+        }
+
+        async fn deliver(&self, key: IdempotencyKey, msg: &FeedMessage) -> Result<(), SinkError> {
+            if !self.panicked.swap(true, Ordering::Relaxed) {
+                panic!("sink bug");
+            }
+            self.inner.deliver(key, msg).await
+        }
+    }
+
+    fn sink(failures: u32) -> Arc<RecordingSink> {
+        Arc::new(RecordingSink {
+            failures_left: Mutex::new(failures),
+            delivered: Mutex::new(Vec::new()),
+        })
+    }
+
+    fn send_messages(input: Sender<FeedMessage>, sequence_numbers: std::ops::Range<u64>) {
+        for seq in sequence_numbers {
+            input.send(feed_message(seq, 0, Vec::new(), None)).unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn every_sink_receives_every_message() {
+        let healthy = sink(0);
+        let flaky = sink(3);
+        let (input_tx, input_rx) = unbounded();
+        let (events_tx, _events_rx) = unbounded();
+
+        let handle = SinkFanOut::new(42161)
+            .with_sink(healthy.clone())
+            .with_sink(flaky.clone())
+            .with_retry_policy(RetryPolicy {
+                max_attempts: 5,
+                initial_backoff: Duration::from_millis(1),
+                max_backoff: Duration::from_millis(1),
+            })
+            .spawn(input_rx, events_tx);
+
+        send_messages(input_tx, 10..20);
+
+        let watermarks = handle.join().await;
+        assert_eq!(
+            watermarks,
+            Watermarks {
+                low: Some(19),
+                high: Some(19)
+            }
+        );
+        let keys: Vec<_> = (10..20).map(|seq| format!("42161:{}", seq)).collect();
+        assert_eq!(*healthy.delivered.lock().unwrap(), keys);
+        assert_eq!(*flaky.delivered.lock().unwrap(), keys);
+    }
+
+    #[tokio::test]
+    async fn panics_fail_the_attempt_instead_of_the_sink() {
+        let recording = sink(0);
+        let (input_tx, input_rx) = unbounded();
+        let (events_tx, _events_rx) = unbounded();
+        let handle = SinkFanOut::new(42161)
+            .with_sink(Arc::new(PanickingSink {
+                panicked: Default::default(),
+                inner: recording.clone(),
+            }))
+            .with_retry_policy(RetryPolicy {
+                max_attempts: 2,
+                initial_backoff: Duration::from_millis(1),
+                max_backoff: Duration::from_millis(1),
+            })
+            .spawn(input_rx, events_tx);
+        send_messages(input_tx, 10..13);
+
+        assert_eq!(handle.join().await.low, Some(12));
+        assert_eq!(recording.delivered.lock().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn failed_messages_hold_the_low_watermark_back() {
+        let retry = RetryPolicy {
+            max_attempts: 2,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(1),
+        };
+        let healthy = sink(0);
+        let (input_tx, input_rx) = unbounded();
+        let (events_tx, _events_rx) = unbounded();
+        let handle = SinkFanOut::new(42161)
+            .with_sink(healthy.clone())
+            .with_sink(sink(u32::MAX))
+            .with_retry_policy(retry.clone())
+            .spawn(input_rx, events_tx);
+        send_messages(input_tx, 10..20);
+
+        // The failing sink stops without processing anything.
+        let watermarks = handle.join().await;
+        assert_eq!(
+            watermarks,
+            Watermarks {
+                low: None,
+                high: Some(19)
+            }
+        );
+        assert_eq!(healthy.delivered.lock().unwrap().len(), 10);
+
+        let dead_letter = sink(0);
+        let (input_tx, input_rx) = unbounded();
+        let (events_tx, _events_rx) = unbounded();
+        let handle = SinkFanOut::new(42161)
+            .with_sink(sink(u32::MAX))
+            .with_dead_letter(dead_letter.clone())
+            .with_retry_policy(retry)
+            .spawn(input_rx, events_tx);
+        send_messages(input_tx, 10..12);
+
+        assert_eq!(handle.join().await.low, Some(11));
+        assert_eq!(
+            *dead_letter.delivered.lock().unwrap(),
+            ["42161:10", "42161:11"]
+        );
+    }
+}
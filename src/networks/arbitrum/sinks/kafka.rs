@@ -0,0 +1,104 @@
+//! A sink publishing feed messages to a Kafka topic.
+
+use super::{encoding::Encoding, IdempotencyKey, Sink};
+use crate::networks::arbitrum::{errors::SinkError, message::FeedMessage};
+use async_trait::async_trait;
+use rdkafka::{
+    config::ClientConfig,
+    producer::{FutureProducer, FutureRecord, Producer},
+    util::Timeout,
+};
+use std::time::Duration;
+
+/// How long `warm_up` waits for the metadata of the topic.
+const WARM_UP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Publishes feed messages to a Kafka topic, keyed by their `IdempotencyKey`.
+///
+/// A message is delivered once the brokers acknowledged it: a failed delivery is retried by the
+/// fan-out, and the idempotent producer keeps the retries from duplicating it.
+pub struct KafkaSink {
+    name: String,
+    producer: FutureProducer,
+    topic: String,
+    encoding: Encoding,
+}
+
+impl KafkaSink {
+    /// Creates a sink publishing to `topic` on the cluster at `brokers`, with an idempotent
+    /// producer.
+    ///
+    /// # Arguments
+    ///
+    /// * `brokers` - The comma separated list of bootstrap brokers.
+    /// * `topic` - The topic to publish to.
+    pub fn new(brokers: &str, topic: impl Into<String>) -> Result<Self, SinkError> {
+        let mut config = ClientConfig::new();
+        config
+            .set("bootstrap.servers", brokers)
+            .set("enable.idempotence", "true")
+            .set("linger.ms", "0")
+            .set("compression.type", "lz4")
+            .set("message.timeout.ms", "30000");
+        Self::from_config(&config, topic)
+    }
+
+    /// Creates a sink publishing to `topic` with a producer created from `config`.
+    pub fn from_config(config: &ClientConfig, topic: impl Into<String>) -> Result<Self, SinkError> {
+        let topic = topic.into();
+        Ok(Self {
+            name: format!("kafka:{}", topic),
+            producer: config.create()?,
+            topic,
+            encoding: Encoding::default(),
+        })
+    }
+
+    pub fn with_encoding(mut self, encoding: Encoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Sets the name identifying the sink, `kafka:<topic>` by default.
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+}
+
+#[async_trait]
+impl Sink for KafkaSink {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Publishes the message and waits for the brokers to acknowledge it, until
+    /// `message.timeout.ms` elapses.
+    async fn deliver(&self, key: IdempotencyKey, msg: &FeedMessage) -> Result<(), SinkError> {
+        let payload = self.encoding.encode(key, msg)?;
+        let record_key = key.kafka_key();
+        let record = FutureRecord::to(&self.topic)
+            .key(&record_key)
+            .payload(&payload);
+        self.producer
+            .send(record, Timeout::Never)
+            .await
+            .map_err(|(e, _)| e)?;
+        Ok(())
+    }
+
+    /// Fetches the metadata of the topic, which connects to the brokers leading it.
+    async fn warm_up(&self) -> Result<(), SinkError> {
+        let producer = self.producer.clone();
+        let topic = self.topic.clone();
+        tokio::task::spawn_blocking(move || {
+            producer
+                .client()
+                .fetch_metadata(Some(&topic), WARM_UP_TIMEOUT)
+                .map(|_| ())
+        })
+        .await
+        .map_err(|e| SinkError::Msg(e.to_string()))??;
+        Ok(())
+    }
+}
@@ -0,0 +1,202 @@
+//! A sink publishing feed messages to NATS subjects, optionally persisted by JetStream.
+
+use super::{encoding::Encoding, IdempotencyKey, Sink};
+use crate::networks::arbitrum::{errors::SinkError, message::FeedMessage};
+use async_nats::{header::NATS_MESSAGE_ID, jetstream, Client, HeaderMap};
+use async_trait::async_trait;
+use log::*;
+use std::time::Duration;
+
+/// The upper bound of the delay between reconnection attempts.
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(10);
+
+/// The subject messages are published to, with placeholders replaced per message.
+///
+/// Supported placeholders are `{chain_id}` and `{kind}`, the L1 message kind, e.g.
+/// `arbitrum.{chain_id}.feed.{kind}`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubjectTemplate(String);
+
+impl SubjectTemplate {
+    const PLACEHOLDERS: [&'static str; 2] = ["{chain_id}", "{kind}"];
+
+    /// Fails if `template` contains an unknown placeholder.
+    pub fn new(template: impl Into<String>) -> Result<Self, SinkError> {
+        let template = template.into();
+        let mut rest = template.as_str();
+        while let Some(start) = rest.find('{') {
+            let end = rest[start..]
+                .find('}')
+                .map_or(rest.len(), |end| start + end + 1);
+            let placeholder = &rest[start..end];
+            if !Self::PLACEHOLDERS.contains(&placeholder) {
+                return Err(SinkError::Msg(format!(
+                    "unknown placeholder {} in subject {}",
+                    placeholder, template
+                )));
+            }
+            rest = &rest[end..];
+        }
+        Ok(Self(template))
+    }
+
+    /// Returns the subject of the message delivered under `key`.
+    pub fn render(&self, key: IdempotencyKey, msg: &FeedMessage) -> String {
+        self.0
+            .replace("{chain_id}", &key.chain_id.to_string())
+            .replace(
+                "{kind}",
+                &msg.message.message.message.header.kind.to_string(),
+            )
+    }
+}
+
+enum Publisher {
+    Core(Client),
+    JetStream(jetstream::Context),
+}
+
+/// Publishes feed messages to NATS.
+///
+/// Every message carries its `IdempotencyKey` in the `Nats-Msg-Id` header, which JetStream uses to
+/// discard redelivered messages within the stream's duplicate window. With JetStream, a delivery
+/// succeeds once the stream acknowledged the message; core NATS only guarantees the message was
+/// handed to the connection. The client reconnects on its own, buffering messages meanwhile.
+pub struct NatsSink {
+    name: String,
+    publisher: Publisher,
+    subject: SubjectTemplate,
+    encoding: Encoding,
+}
+
+impl NatsSink {
+    /// Connects to the NATS server at `url`, retrying until it is reachable.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The server URL, e.g. `nats://127.0.0.1:4222`.
+    /// * `subject` - The subject template messages are published to.
+    pub async fn connect(url: &str, subject: SubjectTemplate) -> Result<Self, SinkError> {
+        let client = async_nats::ConnectOptions::new()
+            .name("sequencer-feed-reader")
+            .retry_on_initial_connect()
+            .reconnect_delay_callback(|attempts| {
+                Duration::from_millis(100)
+                    .saturating_mul(2u32.saturating_pow(attempts as u32))
+                    .min(MAX_RECONNECT_DELAY)
+            })
+            .event_callback(|event| async move {
+                match event {
+                    async_nats::Event::Connected => info!("NATS sink connected"),
+                    event => warn!("NATS sink: {}", event),
+                }
+            })
+            .connect(url)
+            .await
+            .map_err(|e| SinkError::Msg(e.to_string()))?;
+        Ok(Self::new(client, subject))
+    }
+
+    /// Creates a sink publishing with an existing `client`.
+    pub fn new(client: Client, subject: SubjectTemplate) -> Self {
+        Self {
+            name: "nats".to_string(),
+            publisher: Publisher::Core(client),
+            subject,
+            encoding: Encoding::default(),
+        }
+    }
+
+    /// Publishes through JetStream, waiting for the stream to acknowledge every message. A
+    /// stream must capture the subjects the messages are published to.
+    pub fn with_jetstream(mut self) -> Self {
+        if let Publisher::Core(client) = self.publisher {
+            self.publisher = Publisher::JetStream(jetstream::new(client));
+        }
+        self
+    }
+
+    pub fn with_encoding(mut self, encoding: Encoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Sets the name identifying the sink, `nats` by default.
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+}
+
+#[async_trait]
+impl Sink for NatsSink {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn deliver(&self, key: IdempotencyKey, msg: &FeedMessage) -> Result<(), SinkError> {
+        let subject = self.subject.render(key, msg);
+        let payload = self.encoding.encode(key, msg)?.into();
+        let mut headers = HeaderMap::new();
+        headers.insert(NATS_MESSAGE_ID, key.to_string().as_str());
+        headers.insert("Content-Type", self.encoding.content_type());
+
+        match &self.publisher {
+            Publisher::Core(client) => client
+                .publish_with_headers(subject, headers, payload)
+                .await
+                .map_err(|e| SinkError::Msg(e.to_string())),
+            Publisher::JetStream(context) => {
+                let ack = context
+                    .publish_with_headers(subject, headers, payload)
+                    .await
+                    .map_err(|e| SinkError::Msg(e.to_string()))?;
+                ack.await.map_err(|e| SinkError::Msg(e.to_string()))?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Round-trips to the server, or queries the JetStream account.
+    async fn warm_up(&self) -> Result<(), SinkError> {
+        match &self.publisher {
+            Publisher::Core(client) => client
+                .flush()
+                .await
+                .map_err(|e| SinkError::Msg(e.to_string())),
+            Publisher::JetStream(context) => context
+                .query_account()
+                .await
+                .map(|_| ())
+                .map_err(|e| SinkError::Msg(e.to_string())),
+        }
+    }
+
+    async fn flush(&self) -> Result<(), SinkError> {
+        let client = match &self.publisher {
+            Publisher::Core(client) => client,
+            Publisher::JetStream(_) => return Ok(()),
+        };
+        client
+            .flush()
+            .await
+            .map_err(|e| SinkError::Msg(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::networks::arbitrum::fixtures::feed_message;
+
+    #[test]
+    fn renders_subject_templates() {
+        let msg = feed_message(7, 0, Vec::new(), None);
+        let subject = SubjectTemplate::new("arbitrum.{chain_id}.feed.{kind}").unwrap();
+        assert_eq!(
+            subject.render(IdempotencyKey::new(42161, 7), &msg),
+            "arbitrum.42161.feed.3"
+        );
+        assert!(SubjectTemplate::new("feed.{network}").is_err());
+    }
+}
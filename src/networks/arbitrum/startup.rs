@@ -0,0 +1,167 @@
+use crate::networks::arbitrum::{
+    checkpoint::{CheckpointStore, Checkpointer},
+    connect::ConnectOptions,
+    errors::StartupError,
+    sinks::fanout::SinkFanOut,
+};
+use log::*;
+use std::time::Duration;
+
+/// How long sinks may take to warm up by default.
+const DEFAULT_WARM_UP_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Prepares everything downstream of the feed before subscribing to it.
+///
+/// When a client connects, relays first send their backlog, and a client resuming from a
+/// checkpoint receives every message since then at once. Hitting sinks that still have to
+/// connect with that burst fills their queues and triggers backpressure drops, so `prepare`
+/// loads the checkpoint and warms up the sinks first, returning the options to connect with.
+///
+/// # Example
+///
+/// ```no_run
+/// # use sequencer_feed_reader::networks::arbitrum::{
+/// #     checkpoint::{Checkpointer, FileCheckpointStore}, connect::ConnectOptions,
+/// #     sinks::fanout::SinkFanOut, startup::Startup,
+/// # };
+/// # async fn run(fanout: SinkFanOut) -> Result<(), Box<dyn std::error::Error>> {
+/// let checkpointer = Checkpointer::new(FileCheckpointStore::new("checkpoint"));
+/// let options = Startup::new(ConnectOptions::new())
+///     .with_checkpoint(&checkpointer)
+///     .prepare(&fanout)
+///     .await?;
+/// // Connect a `RelayClient` with `options`, then spawn the fan-out.
+/// # Ok(())
+/// # }
+/// ```
+pub struct Startup<'a> {
+    options: ConnectOptions,
+    checkpoint: Option<Box<dyn Fn() -> Result<u64, StartupError> + 'a>>,
+    timeout: Duration,
+}
+
+impl<'a> Startup<'a> {
+    /// # Arguments
+    ///
+    /// * `options` - The options the relay client will connect with.
+    pub fn new(options: ConnectOptions) -> Self {
+        Self {
+            options,
+            checkpoint: None,
+            timeout: DEFAULT_WARM_UP_TIMEOUT,
+        }
+    }
+
+    /// Resumes the feed after the checkpoint of `checkpointer`.
+    pub fn with_checkpoint<S: CheckpointStore>(
+        mut self,
+        checkpointer: &'a Checkpointer<S>,
+    ) -> Self {
+        self.checkpoint = Some(Box::new(|| Ok(checkpointer.resume_sequence_number()?)));
+        self
+    }
+
+    /// Sets how long the sinks may take to warm up, retries included.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Loads the checkpoint and warms up the sinks of `fanout`.
+    ///
+    /// # Returns
+    ///
+    /// The options to connect to the feed with, or the first error that occurred.
+    pub async fn prepare(self, fanout: &SinkFanOut) -> Result<ConnectOptions, StartupError> {
+        let mut options = self.options;
+        if let Some(checkpoint) = &self.checkpoint {
+            options = options.with_sequence_number(checkpoint()?);
+            info!("Resuming the feed at {}", options.sequence_number);
+        }
+
+        tokio::time::timeout(self.timeout, fanout.warm_up())
+            .await
+            .map_err(|_| StartupError::Timeout(self.timeout))??;
+        info!("Sinks warmed up");
+        Ok(options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::networks::arbitrum::{
+        checkpoint::FileCheckpointStore,
+        errors::SinkError,
+        message::FeedMessage,
+        sinks::{IdempotencyKey, RetryPolicy, Sink},
+    };
+    use async_trait::async_trait;
+    use std::sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    };
+
+    /// A sink failing to warm up a number of times.
+    struct ColdSink {
+        failures: u32,
+        attempts: AtomicU32,
+    }
+
+    #[async_trait]
+    impl Sink for ColdSink {
+        fn name(&self) -> &str {
+            "cold"
+        }
+
+        async fn deliver(&self, _key: IdempotencyKey, _msg: &FeedMessage) -> Result<(), SinkError> {
+            Ok(())
+        }
+
+        async fn warm_up(&self) -> Result<(), SinkError> {
+            if self.attempts.fetch_add(1, Ordering::Relaxed) < self.failures {
+                return Err(SinkError::Msg("connection refused".to_string()));
+            }
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn resumes_after_warming_up_sinks() {
+        let path = std::env::temp_dir().join(format!("sfr-startup-{}", std::process::id()));
+        let store = FileCheckpointStore::new(&path);
+        store.save(41).unwrap();
+        let checkpointer = Checkpointer::new(store);
+
+        let retry = RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::ZERO,
+            max_backoff: Duration::ZERO,
+        };
+        let sink = Arc::new(ColdSink {
+            failures: 2,
+            attempts: AtomicU32::new(0),
+        });
+        let fanout = SinkFanOut::new(42161)
+            .with_sink(sink.clone())
+            .with_retry_policy(retry.clone());
+        let options = Startup::new(ConnectOptions::new())
+            .with_checkpoint(&checkpointer)
+            .prepare(&fanout)
+            .await
+            .unwrap();
+        assert_eq!(options.sequence_number, 42);
+        assert_eq!(sink.attempts.load(Ordering::Relaxed), 3);
+
+        let fanout = SinkFanOut::new(42161)
+            .with_sink(Arc::new(ColdSink {
+                failures: 3,
+                attempts: AtomicU32::new(0),
+            }))
+            .with_retry_policy(retry);
+        let result = Startup::new(ConnectOptions::new()).prepare(&fanout).await;
+        assert!(matches!(result, Err(StartupError::Sink { .. })));
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
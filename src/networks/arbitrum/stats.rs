@@ -0,0 +1,253 @@
+use crate::networks::arbitrum::{decoder::DecodedMsg, events::FeedEvent, message::FeedMessage};
+use ethers::types::{H160, U256};
+use serde::Serialize;
+use std::{collections::HashMap, time::Duration};
+
+/// How many of the most active contracts a `FeedSummary` lists by default.
+const DEFAULT_TOP_CONTRACTS: usize = 10;
+
+/// Statistics of the feed over a period, for monitoring dashboards.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct FeedSummary {
+    /// The start of the period, in seconds since the UNIX epoch, as per the message timestamps.
+    pub period_start: u64,
+    pub period_secs: u64,
+    pub first_sequence_number: u64,
+    pub last_sequence_number: u64,
+    pub messages: u64,
+    pub transactions: u64,
+    /// The messages holding a single signed transaction.
+    pub single_messages: u64,
+    /// The messages holding a batch, and the transactions of these batches.
+    pub batch_messages: u64,
+    pub batched_transactions: u64,
+    /// The sum of the gas limits of the transactions. The feed doesn't tell the gas used.
+    pub gas_limit: u64,
+    /// The contracts called the most during the period, most active first.
+    pub top_contracts: Vec<ContractActivity>,
+}
+
+/// How many transactions called a contract.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ContractActivity {
+    #[cfg_attr(feature = "schema", schemars(with = "String"))]
+    pub address: H160,
+    pub transactions: u64,
+}
+
+impl FeedSummary {
+    /// Returns the share of the messages holding a batch, among the transaction messages.
+    pub fn batch_ratio(&self) -> f64 {
+        let total = self.batch_messages + self.single_messages;
+        if total == 0 {
+            return 0.0;
+        }
+        self.batch_messages as f64 / total as f64
+    }
+
+    /// Returns the average number of transactions per batch.
+    pub fn average_batch_size(&self) -> f64 {
+        if self.batch_messages == 0 {
+            return 0.0;
+        }
+        self.batched_transactions as f64 / self.batch_messages as f64
+    }
+
+    /// Returns the number of transactions per minute over the period.
+    pub fn transactions_per_minute(&self) -> f64 {
+        self.transactions as f64 * 60.0 / self.period_secs.max(1) as f64
+    }
+}
+
+/// Aggregates the statistics of the feed in-stream, summarizing them once per period.
+///
+/// Periods are aligned on the message timestamps, so that summaries are reproducible when
+/// replaying the feed. A period is summarized once the first message of a later period is
+/// observed, or once the clock passes its end, see `tick`, so that summaries keep coming while
+/// the feed is quiet. Messages arriving after their period was summarized count in the next one.
+#[derive(Debug, Clone)]
+pub struct FeedStats {
+    period_secs: u64,
+    top_contracts: usize,
+    current: Option<FeedSummary>,
+    contracts: HashMap<H160, u64>,
+    /// The end of the last summarized period.
+    summarized_until: u64,
+}
+
+impl FeedStats {
+    /// # Arguments
+    ///
+    /// * `period` - The period of the summaries, rounded down to whole seconds.
+    pub fn new(period: Duration) -> Self {
+        Self {
+            period_secs: period.as_secs().max(1),
+            top_contracts: DEFAULT_TOP_CONTRACTS,
+            current: None,
+            contracts: HashMap::new(),
+            summarized_until: 0,
+        }
+    }
+
+    /// Lists the `n` most active contracts in the summaries.
+    pub fn with_top_contracts(mut self, n: usize) -> Self {
+        self.top_contracts = n;
+        self
+    }
+
+    /// Adds `msg` to the statistics of its period.
+    ///
+    /// # Returns
+    ///
+    /// A `FeedEvent::Summary` of the previous period if `msg` starts a new one.
+    pub fn observe(&mut self, msg: &FeedMessage) -> Option<FeedEvent> {
+        let timestamp = msg.message.message.message.header.timestamp;
+        let period_start = (timestamp - timestamp % self.period_secs).max(self.summarized_until);
+        let summary = match &self.current {
+            Some(current) if period_start > current.period_start => self.summarize(),
+            _ => None,
+        };
+
+        let sequence_number = msg.sequence_number();
+        let current = self.current.get_or_insert_with(|| FeedSummary {
+            period_start,
+            period_secs: self.period_secs,
+            first_sequence_number: sequence_number,
+            ..Default::default()
+        });
+        current.last_sequence_number = sequence_number;
+        current.messages += 1;
+        let txs = match &msg.decoded {
+            Ok(Some(DecodedMsg::DecodedBatch(txs))) => {
+                current.batch_messages += 1;
+                current.batched_transactions += txs.len() as u64;
+                txs.iter().collect()
+            }
+            Ok(Some(DecodedMsg::DecodedSignedTx(tx))) => {
+                current.single_messages += 1;
+                vec![tx.as_ref()]
+            }
+            _ => Vec::new(),
+        };
+        current.transactions += txs.len() as u64;
+        for tx in txs {
+            let gas_limit = tx.gas.min(U256::from(u64::MAX)).as_u64();
+            current.gas_limit = current.gas_limit.saturating_add(gas_limit);
+            if let Some(to) = tx.to {
+                *self.contracts.entry(to).or_default() += 1;
+            }
+        }
+
+        summary.map(FeedEvent::Summary)
+    }
+
+    /// Ends the current period if `now` is past it.
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - The current time, in seconds since the UNIX epoch.
+    ///
+    /// # Returns
+    ///
+    /// A `FeedEvent::Summary` of the current period if it ended.
+    pub fn tick(&mut self, now: u64) -> Option<FeedEvent> {
+        let end = self.current.as_ref()?.period_start + self.period_secs;
+        if now < end {
+            return None;
+        }
+        self.summarize().map(FeedEvent::Summary)
+    }
+
+    /// Ends the current period, if any.
+    ///
+    /// # Returns
+    ///
+    /// The summary of the period.
+    pub fn summarize(&mut self) -> Option<FeedSummary> {
+        let mut summary = self.current.take()?;
+        self.summarized_until = summary.period_start + self.period_secs;
+        let mut contracts: Vec<_> = self.contracts.drain().collect();
+        contracts.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        summary.top_contracts = contracts
+            .into_iter()
+            .take(self.top_contracts)
+            .map(|(address, transactions)| ContractActivity {
+                address,
+                transactions,
+            })
+            .collect();
+        Some(summary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::networks::arbitrum::fixtures::feed_message;
+    use ethers::types::Transaction;
+
+    #[test]
+    fn summarizes_each_period() {
+        let tx = |byte| Transaction {
+            to: Some(H160::repeat_byte(byte)),
+            gas: 21_000.into(),
+            ..Default::default()
+        };
+        let mut stats = FeedStats::new(Duration::from_secs(60)).with_top_contracts(1);
+
+        let batch = DecodedMsg::DecodedBatch(vec![tx(2), tx(3), tx(2)]);
+        assert_eq!(
+            stats.observe(&feed_message(1, 1_000_020, vec![], Some(batch))),
+            None
+        );
+        let single = DecodedMsg::DecodedSignedTx(Box::new(tx(1)));
+        assert_eq!(
+            stats.observe(&feed_message(2, 1_000_030, vec![], Some(single))),
+            None
+        );
+        let Some(FeedEvent::Summary(summary)) = stats.observe(&feed_message(
+            3,
+            1_000_080,
+            vec![],
+            Some(DecodedMsg::Heartbeat),
+        )) else {
+            panic!("expected a summary");
+        };
+
+        assert_eq!(summary.period_start, 1_000_020);
+        assert_eq!(
+            (summary.first_sequence_number, summary.last_sequence_number),
+            (1, 2)
+        );
+        assert_eq!((summary.messages, summary.transactions), (2, 4));
+        assert_eq!(summary.batch_ratio(), 0.5);
+        assert_eq!(summary.average_batch_size(), 3.0);
+        assert_eq!(summary.gas_limit, 84_000);
+        assert_eq!(
+            summary.top_contracts,
+            [ContractActivity {
+                address: H160::repeat_byte(2),
+                transactions: 2,
+            }]
+        );
+
+        assert_eq!(stats.tick(1_000_110), None);
+        let Some(FeedEvent::Summary(last)) = stats.tick(1_000_140) else {
+            panic!("expected a summary");
+        };
+        assert_eq!((last.first_sequence_number, last.transactions), (3, 0));
+
+        // A late message counts in the next period.
+        stats.observe(&feed_message(
+            4,
+            1_000_100,
+            vec![],
+            Some(DecodedMsg::Heartbeat),
+        ));
+        let late = stats.summarize().unwrap();
+        assert_eq!(late.period_start, 1_000_140);
+    }
+}
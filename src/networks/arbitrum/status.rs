@@ -0,0 +1,78 @@
+use crate::networks::arbitrum::{
+    health::RelayHealth,
+    identity::RelayInfo,
+    metrics::{self, MetricDescriptor, RelayMetricsSnapshot},
+};
+use serde::Serialize;
+use std::{fmt::Write, time::UNIX_EPOCH};
+
+/// The status of a `RelayClient`, as reported by `status --json`.
+///
+/// The field names are part of the crate's public schema and only ever gain new fields.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RelayStatus {
+    pub relay_id: u32,
+    pub url: String,
+    /// The name of the relay, see `RelayInfo`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Where the relay is hosted, see `RelayInfo`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+    pub chain_id: u64,
+    pub health: RelayHealth,
+    pub metrics: RelayMetricsSnapshot,
+}
+
+impl RelayStatus {
+    /// Renders the status in the Prometheus text exposition format, using the metric names of
+    /// `metrics::RELAY_METRICS`.
+    pub fn to_prometheus(statuses: &[RelayStatus]) -> String {
+        let mut out = String::new();
+        for descriptor in metrics::RELAY_METRICS {
+            let name = descriptor.full_name();
+            let _ = writeln!(out, "# HELP {} {}", name, descriptor.help);
+            let _ = writeln!(out, "# TYPE {} {}", name, descriptor.kind.as_str());
+            for status in statuses {
+                if let Some(value) = status.value(descriptor) {
+                    let _ = writeln!(out, "{}{{{}}} {}", name, status.labels(), value);
+                }
+            }
+        }
+        out
+    }
+
+    /// Returns the Prometheus labels identifying the relay.
+    fn labels(&self) -> String {
+        RelayInfo {
+            id: self.relay_id,
+            name: self.name.clone(),
+            url: self.url.clone(),
+            region: self.region.clone(),
+        }
+        .prometheus_labels()
+    }
+
+    /// Returns the value of the metric described by `descriptor`, if known.
+    pub fn value(&self, descriptor: &MetricDescriptor) -> Option<f64> {
+        let value = match descriptor.name {
+            n if n == metrics::FRAMES_RECEIVED.name => self.metrics.frames_received as f64,
+            n if n == metrics::FRAMES_MALFORMED.name => self.metrics.frames_malformed as f64,
+            n if n == metrics::MESSAGES_RECEIVED.name => self.health.messages_received as f64,
+            n if n == metrics::MESSAGES_FORWARDED.name => self.metrics.messages_forwarded as f64,
+            n if n == metrics::DROPPED_OLDEST.name => self.metrics.dropped_oldest as f64,
+            n if n == metrics::DROPPED_NEWEST.name => self.metrics.dropped_newest as f64,
+            n if n == metrics::SEQUENCE_NUMBER.name => self.health.sequence_number? as f64,
+            n if n == metrics::LAST_MESSAGE_TIMESTAMP.name => self
+                .health
+                .last_message_at?
+                .duration_since(UNIX_EPOCH)
+                .ok()?
+                .as_secs_f64(),
+            n if n == metrics::CONNECTED.name => self.health.state.is_connected() as u8 as f64,
+            _ => return None,
+        };
+        Some(value)
+    }
+}
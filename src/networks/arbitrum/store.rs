@@ -0,0 +1,142 @@
+use crate::networks::arbitrum::{
+    archive::Archive, cache::LiveCache, errors::ArchiveError, types::BroadcastFeedMessage,
+};
+use std::{ops::RangeInclusive, sync::Arc};
+
+/// The range of messages selected by a `FeedStore` query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryRange {
+    /// Messages whose sequence number is within the range.
+    Sequence(RangeInclusive<u64>),
+    /// Messages whose L1 header timestamp (in seconds) is within the range.
+    Timestamp(RangeInclusive<u64>),
+}
+
+impl QueryRange {
+    /// Returns `true` if `msg` falls within the range.
+    pub fn contains(&self, msg: &BroadcastFeedMessage) -> bool {
+        match self {
+            QueryRange::Sequence(range) => range.contains(&msg.sequence_number),
+            QueryRange::Timestamp(range) => range.contains(&msg.message.message.header.timestamp),
+        }
+    }
+
+    fn sequence_bounds(&self) -> (u64, u64) {
+        match self {
+            QueryRange::Sequence(range) => (*range.start(), *range.end()),
+            QueryRange::Timestamp(_) => (0, u64::MAX),
+        }
+    }
+}
+
+/// A single query interface over the live cache and the on-disk archive.
+///
+/// Recent messages are served from the live ring buffer; anything older than the oldest cached
+/// message is read from the archive, if one is configured.
+pub struct FeedStore {
+    cache: Arc<LiveCache>,
+    archive: Option<Archive>,
+}
+
+impl FeedStore {
+    pub fn new(cache: Arc<LiveCache>) -> Self {
+        Self {
+            cache,
+            archive: None,
+        }
+    }
+
+    /// Serves messages older than the live cache from `archive`.
+    pub fn with_archive(mut self, archive: Archive) -> Self {
+        self.archive = Some(archive);
+        self
+    }
+
+    pub fn cache(&self) -> &Arc<LiveCache> {
+        &self.cache
+    }
+
+    pub fn archive(&self) -> Option<&Archive> {
+        self.archive.as_ref()
+    }
+
+    /// Returns the messages within `range` accepted by `filter`, ordered by sequence number.
+    ///
+    /// # Arguments
+    ///
+    /// * `range` - The sequence number or timestamp range to query.
+    /// * `filter` - A predicate selecting the messages to return.
+    ///
+    /// # Returns
+    ///
+    /// The matching messages, or an `ArchiveError` if the archive could not be read.
+    pub fn query<F>(
+        &self,
+        range: QueryRange,
+        mut filter: F,
+    ) -> Result<Vec<BroadcastFeedMessage>, ArchiveError>
+    where
+        F: FnMut(&BroadcastFeedMessage) -> bool,
+    {
+        let (from, to) = range.sequence_bounds();
+        let cache_start = self.cache.sequence_range().map(|r| *r.start());
+
+        let mut result = match (&self.archive, cache_start) {
+            (Some(_), Some(0)) | (None, _) => Vec::new(),
+            (Some(archive), cache_start) => {
+                let to = cache_start.map_or(to, |start| to.min(start - 1));
+                if from <= to {
+                    archive.read(from, to, |msg| range.contains(msg) && filter(msg))?
+                } else {
+                    Vec::new()
+                }
+            }
+        };
+
+        result.extend(self.cache.collect(|msg| range.contains(msg) && filter(msg)));
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::networks::arbitrum::{archive::ArchiveWriter, fixtures};
+
+    fn message(sequence_number: u64) -> BroadcastFeedMessage {
+        fixtures::message_with(sequence_number, 1_000 + sequence_number, Vec::new())
+    }
+
+    #[test]
+    fn query_spans_archive_and_cache() {
+        let dir = std::env::temp_dir().join(format!("sfr-store-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let mut writer = ArchiveWriter::new(&dir, 4).unwrap();
+        let cache = Arc::new(LiveCache::new(5));
+        for seq in 0..20 {
+            writer.append(&message(seq)).unwrap();
+            cache.push(message(seq));
+        }
+        writer.flush().unwrap();
+
+        let store = FeedStore::new(cache).with_archive(Archive::open(&dir).unwrap());
+        let seqs = |msgs: Vec<BroadcastFeedMessage>| {
+            msgs.iter().map(|m| m.sequence_number).collect::<Vec<_>>()
+        };
+
+        let msgs = store
+            .query(QueryRange::Sequence(10..=17), |_| true)
+            .unwrap();
+        assert_eq!(seqs(msgs), (10..=17).collect::<Vec<_>>());
+
+        let msgs = store
+            .query(QueryRange::Timestamp(1_002..=1_019), |m| {
+                m.sequence_number % 2 == 0
+            })
+            .unwrap();
+        assert_eq!(seqs(msgs), vec![2, 4, 6, 8, 10, 12, 14, 16, 18]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,262 @@
+use crate::networks::arbitrum::{errors::ConfigError, message::FeedMessage};
+use crossbeam_channel::{Receiver, Sender};
+use log::*;
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+/// A rough size of a message encoded as JSON besides its L2 message: the header, the sequence
+/// number and the signature.
+const MESSAGE_OVERHEAD: usize = 256;
+
+/// The rate a `Throttle` lets messages through at.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Rate {
+    MessagesPerSecond(f64),
+    /// Bytes of the messages encoded as JSON, as most sinks and downstream APIs receive them.
+    /// The size is estimated from the L2 message, base64 encoded in JSON, rather than by encoding
+    /// every message.
+    BytesPerSecond(f64),
+}
+
+impl Rate {
+    fn per_second(&self) -> f64 {
+        match self {
+            Rate::MessagesPerSecond(rate) | Rate::BytesPerSecond(rate) => *rate,
+        }
+    }
+
+    fn cost(&self, msg: &FeedMessage) -> f64 {
+        match self {
+            Rate::MessagesPerSecond(_) => 1.0,
+            Rate::BytesPerSecond(_) => {
+                let l2msg = msg.message.message.message.l2msg.len();
+                base64::encoded_len(l2msg, true)
+                    .map_or(f64::MAX, |len| (len + MESSAGE_OVERHEAD) as f64)
+            }
+        }
+    }
+}
+
+/// What a `Throttle` does with the messages exceeding its rate.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Overflow {
+    /// Hold messages back until the rate allows them, letting the input channel fill up and
+    /// apply its own backpressure upstream.
+    #[default]
+    Delay,
+    /// Discard the messages exceeding the rate.
+    Drop,
+}
+
+/// A token bucket refilled at a constant rate, holding at most `capacity` tokens.
+///
+/// Taking more tokens than the capacity is allowed once the bucket is full, leaving it in debt,
+/// so that messages larger than the burst are delayed rather than blocked forever.
+#[derive(Debug)]
+struct TokenBucket {
+    capacity: f64,
+    rate: f64,
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl TokenBucket {
+    fn new(rate: f64, capacity: f64, now: Instant) -> Self {
+        Self {
+            capacity,
+            rate,
+            tokens: capacity,
+            refilled_at: now,
+        }
+    }
+
+    /// Takes `cost` tokens at `now`, or returns how long to wait until they are available.
+    fn take(&mut self, cost: f64, now: Instant) -> Result<(), Duration> {
+        let elapsed = now.saturating_duration_since(self.refilled_at);
+        self.tokens = (self.tokens + elapsed.as_secs_f64() * self.rate).min(self.capacity);
+        self.refilled_at = now;
+
+        let needed = cost.min(self.capacity);
+        if self.tokens >= needed {
+            self.tokens -= cost;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((needed - self.tokens) / self.rate))
+        }
+    }
+}
+
+/// Limits the rate of a message stream, for consumers feeding rate-limited downstream APIs.
+///
+/// Messages are let through at the configured `Rate`, with bursts of up to one second worth of
+/// messages by default.
+#[derive(Debug)]
+pub struct Throttle {
+    rate: Rate,
+    burst: Option<f64>,
+    overflow: Overflow,
+}
+
+/// The thread of a running `Throttle`.
+pub struct ThrottleHandle {
+    thread: JoinHandle<()>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl Throttle {
+    /// # Arguments
+    ///
+    /// * `rate` - The sustained rate.
+    ///
+    /// # Returns
+    ///
+    /// A `ConfigError::Invalid` if the rate isn't a positive number.
+    pub fn new(rate: Rate) -> Result<Self, ConfigError> {
+        let per_second = rate.per_second();
+        if !(per_second > 0.0 && per_second.is_finite()) {
+            return Err(ConfigError::Invalid(format!(
+                "the throttle rate must be positive, not {}",
+                per_second
+            )));
+        }
+        Ok(Self {
+            rate,
+            burst: None,
+            overflow: Overflow::default(),
+        })
+    }
+
+    /// Sets how many messages, or bytes, may go through at once after a quiet period. Bursts
+    /// that aren't positive are ignored.
+    pub fn with_burst(mut self, burst: f64) -> Self {
+        self.burst = Some(burst);
+        self
+    }
+
+    /// Sets what happens to the messages exceeding the rate.
+    pub fn with_overflow(mut self, overflow: Overflow) -> Self {
+        self.overflow = overflow;
+        self
+    }
+
+    /// Starts forwarding the messages received on `input` to `output` at the configured rate.
+    ///
+    /// The throttle stops, and drops `output`, once either side is disconnected.
+    pub fn spawn(
+        self,
+        input: Receiver<FeedMessage>,
+        output: Sender<FeedMessage>,
+    ) -> ThrottleHandle {
+        let dropped = Arc::new(AtomicU64::new(0));
+        let counter = dropped.clone();
+        let rate = self.rate.per_second();
+        let burst = self.burst.filter(|burst| *burst > 0.0).unwrap_or(rate);
+        let mut bucket = TokenBucket::new(rate, burst, Instant::now());
+
+        let thread = thread::spawn(move || {
+            for msg in input {
+                let cost = self.rate.cost(&msg);
+                let admitted = loop {
+                    match bucket.take(cost, Instant::now()) {
+                        Ok(()) => break true,
+                        Err(wait) if self.overflow == Overflow::Delay => thread::sleep(wait),
+                        Err(_) => break false,
+                    }
+                };
+                if !admitted {
+                    debug!("Throttling message {}", msg.sequence_number());
+                    counter.fetch_add(1, Ordering::Relaxed);
+                    continue;
+                }
+                if output.send(msg).is_err() {
+                    return;
+                }
+            }
+        });
+
+        ThrottleHandle { thread, dropped }
+    }
+}
+
+impl ThrottleHandle {
+    /// Returns how many messages were discarded under `Overflow::Drop`.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Waits for the throttle to forward every pending message and stop.
+    pub fn join(self) {
+        let _ = self.thread.join();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::networks::arbitrum::fixtures::feed_message;
+    use crossbeam_channel::unbounded;
+
+    fn throttle(throttle: Throttle, messages: u64) -> (Vec<u64>, u64) {
+        let (input, received) = unbounded();
+        let (output, forwarded) = unbounded();
+        let handle = throttle.spawn(received, output);
+        for seq in 0..messages {
+            input
+                .send(feed_message(seq, 0, vec![0; 1024], None))
+                .unwrap();
+        }
+        drop(input);
+        let dropped = handle.dropped.clone();
+        handle.join();
+        let forwarded = forwarded.iter().map(|msg| msg.sequence_number()).collect();
+        (forwarded, dropped.load(Ordering::Relaxed))
+    }
+
+    #[test]
+    fn delays_or_drops_the_messages_exceeding_the_rate() {
+        let started = Instant::now();
+        let delay = Throttle::new(Rate::MessagesPerSecond(50.0))
+            .unwrap()
+            .with_burst(1.0);
+        assert_eq!(throttle(delay, 3), (vec![0, 1, 2], 0));
+        assert!(started.elapsed() >= Duration::from_millis(40));
+
+        // About 1.4 KB per message, so only the first one fits in the burst.
+        let drop = Throttle::new(Rate::BytesPerSecond(2_000.0))
+            .unwrap()
+            .with_overflow(Overflow::Drop);
+        assert_eq!(throttle(drop, 3), (vec![0], 2));
+
+        assert!(Throttle::new(Rate::MessagesPerSecond(0.0)).is_err());
+        assert!(Throttle::new(Rate::BytesPerSecond(f64::NAN)).is_err());
+    }
+
+    #[test]
+    fn token_bucket_refills_at_rate() {
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+        let mut bucket = TokenBucket::new(10.0, 2.0, start);
+
+        assert_eq!(bucket.take(1.0, at(0)), Ok(()));
+        assert_eq!(bucket.take(1.0, at(0)), Ok(()));
+        assert_eq!(bucket.take(1.0, at(0)), Err(Duration::from_millis(100)));
+        assert_eq!(bucket.take(1.0, at(100)), Ok(()));
+
+        // Refills never exceed the burst capacity.
+        assert_eq!(bucket.take(2.0, at(10_000)), Ok(()));
+        assert!(bucket.take(1.0, at(10_000)).is_err());
+
+        // A message larger than the burst goes through once the bucket is full, in debt.
+        assert_eq!(bucket.take(5.0, at(10_200)), Ok(()));
+        assert_eq!(
+            bucket.take(1.0, at(10_200)),
+            Err(Duration::from_millis(400))
+        );
+    }
+}
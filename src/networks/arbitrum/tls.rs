@@ -0,0 +1,232 @@
+use crate::networks::arbitrum::errors::RelayError;
+use rustls::{Certificate, ClientConfig, PrivateKey, RootCertStore, ServerName};
+use std::{io, sync::Arc};
+use tokio::net::TcpStream;
+use tokio_rustls::{client::TlsStream, TlsConnector};
+
+/// The TLS setup used to connect to a relay, replacing the default one (WebPKI roots, no client
+/// certificate), e.g. for private relays behind mTLS.
+#[derive(Clone)]
+pub struct TlsOptions {
+    config: Arc<ClientConfig>,
+    /// The name sent as SNI and verified against the relay certificate, instead of the URL host.
+    server_name: Option<String>,
+}
+
+impl TlsOptions {
+    /// Uses a fully custom rustls configuration.
+    pub fn new(config: Arc<ClientConfig>) -> Self {
+        Self {
+            config,
+            server_name: None,
+        }
+    }
+
+    /// Trusts the certificate authorities in `roots` only, without a client certificate.
+    pub fn with_roots(roots: RootCertStore) -> Self {
+        let config = ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        Self::new(Arc::new(config))
+    }
+
+    /// Trusts the certificate authorities in `roots` and authenticates with a client
+    /// certificate.
+    ///
+    /// # Arguments
+    ///
+    /// * `roots` - The trusted certificate authorities.
+    /// * `cert_chain` - The client certificate, followed by its intermediates.
+    /// * `key` - The private key of the client certificate.
+    pub fn with_client_auth(
+        roots: RootCertStore,
+        cert_chain: Vec<Certificate>,
+        key: PrivateKey,
+    ) -> Result<Self, RelayError> {
+        let config = ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(roots)
+            .with_client_auth_cert(cert_chain, key)?;
+        Ok(Self::new(Arc::new(config)))
+    }
+
+    /// Sends `server_name` as SNI, and expects the relay certificate to be valid for it, instead
+    /// of the host of the relay URL.
+    pub fn with_server_name(mut self, server_name: impl Into<String>) -> Self {
+        self.server_name = Some(server_name.into());
+        self
+    }
+
+    /// Performs the TLS handshake with the relay `host` over `stream`.
+    pub(crate) async fn handshake(
+        &self,
+        host: &str,
+        stream: TcpStream,
+    ) -> Result<TlsStream<TcpStream>, RelayError> {
+        let name = self.server_name.as_deref().unwrap_or(host);
+        let server_name = ServerName::try_from(name).map_err(|_| RelayError::InvalidUrl)?;
+        let connector = TlsConnector::from(self.config.clone());
+        Ok(connector.connect(server_name, stream).await?)
+    }
+}
+
+impl std::fmt::Debug for TlsOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TlsOptions")
+            .field("server_name", &self.server_name)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Parses the PEM encoded certificates in `pem`, e.g. a CA bundle or a client certificate chain.
+pub fn certificates_from_pem(mut pem: &[u8]) -> io::Result<Vec<Certificate>> {
+    Ok(rustls_pemfile::certs(&mut pem)?
+        .into_iter()
+        .map(Certificate)
+        .collect())
+}
+
+/// Parses the first PKCS#8, PKCS#1 or SEC1 private key in `pem`.
+pub fn private_key_from_pem(mut pem: &[u8]) -> io::Result<PrivateKey> {
+    loop {
+        match rustls_pemfile::read_one(&mut pem)? {
+            Some(rustls_pemfile::Item::PKCS8Key(key))
+            | Some(rustls_pemfile::Item::RSAKey(key))
+            | Some(rustls_pemfile::Item::ECKey(key)) => return Ok(PrivateKey(key)),
+            Some(_) => continue,
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "no private key found",
+                ))
+            }
+        }
+    }
+}
+
+/// Builds a root store from the PEM encoded certificate authorities in `pem`.
+pub fn root_store_from_pem(pem: &[u8]) -> Result<RootCertStore, RelayError> {
+    let mut roots = RootCertStore::empty();
+    for cert in certificates_from_pem(pem)? {
+        roots
+            .add(&cert)
+            .map_err(|e| RelayError::Tls(rustls::Error::General(e.to_string())))?;
+    }
+    Ok(roots)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::networks::arbitrum::{
+        connect::ConnectOptions,
+        feed_client::RelayClient,
+        mock::{MockRelay, Scenario, SimulatedSequencer, Step},
+    };
+    use crossbeam_channel::unbounded;
+    use rustls::{server::AllowAnyAuthenticatedClient, ServerConfig};
+    use std::net::SocketAddr;
+    use tokio::net::TcpListener;
+    use tokio_rustls::TlsAcceptor;
+    use url::Url;
+
+    const CA: &[u8] = include_bytes!("testdata/tls/ca.pem");
+    const RELAY_CERT: &[u8] = include_bytes!("testdata/tls/relay.pem");
+    const RELAY_KEY: &[u8] = include_bytes!("testdata/tls/relay.key");
+    const CLIENT_CERT: &[u8] = include_bytes!("testdata/tls/client.pem");
+    const CLIENT_KEY: &[u8] = include_bytes!("testdata/tls/client.key");
+
+    /// Terminates TLS for `relay.test` in front of `relay`, requiring a client certificate issued
+    /// by the test CA.
+    async fn mtls_proxy(relay: SocketAddr) -> SocketAddr {
+        let verifier = AllowAnyAuthenticatedClient::new(root_store_from_pem(CA).unwrap());
+        let config = ServerConfig::builder()
+            .with_safe_defaults()
+            .with_client_cert_verifier(verifier.boxed())
+            .with_single_cert(
+                certificates_from_pem(RELAY_CERT).unwrap(),
+                private_key_from_pem(RELAY_KEY).unwrap(),
+            )
+            .unwrap();
+        let acceptor = TlsAcceptor::from(Arc::new(config));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let acceptor = acceptor.clone();
+                tokio::spawn(async move {
+                    let Ok(mut stream) = acceptor.accept(stream).await else {
+                        return;
+                    };
+                    let mut upstream = TcpStream::connect(relay).await.unwrap();
+                    let _ = tokio::io::copy_bidirectional(&mut stream, &mut upstream).await;
+                });
+            }
+        });
+        addr
+    }
+
+    async fn connect(url: &Url, tls: TlsOptions) -> Result<RelayClient, RelayError> {
+        let (sender, _) = unbounded();
+        let (updates, _) = unbounded();
+        let options = ConnectOptions::new().with_tls(tls);
+        RelayClient::connect(url.clone(), 42161, 0, options, sender, updates).await
+    }
+
+    #[tokio::test]
+    async fn connects_to_relays_behind_mtls() {
+        let scenario = Scenario::new()
+            .then(Step::Blocks {
+                count: 2,
+                interval_ms: 1,
+            })
+            .then(Step::Disconnect);
+        let events = SimulatedSequencer::new(0, 1_700_000_000, 1).generate(&scenario);
+        let relay = MockRelay::bind("127.0.0.1:0", 42161, events).await.unwrap();
+        let proxy = mtls_proxy(relay.local_addr().unwrap()).await;
+        relay.spawn();
+        let url = Url::parse(&format!("wss://{}", proxy)).unwrap();
+
+        let roots = || root_store_from_pem(CA).unwrap();
+        let client_auth = || {
+            TlsOptions::with_client_auth(
+                roots(),
+                certificates_from_pem(CLIENT_CERT).unwrap(),
+                private_key_from_pem(CLIENT_KEY).unwrap(),
+            )
+            .unwrap()
+        };
+        // The relay certificate is valid for `relay.test`, not for the address connected to.
+        assert!(connect(&url, client_auth()).await.is_err());
+        // The relay requires a client certificate.
+        assert!(connect(
+            &url,
+            TlsOptions::with_roots(roots()).with_server_name("relay.test")
+        )
+        .await
+        .is_err());
+
+        let (sender, received) = unbounded();
+        let (updates, _) = unbounded();
+        let options = ConnectOptions::new().with_tls(client_auth().with_server_name("relay.test"));
+        let client = RelayClient::connect(url, 42161, 0, options, sender, updates)
+            .await
+            .unwrap();
+        client.run().await.unwrap();
+        let sequence_numbers: Vec<u64> = received
+            .try_iter()
+            .flat_map(|root| root.messages)
+            .map(|msg| msg.sequence_number)
+            .collect();
+        assert_eq!(sequence_numbers, [0, 1]);
+    }
+
+    #[test]
+    fn parses_pem_certificates_and_keys() {
+        assert_eq!(certificates_from_pem(CA).unwrap().len(), 1);
+        assert!(private_key_from_pem(CLIENT_KEY).is_ok());
+        assert!(private_key_from_pem(CLIENT_CERT).is_err());
+        assert!(root_store_from_pem(b"").unwrap().is_empty());
+    }
+}
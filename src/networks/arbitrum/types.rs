@@ -1,14 +1,25 @@
+pub mod borrowed;
+pub mod versioned;
+
+use crate::networks::arbitrum::provenance::Provenance;
+use ethers::types::Bytes;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::hash::{Hash, Hasher};
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct Root {
     pub version: u8,
     pub messages: Vec<BroadcastFeedMessage>,
+    /// Where the frame was read from. Not part of the wire format.
+    #[serde(skip)]
+    pub provenance: Provenance,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct BroadcastFeedMessage {
     pub sequence_number: u64,
@@ -16,7 +27,22 @@ pub struct BroadcastFeedMessage {
     pub signature: Value,
 }
 
+impl BroadcastFeedMessage {
+    /// Feeds the content of the message to `state`: its header, the number of delayed messages
+    /// read and its raw L2 message. The signature is left out.
+    pub fn hash_content<H: Hasher>(&self, state: &mut H) {
+        let message = &self.message.message;
+        message.header.kind.hash(state);
+        message.header.sender.hash(state);
+        message.header.block_number.hash(state);
+        message.header.timestamp.hash(state);
+        self.message.delayed_messages_read.hash(state);
+        message.l2msg.as_ref().hash(state);
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct MessageWithMetadata {
     pub message: L1IncomingMessageHeader,
@@ -24,14 +50,18 @@ pub struct MessageWithMetadata {
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct L1IncomingMessageHeader {
     pub header: Header,
-    #[serde(rename = "l2Msg")]
-    pub l2msg: String,
+    /// The L2 message, base64 decoded while deserializing.
+    #[serde(rename = "l2Msg", with = "base64_bytes")]
+    #[cfg_attr(feature = "schema", schemars(with = "String"))]
+    pub l2msg: Bytes,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct Header {
     pub kind: u8,
@@ -41,3 +71,36 @@ pub struct Header {
     pub request_id: Value,
     pub base_fee_l1: Value,
 }
+
+/// (De)serializes `Bytes` as a standard base64 string, the encoding used by the feed.
+mod base64_bytes {
+    use crate::networks::arbitrum::arena;
+    use base64::{engine::general_purpose, Engine as _};
+    use ethers::types::Bytes;
+    use serde::{de, Deserializer, Serializer};
+    use std::fmt;
+
+    pub fn serialize<S: Serializer>(bytes: &Bytes, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&general_purpose::STANDARD.encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Bytes, D::Error> {
+        deserializer.deserialize_str(Base64Visitor)
+    }
+
+    struct Base64Visitor;
+
+    impl de::Visitor<'_> for Base64Visitor {
+        type Value = Bytes;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a base64 encoded string")
+        }
+
+        fn visit_str<E: de::Error>(self, v: &str) -> Result<Bytes, E> {
+            arena::decode(v.as_bytes())
+                .map(Bytes::from)
+                .map_err(E::custom)
+        }
+    }
+}
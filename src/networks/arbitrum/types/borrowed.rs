@@ -0,0 +1,168 @@
+//! Borrowed counterparts of the feed message types.
+//!
+//! Deserializing into these types borrows strings (most importantly the base64 encoded `l2Msg`)
+//! from the input buffer instead of allocating them, which matters on high-throughput feeds.
+//! Strings containing JSON escapes can't be borrowed and fall back to an owned `Cow`. Unlike the
+//! owned types, the `l2Msg` is only base64 decoded when the message is decoded or converted.
+
+use super::{BroadcastFeedMessage, Header, L1IncomingMessageHeader, MessageWithMetadata, Root};
+use crate::networks::arbitrum::{
+    arena,
+    decoder::{decode_l2msg, DecodeOptions, DecodedMsg},
+    errors::DecodeError,
+};
+use serde::Deserialize;
+use serde_json::Value;
+use std::borrow::Cow;
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RootRef<'a> {
+    pub version: u8,
+    #[serde(borrow)]
+    pub messages: Vec<BroadcastFeedMessageRef<'a>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BroadcastFeedMessageRef<'a> {
+    pub sequence_number: u64,
+    #[serde(borrow)]
+    pub message: MessageWithMetadataRef<'a>,
+    pub signature: Value,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageWithMetadataRef<'a> {
+    #[serde(borrow)]
+    pub message: L1IncomingMessageHeaderRef<'a>,
+    pub delayed_messages_read: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct L1IncomingMessageHeaderRef<'a> {
+    #[serde(borrow)]
+    pub header: HeaderRef<'a>,
+    #[serde(rename = "l2Msg", borrow)]
+    pub l2msg: Cow<'a, str>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HeaderRef<'a> {
+    pub kind: u8,
+    #[serde(borrow)]
+    pub sender: Cow<'a, str>,
+    pub block_number: u64,
+    pub timestamp: u64,
+    pub request_id: Value,
+    pub base_fee_l1: Value,
+}
+
+impl<'a> RootRef<'a> {
+    /// Deserializes a frame, borrowing from `data`.
+    pub fn from_slice(data: &'a [u8]) -> Result<Self, serde_json::Error> {
+        serde_json::from_slice(data)
+    }
+
+    /// Deserializes a frame with the SIMD accelerated parser, borrowing from `data`.
+    ///
+    /// The parser works in place, so `data` is left in an unspecified state.
+    #[cfg(feature = "simd-json")]
+    pub fn from_slice_simd(data: &'a mut [u8]) -> Result<Self, simd_json::Error> {
+        simd_json::serde::from_slice(data)
+    }
+
+    /// Converts the frame into its owned representation.
+    ///
+    /// Fails if an `l2Msg` is not valid base64.
+    pub fn into_owned(self) -> Result<Root, DecodeError> {
+        Ok(Root {
+            version: self.version,
+            messages: self
+                .messages
+                .into_iter()
+                .map(BroadcastFeedMessageRef::into_owned)
+                .collect::<Result<_, _>>()?,
+            provenance: Default::default(),
+        })
+    }
+}
+
+impl BroadcastFeedMessageRef<'_> {
+    pub fn into_owned(self) -> Result<BroadcastFeedMessage, DecodeError> {
+        Ok(BroadcastFeedMessage {
+            sequence_number: self.sequence_number,
+            message: MessageWithMetadata {
+                message: self.message.message.into_owned()?,
+                delayed_messages_read: self.message.delayed_messages_read,
+            },
+            signature: self.signature,
+        })
+    }
+}
+
+impl L1IncomingMessageHeaderRef<'_> {
+    /// Decodes the L2 message without first converting the message into its owned form.
+    pub fn try_decode(&self) -> Result<Option<DecodedMsg>, DecodeError> {
+        self.try_decode_with(DecodeOptions::default())
+    }
+
+    /// Like `try_decode`, skipping the work disabled in `options` and enforcing its size limit.
+    pub fn try_decode_with(
+        &self,
+        options: DecodeOptions,
+    ) -> Result<Option<DecodedMsg>, DecodeError> {
+        decode_l2msg(self.header.kind, &self.header.sender, &self.l2msg, options)
+    }
+
+    pub fn into_owned(self) -> Result<L1IncomingMessageHeader, DecodeError> {
+        Ok(L1IncomingMessageHeader {
+            header: Header {
+                kind: self.header.kind,
+                sender: self.header.sender.into_owned(),
+                block_number: self.header.block_number,
+                timestamp: self.header.timestamp,
+                request_id: self.header.request_id,
+                base_fee_l1: self.header.base_fee_l1,
+            },
+            l2msg: arena::decode(self.l2msg.as_bytes())?.into(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAME: &str = r#"{"version":1,"messages":[{"sequenceNumber":42,"message":{"message":{"header":{"kind":3,"sender":"0xa4b000000000000000000073657175656e636572","blockNumber":18000000,"timestamp":1690000000,"requestId":null,"baseFeeL1":null},"l2Msg":"BAE="},"delayedMessagesRead":1234},"signature":null}]}"#;
+
+    #[test]
+    fn borrows_strings_and_matches_owned() {
+        let root = RootRef::from_slice(FRAME.as_bytes()).unwrap();
+        let l2msg = &root.messages[0].message.message.l2msg;
+        assert!(matches!(l2msg, Cow::Borrowed("BAE=")));
+
+        let options = DecodeOptions {
+            max_l2_message_size: 1,
+            ..Default::default()
+        };
+        assert!(matches!(
+            root.messages[0].message.message.try_decode_with(options),
+            Err(DecodeError::MessageTooLarge { size: 2, max: 1 })
+        ));
+        // Oversized messages are rejected before being decoded, even if not valid base64.
+        let mut oversized = root.messages[0].message.message.clone();
+        oversized.l2msg = Cow::Borrowed("!!!!!!!!");
+        assert!(matches!(
+            oversized.try_decode_with(options),
+            Err(DecodeError::MessageTooLarge { size: 6, max: 1 })
+        ));
+
+        let owned: Root = serde_json::from_str(FRAME).unwrap();
+        assert_eq!(root.into_owned().unwrap(), owned);
+        assert_eq!(owned.messages[0].message.message.l2msg.as_ref(), &[4, 1]);
+    }
+}
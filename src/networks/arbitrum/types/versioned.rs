@@ -0,0 +1,159 @@
+//! Parsing of the feed across broadcast format versions.
+//!
+//! Each frame announces its format in `version`. Every supported version keeps its own schema, so
+//! that a relay upgrading its format doesn't break parsing: frames are parsed with the schema of
+//! their version and converted into the owned `Root` used by the rest of the crate. Relays have
+//! only used version 1 so far.
+
+use super::{borrowed::RootRef, Root};
+use crate::networks::arbitrum::errors::FrameError;
+use serde::Deserialize;
+
+#[cfg(feature = "simd-json")]
+thread_local! {
+    /// The copy of the frame the SIMD parser works on, as it parses in place.
+    static SIMD_SCRATCH: std::cell::RefCell<Vec<u8>> = const { std::cell::RefCell::new(Vec::new()) };
+}
+
+/// The broadcast format versions the reader can parse.
+pub const SUPPORTED_VERSIONS: [u8; 1] = [1];
+
+/// A frame parsed with the schema of its broadcast version.
+#[derive(Debug, Clone, PartialEq)]
+pub enum VersionedRoot {
+    /// The original format, parsed into the crate's own `Root`.
+    V1(Root),
+}
+
+/// Only the version of a frame, read to tell why a frame failed to parse.
+#[derive(Deserialize)]
+struct VersionOnly {
+    version: u8,
+}
+
+impl VersionedRoot {
+    /// Parses a frame with the schema of the version it announces.
+    ///
+    /// Frames are parsed straight into the schema of the latest version, and only read again to
+    /// find their version if that fails, so that supported frames are parsed in one pass.
+    ///
+    /// # Returns
+    ///
+    /// A `FrameError::UnsupportedVersion` if the frame uses a version newer than the reader
+    /// knows, or `FrameError::Json` if it doesn't match the schema of its version.
+    pub fn parse(data: &[u8]) -> Result<Self, FrameError> {
+        match serde_json::from_slice::<Root>(data) {
+            Ok(root) => Self::from_root(root),
+            Err(error) => Err(Self::parse_error(data, error)),
+        }
+    }
+
+    /// Like `parse`, through the borrowed `RootRef`, with the SIMD accelerated parser when the
+    /// `simd-json` feature is enabled. This is how relay clients parse frames.
+    ///
+    /// Malformed frames are parsed again with `parse`, to report why.
+    pub fn parse_borrowed(data: &[u8]) -> Result<Self, FrameError> {
+        #[cfg(feature = "simd-json")]
+        let root = SIMD_SCRATCH.with(|scratch| {
+            let mut scratch = scratch.borrow_mut();
+            scratch.clear();
+            scratch.extend_from_slice(data);
+            RootRef::from_slice_simd(&mut scratch)
+                .ok()
+                .map(RootRef::into_owned)
+        });
+        #[cfg(not(feature = "simd-json"))]
+        let root = RootRef::from_slice(data).ok().map(RootRef::into_owned);
+
+        match root {
+            Some(Ok(root)) => Self::from_root(root),
+            _ => Self::parse(data),
+        }
+    }
+
+    /// Wraps a frame parsed with the schema of version 1, checking it announces a supported
+    /// version.
+    pub fn from_root(root: Root) -> Result<Self, FrameError> {
+        match root.version {
+            1 => Ok(VersionedRoot::V1(root)),
+            version => Err(FrameError::UnsupportedVersion(version)),
+        }
+    }
+
+    /// Tells why `data` failed to parse with `error`: a version the reader doesn't know, or a
+    /// frame not matching the schema of its version.
+    pub(crate) fn parse_error(data: &[u8], error: serde_json::Error) -> FrameError {
+        match serde_json::from_slice::<VersionOnly>(data) {
+            Ok(VersionOnly { version }) if !SUPPORTED_VERSIONS.contains(&version) => {
+                FrameError::UnsupportedVersion(version)
+            }
+            _ => FrameError::Json(error),
+        }
+    }
+
+    pub fn version(&self) -> u8 {
+        match self {
+            VersionedRoot::V1(root) => root.version,
+        }
+    }
+
+    /// Converts the frame into a `Root`, dropping the fields that only exist in newer versions.
+    pub fn into_root(self) -> Root {
+        match self {
+            VersionedRoot::V1(root) => root,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::networks::arbitrum::bench;
+
+    const MESSAGE: &str = r#"{"sequenceNumber":42,"message":{"message":{"header":{"kind":3,"sender":"0xa4b000000000000000000073657175656e636572","blockNumber":18000000,"timestamp":1690000000,"requestId":null,"baseFeeL1":null},"l2Msg":"BAE="},"delayedMessagesRead":1234},"signature":null}"#;
+
+    #[test]
+    fn parses_each_version_with_its_schema() {
+        let v1 = format!(r#"{{"version":1,"messages":[{}]}}"#, MESSAGE);
+        let v1 = VersionedRoot::parse(v1.as_bytes()).unwrap();
+        assert_eq!(v1.version(), 1);
+        assert_eq!(v1.into_root().messages[0].sequence_number, 42);
+
+        // Newer versions are reported whether or not they match the schema of version 1.
+        let v2 = format!(r#"{{"version":2,"messages":[{}]}}"#, MESSAGE);
+        assert!(matches!(
+            VersionedRoot::parse(v2.as_bytes()),
+            Err(FrameError::UnsupportedVersion(2))
+        ));
+        assert!(matches!(
+            VersionedRoot::parse(br#"{"version":3,"blocks":[]}"#),
+            Err(FrameError::UnsupportedVersion(3))
+        ));
+
+        let error = VersionedRoot::parse(br#"{"version":1,"messages":[{}]}"#).unwrap_err();
+        assert!(error.to_string().contains("sequenceNumber"), "{}", error);
+        assert!(matches!(
+            VersionedRoot::parse(b"[]"),
+            Err(FrameError::Json(_))
+        ));
+    }
+
+    #[test]
+    fn parses_borrowed_frames_like_owned_ones() {
+        let frame = bench::frame(4);
+        assert_eq!(
+            VersionedRoot::parse_borrowed(&frame).unwrap(),
+            VersionedRoot::parse(&frame).unwrap()
+        );
+        assert!(matches!(
+            VersionedRoot::parse_borrowed(br#"{"version":3,"blocks":[]}"#),
+            Err(FrameError::UnsupportedVersion(3))
+        ));
+        let invalid_base64 =
+            format!(r#"{{"version":1,"messages":[{}]}}"#, MESSAGE).replace("BAE=", "B!E=");
+        assert!(matches!(
+            VersionedRoot::parse_borrowed(invalid_base64.as_bytes()),
+            Err(FrameError::Json(_))
+        ));
+    }
+}
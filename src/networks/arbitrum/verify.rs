@@ -0,0 +1,196 @@
+//! Cross-verification of the feed against the blocks the chain eventually reports.
+
+use crate::networks::arbitrum::{
+    blocks::PendingBlock, errors::VerificationError, events::FeedEvent,
+};
+use crossbeam_channel::{Receiver, Sender};
+use ethers::providers::Middleware;
+use log::*;
+use std::{
+    collections::HashSet,
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+use tokio::{sync::mpsc, task::JoinHandle};
+
+/// How long after a block is assembled from the feed it is checked by default.
+const DEFAULT_DELAY: Duration = Duration::from_secs(10);
+/// How many blocks may wait for their check before new ones are skipped.
+const QUEUE_DEPTH: usize = 4096;
+
+/// Checks the blocks assembled from the feed against the blocks reported by an L2 RPC endpoint.
+///
+/// The feed is a promise of what the sequencer will produce: a block whose transactions or
+/// timestamp differ on chain is reported as a `FeedEvent::ChainDivergence`, which usually means
+/// the sequencer reorged the feed.
+#[derive(Debug, Clone)]
+pub struct ChainVerifier<M> {
+    provider: Arc<M>,
+    delay: Duration,
+    sample_every: u64,
+}
+
+impl<M: Middleware + 'static> ChainVerifier<M> {
+    /// # Arguments
+    ///
+    /// * `provider` - A provider connected to the L2 chain of the feed.
+    pub fn new(provider: Arc<M>) -> Self {
+        Self {
+            provider,
+            delay: DEFAULT_DELAY,
+            sample_every: 1,
+        }
+    }
+
+    /// Sets how long after a block is assembled it is checked, leaving the chain and the RPC
+    /// endpoint time to catch up with the feed.
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Only checks one block out of `blocks`, to limit the load on the RPC endpoint.
+    pub fn with_sample_every(mut self, blocks: u64) -> Self {
+        self.sample_every = blocks.max(1);
+        self
+    }
+
+    /// Checks `block` against the block of the same number on chain.
+    ///
+    /// Internal ArbOS transactions and the transactions of delayed messages are only found on
+    /// chain: the chain must include every transaction of `block`, not only them.
+    ///
+    /// # Returns
+    ///
+    /// A `FeedEvent::ChainDivergence` if the chain reports the block differently, `None` if it
+    /// matches, or a `VerificationError` if the chain doesn't have the block yet or the provider
+    /// failed.
+    pub async fn verify(
+        &self,
+        block: &PendingBlock,
+    ) -> Result<Option<FeedEvent>, VerificationError> {
+        let on_chain = self
+            .provider
+            .get_block(block.number)
+            .await
+            .map_err(|e| VerificationError::Provider(e.to_string()))?
+            .ok_or(VerificationError::BlockNotFound(block.number))?;
+
+        let hashes: HashSet<_> = on_chain.transactions.iter().collect();
+        let missing_txs = block
+            .txs
+            .iter()
+            .filter(|tx| !hashes.contains(&tx.hash))
+            .count();
+        let timestamp_differs = on_chain.timestamp.low_u64() != block.timestamp;
+        if missing_txs == 0 && !timestamp_differs {
+            return Ok(None);
+        }
+        Ok(Some(FeedEvent::ChainDivergence {
+            sequence_number: block.sequence_number,
+            block_number: block.number,
+            missing_txs,
+            timestamp_differs,
+        }))
+    }
+
+    /// Checks the blocks received on `input`, each once the configured delay has elapsed. Must be
+    /// called within a Tokio runtime.
+    ///
+    /// Blocks arriving while too many wait for their check are skipped, so that verification
+    /// never holds the feed back.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - The receiver channel of `PendingBlock`s, e.g. fed by a `BlockAssembler`.
+    /// * `events` - The sender channel for sending `FeedEvent::ChainDivergence` events.
+    pub fn spawn(self, input: Receiver<PendingBlock>, events: Sender<FeedEvent>) -> JoinHandle<()> {
+        let (queue, mut blocks) = mpsc::channel(QUEUE_DEPTH);
+        let sample_every = self.sample_every;
+        thread::spawn(move || {
+            for block in input {
+                if block.number % sample_every != 0 {
+                    continue;
+                }
+                match queue.try_send((Instant::now(), block)) {
+                    Ok(()) => (),
+                    Err(mpsc::error::TrySendError::Full((_, block))) => {
+                        debug!("Skipped the verification of block {}", block.number)
+                    }
+                    Err(mpsc::error::TrySendError::Closed(_)) => return,
+                }
+            }
+        });
+
+        tokio::spawn(async move {
+            while let Some((received_at, block)) = blocks.recv().await {
+                tokio::time::sleep_until((received_at + self.delay).into()).await;
+                match self.verify(&block).await {
+                    Ok(None) => (),
+                    Ok(Some(event)) => {
+                        warn!("The chain diverged from the feed: {:?}", event);
+                        if events.send(event).is_err() {
+                            return;
+                        }
+                    }
+                    Err(e) => warn!("Failed to verify block {}: {}", block.number, e),
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::{
+        providers::Provider,
+        types::{Block, Transaction, H256},
+    };
+
+    #[tokio::test]
+    async fn reports_blocks_differing_on_chain() {
+        let (provider, mock) = Provider::mocked();
+        let verifier = ChainVerifier::new(Arc::new(provider));
+        let tx = |byte| Transaction {
+            hash: H256::repeat_byte(byte),
+            ..Default::default()
+        };
+        let pending = PendingBlock {
+            number: 100,
+            timestamp: 1_700_000_000,
+            l1_block_number: 18_000_000,
+            sequence_number: 90,
+            txs: vec![tx(1), tx(2)],
+            missing_before: 0,
+        };
+        // The chain also holds the internal ArbOS transaction of the block.
+        let on_chain = |txs: Vec<u8>| Block::<H256> {
+            number: Some(100.into()),
+            timestamp: 1_700_000_000.into(),
+            transactions: txs.into_iter().map(H256::repeat_byte).collect(),
+            ..Default::default()
+        };
+
+        mock.push(on_chain(vec![0, 1, 2])).unwrap();
+        assert_eq!(verifier.verify(&pending).await.unwrap(), None);
+
+        mock.push(on_chain(vec![0, 2, 3])).unwrap();
+        assert_eq!(
+            verifier.verify(&pending).await.unwrap(),
+            Some(FeedEvent::ChainDivergence {
+                sequence_number: 90,
+                block_number: 100,
+                missing_txs: 1,
+                timestamp_differs: false,
+            })
+        );
+
+        mock.push(serde_json::Value::Null).unwrap();
+        assert!(matches!(
+            verifier.verify(&pending).await,
+            Err(VerificationError::BlockNotFound(100))
+        ));
+    }
+}
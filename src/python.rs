@@ -0,0 +1,148 @@
+//! Python bindings, built as the `sequencer_feed_reader` extension module by the `ffi` crate with
+//! `maturin build -m ffi/Cargo.toml --features python`.
+//!
+//! ```python
+//! from sequencer_feed_reader import FeedReader
+//!
+//! for tx in FeedReader(network="one"):
+//!     print(tx["sequenceNumber"], tx["hash"], tx["to"])
+//! ```
+
+use crate::{
+    networks::arbitrum::{
+        connect::ConnectOptions, errors::RelayError, message::FeedTransaction,
+        network::ArbitrumNetwork,
+    },
+    sync::RelayClientSync,
+};
+use pyo3::{
+    exceptions::{PyConnectionError, PyValueError},
+    prelude::*,
+    types::{PyDict, PyList},
+};
+use serde_json::Value;
+use std::collections::VecDeque;
+use url::Url;
+
+impl From<RelayError> for PyErr {
+    fn from(e: RelayError) -> Self {
+        PyConnectionError::new_err(format!("[{}] {}", e.code(), e))
+    }
+}
+
+/// Reads a relay and yields its decoded transactions as dicts: the fields of the transaction as
+/// returned by JSON-RPC, plus `sequenceNumber`, `indexInMessage`, `l1BlockNumber`, `timestamp`
+/// and `receivedAtMs`.
+#[pyclass(name = "FeedReader")]
+pub struct PyFeedReader {
+    client: Option<RelayClientSync>,
+    pending: VecDeque<FeedTransaction>,
+}
+
+#[pymethods]
+impl PyFeedReader {
+    /// Connects to the relay at `url`, or to the public relay of `network` ("one" or "nova").
+    #[new]
+    #[pyo3(signature = (url=None, chain_id=None, network=None, sequence_number=0))]
+    fn new(
+        py: Python<'_>,
+        url: Option<&str>,
+        chain_id: Option<u64>,
+        network: Option<&str>,
+        sequence_number: u64,
+    ) -> PyResult<Self> {
+        let network = network
+            .map(str::parse::<ArbitrumNetwork>)
+            .transpose()
+            .map_err(PyValueError::new_err)?;
+        let url = match (url, network) {
+            (Some(url), _) => Url::parse(url).map_err(|e| PyValueError::new_err(e.to_string()))?,
+            (None, Some(network)) => network.feed_url(),
+            (None, None) => return Err(PyValueError::new_err("either url or network is required")),
+        };
+        let chain_id = chain_id
+            .or(network.map(|network| network.chain_id()))
+            .ok_or_else(|| PyValueError::new_err("either chain_id or network is required"))?;
+
+        let options = ConnectOptions::new().with_sequence_number(sequence_number);
+        let client = py.allow_threads(|| RelayClientSync::connect(url, chain_id, options))?;
+        Ok(Self {
+            client: Some(client),
+            pending: VecDeque::new(),
+        })
+    }
+
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    /// Blocks until the next transaction, releasing the GIL while waiting.
+    fn __next__(&mut self, py: Python<'_>) -> PyResult<Option<PyObject>> {
+        loop {
+            if let Some(tx) = self.pending.pop_front() {
+                return to_python(py, &transaction_json(&tx)).map(Some);
+            }
+            let Some(client) = self.client.as_mut() else {
+                return Ok(None);
+            };
+            match py.allow_threads(|| client.recv()) {
+                Some(msg) => self.pending.extend(msg.transactions()),
+                None => return Ok(None),
+            }
+        }
+    }
+
+    /// Closes the connection. Iterating afterwards yields nothing.
+    fn close(&mut self, py: Python<'_>) -> Result<(), RelayError> {
+        match self.client.take() {
+            Some(client) => py.allow_threads(|| client.close()),
+            None => Ok(()),
+        }
+    }
+}
+
+fn transaction_json(tx: &FeedTransaction) -> Value {
+    let mut json = serde_json::to_value(&tx.tx).unwrap_or_default();
+    if let Value::Object(fields) = &mut json {
+        fields.insert("sequenceNumber".into(), tx.sequence_number.into());
+        fields.insert("indexInMessage".into(), tx.index.into());
+        fields.insert("l1BlockNumber".into(), tx.header.block_number.into());
+        fields.insert("timestamp".into(), tx.header.timestamp.into());
+        fields.insert("receivedAtMs".into(), tx.received_at_ms.into());
+    }
+    json
+}
+
+fn to_python(py: Python<'_>, value: &Value) -> PyResult<PyObject> {
+    Ok(match value {
+        Value::Null => py.None(),
+        Value::Bool(b) => b.into_py(py),
+        Value::Number(n) => match (n.as_u64(), n.as_i64()) {
+            (Some(n), _) => n.into_py(py),
+            (None, Some(n)) => n.into_py(py),
+            (None, None) => n.as_f64().into_py(py),
+        },
+        Value::String(s) => s.into_py(py),
+        Value::Array(values) => {
+            let list = PyList::empty_bound(py);
+            for value in values {
+                list.append(to_python(py, value)?)?;
+            }
+            list.into_py(py)
+        }
+        Value::Object(fields) => {
+            let dict = PyDict::new_bound(py);
+            for (key, value) in fields {
+                dict.set_item(key, to_python(py, value)?)?;
+            }
+            dict.into_py(py)
+        }
+    })
+}
+
+/// The `sequencer_feed_reader` Python module.
+#[pymodule]
+fn sequencer_feed_reader(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyFeedReader>()?;
+    Ok(())
+}
@@ -0,0 +1,451 @@
+use crate::networks::arbitrum::{
+    errors::{ConnectionUpdate, RelayError},
+    events::FeedEvent,
+    feed_client::RelayClient,
+    identity::RelayInfo,
+    message::FeedMessage,
+    network::ArbitrumNetwork,
+    readiness::ReadinessMonitor,
+    types::Root,
+};
+use futures::{stream, Stream};
+use log::*;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, OnceLock,
+    },
+    thread,
+    time::{Duration, SystemTime},
+};
+use tokio::sync::broadcast::{self, error::RecvError};
+use url::Url;
+
+/// How many messages a subscriber may lag behind before it starts missing messages.
+const SUBSCRIBER_BUFFER: usize = 4096;
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+static SHARED: OnceLock<Mutex<HashMap<ArbitrumNetwork, broadcast::Sender<Arc<FeedMessage>>>>> =
+    OnceLock::new();
+
+/// The converted streams of `subscribe_as`, keyed by network, target type and projection name.
+/// Values are `broadcast::Sender<Arc<T>>`s.
+type ProjectedKey = (ArbitrumNetwork, TypeId, &'static str);
+static PROJECTED: OnceLock<Mutex<HashMap<ProjectedKey, Box<dyn Any + Send>>>> = OnceLock::new();
+
+/// How `subscribe_as` turns feed messages into user types.
+#[derive(Debug, Clone, Copy)]
+pub struct Projection {
+    /// Identifies the projection. Subscribers using the same projection name and target type
+    /// share a single conversion of every message.
+    pub name: &'static str,
+    /// Extracts the JSON value deserialized into the target type, or `None` to skip the message.
+    pub project: fn(&FeedMessage) -> Option<Value>,
+}
+
+impl Projection {
+    /// Projects the broadcast message as received from the feed, e.g. for types mirroring
+    /// `BroadcastFeedMessage` with only the fields a subscriber needs.
+    pub const MESSAGE: Projection = Projection {
+        name: "message",
+        project: |msg| serde_json::to_value(&msg.message).ok(),
+    };
+}
+
+/// Subscribes to the decoded messages of `network` that match `filter`.
+///
+/// The first subscription to a network starts a background client, shared by every later
+/// subscription and kept running (and reconnected) for the lifetime of the process. This is
+/// meant for scripts and quick experiments; use `RelayClient` directly for control over the
+/// connection, backpressure and decoding.
+///
+/// Subscribers falling more than a few thousand messages behind skip the messages they missed.
+///
+/// # Example
+///
+/// ```no_run
+/// use futures::StreamExt;
+/// use sequencer_feed_reader::{networks::arbitrum::network::ArbitrumNetwork, subscribe};
+///
+/// # async fn run() {
+/// let mut messages = Box::pin(subscribe(ArbitrumNetwork::One, |_| true));
+/// while let Some(msg) = messages.next().await {
+///     println!("{}", msg.sequence_number());
+/// }
+/// # }
+/// ```
+pub fn subscribe<F>(network: ArbitrumNetwork, filter: F) -> impl Stream<Item = Arc<FeedMessage>>
+where
+    F: Fn(&FeedMessage) -> bool + Send + 'static,
+{
+    filtered(shared(network).subscribe(), filter)
+}
+
+/// Subscribes to the messages of `network`, converted to `T` by `projection`.
+///
+/// Every message is converted once for all subscribers sharing the same `T` and projection name,
+/// and delivered to them behind an `Arc`. Messages the projection skips, or that can't be
+/// deserialized into `T`, are not delivered. See `subscribe` for the lifetime of the shared
+/// client.
+///
+/// # Example
+///
+/// ```no_run
+/// use futures::StreamExt;
+/// use sequencer_feed_reader::{networks::arbitrum::network::ArbitrumNetwork, subscribe_as, Projection};
+///
+/// #[derive(serde::Deserialize)]
+/// #[serde(rename_all = "camelCase")]
+/// struct Sequenced {
+///     sequence_number: u64,
+/// }
+///
+/// # async fn run() {
+/// let mut messages = Box::pin(subscribe_as::<Sequenced, _>(
+///     ArbitrumNetwork::One,
+///     Projection::MESSAGE,
+///     |_| true,
+/// ));
+/// while let Some(msg) = messages.next().await {
+///     println!("{}", msg.sequence_number);
+/// }
+/// # }
+/// ```
+pub fn subscribe_as<T, F>(
+    network: ArbitrumNetwork,
+    projection: Projection,
+    filter: F,
+) -> impl Stream<Item = Arc<T>>
+where
+    T: DeserializeOwned + Send + Sync + 'static,
+    F: Fn(&T) -> bool + Send + 'static,
+{
+    let receiver = {
+        let key = (network, TypeId::of::<T>(), projection.name);
+        let mut projected = PROJECTED.get_or_init(Default::default).lock().unwrap();
+        projected
+            .entry(key)
+            .or_insert_with(|| {
+                let messages = shared(network).subscribe();
+                Box::new(start_projection::<T>(key, messages, projection).0)
+            })
+            .downcast_ref::<broadcast::Sender<Arc<T>>>()
+            .expect("projected stream of another type")
+            .subscribe()
+    };
+    filtered(receiver, filter)
+}
+
+/// Like `subscribe`, reading a relay with a client of its own rather than the shared client of a
+/// network, and reporting the connection updates and errors of the client to `readiness`, e.g.
+/// for a daemon serving readiness probes.
+///
+/// The client is reconnected after failures for the lifetime of the process, unless it fails
+/// fatally.
+pub fn subscribe_relay(
+    url: Url,
+    chain_id: u64,
+    readiness: Arc<ReadinessMonitor>,
+) -> impl Stream<Item = Arc<FeedMessage>> {
+    let feed = Feed {
+        name: url.to_string(),
+        url,
+        chain_id,
+        readiness: Some(readiness),
+    };
+    filtered(start(feed).subscribe(), |_| true)
+}
+
+/// A relay read by a client of `start`.
+struct Feed {
+    /// Names the relay in logs.
+    name: String,
+    url: Url,
+    chain_id: u64,
+    readiness: Option<Arc<ReadinessMonitor>>,
+}
+
+impl From<ArbitrumNetwork> for Feed {
+    fn from(network: ArbitrumNetwork) -> Self {
+        Self {
+            name: format!("{:?}", network),
+            url: network.feed_url(),
+            chain_id: network.chain_id(),
+            readiness: None,
+        }
+    }
+}
+
+/// Returns the sender of the shared client of `network`, starting it if needed.
+fn shared(network: ArbitrumNetwork) -> broadcast::Sender<Arc<FeedMessage>> {
+    let mut shared = SHARED.get_or_init(Default::default).lock().unwrap();
+    shared
+        .entry(network)
+        .or_insert_with(|| start(network.into()))
+        .clone()
+}
+
+fn filtered<T, F>(receiver: broadcast::Receiver<Arc<T>>, filter: F) -> impl Stream<Item = Arc<T>>
+where
+    T: Send + Sync + 'static,
+    F: Fn(&T) -> bool + Send + 'static,
+{
+    stream::unfold((receiver, filter), |(mut receiver, filter)| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(msg) if filter(&msg) => return Some((msg, (receiver, filter))),
+                Ok(_) => (),
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!("Subscriber lagging behind, skipped {} messages", skipped)
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    })
+}
+
+/// Starts converting `messages` on its own thread, for the subscribers of the projected stream of
+/// `key`.
+///
+/// The thread stops once nobody subscribes to the projected stream anymore, and removes it from
+/// `PROJECTED`.
+fn start_projection<T>(
+    key: ProjectedKey,
+    mut messages: broadcast::Receiver<Arc<FeedMessage>>,
+    projection: Projection,
+) -> (broadcast::Sender<Arc<T>>, thread::JoinHandle<()>)
+where
+    T: DeserializeOwned + Send + Sync + 'static,
+{
+    let (tx, _) = broadcast::channel(SUBSCRIBER_BUFFER);
+    let projected = tx.clone();
+    let thread = thread::spawn(move || loop {
+        match messages.blocking_recv() {
+            Ok(msg) => {
+                let Some(value) = (projection.project)(&msg) else {
+                    continue;
+                };
+                match serde_json::from_value::<T>(value) {
+                    Ok(converted) => {
+                        // Sending only fails while nobody is subscribed.
+                        if projected.send(Arc::new(converted)).is_err() && forget::<T>(key) {
+                            debug!("Projection {} has no subscriber left", projection.name);
+                            return;
+                        }
+                    }
+                    Err(e) => debug!(
+                        "Projection {} failed for message {}: {}",
+                        projection.name,
+                        msg.sequence_number(),
+                        e
+                    ),
+                }
+            }
+            Err(RecvError::Lagged(skipped)) => {
+                warn!(
+                    "Projection {} skipped {} messages",
+                    projection.name, skipped
+                )
+            }
+            Err(RecvError::Closed) => return,
+        }
+    });
+    (tx, thread)
+}
+
+/// Removes the projected stream of `key` from `PROJECTED` unless somebody subscribed to it.
+///
+/// # Returns
+///
+/// `true` if nobody subscribes to the stream anymore.
+fn forget<T: Send + Sync + 'static>(key: ProjectedKey) -> bool {
+    let mut projected = PROJECTED.get_or_init(Default::default).lock().unwrap();
+    let unused = projected
+        .get(&key)
+        .and_then(|tx| tx.downcast_ref::<broadcast::Sender<Arc<T>>>())
+        .is_none_or(|tx| tx.receiver_count() == 0);
+    if unused {
+        projected.remove(&key);
+    }
+    unused
+}
+
+/// Starts a client reading `feed` on its own threads.
+fn start(feed: Feed) -> broadcast::Sender<Arc<FeedMessage>> {
+    let (broadcast_tx, _) = broadcast::channel(SUBSCRIBER_BUFFER);
+    let (root_tx, root_rx) = crossbeam_channel::unbounded::<Root>();
+    let next_sequence_number = Arc::new(AtomicU64::new(0));
+
+    let tx = broadcast_tx.clone();
+    let next = next_sequence_number.clone();
+    thread::spawn(move || {
+        for root in root_rx {
+            for message in root.messages {
+                next.store(message.sequence_number + 1, Ordering::Release);
+                let decoded = message.message.message.try_decode();
+                // Sending only fails while nobody is subscribed.
+                let _ = tx.send(Arc::new(FeedMessage {
+                    message,
+                    decoded,
+                    provenance: root.provenance.clone(),
+                }));
+            }
+        }
+    });
+
+    thread::spawn(move || {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("failed to build the subscriber runtime");
+        runtime.block_on(run_client(feed, root_tx, next_sequence_number));
+    });
+
+    broadcast_tx
+}
+
+async fn run_client(
+    feed: Feed,
+    sender: crossbeam_channel::Sender<Root>,
+    next_sequence_number: Arc<AtomicU64>,
+) {
+    let (update_tx, update_rx) = crossbeam_channel::unbounded::<ConnectionUpdate>();
+    let (name, readiness) = (feed.name.clone(), feed.readiness.clone());
+    // Nobody else reads the connection updates, which would otherwise pile up.
+    thread::spawn(move || {
+        for update in update_rx {
+            debug!("{} feed: {:?}", name, update);
+            if let Some(readiness) = &readiness {
+                readiness.observe_update(&update);
+            }
+        }
+    });
+    let events = feed.readiness.clone().map(|readiness| {
+        let (events, received) = crossbeam_channel::unbounded::<FeedEvent>();
+        thread::spawn(move || {
+            for event in received {
+                readiness.observe_event(&event);
+            }
+        });
+        events
+    });
+    let relay = Arc::new(RelayInfo::new(0, &feed.url));
+    let mut generation = 0;
+    let mut attempt = 0;
+    loop {
+        let client = RelayClient::new_from(
+            feed.url.clone(),
+            feed.chain_id,
+            0,
+            next_sequence_number.load(Ordering::Acquire),
+            sender.clone(),
+            update_tx.clone(),
+        )
+        .await;
+
+        let result = match client {
+            Ok(mut client) => {
+                attempt = 0;
+                if let Some(events) = &events {
+                    client = client.with_events(events.clone());
+                }
+                client.with_generation(generation).run().await.map_err(|e| {
+                    error!("{} feed client stopped [{}]: {}", feed.name, e.code(), e);
+                    e
+                })
+            }
+            Err(e) => {
+                error!(
+                    "Failed to connect to the {} feed [{}]: {}",
+                    feed.name,
+                    e.code(),
+                    e
+                );
+                Err(e)
+            }
+        };
+        if let (Err(e), Some(readiness)) = (&result, &feed.readiness) {
+            readiness.observe_error(e);
+        }
+        if result.as_ref().is_err_and(RelayError::is_fatal) {
+            error!("Giving up on the {} feed", feed.name);
+            return;
+        }
+        generation += 1;
+        attempt += 1;
+        let _ = update_tx.send(ConnectionUpdate::Reconnecting {
+            relay: relay.clone(),
+            attempt,
+            at: SystemTime::now(),
+        });
+        tokio::time::sleep(RECONNECT_DELAY).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::networks::arbitrum::{
+        fixtures::feed_message,
+        mock::{MockRelay, Scenario, SimEvent, SimulatedSequencer, Step},
+    };
+    use futures::StreamExt;
+
+    #[test]
+    fn projection_stops_once_unsubscribed() {
+        let (messages, _) = broadcast::channel(SUBSCRIBER_BUFFER);
+        let projection = Projection {
+            name: "sequence-number",
+            project: |msg| Some(msg.sequence_number().into()),
+        };
+        let key = (ArbitrumNetwork::One, TypeId::of::<u64>(), projection.name);
+        let (projected, thread) = start_projection::<u64>(key, messages.subscribe(), projection);
+        let msg = |seq| Arc::new(feed_message(seq, 0, vec![], None));
+
+        let mut subscriber = projected.subscribe();
+        messages.send(msg(7)).unwrap();
+        assert_eq!(*subscriber.blocking_recv().unwrap(), 7);
+        drop(subscriber);
+        messages.send(msg(8)).unwrap();
+        thread.join().unwrap();
+    }
+
+    #[tokio::test]
+    async fn readiness_drops_after_a_disconnect() {
+        let scenario = Scenario::new()
+            .then(Step::Blocks {
+                count: 2,
+                interval_ms: 10,
+            })
+            .then(Step::Stall { ms: 200 });
+        let mut events = SimulatedSequencer::new(0, 1_700_000_000, 1).generate(&scenario);
+        events.push(SimEvent::Disconnect);
+        let relay = MockRelay::bind("127.0.0.1:0", 42161, events).await.unwrap();
+        let url = Url::parse(&format!("ws://{}", relay.local_addr().unwrap())).unwrap();
+        relay.spawn();
+
+        let readiness = Arc::new(ReadinessMonitor::new());
+        let mut messages = Box::pin(subscribe_relay(url, 42161, readiness.clone()));
+        let connected = || readiness.report().conditions[0].ok;
+        for _ in 0..2 {
+            let msg = messages.next().await.unwrap();
+            readiness.observe_message(msg.sequence_number());
+        }
+        assert!(connected());
+
+        // The relay is gone after the disconnect, so the client can't reconnect.
+        for _ in 0..100 {
+            if !connected() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        let report = readiness.report();
+        assert!(!report.ready);
+        assert_eq!(report.conditions[0].detail, "0 of 1 relays connected");
+    }
+}
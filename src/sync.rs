@@ -0,0 +1,206 @@
+//! A blocking facade over `RelayClient`, for scripts and applications that don't run an async
+//! runtime.
+
+use crate::networks::arbitrum::{
+    connect::ConnectOptions, errors::RelayError, feed_client::RelayClient,
+    handle::RelayClientHandle, message::FeedMessage, network::ArbitrumNetwork, types::Root,
+};
+use crossbeam_channel::{Receiver, RecvTimeoutError};
+use std::{
+    collections::VecDeque,
+    sync::mpsc,
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+use url::Url;
+
+/// A relay client running on its own thread and Tokio runtime, read from with blocking calls.
+///
+/// The client reads a single connection: once the relay closes it, the remaining messages are
+/// delivered and the iterator ends. Use `FeedService` for reconnections and failover.
+///
+/// # Example
+///
+/// ```no_run
+/// use sequencer_feed_reader::{networks::arbitrum::network::ArbitrumNetwork, sync::RelayClientSync};
+///
+/// let client = RelayClientSync::network(ArbitrumNetwork::One).unwrap();
+/// for msg in client.messages().take(10) {
+///     println!("{} {:?}", msg.sequence_number(), msg.decoded);
+/// }
+/// ```
+pub struct RelayClientSync {
+    roots: Receiver<Root>,
+    pending: VecDeque<FeedMessage>,
+    handle: RelayClientHandle,
+    thread: Option<JoinHandle<Result<(), RelayError>>>,
+}
+
+impl RelayClientSync {
+    /// Connects to the public relay of `network`.
+    pub fn network(network: ArbitrumNetwork) -> Result<Self, RelayError> {
+        Self::connect(
+            network.feed_url(),
+            network.chain_id(),
+            ConnectOptions::new(),
+        )
+    }
+
+    /// Connects to a relay, blocking until the connection is established.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL of the relay.
+    /// * `chain_id` - The expected chain ID of the relay.
+    /// * `options` - How to connect to the relay, e.g. the sequence number to start at.
+    pub fn connect(url: Url, chain_id: u64, options: ConnectOptions) -> Result<Self, RelayError> {
+        let (sender, roots) = crossbeam_channel::unbounded();
+        let (connected_tx, connected) = mpsc::channel();
+
+        let thread = thread::spawn(move || {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()?;
+            runtime.block_on(async move {
+                let (updates, _) = crossbeam_channel::unbounded();
+                let client =
+                    match RelayClient::connect(url, chain_id, 0, options, sender, updates).await {
+                        Ok(client) => client,
+                        Err(e) => {
+                            let _ = connected_tx.send(Err(e));
+                            return Ok(());
+                        }
+                    };
+                let _ = connected_tx.send(Ok(client.handle()));
+                client.run().await
+            })
+        });
+
+        match connected.recv() {
+            Ok(Ok(handle)) => Ok(Self {
+                roots,
+                pending: VecDeque::new(),
+                handle,
+                thread: Some(thread),
+            }),
+            Ok(Err(e)) => Err(e),
+            // The thread failed before connecting, i.e. while building its runtime.
+            Err(_) => Err(thread
+                .join()
+                .expect("relay client thread panicked")
+                .expect_err("the client thread only stops before connecting on errors")),
+        }
+    }
+
+    /// Blocks until the next message, decoded.
+    ///
+    /// # Returns
+    ///
+    /// The message, or `None` once the connection is closed and every message was read.
+    pub fn recv(&mut self) -> Option<FeedMessage> {
+        loop {
+            if let Some(msg) = self.pending.pop_front() {
+                return Some(msg);
+            }
+            let root = self.roots.recv().ok()?;
+            self.push(root);
+        }
+    }
+
+    /// Like `recv`, giving up after `timeout`.
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Result<FeedMessage, RecvTimeoutError> {
+        loop {
+            if let Some(msg) = self.pending.pop_front() {
+                return Ok(msg);
+            }
+            let root = self.roots.recv_timeout(timeout)?;
+            self.push(root);
+        }
+    }
+
+    /// Returns a blocking iterator over the messages, ending when the connection is closed.
+    pub fn messages(self) -> Messages {
+        Messages(self)
+    }
+
+    /// Returns a handle to inspect and control the underlying client.
+    pub fn handle(&self) -> &RelayClientHandle {
+        &self.handle
+    }
+
+    /// Closes the connection and waits for the client to stop.
+    ///
+    /// # Returns
+    ///
+    /// The error that stopped the client, if it stopped on its own because of one.
+    pub fn close(mut self) -> Result<(), RelayError> {
+        self.stop()
+    }
+
+    fn push(&mut self, root: Root) {
+        self.pending
+            .extend(root.messages.into_iter().map(|message| FeedMessage {
+                decoded: message.message.message.try_decode(),
+                message,
+                provenance: root.provenance.clone(),
+            }));
+    }
+
+    fn stop(&mut self) -> Result<(), RelayError> {
+        self.handle.shutdown();
+        match self.thread.take() {
+            Some(thread) => thread.join().expect("relay client thread panicked"),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for RelayClientSync {
+    fn drop(&mut self) {
+        let _ = self.stop();
+    }
+}
+
+/// The blocking iterator of `RelayClientSync::messages`.
+pub struct Messages(RelayClientSync);
+
+impl Iterator for Messages {
+    type Item = FeedMessage;
+
+    fn next(&mut self) -> Option<FeedMessage> {
+        self.0.recv()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::networks::arbitrum::mock::{
+        MockRelay, Scenario, SimEvent, SimulatedSequencer, Step,
+    };
+
+    #[test]
+    fn reads_messages_without_a_runtime() {
+        let scenario = Scenario::new().then(Step::Blocks {
+            count: 3,
+            interval_ms: 10,
+        });
+        let mut events = SimulatedSequencer::new(0, 1_700_000_000, 1).generate(&scenario);
+        events.push(SimEvent::Disconnect);
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let relay = runtime
+            .block_on(MockRelay::bind("127.0.0.1:0", 42161, events))
+            .unwrap();
+        let url = Url::parse(&format!("ws://{}", relay.local_addr().unwrap())).unwrap();
+        let server = thread::spawn(move || runtime.block_on(async { relay.spawn().await }));
+
+        let client = RelayClientSync::connect(url, 42161, ConnectOptions::new()).unwrap();
+        let sequence_numbers: Vec<_> = client.messages().map(|m| m.sequence_number()).collect();
+        assert_eq!(sequence_numbers, [0, 1, 2]);
+        server.join().unwrap().unwrap();
+    }
+}
@@ -0,0 +1,135 @@
+//! A feed client for the browser, built on the browser's `WebSocket` instead of
+//! tokio-tungstenite, for dashboards consuming the feed directly.
+//!
+//! Frames are parsed like the native client parses them, with `VersionedRoot::parse_borrowed`,
+//! and every message is passed to a JavaScript callback as the JSON object of the JSON sinks,
+//! `JsonMessage`.
+//!
+//! Browsers don't let websocket clients set request headers, so the client can neither request a
+//! starting sequence number nor check the chain ID announced by the relay: it reads the live feed
+//! from its current position.
+//!
+//! ```js
+//! import init, { BrowserFeedClient } from "sequencer_feed_reader";
+//!
+//! await init();
+//! const client = new BrowserFeedClient("wss://arb1.arbitrum.io/feed", 42161n, (json) => {
+//!     const message = JSON.parse(json);
+//!     console.log(message.sequenceNumber, message.transactions.length);
+//! });
+//! client.onClose((code, reason) => console.log("relay closed the connection", code, reason));
+//! ```
+//!
+//! [`JsonMessage`]: crate::networks::arbitrum::sinks::encoding::JsonMessage
+
+use crate::networks::arbitrum::{
+    message::FeedMessage,
+    provenance::Provenance,
+    sinks::{encoding::Encoding, IdempotencyKey},
+    types::versioned::VersionedRoot,
+};
+use js_sys::{ArrayBuffer, Date, Function, Uint8Array};
+use log::*;
+use wasm_bindgen::{closure::Closure, prelude::*, JsCast};
+use web_sys::{BinaryType, CloseEvent, MessageEvent, WebSocket};
+
+/// Reads a relay from the browser, passing every message to a JavaScript callback.
+#[wasm_bindgen]
+pub struct BrowserFeedClient {
+    socket: WebSocket,
+    // Kept alive for as long as the socket may call them.
+    _on_message: Closure<dyn FnMut(MessageEvent)>,
+    _on_close: Option<Closure<dyn FnMut(CloseEvent)>>,
+}
+
+#[wasm_bindgen]
+impl BrowserFeedClient {
+    /// Opens a connection to a relay.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL of the relay.
+    /// * `chain_id` - The chain ID of the relay, used in the key of the messages.
+    /// * `on_message` - Called with every message, as a JSON string.
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        url: &str,
+        chain_id: u64,
+        on_message: Function,
+    ) -> Result<BrowserFeedClient, JsValue> {
+        let socket = WebSocket::new(url)?;
+        socket.set_binary_type(BinaryType::Arraybuffer);
+
+        let on_message = Closure::<dyn FnMut(MessageEvent)>::new(move |event: MessageEvent| {
+            let data = event.data();
+            let payload = match data.as_string() {
+                Some(text) => text.into_bytes(),
+                None => match data.dyn_into::<ArrayBuffer>() {
+                    Ok(buffer) => Uint8Array::new(&buffer).to_vec(),
+                    Err(_) => return,
+                },
+            };
+            for json in decode_frame(chain_id, &payload) {
+                if let Err(e) = on_message.call1(&JsValue::NULL, &JsValue::from_str(&json)) {
+                    warn!("Message callback failed: {:?}", e);
+                }
+            }
+        });
+        socket.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+
+        Ok(Self {
+            socket,
+            _on_message: on_message,
+            _on_close: None,
+        })
+    }
+
+    /// Calls `callback` with the close code and reason once the connection is closed.
+    #[wasm_bindgen(js_name = onClose)]
+    pub fn on_close(&mut self, callback: Function) {
+        let on_close = Closure::<dyn FnMut(CloseEvent)>::new(move |event: CloseEvent| {
+            let code = JsValue::from(event.code());
+            let reason = JsValue::from_str(&event.reason());
+            if let Err(e) = callback.call2(&JsValue::NULL, &code, &reason) {
+                warn!("Close callback failed: {:?}", e);
+            }
+        });
+        self.socket
+            .set_onclose(Some(on_close.as_ref().unchecked_ref()));
+        self._on_close = Some(on_close);
+    }
+
+    /// Closes the connection.
+    pub fn close(&self) -> Result<(), JsValue> {
+        self.socket.set_onmessage(None);
+        self.socket.close()
+    }
+}
+
+/// Parses a frame and encodes its messages into the JSON objects passed to the callback.
+fn decode_frame(chain_id: u64, payload: &[u8]) -> Vec<String> {
+    let root = match VersionedRoot::parse_borrowed(payload) {
+        Ok(versioned) => versioned.into_root(),
+        Err(e) => {
+            warn!("Skipping malformed frame: {}", e);
+            return Vec::new();
+        }
+    };
+    let provenance = Provenance::live(0, 0).with_received_at_ms(Date::now() as u64);
+    root.messages
+        .into_iter()
+        .filter_map(|message| {
+            let msg = FeedMessage {
+                decoded: message.message.message.try_decode(),
+                message,
+                provenance: provenance.clone(),
+            };
+            let key = IdempotencyKey::new(chain_id, msg.sequence_number());
+            let json = Encoding::Json
+                .encode(key, &msg)
+                .map_err(|e| warn!("Failed to encode message: {}", e))
+                .ok()?;
+            String::from_utf8(json).ok()
+        })
+        .collect()
+}